@@ -1,6 +1,11 @@
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::config::raydium::USDC_MINT;
+use crate::core::slippage::Slippage;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -9,22 +14,46 @@ use clap::{Args, Parser, Subcommand, ValueEnum};
     about = "Wallet Manager CLI for Solana and EVM networks"
 )]
 pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "FORMAT",
+        help = "Output format (default text)"
+    )]
+    pub output: Option<OutputFormat>,
     #[command(subcommand)]
     pub command: Option<Command>,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Command {
-    /// Get SOL and USDC balances for an address or resolved wallet
+    /// Get SOL and token balances for an address or resolved wallet
     Balance(BalanceArgs),
     /// Send tokens on Solana or EVM
     Send(SendCommand),
-    /// Show SOL/USDC price from Raydium pool
+    /// Show a token price from a Raydium pool
     Price(PriceArgs),
-    /// Buy SOL/USDC on Raydium
+    /// Quote a Raydium swap without a wallet
+    Quote(QuoteArgs),
+    /// Buy a token on Raydium
     Buy(TradeArgs),
-    /// Sell SOL/USDC on Raydium
+    /// Sell a token on Raydium
     Sell(TradeArgs),
+    /// Wrap SOL into the wallet's wSOL token account
+    Wrap(WrapArgs),
+    /// Unwrap wSOL back to SOL
+    Unwrap(UnwrapArgs),
+    /// Add or remove liquidity on Raydium AMM v4 pools
+    Lp(LpArgs),
+    /// Price-triggered buy and sell orders, run by `order watch`
+    Order(OrderArgs),
+    /// Dollar-cost-averaging schedules, run by `dca run` or `daemon`
+    Dca(DcaArgs),
+    /// Run open orders and due DCA fills until interrupted
+    Daemon(DaemonArgs),
+    /// Journal of landed trades: list, CSV export and realised P&L
+    Trades(TradesArgs),
     /// Start interactive mode (REPL)
     Repl,
     /// Manage saved defaults (.wmgr)
@@ -41,6 +70,7 @@ pub struct ConfigArgs {
 }
 
 #[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum ConfigCommand {
     /// Show current config values
     Show,
@@ -87,9 +117,21 @@ pub struct ConfigSetArgs {
     #[arg(
         long,
         value_name = "PERCENT",
-        help = "Default slippage percent for buy/sell (default 0.1)"
+        help = "Default slippage percent for buy/sell, in steps of 0.01 (default 0.1)"
+    )]
+    pub slippage: Option<Slippage>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Default price impact limit for buy/sell (default none)"
     )]
-    pub slippage: Option<f64>,
+    pub max_price_impact: Option<f64>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Default quote move that triggers a re-quote before sending (default 0.5)"
+    )]
+    pub requote_threshold: Option<f64>,
     #[arg(
         long,
         value_enum,
@@ -117,6 +159,22 @@ pub struct ConfigSetArgs {
         alias = "evm-gas-price"
     )]
     pub gas_price: Option<String>,
+    #[arg(
+        long,
+        value_name = "GWEI",
+        help = "Default EIP-1559 max fee per gas in gwei",
+        alias = "evm_max_fee",
+        alias = "evm-max-fee"
+    )]
+    pub max_fee: Option<String>,
+    #[arg(
+        long,
+        value_name = "GWEI",
+        help = "Default EIP-1559 priority fee per gas in gwei",
+        alias = "evm_priority_fee",
+        alias = "evm-priority-fee"
+    )]
+    pub priority_fee: Option<String>,
     #[arg(
         long,
         value_name = "NUMBER",
@@ -125,6 +183,20 @@ pub struct ConfigSetArgs {
         alias = "evm-gas-limit"
     )]
     pub gas_limit: Option<u64>,
+    #[arg(
+        long,
+        value_name = "MICRO_LAMPORTS|auto",
+        help = "Default Solana priority fee per compute unit",
+        alias = "solana_priority_fee"
+    )]
+    pub solana_priority_fee: Option<String>,
+    #[arg(
+        long,
+        value_name = "UNITS",
+        help = "Default Solana compute unit limit",
+        alias = "solana_compute_units"
+    )]
+    pub solana_compute_units: Option<u32>,
     #[arg(
         long = "svpi_cmd",
         value_name = "PATH",
@@ -167,6 +239,19 @@ pub struct BalanceArgs {
         help = "Solana commitment (default confirmed)"
     )]
     pub commitment: Option<CommitmentArg>,
+    #[arg(
+        long,
+        conflicts_with = "network",
+        help = "List all SPL Token and Token-2022 accounts (Solana only)"
+    )]
+    pub tokens: bool,
+    #[arg(
+        long = "token",
+        value_name = "ADDR",
+        requires = "network",
+        help = "ERC-20 token contract to include (repeatable, EVM only)"
+    )]
+    pub token: Vec<String>,
     #[command(flatten)]
     pub key: BalanceKeyOptions,
 }
@@ -261,6 +346,8 @@ pub enum SendKind {
     Sol(SendSolArgs),
     /// Send USDC (SPL token)
     Usdc(SendUsdcArgs),
+    /// Send any SPL token by mint (SPL Token or Token-2022)
+    Spl(SendSplArgs),
     /// Send native ETH (or network native coin)
     Eth(SendEthArgs),
     /// Send ERC-20/BEP-20/PIP-20 token
@@ -277,6 +364,8 @@ pub struct SendSolArgs {
     pub key: SolanaKeyOptions,
     #[command(flatten)]
     pub rpc: SolanaRpcOptions,
+    #[command(flatten)]
+    pub confirm: ConfirmOptions,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -289,6 +378,30 @@ pub struct SendUsdcArgs {
     pub key: SolanaKeyOptions,
     #[command(flatten)]
     pub rpc: SolanaRpcOptions,
+    #[command(flatten)]
+    pub confirm: ConfirmOptions,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SendSplArgs {
+    #[arg(value_name = "MINT", help = "Token mint address (base58)")]
+    pub mint: String,
+    #[arg(value_name = "TO", help = "Recipient Solana address (base58)")]
+    pub to: String,
+    #[arg(value_name = "AMOUNT", help = "Token amount to send")]
+    pub amount: String,
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Memo to attach (required by Token-2022 accounts with memo transfers enabled)"
+    )]
+    pub memo: Option<String>,
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+    #[command(flatten)]
+    pub confirm: ConfirmOptions,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -301,6 +414,8 @@ pub struct SendEthArgs {
     pub key: EvmKeyOptions,
     #[command(flatten)]
     pub tx: EvmTxOptions,
+    #[command(flatten)]
+    pub confirm: ConfirmOptions,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -317,30 +432,535 @@ pub struct SendErc20Args {
     pub key: EvmKeyOptions,
     #[command(flatten)]
     pub tx: EvmTxOptions,
+    #[command(flatten)]
+    pub confirm: ConfirmOptions,
 }
 
 #[derive(Args, Debug, Clone)]
 pub struct PriceArgs {
-    #[arg(value_enum, value_name = "TOKEN", help = "Token symbol (sol|usdc)")]
+    #[arg(value_name = "TOKEN", help = "Token to price (sol|usdc|<MINT>)")]
     pub token: SwapToken,
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        help = "Token to quote the price in (default: the pool's other mint, else usdc or sol)"
+    )]
+    pub with: Option<SwapToken>,
+    #[arg(
+        long,
+        value_name = "ID",
+        help = "Pool id (Raydium AMM v4, CPMM, CLMM or Orca Whirlpool)"
+    )]
+    pub pool: Option<String>,
     #[command(flatten)]
-    pub key: SolanaKeyOptions,
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct QuoteArgs {
+    #[arg(
+        value_enum,
+        value_name = "SIDE",
+        help = "Quote a buy or a sell of TOKEN"
+    )]
+    pub side: QuoteSide,
+    #[arg(value_name = "TOKEN", help = "Token to buy or sell (sol|usdc|<MINT>)")]
+    pub token: SwapToken,
+    #[arg(
+        value_name = "AMOUNT",
+        required_unless_present = "sizes",
+        help = "Amount of token to buy or sell"
+    )]
+    pub amount: Option<String>,
+    #[arg(
+        long,
+        value_name = "AMOUNTS",
+        value_delimiter = ',',
+        conflicts_with = "amount",
+        help = "Comma-separated ladder of amounts, e.g. 1,10,100"
+    )]
+    pub sizes: Vec<String>,
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        help = "Token paid or received (default: the pool's other mint, else usdc or sol)"
+    )]
+    pub with: Option<SwapToken>,
+    #[arg(
+        long,
+        value_name = "ID",
+        help = "Pool id (Raydium AMM v4, CPMM, CLMM or Orca Whirlpool)"
+    )]
+    pub pool: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = VenueArg::Raydium,
+        conflicts_with = "pool",
+        help = "Where to find a pool when --pool is not given; best quotes both and takes the better"
+    )]
+    pub venue: VenueArg,
+    #[arg(
+        long,
+        conflicts_with = "pool",
+        help = "Also consider two-hop routes through SOL or USDC, swapped in one transaction"
+    )]
+    pub route: bool,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Slippage tolerance percent, in steps of 0.01 (default 0.1)"
+    )]
+    pub slippage: Option<Slippage>,
     #[command(flatten)]
     pub rpc: SolanaRpcOptions,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteSide {
+    Buy,
+    Sell,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VenueArg {
+    /// Raydium AMM v4 or CLMM
+    Raydium,
+    /// Orca Whirlpools
+    Orca,
+    /// Whichever quotes the better output (sell) or input (buy)
+    Best,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct TradeArgs {
     #[arg(value_name = "AMOUNT", help = "Amount of token to buy or sell")]
     pub amount: String,
-    #[arg(value_enum, value_name = "TOKEN", help = "Token symbol (sol|usdc)")]
+    #[arg(value_name = "TOKEN", help = "Token to buy or sell (sol|usdc|<MINT>)")]
     pub token: SwapToken,
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        help = "Token paid or received (default: the pool's other mint, else usdc or sol)"
+    )]
+    pub with: Option<SwapToken>,
+    #[arg(
+        long,
+        value_name = "ID",
+        help = "Pool id (Raydium AMM v4, CPMM, CLMM or Orca Whirlpool)"
+    )]
+    pub pool: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = VenueArg::Raydium,
+        conflicts_with = "pool",
+        help = "Where to find a pool when --pool is not given; best quotes both and takes the better"
+    )]
+    pub venue: VenueArg,
+    #[arg(
+        long,
+        conflicts_with = "pool",
+        help = "Also consider two-hop routes through SOL or USDC, swapped in one transaction"
+    )]
+    pub route: bool,
     #[arg(
         long,
         value_name = "PERCENT",
-        help = "Slippage tolerance percent (default 0.1)"
+        help = "Slippage tolerance percent, in steps of 0.01 (default 0.1)"
     )]
-    pub slippage: Option<f64>,
+    pub slippage: Option<Slippage>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Abort when the quote's price impact exceeds this percent"
+    )]
+    pub max_price_impact: Option<f64>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Re-quote and ask again when the quote moved more than this percent while prompting (default 0.5)"
+    )]
+    pub requote_threshold: Option<f64>,
+    #[arg(
+        long,
+        help = "Spend wrapped SOL already held before wrapping more (SOL input)"
+    )]
+    pub use_wsol: bool,
+    #[arg(
+        long,
+        help = "Keep SOL output wrapped in the wSOL account (SOL output)"
+    )]
+    pub keep_wrapped: bool,
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct OrderArgs {
+    #[command(subcommand)]
+    pub command: OrderCommand,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum OrderCommand {
+    /// Save an order that trades when the price crosses a level
+    Add(OrderAddArgs),
+    /// List saved orders
+    List(OrderListArgs),
+    /// Cancel an open order
+    Cancel(OrderCancelArgs),
+    /// Poll prices and execute open orders when they trigger
+    Watch(OrderWatchArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(group(
+    ArgGroup::new("trigger")
+        .required(true)
+        .args(["when_price_below", "when_price_above"])
+))]
+pub struct OrderAddArgs {
+    #[arg(
+        value_enum,
+        value_name = "SIDE",
+        help = "Buy or sell TOKEN when triggered"
+    )]
+    pub side: QuoteSide,
+    #[arg(value_name = "AMOUNT", help = "Amount of token to buy or sell")]
+    pub amount: String,
+    #[arg(value_name = "TOKEN", help = "Token to buy or sell (sol|usdc|<MINT>)")]
+    pub token: SwapToken,
+    #[arg(
+        long,
+        value_name = "PRICE",
+        help = "Trigger when TOKEN's price in the counter token is at or below PRICE"
+    )]
+    pub when_price_below: Option<String>,
+    #[arg(
+        long,
+        value_name = "PRICE",
+        help = "Trigger when TOKEN's price in the counter token is at or above PRICE"
+    )]
+    pub when_price_above: Option<String>,
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        help = "Token paid or received (default: the pool's other mint, else usdc or sol)"
+    )]
+    pub with: Option<SwapToken>,
+    #[arg(
+        long,
+        value_name = "ID",
+        help = "Pool id (Raydium AMM v4, CPMM, CLMM or Orca Whirlpool)"
+    )]
+    pub pool: Option<String>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Slippage tolerance percent, in steps of 0.01 (default: config, else 0.1)"
+    )]
+    pub slippage: Option<Slippage>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Fail the order when the quote's price impact exceeds this percent"
+    )]
+    pub max_price_impact: Option<f64>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct OrderListArgs {
+    #[arg(long, help = "Include filled, failed and cancelled orders")]
+    pub all: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct OrderCancelArgs {
+    #[arg(value_name = "ID", help = "Order id from order list")]
+    pub id: u32,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct OrderWatchArgs {
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 10,
+        help = "Seconds between price checks"
+    )]
+    pub interval: u64,
+    #[arg(long, help = "Check every open order once and exit")]
+    pub once: bool,
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct DcaArgs {
+    #[command(subcommand)]
+    pub command: DcaCommand,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum DcaCommand {
+    /// Save a schedule that buys a token with a fixed amount at a fixed interval
+    Create(DcaCreateArgs),
+    /// List schedules with progress and average price
+    List(DcaListArgs),
+    /// Show a schedule and its fill history
+    Show(DcaIdArgs),
+    /// Cancel an active schedule
+    Cancel(DcaIdArgs),
+    /// Execute the fills that are due now, then exit
+    Run(DcaRunArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DcaCreateArgs {
+    #[arg(long, value_name = "TOKEN", help = "Token to buy (sol|usdc|<MINT>)")]
+    pub buy: SwapToken,
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["AMOUNT", "TOKEN"],
+        help = "Amount and token spent per fill, e.g. --spend 50 usdc"
+    )]
+    pub spend: Vec<String>,
+    #[arg(
+        long,
+        value_name = "INTERVAL",
+        help = "Time between fills: a number with s, m, h, d or w, e.g. 1d"
+    )]
+    pub every: String,
+    #[arg(long, value_name = "N", help = "Number of fills")]
+    pub count: u32,
+    #[arg(
+        long,
+        value_name = "ID",
+        help = "Pool id (Raydium AMM v4, CPMM, CLMM or Orca Whirlpool)"
+    )]
+    pub pool: Option<String>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Slippage tolerance percent, in steps of 0.01 (default: config, else 0.1)"
+    )]
+    pub slippage: Option<Slippage>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Skip a fill when its quote's price impact exceeds this percent"
+    )]
+    pub max_price_impact: Option<f64>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DcaListArgs {
+    #[arg(long, help = "Include completed and cancelled schedules")]
+    pub all: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DcaIdArgs {
+    #[arg(value_name = "ID", help = "Schedule id from dca list")]
+    pub id: u32,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DcaRunArgs {
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DaemonArgs {
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 10,
+        help = "Seconds between passes over orders and schedules"
+    )]
+    pub interval: u64,
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct TradesArgs {
+    #[command(subcommand)]
+    pub command: TradesCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TradesCommand {
+    /// List journaled trades, oldest first
+    List(TradesListArgs),
+    /// Write the journal as CSV
+    Export(TradesExportArgs),
+    /// Cost basis and realised P&L per token
+    Pnl(TradesPnlArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct TradesListArgs {
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        help = "Only trades of this token (sol|usdc|<MINT>)"
+    )]
+    pub token: Option<SwapToken>,
+    #[arg(long, value_name = "N", help = "Show only the last N trades")]
+    pub last: Option<usize>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct TradesExportArgs {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write the CSV to PATH instead of standard output"
+    )]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct TradesPnlArgs {
+    #[arg(long, value_name = "TOKEN", help = "Only this token (sol|usdc|<MINT>)")]
+    pub token: Option<SwapToken>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = CostMethodArg::Fifo,
+        help = "How sells are matched against buys"
+    )]
+    pub method: CostMethodArg,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum CostMethodArg {
+    /// Oldest buys first
+    Fifo,
+    /// Running average cost
+    Average,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct WrapArgs {
+    #[arg(value_name = "AMOUNT", help = "Amount of SOL to wrap")]
+    pub amount: String,
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct UnwrapArgs {
+    #[arg(
+        value_name = "AMOUNT",
+        default_value = "all",
+        help = "Amount of wSOL to unwrap, or all to close the wSOL account"
+    )]
+    pub amount: String,
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct LpArgs {
+    #[command(subcommand)]
+    pub command: LpCommand,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum LpCommand {
+    /// Deposit both tokens of a pool at its current ratio
+    Add(LpAddArgs),
+    /// Burn LP tokens for the pool's tokens
+    Remove(LpRemoveArgs),
+    /// Show the wallet's LP balance and its share of the pool
+    Show(LpShowArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(group(
+    ArgGroup::new("amount")
+        .required(true)
+        .multiple(true)
+        .args(["amount_a", "amount_b"])
+))]
+pub struct LpAddArgs {
+    #[arg(value_name = "POOL", help = "Raydium AMM v4 pool id")]
+    pub pool: String,
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Amount of the pool's base token (token A)"
+    )]
+    pub amount_a: Option<String>,
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Amount of the pool's quote token (token B)"
+    )]
+    pub amount_b: Option<String>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Slippage tolerance on the derived amount, in steps of 0.01 (default: config, else 0.1)"
+    )]
+    pub slippage: Option<Slippage>,
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct LpRemoveArgs {
+    #[arg(value_name = "POOL", help = "Raydium AMM v4 pool id")]
+    pub pool: String,
+    #[arg(
+        value_name = "LP_AMOUNT",
+        help = "LP tokens to burn, or all for the whole balance"
+    )]
+    pub amount: String,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Slippage tolerance on the amounts out, in steps of 0.01 (default: config, else 0.1)"
+    )]
+    pub slippage: Option<Slippage>,
+    #[command(flatten)]
+    pub key: SolanaKeyOptions,
+    #[command(flatten)]
+    pub rpc: SolanaRpcOptions,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct LpShowArgs {
+    #[arg(value_name = "POOL", help = "Raydium AMM v4 pool id")]
+    pub pool: String,
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Wallet to show instead of the resolved key"
+    )]
+    pub address: Option<String>,
     #[command(flatten)]
     pub key: SolanaKeyOptions,
     #[command(flatten)]
@@ -422,6 +1042,18 @@ pub struct EvmKeyOptions {
     pub svpi_pass: Option<String>,
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct ConfirmOptions {
+    #[arg(
+        long,
+        help = "Show the review and simulation without sending",
+        conflicts_with = "yes"
+    )]
+    pub dry_run: bool,
+    #[arg(long, help = "Send without asking for confirmation")]
+    pub yes: bool,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct SolanaRpcOptions {
     #[arg(
@@ -439,6 +1071,18 @@ pub struct SolanaRpcOptions {
         help = "Solana commitment (default confirmed)"
     )]
     pub commitment: Option<CommitmentArg>,
+    #[arg(
+        long,
+        value_name = "MICRO_LAMPORTS|auto",
+        help = "Priority fee per compute unit in micro-lamports, or auto from recent fees"
+    )]
+    pub priority_fee: Option<String>,
+    #[arg(
+        long,
+        value_name = "UNITS",
+        help = "Compute unit limit (default: simulated usage + 10% when a priority fee is set)"
+    )]
+    pub compute_units: Option<u32>,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -452,36 +1096,85 @@ pub struct EvmTxOptions {
     pub network: Option<EvmNetworkArg>,
     #[arg(long, value_name = "URL", help = "Custom EVM RPC URL")]
     pub rpc: Option<String>,
-    #[arg(long, value_name = "GWEI", help = "Gas price in gwei")]
+    #[arg(
+        long,
+        value_name = "GWEI",
+        help = "Legacy gas price in gwei (disables EIP-1559)"
+    )]
     pub gas_price: Option<String>,
+    #[arg(
+        long,
+        value_name = "GWEI",
+        help = "EIP-1559 max fee per gas in gwei (default from eth_feeHistory)"
+    )]
+    pub max_fee: Option<String>,
+    #[arg(
+        long,
+        value_name = "GWEI",
+        help = "EIP-1559 max priority fee per gas in gwei (default from eth_feeHistory)"
+    )]
+    pub priority_fee: Option<String>,
     #[arg(long, value_name = "NUMBER", help = "Gas limit override")]
     pub gas_limit: Option<u64>,
 }
 
-#[derive(ValueEnum, Debug, Clone, Copy)]
+/// A swap leg: `sol`, `usdc` or any SPL Token mint address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SwapToken {
-    #[value(name = "sol")]
     Sol,
-    #[value(name = "usdc")]
     Usdc,
+    Mint(Pubkey),
 }
 
 impl SwapToken {
-    pub fn other(self) -> Self {
+    pub fn mint(self) -> Pubkey {
+        match self {
+            SwapToken::Sol => spl_token::native_mint::id(),
+            SwapToken::Usdc => Pubkey::from_str_const(USDC_MINT),
+            SwapToken::Mint(mint) => mint,
+        }
+    }
+
+    /// Counterpart used when neither `--with` nor `--pool` picks one.
+    pub fn default_counter(self) -> Self {
         match self {
-            SwapToken::Sol => SwapToken::Usdc,
             SwapToken::Usdc => SwapToken::Sol,
+            _ => SwapToken::Usdc,
         }
     }
+}
+
+impl FromStr for SwapToken {
+    type Err = String;
 
-    pub fn symbol(self) -> &'static str {
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "sol" => Ok(SwapToken::Sol),
+            "usdc" => Ok(SwapToken::Usdc),
+            _ => Pubkey::from_str(value.trim())
+                .map(SwapToken::Mint)
+                .map_err(|_| format!("expected sol, usdc or a mint address, got '{value}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for SwapToken {
+    /// The form `from_str` accepts back.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SwapToken::Sol => "SOL",
-            SwapToken::Usdc => "USDC",
+            SwapToken::Sol => f.write_str("sol"),
+            SwapToken::Usdc => f.write_str("usdc"),
+            SwapToken::Mint(mint) => write!(f, "{mint}"),
         }
     }
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
 #[derive(ValueEnum, Debug, Clone, Copy)]
 pub enum CommitmentArg {
     Processed,
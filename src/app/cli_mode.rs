@@ -6,14 +6,17 @@ use clap::{CommandFactory, Parser};
 use crate::{
     app::{
         cli::{Cli, Command},
-        commands,
+        commands, output,
     },
     config::app_config::WmgrConfig,
+    error::ErrorCode,
     utils::terminal::ReplReader,
 };
 
 pub async fn run_repl(cfg: &mut WmgrConfig) -> Result<()> {
     let mut reader = ReplReader::new();
+    // `wmgr --output json` applies to every REPL line unless the line overrides it.
+    let default_format = output::format();
 
     loop {
         let line = match reader.read_line("wmgr> ") {
@@ -23,7 +26,7 @@ pub async fn run_repl(cfg: &mut WmgrConfig) -> Result<()> {
                 break;
             }
             Err(err) => {
-                eprintln!("device_error: Failed to read command: {err}");
+                output::print_error(ErrorCode::Io, &format!("Failed to read command: {err}"));
                 break;
             }
         };
@@ -56,7 +59,7 @@ pub async fn run_repl(cfg: &mut WmgrConfig) -> Result<()> {
         reader.add_history_entry(input);
 
         let Some(tokens) = shlex::split(input) else {
-            eprintln!("invalid_argument: Failed to parse command line");
+            output::print_error(ErrorCode::InvalidArgument, "Failed to parse command line");
             continue;
         };
 
@@ -80,12 +83,13 @@ pub async fn run_repl(cfg: &mut WmgrConfig) -> Result<()> {
         };
 
         if matches!(cmd, Command::Repl) {
-            eprintln!("invalid_argument: repl is already running");
+            output::print_error(ErrorCode::InvalidArgument, "repl is already running");
             continue;
         }
 
+        output::set_format(parsed.output.unwrap_or(default_format));
         if let Err(err) = commands::run_command(cmd, cfg).await {
-            eprintln!("Error: {err}");
+            output::report_error(&err);
         }
     }
 
@@ -1,8 +1,9 @@
 use std::str::FromStr;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use ethers::signers::Signer as EvmSigner;
 use ethers::types::Address;
+use serde_json::{json, Value};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signer::Signer as SolanaSigner;
 
@@ -11,12 +12,18 @@ use crate::app::commands::commitment_from_arg;
 use crate::app::defaults::{
     apply_evm_key_defaults, apply_solana_key_defaults, resolve_balance_solana_defaults,
 };
+use crate::app::output::{emit, say};
 use crate::config::app_config::WmgrConfig;
 use crate::config::clusters::{get_cluster_config, get_usdc_mint_for_cluster};
-use crate::infra::evm::{create_evm_provider, get_native_balance};
+use crate::config::tokens::get_token_symbol;
+use crate::core::amount::format_integer_amount;
+use crate::error::wmgr_error;
+use crate::infra::evm::{create_evm_provider, get_erc20_balance, get_native_balance};
 use crate::infra::keys::evm::resolve_evm_wallet;
 use crate::infra::keys::solana::resolve_solana_keypair;
-use crate::infra::solana::{create_rpc_client, get_balances};
+use crate::infra::solana::{
+    create_rpc_client, get_balances, get_token_holdings, token_program_label, TokenHolding,
+};
 
 pub async fn handle_balance(args: BalanceArgs, cfg: &WmgrConfig) -> Result<()> {
     let BalanceArgs {
@@ -25,13 +32,16 @@ pub async fn handle_balance(args: BalanceArgs, cfg: &WmgrConfig) -> Result<()> {
         rpc,
         cluster,
         commitment,
+        tokens,
+        token,
         key,
     } = args;
 
     match network {
         Some(network) => {
             let owner = if let Some(address) = address.as_deref() {
-                Address::from_str(address).map_err(|err| anyhow!("Invalid address: {err}"))?
+                Address::from_str(address)
+                    .map_err(|err| wmgr_error!(InvalidArgument, "Invalid address: {err}"))?
             } else {
                 let key = apply_evm_key_defaults(key.into_evm(), cfg);
                 let wallet = resolve_evm_wallet(&key)?;
@@ -40,18 +50,47 @@ pub async fn handle_balance(args: BalanceArgs, cfg: &WmgrConfig) -> Result<()> {
 
             let rpc = rpc.or_else(|| cfg.evm_rpc.clone());
             let (provider, evm_cfg) = create_evm_provider(network.as_str(), rpc.as_deref())?;
-            println!("Using network: {}, RPC: {}", evm_cfg.name, evm_cfg.rpc_url);
+            say!("Using network: {}, RPC: {}", evm_cfg.name, evm_cfg.rpc_url);
+
+            let (raw, formatted) = get_native_balance(provider.clone(), owner).await?;
+            say!("Address: {}", owner);
+            say!("{}: {}", evm_cfg.native_symbol, formatted);
 
-            let (_raw, formatted) = get_native_balance(provider, owner).await?;
-            println!("Address: {}", owner);
-            println!("Native: {}", formatted);
+            let mut token_docs = Vec::new();
+            for token in &token {
+                let balance = get_erc20_balance(provider.clone(), token, owner)
+                    .await
+                    .map_err(|err| {
+                        wmgr_error!(Rpc, "Failed to read ERC-20 balance for {token}: {err}")
+                    })?;
+                let symbol = balance.symbol.as_deref().unwrap_or("?");
+                say!("{}: {} ({:?})", symbol, balance.formatted, balance.token);
+                token_docs.push(json!({
+                    "token": format!("{:?}", balance.token),
+                    "symbol": balance.symbol,
+                    "amount": balance.formatted,
+                }));
+            }
+            emit(json!({
+                "chain": "evm",
+                "network": evm_cfg.name,
+                "chain_id": evm_cfg.chain_id,
+                "address": format!("{owner:?}"),
+                "native": {
+                    "symbol": evm_cfg.native_symbol,
+                    "raw": raw.to_string(),
+                    "amount": formatted,
+                },
+                "tokens": token_docs,
+            }));
             Ok(())
         }
         None => {
             let sol = resolve_balance_solana_defaults(cluster, rpc, commitment, cfg);
 
             let owner = if let Some(address) = address.as_deref() {
-                Pubkey::from_str(address).map_err(|err| anyhow!("Invalid address: {err}"))?
+                Pubkey::from_str(address)
+                    .map_err(|err| wmgr_error!(InvalidArgument, "Invalid address: {err}"))?
             } else {
                 let key = apply_solana_key_defaults(key.into_solana(), cfg);
                 let keypair = resolve_solana_keypair(&key)?;
@@ -59,19 +98,130 @@ pub async fn handle_balance(args: BalanceArgs, cfg: &WmgrConfig) -> Result<()> {
             };
 
             let cluster = get_cluster_config(&sol.cluster, sol.rpc.as_deref())?;
-            println!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+            say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
             let commitment = commitment_from_arg(sol.commitment);
             let client = create_rpc_client(&cluster.rpc_url, commitment);
 
+            if tokens {
+                let lamports = client.get_balance(&owner).await?;
+                let holdings = get_token_holdings(&client, &owner).await?;
+                say!("Address: {owner}");
+                say!("SOL: {}", format_integer_amount(lamports as u128, 9));
+                print_token_holdings(&cluster.name, &holdings);
+                emit(json!({
+                    "chain": "solana",
+                    "cluster": cluster.name,
+                    "address": owner.to_string(),
+                    "sol": {
+                        "lamports": lamports,
+                        "amount": format_integer_amount(lamports as u128, 9),
+                    },
+                    "tokens": holdings_json(&cluster.name, &holdings),
+                }));
+                return Ok(());
+            }
+
             let mint_str = get_usdc_mint_for_cluster(&cluster.name)?;
-            let mint =
-                Pubkey::from_str(mint_str).map_err(|err| anyhow!("Invalid mint address: {err}"))?;
+            let mint = Pubkey::from_str(mint_str)
+                .map_err(|err| wmgr_error!(Config, "Invalid mint address: {err}"))?;
 
-            let balances = get_balances(&client, &owner, &mint)?;
-            println!("Address: {}", balances.address);
-            println!("SOL: {}", balances.sol);
-            println!("USDC: {}", balances.usdc);
+            let balances = get_balances(&client, &owner, &mint).await?;
+            say!("Address: {}", balances.address);
+            say!("SOL: {}", balances.sol);
+            say!("USDC: {}", balances.usdc);
+            emit(json!({
+                "chain": "solana",
+                "cluster": cluster.name,
+                "address": balances.address,
+                "sol": {
+                    "lamports": balances.sol_lamports,
+                    "amount": balances.sol,
+                },
+                "usdc": {
+                    "mint": mint_str,
+                    "raw": balances.usdc_raw,
+                    "amount": balances.usdc,
+                    "decimals": balances.usdc_decimals,
+                },
+            }));
             Ok(())
         }
     }
 }
+
+fn holdings_json(cluster: &str, holdings: &[TokenHolding]) -> Value {
+    holdings
+        .iter()
+        .map(|holding| {
+            let mint = holding.mint.to_string();
+            let accounts: Vec<Value> = holding
+                .accounts
+                .iter()
+                .map(|account| {
+                    json!({
+                        "address": account.address.to_string(),
+                        "raw": account.amount,
+                        "amount": format_integer_amount(account.amount as u128, holding.decimals),
+                        "ata": account.is_ata,
+                        "frozen": account.frozen,
+                    })
+                })
+                .collect();
+            json!({
+                "mint": mint,
+                "symbol": get_token_symbol(cluster, &mint),
+                "program": token_program_label(&holding.program_id),
+                "decimals": holding.decimals,
+                "raw": holding.total.to_string(),
+                "amount": format_integer_amount(holding.total, holding.decimals),
+                "accounts": accounts,
+            })
+        })
+        .collect()
+}
+
+fn print_token_holdings(cluster: &str, holdings: &[TokenHolding]) {
+    if holdings.is_empty() {
+        say!("Tokens: (none)");
+        return;
+    }
+
+    say!("Tokens ({}):", holdings.len());
+    for holding in holdings {
+        let mint = holding.mint.to_string();
+        let symbol = get_token_symbol(cluster, &mint).unwrap_or("?");
+        say!(
+            "  {:<8} {:<24} {} ({})",
+            symbol,
+            format_integer_amount(holding.total, holding.decimals),
+            mint,
+            token_program_label(&holding.program_id)
+        );
+
+        let show_accounts = holding.accounts.len() > 1
+            || holding.accounts.iter().any(|acc| !acc.is_ata || acc.frozen);
+        if !show_accounts {
+            continue;
+        }
+        for account in &holding.accounts {
+            let mut flags = Vec::new();
+            if !account.is_ata {
+                flags.push("non-ATA");
+            }
+            if account.frozen {
+                flags.push("frozen");
+            }
+            let flags = if flags.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", flags.join(", "))
+            };
+            say!(
+                "    account {}: {}{}",
+                account.address,
+                format_integer_amount(account.amount as u128, holding.decimals),
+                flags
+            );
+        }
+    }
+}
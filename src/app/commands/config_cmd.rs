@@ -1,91 +1,137 @@
-use std::io::ErrorKind;
+use std::{io::ErrorKind, str::FromStr};
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use serde_json::{json, Value};
 
+use crate::error::wmgr_error;
 use crate::{
-    app::cli::{ConfigArgs, ConfigCommand},
+    app::{
+        cli::{ConfigArgs, ConfigCommand},
+        defaults::resolve_slippage,
+        output::{emit, say},
+    },
     config::app_config::{WmgrConfig, CONFIG_FILE_NAME},
+    infra::solana::PriorityFee,
 };
 
 pub fn handle_config(args: ConfigArgs, cfg: &mut WmgrConfig) -> Result<()> {
     match args.command {
         ConfigCommand::Show => {
-            println!("config_file: {CONFIG_FILE_NAME}");
-            println!();
-            println!("svpi:");
-            println!("{:15}{}", "--svpi:", cfg.is_svpi_mode());
-            println!(
-                "{:15}{}",
+            say!("config_file: {CONFIG_FILE_NAME}");
+            say!();
+            say!("svpi:");
+            say!("{:21}{}", "--svpi:", cfg.is_svpi_mode());
+            say!(
+                "{:21}{}",
                 "--svpi-name:",
                 cfg.svpi_name.as_deref().unwrap_or("(not set)")
             );
-            println!(
-                "{:15}{}",
+            say!(
+                "{:21}{}",
                 "--svpi-file:",
                 cfg.svpi_file.as_deref().unwrap_or("(not set)")
             );
-            println!(
-                "{:15}{}",
+            say!(
+                "{:21}{}",
                 "--svpi_cmd:",
                 cfg.svpi_cmd.as_deref().unwrap_or("svpi")
             );
 
-            println!();
-            println!("solana:");
-            println!(
-                "{:15}{}",
+            say!();
+            say!("solana:");
+            say!(
+                "{:21}{}",
                 "--cluster:",
                 cfg.solana_cluster.as_deref().unwrap_or("mainnet-beta")
             );
-            println!(
-                "{:15}{}",
+            say!(
+                "{:21}{}",
                 "--rpc:",
                 cfg.solana_rpc.as_deref().unwrap_or("(not set)")
             );
-            println!(
-                "{:15}{}",
+            say!(
+                "{:21}{}",
                 "--commitment:",
                 solana_commitment_label(cfg.solana_commitment)
             );
-            println!(
-                "{:15}{}",
-                "--slippage:",
-                cfg.slippage
+            say!("{:21}{}", "--slippage:", resolve_slippage(None, cfg));
+            say!(
+                "{:21}{}",
+                "--max-price-impact:",
+                cfg.max_price_impact
                     .map(|v| v.to_string())
-                    .unwrap_or_else(|| "0.1".to_string())
+                    .unwrap_or_else(|| "(not set)".to_string())
+            );
+            say!(
+                "{:21}{}",
+                "--requote-threshold:",
+                cfg.requote_threshold
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "0.5".to_string())
+            );
+            say!(
+                "{:21}{}",
+                "--priority-fee:",
+                cfg.solana_priority_fee.as_deref().unwrap_or("(not set)")
+            );
+            say!(
+                "{:21}{}",
+                "--compute-units:",
+                cfg.solana_compute_units
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "(auto)".to_string())
             );
 
-            println!();
-            println!("evm:");
-            println!(
-                "{:15}{}",
+            say!();
+            say!("evm:");
+            say!(
+                "{:21}{}",
                 "--network:",
                 cfg.evm_network.as_deref().unwrap_or("mainnet")
             );
-            println!(
-                "{:15}{}",
+            say!(
+                "{:21}{}",
                 "--rpc:",
                 cfg.evm_rpc.as_deref().unwrap_or("(not set)")
             );
-            println!(
-                "{:15}{}",
+            say!(
+                "{:21}{}",
                 "--gas-price:",
                 cfg.evm_gas_price.as_deref().unwrap_or("(not set)")
             );
-            println!(
-                "{:15}{}",
+            say!(
+                "{:21}{}",
+                "--max-fee:",
+                cfg.evm_max_fee.as_deref().unwrap_or("(auto)")
+            );
+            say!(
+                "{:21}{}",
+                "--priority-fee:",
+                cfg.evm_priority_fee.as_deref().unwrap_or("(auto)")
+            );
+            say!(
+                "{:21}{}",
                 "--gas-limit:",
                 cfg.evm_gas_limit
                     .map(|v| v.to_string())
                     .unwrap_or_else(|| "(not set)".to_string())
             );
+            emit(config_json(cfg));
             Ok(())
         }
         ConfigCommand::Set(args) => {
-            let set_solana_rpc =
-                args.cluster.is_some() || args.commitment.is_some() || args.slippage.is_some();
-            let set_evm_rpc =
-                args.network.is_some() || args.gas_price.is_some() || args.gas_limit.is_some();
+            let set_solana_rpc = args.cluster.is_some()
+                || args.commitment.is_some()
+                || args.slippage.is_some()
+                || args.max_price_impact.is_some()
+                || args.requote_threshold.is_some()
+                || args.solana_priority_fee.is_some()
+                || args.solana_compute_units.is_some();
+            let set_evm_rpc = args.network.is_some()
+                || args.gas_price.is_some()
+                || args.max_fee.is_some()
+                || args.priority_fee.is_some()
+                || args.gas_limit.is_some();
 
             if args.svpi {
                 cfg.mode = 1;
@@ -104,13 +150,46 @@ pub fn handle_config(args: ConfigArgs, cfg: &mut WmgrConfig) -> Result<()> {
                 });
             }
             if let Some(slippage) = args.slippage {
-                cfg.slippage = Some(slippage);
+                cfg.slippage_bps = Some(slippage.bps());
+            }
+            if let Some(max_price_impact) = args.max_price_impact {
+                cfg.max_price_impact =
+                    Some(checked_percent("--max-price-impact", max_price_impact)?);
+            }
+            if let Some(requote_threshold) = args.requote_threshold {
+                cfg.requote_threshold =
+                    Some(checked_percent("--requote-threshold", requote_threshold)?);
+            }
+            if let Some(priority_fee) = args.solana_priority_fee {
+                // Validate before saving so a typo is not discovered on the next send.
+                PriorityFee::from_str(&priority_fee)?;
+                cfg.solana_priority_fee = Some(priority_fee);
+            }
+            if let Some(compute_units) = args.solana_compute_units {
+                cfg.solana_compute_units = Some(compute_units);
             }
             if let Some(network) = args.network {
                 cfg.evm_network = Some(network.as_str().to_string());
             }
+            if args.gas_price.is_some() && (args.max_fee.is_some() || args.priority_fee.is_some()) {
+                return Err(wmgr_error!(
+                    InvalidArgument,
+                    "Use either --gas-price (legacy) or --max-fee/--priority-fee (EIP-1559)"
+                ));
+            }
+            // Legacy and EIP-1559 defaults are mutually exclusive; saving one clears the other.
             if let Some(gas_price) = args.gas_price {
                 cfg.evm_gas_price = Some(gas_price);
+                cfg.evm_max_fee = None;
+                cfg.evm_priority_fee = None;
+            }
+            if let Some(max_fee) = args.max_fee {
+                cfg.evm_max_fee = Some(max_fee);
+                cfg.evm_gas_price = None;
+            }
+            if let Some(priority_fee) = args.priority_fee {
+                cfg.evm_priority_fee = Some(priority_fee);
+                cfg.evm_gas_price = None;
             }
             if let Some(gas_limit) = args.gas_limit {
                 cfg.evm_gas_limit = Some(gas_limit);
@@ -136,28 +215,79 @@ pub fn handle_config(args: ConfigArgs, cfg: &mut WmgrConfig) -> Result<()> {
             }
 
             cfg.save_to_cwd()
-                .map_err(|err| anyhow!("Failed to write {CONFIG_FILE_NAME}: {err}"))?;
+                .map_err(|err| wmgr_error!(Io, "Failed to write {CONFIG_FILE_NAME}: {err}"))?;
 
-            println!("OK: saved {CONFIG_FILE_NAME}");
+            say!("OK: saved {CONFIG_FILE_NAME}");
+            emit(
+                json!({ "action": "saved", "file": CONFIG_FILE_NAME, "config": config_json(cfg) }),
+            );
             Ok(())
         }
         ConfigCommand::Reset => {
             *cfg = WmgrConfig::default();
 
-            let path = WmgrConfig::path_in_cwd()
-                .map_err(|err| anyhow!("Failed to resolve {CONFIG_FILE_NAME} path: {err}"))?;
+            let path = WmgrConfig::path_in_cwd().map_err(|err| {
+                wmgr_error!(Io, "Failed to resolve {CONFIG_FILE_NAME} path: {err}")
+            })?;
             match std::fs::remove_file(&path) {
                 Ok(()) => {}
                 Err(err) if err.kind() == ErrorKind::NotFound => {}
-                Err(err) => return Err(anyhow!("Failed to remove {CONFIG_FILE_NAME}: {err}")),
+                Err(err) => {
+                    return Err(wmgr_error!(
+                        Io,
+                        "Failed to remove {CONFIG_FILE_NAME}: {err}"
+                    ))
+                }
             }
 
-            println!("OK: reset {CONFIG_FILE_NAME}");
+            say!("OK: reset {CONFIG_FILE_NAME}");
+            emit(json!({ "action": "reset", "file": CONFIG_FILE_NAME }));
             Ok(())
         }
     }
 }
 
+/// Saved values only; unset fields are `null` rather than the built-in defaults shown as text.
+fn config_json(cfg: &WmgrConfig) -> Value {
+    json!({
+        "svpi": {
+            "enabled": cfg.is_svpi_mode(),
+            "name": cfg.svpi_name,
+            "file": cfg.svpi_file,
+            "cmd": cfg.svpi_cmd,
+        },
+        "solana": {
+            "cluster": cfg.solana_cluster,
+            "rpc": cfg.solana_rpc,
+            "commitment": cfg.solana_commitment.map(|v| solana_commitment_label(Some(v))),
+            "slippage": cfg.slippage_bps.map(|bps| bps as f64 / 100.0),
+            "slippage_bps": cfg.slippage_bps,
+            "max_price_impact": cfg.max_price_impact,
+            "requote_threshold": cfg.requote_threshold,
+            "priority_fee": cfg.solana_priority_fee,
+            "compute_units": cfg.solana_compute_units,
+        },
+        "evm": {
+            "network": cfg.evm_network,
+            "rpc": cfg.evm_rpc,
+            "gas_price": cfg.evm_gas_price,
+            "max_fee": cfg.evm_max_fee,
+            "priority_fee": cfg.evm_priority_fee,
+            "gas_limit": cfg.evm_gas_limit,
+        },
+    })
+}
+
+fn checked_percent(flag: &str, value: f64) -> Result<f64> {
+    if !(0.0..=100.0).contains(&value) {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "{flag} must be between 0 and 100"
+        ));
+    }
+    Ok(value)
+}
+
 fn solana_commitment_label(value: Option<u8>) -> String {
     match value {
         None => "confirmed".to_string(),
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signature::{Keypair, Signature};
+use tokio::time::sleep;
+
+use crate::app::cli::{
+    DaemonArgs, SolanaKeyOptions, SolanaRpcOptions, SwapToken, TradeArgs, VenueArg,
+};
+use crate::app::commands::swap::{execute_trade, PendingTrade, Review, TradeOutcome, TradeSide};
+use crate::app::commands::{commitment_from_arg, dca, order};
+use crate::app::defaults::{apply_solana_key_defaults, resolve_solana_rpc_defaults};
+use crate::app::output::{emit, say};
+use crate::config::app_config::WmgrConfig;
+use crate::config::clusters::get_cluster_config;
+use crate::error::{error_code, wmgr_error, ErrorCode};
+use crate::infra::keys::solana::resolve_solana_keypair;
+use crate::infra::solana::{signature_state, SignatureState};
+
+/// Signer and RPC client for trades sent without a prompt (`order watch`, `dca run`, `daemon`).
+/// The key is resolved once, so SVPI asks for its password only at start.
+pub(super) struct Session {
+    pub keypair: Keypair,
+    pub rpc: RpcClient,
+    pub cluster: String,
+    key: SolanaKeyOptions,
+    rpc_options: SolanaRpcOptions,
+}
+
+impl Session {
+    pub fn connect(key: SolanaKeyOptions, rpc: SolanaRpcOptions, cfg: &WmgrConfig) -> Result<Self> {
+        let keypair = resolve_solana_keypair(&apply_solana_key_defaults(key.clone(), cfg))?;
+        let resolved = resolve_solana_rpc_defaults(rpc.clone(), cfg);
+        let cluster = get_cluster_config(&resolved.cluster, resolved.rpc.as_deref())?;
+        say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+        Ok(Self {
+            keypair,
+            rpc: RpcClient::new_with_commitment(
+                cluster.rpc_url.clone(),
+                commitment_from_arg(resolved.commitment),
+            ),
+            cluster: cluster.name,
+            key,
+            rpc_options: rpc,
+        })
+    }
+
+    /// Trade arguments with this session's key and RPC options and no guards set.
+    pub fn trade_args(
+        &self,
+        amount: String,
+        token: SwapToken,
+        with: Option<SwapToken>,
+        pool: Option<String>,
+    ) -> TradeArgs {
+        TradeArgs {
+            amount,
+            token,
+            with,
+            pool,
+            venue: VenueArg::Raydium,
+            route: false,
+            slippage: None,
+            max_price_impact: None,
+            requote_threshold: None,
+            use_wsol: false,
+            keep_wrapped: false,
+            key: self.key.clone(),
+            rpc: self.rpc_options.clone(),
+        }
+    }
+
+    /// Runs a trade without prompting, journaled as `journal_side`. `record` must persist the
+    /// pending signature: the trade is sent only once it has.
+    pub async fn trade(
+        &self,
+        (side, journal_side): (TradeSide, TradeSide),
+        args: TradeArgs,
+        cfg: &WmgrConfig,
+        record: &dyn Fn(&PendingTrade) -> Result<()>,
+    ) -> Result<TradeOutcome> {
+        execute_trade(
+            side,
+            journal_side,
+            args,
+            &self.keypair,
+            cfg,
+            Review::Unattended,
+            record,
+        )
+        .await
+    }
+
+    /// Where a transaction recorded before its broadcast stands now.
+    pub async fn signature_state(
+        &self,
+        signature: &str,
+        last_valid_block_height: u64,
+    ) -> Result<SignatureState> {
+        let signature = Signature::from_str(signature)
+            .map_err(|err| wmgr_error!(InvalidArgument, "Invalid signature {signature}: {err}"))?;
+        signature_state(&self.rpc, &signature, last_valid_block_height).await
+    }
+}
+
+/// Failures worth retrying on the next pass: nothing was sent, or it expired unconfirmed. RPC
+/// errors after the broadcast are `tx_unconfirmed` instead, and must be looked up by signature.
+pub(super) fn is_transient(err: &anyhow::Error) -> bool {
+    matches!(error_code(err), ErrorCode::Rpc | ErrorCode::TxExpired)
+}
+
+pub(super) fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Runs open orders and due DCA fills until interrupted.
+pub async fn handle_daemon(args: DaemonArgs, cfg: &WmgrConfig) -> Result<()> {
+    let session = Session::connect(args.key, args.rpc, cfg)?;
+    let mut pools = HashMap::new();
+    say!(
+        "Watching orders and DCA schedules every {}s; Ctrl-C to stop.",
+        args.interval.max(1)
+    );
+    loop {
+        order::watch_pass(&session, &mut pools, cfg).await?;
+        for fill in dca::run_pass(&session, cfg).await? {
+            emit(fill);
+        }
+        sleep(Duration::from_secs(args.interval.max(1))).await;
+    }
+}
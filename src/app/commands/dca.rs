@@ -0,0 +1,571 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::app::cli::{DcaArgs, DcaCommand, DcaCreateArgs, DcaIdArgs, DcaListArgs, SwapToken};
+use crate::app::cli::{DcaRunArgs, TradeArgs};
+use crate::app::commands::daemon::{is_transient, now_unix, Session};
+use crate::app::commands::swap::{PendingTrade, TradeSide};
+use crate::app::output::{emit, say};
+use crate::config::app_config::WmgrConfig;
+use crate::config::dca::{DcaBook, DcaFill, DcaSchedule, DcaStatus, FillOutcome, DCA_FILE_NAME};
+use crate::core::amount::{format_integer_amount, parse_amount_to_u128};
+use crate::core::schedule::{due_slots, parse_interval};
+use crate::core::slippage::Slippage;
+use crate::error::{error_code, wmgr_error, ErrorCode};
+use crate::infra::solana::SignatureState;
+
+pub async fn handle_dca(args: DcaArgs, cfg: &WmgrConfig) -> Result<()> {
+    match args.command {
+        DcaCommand::Create(args) => handle_create(args),
+        DcaCommand::List(args) => handle_list(args),
+        DcaCommand::Show(args) => handle_show(args),
+        DcaCommand::Cancel(args) => handle_cancel(args),
+        DcaCommand::Run(args) => handle_run(args, cfg).await,
+    }
+}
+
+fn handle_create(args: DcaCreateArgs) -> Result<()> {
+    let DcaCreateArgs {
+        buy,
+        spend,
+        every,
+        count,
+        pool,
+        slippage,
+        max_price_impact,
+    } = args;
+    let [spend_amount, spend_token] = spend.as_slice() else {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "--spend takes an amount and a token, e.g. --spend 50 usdc"
+        ));
+    };
+    let spend_token: SwapToken = parse_token(spend_token)?;
+    if spend_token.mint() == buy.mint() {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "--buy and --spend must be different tokens"
+        ));
+    }
+    // Decimals are unknown until the pool is loaded; this only rejects malformed amounts.
+    if parse_amount_to_u128(spend_amount, 18)? == 0 {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Amount must be greater than zero"
+        ));
+    }
+    let interval_secs = parse_interval(&every)?;
+    if count == 0 {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "--count must be greater than zero"
+        ));
+    }
+    if let Some(pool) = &pool {
+        Pubkey::from_str(pool)
+            .map_err(|err| wmgr_error!(InvalidArgument, "Invalid pool id: {err}"))?;
+    }
+
+    let now = now_unix();
+    let mut book = load_book()?;
+    let id = book.add(DcaSchedule {
+        id: 0,
+        buy_token: buy.to_string(),
+        spend_token: spend_token.to_string(),
+        spend_amount: spend_amount.trim().to_string(),
+        pool,
+        every: every.trim().to_string(),
+        interval_secs,
+        count,
+        slippage_bps: slippage.map(|slippage| slippage.bps()),
+        max_price_impact,
+        created_at: now,
+        next_at: now,
+        status: DcaStatus::Active,
+        spend_decimals: None,
+        buy_decimals: None,
+        fills: Vec::new(),
+    });
+    save_book(&book)?;
+
+    let schedule = book.get_mut(id).expect("schedule just added");
+    say!("OK: DCA #{id} saved: {}", describe(schedule));
+    say!("The first fill is due now: run `wmgr dca run` or keep `wmgr daemon` running.");
+    emit(schedule_json(schedule, false));
+    Ok(())
+}
+
+fn handle_list(args: DcaListArgs) -> Result<()> {
+    let book = load_book()?;
+    let schedules: Vec<&DcaSchedule> = book
+        .schedules
+        .iter()
+        .filter(|schedule| args.all || schedule.status == DcaStatus::Active)
+        .collect();
+    if schedules.is_empty() {
+        say!("No {}DCA schedules.", if args.all { "" } else { "active " });
+    }
+    for schedule in &schedules {
+        say!(
+            "#{:<4} {:<10} {}, {}/{} slots, average {}",
+            schedule.id,
+            schedule.status.label(),
+            describe(schedule),
+            schedule.fills.len(),
+            schedule.count,
+            average_label(schedule)
+        );
+    }
+    emit(json!({
+        "schedules": schedules
+            .iter()
+            .map(|schedule| schedule_json(schedule, false))
+            .collect::<Vec<_>>(),
+    }));
+    Ok(())
+}
+
+fn handle_show(args: DcaIdArgs) -> Result<()> {
+    let mut book = load_book()?;
+    let schedule = find(&mut book, args.id)?;
+    say!(
+        "#{} {}: {}",
+        schedule.id,
+        schedule.status.label(),
+        describe(schedule)
+    );
+    let (spent, received) = schedule.totals();
+    say!(
+        "Slots: {}/{}, spent: {} {}, received: {} {}, average: {}",
+        schedule.fills.len(),
+        schedule.count,
+        format_raw(spent, schedule.spend_decimals),
+        schedule.spend_token,
+        format_raw(received, schedule.buy_decimals),
+        schedule.buy_token,
+        average_label(schedule)
+    );
+    if schedule.status == DcaStatus::Active {
+        say!("Next fill: {} (Unix time)", schedule.next_at);
+    }
+    for fill in &schedule.fills {
+        match fill.outcome {
+            FillOutcome::Filled => say!(
+                "{:<12} filled   {} {} for {} {} ({})",
+                fill.at,
+                format_raw(fill.spent as u128, schedule.spend_decimals),
+                schedule.spend_token,
+                format_raw(fill.received as u128, schedule.buy_decimals),
+                schedule.buy_token,
+                fill.signature.as_deref().unwrap_or("no signature")
+            ),
+            FillOutcome::Skipped => say!(
+                "{:<12} skipped  {}",
+                fill.at,
+                fill.reason.as_deref().unwrap_or("")
+            ),
+            FillOutcome::Pending { .. } => say!(
+                "{:<12} pending  {}",
+                fill.at,
+                fill.signature.as_deref().unwrap_or("no signature")
+            ),
+        }
+    }
+    emit(schedule_json(schedule, true));
+    Ok(())
+}
+
+fn handle_cancel(args: DcaIdArgs) -> Result<()> {
+    let mut book = load_book()?;
+    let schedule = find(&mut book, args.id)?;
+    if schedule.status != DcaStatus::Active {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "DCA #{} is already {}",
+            args.id,
+            schedule.status.label()
+        ));
+    }
+    schedule.status = DcaStatus::Cancelled;
+    let doc = schedule_json(schedule, false);
+    save_book(&book)?;
+    say!("OK: DCA #{} cancelled", args.id);
+    emit(doc);
+    Ok(())
+}
+
+async fn handle_run(args: DcaRunArgs, cfg: &WmgrConfig) -> Result<()> {
+    let session = Session::connect(args.key, args.rpc, cfg)?;
+    let fills = run_pass(&session, cfg).await?;
+    if fills.is_empty() {
+        say!("No DCA fills are due.");
+    }
+    emit(json!({ "fills": fills }));
+    Ok(())
+}
+
+/// Settles fills sent on an earlier pass, then runs every active schedule whose next slot is due,
+/// and returns the recorded slots as JSON.
+pub(super) async fn run_pass(session: &Session, cfg: &WmgrConfig) -> Result<Vec<Value>> {
+    let mut recorded = settle_pending(session).await?;
+    let now = now_unix();
+    let due: Vec<DcaSchedule> = load_book()?
+        .schedules
+        .into_iter()
+        .filter(|schedule| schedule.status == DcaStatus::Active && schedule.next_at <= now)
+        .collect();
+    for schedule in due {
+        recorded.extend(run_schedule(session, &schedule, now, cfg).await?);
+    }
+    Ok(recorded)
+}
+
+/// Looks up the transaction of every fill left `pending` by a send that was cut off before it
+/// confirmed. A landed one becomes `filled` and a failed one `skipped`; one that expired unseen
+/// is dropped so its slot runs again. The slot is never bought twice while the first
+/// transaction can still land.
+async fn settle_pending(session: &Session) -> Result<Vec<Value>> {
+    let pending: Vec<(u32, DcaFill)> = load_book()?
+        .schedules
+        .iter()
+        .filter_map(|schedule| Some((schedule.id, schedule.pending_fill()?.clone())))
+        .collect();
+    let mut recorded = Vec::new();
+    for (id, fill) in pending {
+        let (
+            FillOutcome::Pending {
+                last_valid_block_height,
+            },
+            Some(signature),
+        ) = (fill.outcome, fill.signature.as_deref())
+        else {
+            continue;
+        };
+        let state = match session
+            .signature_state(signature, last_valid_block_height)
+            .await
+        {
+            Ok(state) => state,
+            Err(err) => {
+                say!("DCA #{id}: status check of {signature} failed: {err}");
+                continue;
+            }
+        };
+        let mut book = load_book()?;
+        let stored = find(&mut book, id)?;
+        if stored.pending_fill().map(|pending| pending.at) != Some(fill.at) {
+            continue;
+        }
+        let last = stored.fills.len() - 1;
+        match state {
+            SignatureState::Pending => continue,
+            SignatureState::Landed { .. } => {
+                stored.fills[last].outcome = FillOutcome::Filled;
+                say!("DCA #{id}: filled ({signature}).");
+            }
+            SignatureState::Failed { slot, err } => {
+                stored.fills[last] = DcaFill {
+                    signature: fill.signature.clone(),
+                    ..skipped(
+                        fill.at,
+                        format!("Transaction failed on chain in slot {slot}: {err}"),
+                    )
+                };
+                say!("DCA #{id}: slot skipped, failed on chain: {err}");
+            }
+            SignatureState::Expired => {
+                stored.fills.pop();
+                if stored.status == DcaStatus::Completed {
+                    stored.status = DcaStatus::Active;
+                }
+                if stored.status == DcaStatus::Active {
+                    stored.next_at = fill.at;
+                }
+                say!("DCA #{id}: {signature} expired without landing; the slot runs again.");
+            }
+        }
+        if let Some(fill) = stored.fills.get(last) {
+            let mut doc = fill_json(stored, fill);
+            doc["schedule"] = json!(stored.id);
+            recorded.push(doc);
+        }
+        save_book(&book)?;
+    }
+    Ok(recorded)
+}
+
+/// Runs the latest due slot of `schedule`. Slots that came due while nothing was running are
+/// recorded as skipped rather than bought late. The fill is saved as `pending` with its signature
+/// before the broadcast. A fill that fails for a transient reason before it (RPC) or expires is
+/// retried on the next pass, and one whose outcome is unknown stays `pending` until the next pass
+/// looks it up; any other failure, such as the price impact guard or a simulation that would
+/// break the slippage bound, skips the slot with the reason.
+async fn run_schedule(
+    session: &Session,
+    schedule: &DcaSchedule,
+    now: u64,
+    cfg: &WmgrConfig,
+) -> Result<Vec<Value>> {
+    let (due, next_at) = due_slots(schedule.next_at, schedule.interval_secs, now);
+    let remaining = schedule.remaining() as u64;
+    let missed = (due.saturating_sub(1)).min(remaining);
+    let current_slot = next_at.saturating_sub(schedule.interval_secs);
+    let first_new = schedule.fills.len();
+
+    let result = if remaining > missed {
+        // Cancelled since the pass started.
+        if find(&mut load_book()?, schedule.id)?.status != DcaStatus::Active {
+            return Ok(Vec::new());
+        }
+        say!("DCA #{}: {}", schedule.id, describe(schedule));
+        let trade = TradeArgs {
+            slippage: schedule.slippage_bps.map(Slippage::from_bps).transpose()?,
+            max_price_impact: schedule.max_price_impact,
+            ..session.trade_args(
+                schedule.spend_amount.clone(),
+                parse_token(&schedule.spend_token)?,
+                Some(parse_token(&schedule.buy_token)?),
+                schedule.pool.clone(),
+            )
+        };
+        let record = |pending: &PendingTrade| {
+            let mut book = load_book()?;
+            let stored = find(&mut book, schedule.id)?;
+            if stored.status != DcaStatus::Active || stored.fills.len() != first_new {
+                return Err(wmgr_error!(
+                    InvalidArgument,
+                    "DCA #{} changed while its fill was prepared",
+                    schedule.id
+                ));
+            }
+            record_missed(stored, schedule, missed, next_at);
+            stored.spend_decimals = Some(pending.decimals_in);
+            stored.buy_decimals = Some(pending.decimals_out);
+            stored.fills.push(DcaFill {
+                at: current_slot,
+                outcome: FillOutcome::Pending {
+                    last_valid_block_height: pending.last_valid_block_height,
+                },
+                spent: pending.input,
+                received: pending.output,
+                signature: Some(pending.signature.clone()),
+                reason: None,
+            });
+            save_book(&book)
+        };
+        // Spending a fixed amount is a sell, but the schedule is buying `buy_token`.
+        let sides = (TradeSide::Sell, TradeSide::Buy);
+        Some(session.trade(sides, trade, cfg, &record).await)
+    } else {
+        None
+    };
+
+    let mut book = load_book()?;
+    let stored = find(&mut book, schedule.id)?;
+    // The fill was saved as pending before it was sent, together with the missed slots.
+    let sent = stored
+        .pending_fill()
+        .is_some_and(|fill| fill.at == current_slot)
+        && stored.fills.len() > first_new;
+    if !sent {
+        if stored.status != DcaStatus::Active {
+            return Ok(Vec::new());
+        }
+        record_missed(stored, schedule, missed, next_at);
+    }
+    match result {
+        Some(Ok(outcome)) => {
+            stored.spend_decimals = Some(outcome.decimals_in);
+            stored.buy_decimals = Some(outcome.decimals_out);
+            settle_slot(
+                stored,
+                sent,
+                DcaFill {
+                    at: current_slot,
+                    outcome: FillOutcome::Filled,
+                    spent: outcome.input,
+                    received: outcome.output,
+                    signature: Some(outcome.signature),
+                    reason: None,
+                },
+            );
+            say!("DCA #{}: filled.", schedule.id);
+        }
+        Some(Err(err)) if error_code(&err) == ErrorCode::TxUnconfirmed => {
+            say!(
+                "DCA #{}: sent but not confirmed; the next pass checks its signature: {err}",
+                schedule.id
+            );
+        }
+        Some(Err(err)) if is_transient(&err) => {
+            if sent {
+                stored.fills.pop();
+            }
+            if stored.status == DcaStatus::Active {
+                stored.next_at = current_slot;
+            }
+            say!("DCA #{}: not sent, will retry: {err}", schedule.id);
+        }
+        Some(Err(err)) => {
+            settle_slot(stored, sent, skipped(current_slot, err.to_string()));
+            say!("DCA #{}: slot skipped: {err}", schedule.id);
+        }
+        None => {}
+    }
+    if stored.status == DcaStatus::Active && stored.remaining() == 0 {
+        stored.status = DcaStatus::Completed;
+        say!(
+            "DCA #{} completed, average {}.",
+            schedule.id,
+            average_label(stored)
+        );
+    }
+    let recorded = stored.fills[first_new..]
+        .iter()
+        .map(|fill| {
+            let mut doc = fill_json(stored, fill);
+            doc["schedule"] = json!(stored.id);
+            doc
+        })
+        .collect();
+    save_book(&book)?;
+    Ok(recorded)
+}
+
+/// Records the slots before the current one as missed and moves the schedule past it.
+fn record_missed(stored: &mut DcaSchedule, schedule: &DcaSchedule, missed: u64, next_at: u64) {
+    for slot in 0..missed {
+        stored.fills.push(skipped(
+            schedule.next_at + slot * schedule.interval_secs,
+            "missed: nothing was running when it came due".to_string(),
+        ));
+    }
+    stored.next_at = next_at;
+}
+
+/// Replaces the slot's pending fill once its outcome is known, keeping its signature, or appends
+/// it when nothing was sent.
+fn settle_slot(stored: &mut DcaSchedule, sent: bool, fill: DcaFill) {
+    match stored.fills.last_mut() {
+        Some(last) if sent => {
+            *last = DcaFill {
+                signature: fill.signature.or(last.signature.take()),
+                ..fill
+            }
+        }
+        _ => stored.fills.push(fill),
+    }
+}
+
+fn skipped(at: u64, reason: String) -> DcaFill {
+    DcaFill {
+        at,
+        outcome: FillOutcome::Skipped,
+        spent: 0,
+        received: 0,
+        signature: None,
+        reason: Some(reason),
+    }
+}
+
+fn find(book: &mut DcaBook, id: u32) -> Result<&mut DcaSchedule> {
+    book.get_mut(id)
+        .ok_or_else(|| wmgr_error!(InvalidArgument, "No DCA schedule #{id}"))
+}
+
+fn load_book() -> Result<DcaBook> {
+    DcaBook::load_from_cwd().map_err(|err| wmgr_error!(Io, "Failed to read {DCA_FILE_NAME}: {err}"))
+}
+
+fn save_book(book: &DcaBook) -> Result<()> {
+    book.save_to_cwd()
+        .map_err(|err| wmgr_error!(Io, "Failed to write {DCA_FILE_NAME}: {err}"))
+}
+
+fn parse_token(value: &str) -> Result<SwapToken> {
+    SwapToken::from_str(value).map_err(|err| wmgr_error!(InvalidArgument, "{err}"))
+}
+
+/// Raw amounts are shown once the first fill has taught us the decimals.
+fn format_raw(amount: u128, decimals: Option<u8>) -> String {
+    match decimals {
+        Some(decimals) => format_integer_amount(amount, decimals),
+        None => amount.to_string(),
+    }
+}
+
+/// `buy sol with 50 usdc every 1d`
+fn describe(schedule: &DcaSchedule) -> String {
+    format!(
+        "buy {} with {} {} every {}",
+        schedule.buy_token, schedule.spend_amount, schedule.spend_token, schedule.every
+    )
+}
+
+fn average_label(schedule: &DcaSchedule) -> String {
+    schedule
+        .average_price()
+        .map(|price| {
+            format!(
+                "{:.8} {} per {}",
+                price.to_f64(),
+                schedule.spend_token,
+                schedule.buy_token
+            )
+        })
+        .unwrap_or_else(|| "n/a".to_string())
+}
+
+fn fill_json(schedule: &DcaSchedule, fill: &DcaFill) -> Value {
+    json!({
+        "at": fill.at,
+        "outcome": match fill.outcome {
+            FillOutcome::Filled => "filled",
+            FillOutcome::Skipped => "skipped",
+            FillOutcome::Pending { .. } => "pending",
+        },
+        "spent": format_raw(fill.spent as u128, schedule.spend_decimals),
+        "received": format_raw(fill.received as u128, schedule.buy_decimals),
+        "signature": fill.signature,
+        "reason": fill.reason,
+    })
+}
+
+fn schedule_json(schedule: &DcaSchedule, with_fills: bool) -> Value {
+    let (spent, received) = schedule.totals();
+    let mut doc = json!({
+        "id": schedule.id,
+        "status": schedule.status.label(),
+        "buy": schedule.buy_token,
+        "spend": schedule.spend_token,
+        "spend_amount": schedule.spend_amount,
+        "pool": schedule.pool,
+        "every": schedule.every,
+        "interval_secs": schedule.interval_secs,
+        "count": schedule.count,
+        "slots_done": schedule.fills.len(),
+        "filled": schedule
+            .fills
+            .iter()
+            .filter(|fill| fill.outcome == FillOutcome::Filled)
+            .count(),
+        "slippage_bps": schedule.slippage_bps,
+        "max_price_impact": schedule.max_price_impact,
+        "created_at": schedule.created_at,
+        "next_at": (schedule.status == DcaStatus::Active).then_some(schedule.next_at),
+        "total_spent": format_raw(spent, schedule.spend_decimals),
+        "total_received": format_raw(received, schedule.buy_decimals),
+        "average_price": schedule.average_price().map(|price| price.to_f64()),
+    });
+    if with_fills {
+        doc["fills"] = schedule
+            .fills
+            .iter()
+            .map(|fill| fill_json(schedule, fill))
+            .collect();
+    }
+    doc
+}
@@ -5,50 +5,54 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
 
+use crate::app::output::{emit, say};
 use crate::config::app_config::{WmgrConfig, CONFIG_FILE_NAME};
+use crate::error::wmgr_error;
 
 const SVPI_CONFIG_FILE_NAME: &str = ".svpi";
 
 pub fn handle_self_hash(cfg: &WmgrConfig) -> Result<()> {
     let exe_path = std::env::current_exe()
-        .map_err(|err| anyhow!("Failed to resolve current executable path: {err}"))?;
+        .map_err(|err| wmgr_error!(Io, "Failed to resolve current executable path: {err}"))?;
     let wmgr_hash = sha256_file_hex(&exe_path)
-        .map_err(|err| anyhow!("Failed to hash wmgr executable: {err}"))?;
+        .map_err(|err| wmgr_error!(Io, "Failed to hash wmgr executable: {err}"))?;
 
-    println!("wmgr:");
-    println!("{:15}{}", "app:", wmgr_hash);
+    say!("wmgr:");
+    say!("{:15}{}", "app:", wmgr_hash);
 
-    let cfg_hash = {
+    let wmgr_cfg_hash = {
         let cfg_path = WmgrConfig::path_in_cwd()
-            .map_err(|err| anyhow!("Failed to resolve {CONFIG_FILE_NAME}: {err}"))?;
+            .map_err(|err| wmgr_error!(Io, "Failed to resolve {CONFIG_FILE_NAME}: {err}"))?;
         match sha256_file_hex(&cfg_path) {
             Ok(hash) => Some(hash),
             Err(err) if err.kind() == ErrorKind::NotFound => None,
-            Err(err) => return Err(anyhow!("Failed to hash {CONFIG_FILE_NAME}: {err}")),
+            Err(err) => return Err(wmgr_error!(Io, "Failed to hash {CONFIG_FILE_NAME}: {err}")),
         }
     };
-    println!(
+    say!(
         "{:15}{}",
         format!("config({CONFIG_FILE_NAME}):"),
-        cfg_hash.as_deref().unwrap_or("(not found)")
+        wmgr_cfg_hash.as_deref().unwrap_or("(not found)")
     );
 
+    let mut svpi_doc = Value::Null;
     if cfg.is_svpi_mode() {
         let svpi_cmd = cfg.svpi_cmd.as_deref().unwrap_or("svpi");
-        println!();
-        println!("svpi:");
+        say!();
+        say!("svpi:");
 
         let app_hash = match resolve_executable_path(svpi_cmd) {
             Some(svpi_path) => Some(
                 sha256_file_hex(&svpi_path)
-                    .map_err(|err| anyhow!("Failed to hash svpi executable: {err}"))?,
+                    .map_err(|err| wmgr_error!(Io, "Failed to hash svpi executable: {err}"))?,
             ),
             None => None,
         };
-        println!(
+        say!(
             "{:15}{}",
             "app:",
             app_hash.as_deref().unwrap_or("(not found)")
@@ -56,21 +60,31 @@ pub fn handle_self_hash(cfg: &WmgrConfig) -> Result<()> {
 
         let cfg_hash = {
             let cfg_path = std::env::current_dir()
-                .map_err(|err| anyhow!("Failed to resolve {SVPI_CONFIG_FILE_NAME}: {err}"))?
+                .map_err(|err| wmgr_error!(Io, "Failed to resolve {SVPI_CONFIG_FILE_NAME}: {err}"))?
                 .join(SVPI_CONFIG_FILE_NAME);
             match sha256_file_hex(&cfg_path) {
                 Ok(hash) => Some(hash),
                 Err(err) if err.kind() == ErrorKind::NotFound => None,
-                Err(err) => return Err(anyhow!("Failed to hash {SVPI_CONFIG_FILE_NAME}: {err}")),
+                Err(err) => {
+                    return Err(wmgr_error!(
+                        Io,
+                        "Failed to hash {SVPI_CONFIG_FILE_NAME}: {err}"
+                    ))
+                }
             }
         };
-        println!(
+        say!(
             "{:15}{}",
             format!("config({SVPI_CONFIG_FILE_NAME}):"),
             cfg_hash.as_deref().unwrap_or("(not found)")
         );
+        svpi_doc = json!({ "app": app_hash, "config": cfg_hash });
     }
 
+    emit(json!({
+        "wmgr": { "app": wmgr_hash, "config": wmgr_cfg_hash },
+        "svpi": svpi_doc,
+    }));
     Ok(())
 }
 
@@ -0,0 +1,519 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+
+use crate::app::cli::SolanaRpcOptions;
+use crate::app::cli::{LpAddArgs, LpArgs, LpCommand, LpRemoveArgs, LpShowArgs};
+use crate::app::commands::swap::token_symbol;
+use crate::app::commands::{commitment_from_arg, print_landed};
+use crate::app::defaults::{
+    apply_solana_key_defaults, resolve_slippage, resolve_solana_rpc_defaults,
+};
+use crate::app::output::{emit, say};
+use crate::config::app_config::WmgrConfig;
+use crate::config::clusters::get_cluster_config;
+use crate::core::amount::{format_integer_amount, parse_amount_to_u64};
+use crate::core::lp_math::DepositSide;
+use crate::error::{wmgr_error, WmgrError};
+use crate::infra::keys::solana::resolve_solana_keypair;
+use crate::infra::raydium::{
+    build_deposit_instructions, build_withdraw_instructions, load_pool, lp_token_address,
+    LiquidityPlan, LoadedAmmPool, RaydiumPool,
+};
+use crate::infra::solana::{
+    create_rpc_client, get_mint_info, get_transaction_fee, prepare_transaction, refresh_if_expired,
+    simulate_prepared, simulation_error, snapshot_accounts, submit_prepared, with_compute_budget,
+    AccountSnapshot, ComputeBudget,
+};
+use crate::infra::wsol::load_wsol_context;
+use crate::utils::prompt::confirm;
+
+const SOL_DECIMALS: u8 = 9;
+
+#[derive(Clone, Copy)]
+enum LpAction {
+    Add,
+    Remove,
+}
+
+impl LpAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            LpAction::Add => "add",
+            LpAction::Remove => "remove",
+        }
+    }
+}
+
+/// Token symbols and decimals of a pool and its LP mint.
+struct PoolTokens {
+    base: String,
+    quote: String,
+    base_decimals: u8,
+    quote_decimals: u8,
+    lp_decimals: u8,
+}
+
+impl PoolTokens {
+    fn base(&self, amount: u64) -> String {
+        format_integer_amount(amount as u128, self.base_decimals)
+    }
+
+    fn quote(&self, amount: u64) -> String {
+        format_integer_amount(amount as u128, self.quote_decimals)
+    }
+
+    fn lp(&self, amount: u64) -> String {
+        format_integer_amount(amount as u128, self.lp_decimals)
+    }
+}
+
+pub async fn handle_lp(args: LpArgs, cfg: &WmgrConfig) -> Result<()> {
+    match args.command {
+        LpCommand::Add(args) => handle_add(args, cfg).await,
+        LpCommand::Remove(args) => handle_remove(args, cfg).await,
+        LpCommand::Show(args) => handle_show(args, cfg).await,
+    }
+}
+
+async fn handle_add(args: LpAddArgs, cfg: &WmgrConfig) -> Result<()> {
+    let LpAddArgs {
+        pool,
+        amount_a,
+        amount_b,
+        slippage,
+        key,
+        rpc,
+    } = args;
+    let slippage = resolve_slippage(slippage, cfg);
+    let keypair = resolve_solana_keypair(&apply_solana_key_defaults(key, cfg))?;
+    let (client, cluster, budget) = connect(rpc, cfg)?;
+    let pool = load_amm_pool(&client, &pool).await?;
+    let tokens = pool_tokens(&client, &cluster, &pool).await?;
+
+    let base = amount_a
+        .as_deref()
+        .map(|amount| parse_amount_to_u64(amount, pool.base_decimals))
+        .transpose()?;
+    let quote = amount_b
+        .as_deref()
+        .map(|amount| parse_amount_to_u64(amount, pool.quote_decimals))
+        .transpose()?;
+    let plan = pool.lp_reserves().plan_deposit(base, quote, slippage)?;
+
+    say!("Pool: {} (Raydium AMM v4)", pool.id);
+    say!("Review:");
+    say!("{:16}{}", "Action:", "add liquidity");
+    match plan.side {
+        DepositSide::Base => {
+            say!(
+                "{:16}{} {}",
+                "Deposit:",
+                tokens.base(plan.base),
+                tokens.base
+            );
+            say!(
+                "{:16}{} {} (max {})",
+                "Deposit:",
+                tokens.quote(plan.quote),
+                tokens.quote,
+                tokens.quote(plan.max_quote)
+            );
+        }
+        DepositSide::Quote => {
+            say!(
+                "{:16}{} {} (max {})",
+                "Deposit:",
+                tokens.base(plan.base),
+                tokens.base,
+                tokens.base(plan.max_base)
+            );
+            say!(
+                "{:16}{} {}",
+                "Deposit:",
+                tokens.quote(plan.quote),
+                tokens.quote
+            );
+        }
+    }
+    say!("{:16}{} LP", "Expected LP:", tokens.lp(plan.lp));
+    say!("{:16}{}", "Slippage:", slippage);
+
+    let owner = keypair.pubkey();
+    let rent = load_wsol_context(&client, &owner, false, false).await?.rent;
+    let liquidity = build_deposit_instructions(&pool, &owner, &plan, rent)?;
+    let doc = json!({
+        "deposit": {
+            "exact": match plan.side {
+                DepositSide::Base => "a",
+                DepositSide::Quote => "b",
+            },
+            "amount_a": tokens.base(plan.base),
+            "amount_b": tokens.quote(plan.quote),
+            "max_a": tokens.base(plan.max_base),
+            "max_b": tokens.quote(plan.max_quote),
+            "expected_lp": tokens.lp(plan.lp),
+        },
+    });
+    review_and_submit(
+        &client,
+        &keypair,
+        &budget,
+        &pool,
+        &tokens,
+        liquidity,
+        LpAction::Add,
+        doc,
+    )
+    .await
+}
+
+async fn handle_remove(args: LpRemoveArgs, cfg: &WmgrConfig) -> Result<()> {
+    let LpRemoveArgs {
+        pool,
+        amount,
+        slippage,
+        key,
+        rpc,
+    } = args;
+    let slippage = resolve_slippage(slippage, cfg);
+    let keypair = resolve_solana_keypair(&apply_solana_key_defaults(key, cfg))?;
+    let (client, cluster, budget) = connect(rpc, cfg)?;
+    let pool = load_amm_pool(&client, &pool).await?;
+    let tokens = pool_tokens(&client, &cluster, &pool).await?;
+
+    let owner = keypair.pubkey();
+    let balance = lp_balance(&client, &pool, &owner).await?;
+    let lp = if amount.trim().eq_ignore_ascii_case("all") {
+        balance
+    } else {
+        parse_amount_to_u64(&amount, tokens.lp_decimals)?
+    };
+    if lp == 0 {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "No LP tokens to remove from pool {}",
+            pool.id
+        ));
+    }
+    if lp > balance {
+        return Err(wmgr_error!(
+            InsufficientFunds,
+            "Cannot remove {} LP: balance is {} LP",
+            tokens.lp(lp),
+            tokens.lp(balance)
+        ));
+    }
+    let (base, quote) = pool.lp_reserves().withdraw(lp)?;
+    let (min_base, min_quote) = (slippage.min_out(base), slippage.min_out(quote));
+
+    say!("Pool: {} (Raydium AMM v4)", pool.id);
+    say!("Review:");
+    say!("{:16}{}", "Action:", "remove liquidity");
+    say!("{:16}{} LP", "Burn:", tokens.lp(lp));
+    say!(
+        "{:16}{} {} (min {})",
+        "Receive:",
+        tokens.base(base),
+        tokens.base,
+        tokens.base(min_base)
+    );
+    say!(
+        "{:16}{} {} (min {})",
+        "Receive:",
+        tokens.quote(quote),
+        tokens.quote,
+        tokens.quote(min_quote)
+    );
+    say!("{:16}{}", "Slippage:", slippage);
+
+    let rent = load_wsol_context(&client, &owner, false, false).await?.rent;
+    let liquidity = build_withdraw_instructions(&pool, &owner, lp, (min_base, min_quote), rent)?;
+    let doc = json!({
+        "withdraw": {
+            "lp": tokens.lp(lp),
+            "expected_a": tokens.base(base),
+            "expected_b": tokens.quote(quote),
+            "min_a": tokens.base(min_base),
+            "min_b": tokens.quote(min_quote),
+        },
+    });
+    review_and_submit(
+        &client,
+        &keypair,
+        &budget,
+        &pool,
+        &tokens,
+        liquidity,
+        LpAction::Remove,
+        doc,
+    )
+    .await
+}
+
+async fn handle_show(args: LpShowArgs, cfg: &WmgrConfig) -> Result<()> {
+    let LpShowArgs {
+        pool,
+        address,
+        key,
+        rpc,
+    } = args;
+    let owner = match address.as_deref() {
+        Some(address) => Pubkey::from_str(address)
+            .map_err(|err| wmgr_error!(InvalidArgument, "Invalid address: {err}"))?,
+        None => resolve_solana_keypair(&apply_solana_key_defaults(key, cfg))?.pubkey(),
+    };
+    let (client, cluster, _) = connect(rpc, cfg)?;
+    let pool = load_amm_pool(&client, &pool).await?;
+    let tokens = pool_tokens(&client, &cluster, &pool).await?;
+
+    let balance = lp_balance(&client, &pool, &owner).await?;
+    let (base, quote) = pool.lp_reserves().withdraw(balance)?;
+    let share = if pool.lp_supply == 0 {
+        0.0
+    } else {
+        balance as f64 / pool.lp_supply as f64 * 100.0
+    };
+
+    say!("Pool: {} (Raydium AMM v4)", pool.id);
+    say!("Wallet: {owner}");
+    say!("LP mint: {}", pool.keys.lp_mint);
+    say!("LP balance: {} LP", tokens.lp(balance));
+    say!("Share of pool: {share:.6}%");
+    say!("Underlying: {} {}", tokens.base(base), tokens.base);
+    say!("            {} {}", tokens.quote(quote), tokens.quote);
+    say!(
+        "Pool reserves: {} {} / {} {}",
+        tokens.base(pool.base_reserve),
+        tokens.base,
+        tokens.quote(pool.quote_reserve),
+        tokens.quote
+    );
+    emit(json!({
+        "pool": pool.id.to_string(),
+        "owner": owner.to_string(),
+        "lp_mint": pool.keys.lp_mint.to_string(),
+        "lp_account": lp_token_address(&pool, &owner).to_string(),
+        "lp_balance": tokens.lp(balance),
+        "lp_supply": tokens.lp(pool.lp_supply),
+        "share_percent": share,
+        "a": {
+            "symbol": tokens.base,
+            "mint": pool.base_mint.to_string(),
+            "amount": tokens.base(base),
+            "reserve": tokens.base(pool.base_reserve),
+        },
+        "b": {
+            "symbol": tokens.quote,
+            "mint": pool.quote_mint.to_string(),
+            "amount": tokens.quote(quote),
+            "reserve": tokens.quote(pool.quote_reserve),
+        },
+    }));
+    Ok(())
+}
+
+/// Liquidity is only supported on AMM v4; CPMM and CLMM positions work differently.
+async fn load_amm_pool(client: &RpcClient, pool: &str) -> Result<LoadedAmmPool> {
+    let pool_id = Pubkey::from_str(pool)
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid pool id: {err}"))?;
+    match load_pool(client, &pool_id).await? {
+        RaydiumPool::AmmV4(pool) => Ok(pool),
+        other => Err(wmgr_error!(
+            InvalidArgument,
+            "Pool {pool_id} is a Raydium {} pool; lp supports AMM v4 pools only",
+            other.kind()
+        )),
+    }
+}
+
+async fn pool_tokens(
+    client: &RpcClient,
+    cluster: &str,
+    pool: &LoadedAmmPool,
+) -> Result<PoolTokens> {
+    let lp_mint = get_mint_info(client, &pool.keys.lp_mint).await?;
+    Ok(PoolTokens {
+        base: token_symbol(cluster, &pool.base_mint),
+        quote: token_symbol(cluster, &pool.quote_mint),
+        base_decimals: pool.base_decimals,
+        quote_decimals: pool.quote_decimals,
+        lp_decimals: lp_mint.decimals,
+    })
+}
+
+/// The owner's LP token balance; zero without an LP account.
+async fn lp_balance(client: &RpcClient, pool: &LoadedAmmPool, owner: &Pubkey) -> Result<u64> {
+    let account = lp_token_address(pool, owner);
+    let snapshots = snapshot_accounts(client, &[account]).await?;
+    Ok(snapshots
+        .first()
+        .copied()
+        .flatten()
+        .and_then(|snapshot| snapshot.token_amount)
+        .unwrap_or_default())
+}
+
+/// An RPC client, the cluster name for token symbols and the compute budget.
+fn connect(rpc: SolanaRpcOptions, cfg: &WmgrConfig) -> Result<(RpcClient, String, ComputeBudget)> {
+    let rpc = resolve_solana_rpc_defaults(rpc, cfg);
+    let budget = rpc.compute_budget()?;
+    let cluster = get_cluster_config(&rpc.cluster, rpc.rpc.as_deref())?;
+    say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+    let client = create_rpc_client(&cluster.rpc_url, commitment_from_arg(rpc.commitment));
+    Ok((client, cluster.name, budget))
+}
+
+/// Simulates the deposit or withdrawal, shows the wallet's token changes and submits the same
+/// transaction once confirmed.
+#[allow(clippy::too_many_arguments)]
+async fn review_and_submit(
+    client: &RpcClient,
+    keypair: &Keypair,
+    budget: &ComputeBudget,
+    pool: &LoadedAmmPool,
+    tokens: &PoolTokens,
+    liquidity: LiquidityPlan,
+    action: LpAction,
+    mut doc: Value,
+) -> Result<()> {
+    let owner = keypair.pubkey();
+    let instructions = with_compute_budget(client, keypair, liquidity.instructions, budget).await?;
+    let watched = [
+        owner,
+        liquidity.base_account,
+        liquidity.quote_account,
+        liquidity.lp_account,
+    ];
+
+    let prepared = prepare_transaction(client, keypair, &instructions).await?;
+    let network_fee = get_transaction_fee(client, &prepared).await?;
+    let before = snapshot_accounts(client, &watched).await?;
+    say!("Simulating...");
+    let (simulation, after) = simulate_prepared(client, &prepared, &watched).await?;
+    if let Some(err) = &simulation.err {
+        if let Some(logs) = &simulation.logs {
+            for log in logs {
+                say!("{log}");
+            }
+        }
+        return Err(simulation_error(&err.clone().into(), &instructions));
+    }
+
+    let native_mint = spl_token::native_mint::id();
+    let changes = wallet_changes(
+        &before,
+        &after,
+        network_fee,
+        [
+            pool.base_mint == native_mint,
+            pool.quote_mint == native_mint,
+        ],
+    );
+    let signed = |delta: i128, decimals: u8| {
+        let amount = format_integer_amount(delta.unsigned_abs(), decimals);
+        if delta < 0 {
+            format!("-{amount}")
+        } else {
+            format!("+{amount}")
+        }
+    };
+    match &changes {
+        Some([base, quote, lp]) => {
+            say!(
+                "{:16}{} {}, {} {}, {} LP",
+                "Simulation:",
+                signed(*base, tokens.base_decimals),
+                tokens.base,
+                signed(*quote, tokens.quote_decimals),
+                tokens.quote,
+                signed(*lp, tokens.lp_decimals)
+            );
+        }
+        None => say!("{:16}ok", "Simulation:"),
+    }
+    say!(
+        "{:16}{} SOL",
+        "Network fee:",
+        format_integer_amount(network_fee as u128, SOL_DECIMALS)
+    );
+    doc["simulation"] = json!({
+        "a": changes.map(|[base, _, _]| signed(base, tokens.base_decimals)),
+        "b": changes.map(|[_, quote, _]| signed(quote, tokens.quote_decimals)),
+        "lp": changes.map(|[_, _, lp]| signed(lp, tokens.lp_decimals)),
+        "compute_units": simulation.units_consumed,
+    });
+
+    if !confirm("Continue? (y/N):")? {
+        return Err(WmgrError::UserAborted.into());
+    }
+
+    let prepared = refresh_if_expired(client, keypair, &instructions, prepared).await?;
+    let landed = submit_prepared(client, &prepared).await?;
+    say!(
+        "SUCCESS: lp {} signature: {}",
+        action.as_str(),
+        landed.signature
+    );
+    print_landed(&landed);
+    doc["chain"] = json!("solana");
+    doc["status"] = json!("sent");
+    doc["action"] = json!(action.as_str());
+    doc["pool"] = json!(pool.id.to_string());
+    doc["a"] = json!({ "symbol": tokens.base, "mint": pool.base_mint.to_string() });
+    doc["b"] = json!({ "symbol": tokens.quote, "mint": pool.quote_mint.to_string() });
+    doc["network_fee"] = json!(network_fee);
+    doc["signature"] = json!(landed.signature.to_string());
+    doc["slot"] = json!(landed.slot);
+    doc["fee"] = json!(landed.fee);
+    doc["compute_units"] = json!(landed.compute_units);
+    emit(doc);
+    Ok(())
+}
+
+/// Base, quote and LP changes of the wallet, positive when received. `snapshots` hold the owner,
+/// base, quote and LP accounts. A SOL side moves through a temporary account, so its change is
+/// read from the owner's lamports net of the network fee and rent for accounts left open.
+fn wallet_changes(
+    before: &[Option<AccountSnapshot>],
+    after: &[Option<AccountSnapshot>],
+    network_fee: u64,
+    is_sol: [bool; 2],
+) -> Option<[i128; 3]> {
+    let [owner_before, accounts_before @ ..] = before else {
+        return None;
+    };
+    let [owner_after, accounts_after @ ..] = after else {
+        return None;
+    };
+    let token = |snapshot: &Option<AccountSnapshot>| {
+        snapshot
+            .and_then(|snapshot| snapshot.token_amount)
+            .unwrap_or_default() as i128
+    };
+    let new_rent: u64 = accounts_before
+        .iter()
+        .zip(accounts_after)
+        .map(|pair| match pair {
+            (None, Some(after)) => after.rent_lamports(),
+            _ => 0,
+        })
+        .sum();
+    let sol = owner_after.as_ref()?.lamports as i128 - owner_before.as_ref()?.lamports as i128
+        + (network_fee + new_rent) as i128;
+
+    let mut changes = [0i128; 3];
+    for (index, change) in changes.iter_mut().enumerate() {
+        *change = if index < 2 && is_sol[index] {
+            sol
+        } else {
+            token(accounts_after.get(index)?) - token(accounts_before.get(index)?)
+        };
+    }
+    Some(changes)
+}
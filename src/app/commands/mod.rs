@@ -3,21 +3,51 @@ use solana_commitment_config::{CommitmentConfig, CommitmentLevel};
 
 use crate::{
     app::{
-        cli::{Cli, Command, CommitmentArg},
+        cli::{Cli, Command, CommitmentArg, ConfigArgs, ConfigCommand, OutputFormat},
         cli_mode,
+        output::{self, say},
     },
-    config::app_config::WmgrConfig,
+    config::app_config::{WmgrConfig, CONFIG_FILE_NAME},
+    core::amount::format_integer_amount,
+    error::wmgr_error,
+    infra::solana::LandedTransaction,
 };
 
 pub mod balance;
 pub mod config_cmd;
+pub mod daemon;
+pub mod dca;
 pub mod hash;
+pub mod lp;
+pub mod order;
 pub mod price;
+pub mod quote;
 pub mod send;
 pub mod swap;
+pub mod trades;
+pub mod wsol;
 
 pub async fn run(cli: Cli) -> Result<()> {
-    let mut cfg = WmgrConfig::load_from_cwd()?.unwrap_or_default();
+    output::set_format(cli.output.unwrap_or(OutputFormat::Text));
+    // `config reset` must work on a file that can no longer be read.
+    let reset = matches!(
+        &cli.command,
+        Some(Command::Config(ConfigArgs {
+            command: ConfigCommand::Reset
+        }))
+    );
+    let mut cfg = if reset {
+        WmgrConfig::default()
+    } else {
+        WmgrConfig::load_from_cwd()
+            .map_err(|err| {
+                wmgr_error!(
+                    Config,
+                    "Failed to read {CONFIG_FILE_NAME}: {err}. Run `wmgr config reset` to start over."
+                )
+            })?
+            .unwrap_or_default()
+    };
     match cli.command {
         Some(Command::Repl) => cli_mode::run_repl(&mut cfg).await,
         Some(cmd) => run_command(cmd, &mut cfg).await,
@@ -30,8 +60,16 @@ pub async fn run_command(cmd: Command, cfg: &mut WmgrConfig) -> Result<()> {
         Command::Balance(args) => balance::handle_balance(args, cfg).await,
         Command::Send(cmd) => send::handle_send(cmd, cfg).await,
         Command::Price(args) => price::handle_price(args, cfg).await,
+        Command::Quote(args) => quote::handle_quote(args, cfg).await,
         Command::Buy(args) => swap::handle_buy(args, cfg).await,
         Command::Sell(args) => swap::handle_sell(args, cfg).await,
+        Command::Wrap(args) => wsol::handle_wrap(args, cfg).await,
+        Command::Unwrap(args) => wsol::handle_unwrap(args, cfg).await,
+        Command::Lp(args) => lp::handle_lp(args, cfg).await,
+        Command::Order(args) => order::handle_order(args, cfg).await,
+        Command::Dca(args) => dca::handle_dca(args, cfg).await,
+        Command::Daemon(args) => daemon::handle_daemon(args, cfg).await,
+        Command::Trades(args) => trades::handle_trades(args),
         Command::Config(args) => config_cmd::handle_config(args, cfg),
         Command::SelfHash => hash::handle_self_hash(cfg),
         Command::Repl => Ok(()),
@@ -46,3 +84,18 @@ pub fn commitment_from_arg(arg: CommitmentArg) -> CommitmentConfig {
     };
     CommitmentConfig { commitment: level }
 }
+
+pub fn print_landed(landed: &LandedTransaction) {
+    let fee = landed
+        .fee
+        .map(|v| format!("{} SOL", format_integer_amount(v as u128, 9)))
+        .unwrap_or_else(|| "unknown".to_string());
+    let compute_units = landed
+        .compute_units
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    say!(
+        "Slot: {}, fee: {fee}, compute units: {compute_units}",
+        landed.slot
+    );
+}
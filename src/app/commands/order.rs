@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::sleep;
+
+use crate::app::cli::{
+    OrderAddArgs, OrderArgs, OrderCancelArgs, OrderCommand, OrderListArgs, OrderWatchArgs,
+    QuoteSide, SwapToken, TradeArgs,
+};
+use crate::app::commands::daemon::{is_transient, now_unix, Session};
+use crate::app::commands::swap::{resolve_pool, token_symbol, PendingTrade, TradeSide};
+use crate::app::output::{emit, say};
+use crate::config::app_config::WmgrConfig;
+use crate::config::orders::{
+    Order, OrderBook, OrderSide, OrderStatus, OrderTrigger, ORDERS_FILE_NAME,
+};
+use crate::core::price::Price;
+use crate::core::slippage::Slippage;
+use crate::error::{error_code, wmgr_error, ErrorCode, WmgrError};
+use crate::infra::solana::SignatureState;
+use crate::infra::venue::load_pool;
+
+pub async fn handle_order(args: OrderArgs, cfg: &WmgrConfig) -> Result<()> {
+    match args.command {
+        OrderCommand::Add(args) => handle_add(args),
+        OrderCommand::List(args) => handle_list(args),
+        OrderCommand::Cancel(args) => handle_cancel(args),
+        OrderCommand::Watch(args) => handle_watch(args, cfg).await,
+    }
+}
+
+fn handle_add(args: OrderAddArgs) -> Result<()> {
+    let OrderAddArgs {
+        side,
+        amount,
+        token,
+        when_price_below,
+        when_price_above,
+        with,
+        pool,
+        slippage,
+        max_price_impact,
+    } = args;
+    if let Some(pool) = &pool {
+        Pubkey::from_str(pool)
+            .map_err(|err| wmgr_error!(InvalidArgument, "Invalid pool id: {err}"))?;
+    }
+    let (trigger, trigger_price) = match (when_price_below, when_price_above) {
+        (Some(price), None) => (OrderTrigger::PriceBelow, price),
+        (None, Some(price)) => (OrderTrigger::PriceAbove, price),
+        _ => {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Give exactly one of --when-price-below and --when-price-above"
+            ))
+        }
+    };
+    let trigger_price = trigger_price.trim().to_string();
+    Price::from_str(&trigger_price)?;
+
+    let mut book = load_book()?;
+    let order = Order {
+        id: 0,
+        side: match side {
+            QuoteSide::Buy => OrderSide::Buy,
+            QuoteSide::Sell => OrderSide::Sell,
+        },
+        amount: amount.trim().to_string(),
+        token: token.to_string(),
+        with: with.map(|with| with.to_string()),
+        pool,
+        trigger,
+        trigger_price,
+        slippage_bps: slippage.map(|slippage| slippage.bps()),
+        max_price_impact,
+        created_at: now_unix(),
+        status: OrderStatus::Open,
+        closed_at: None,
+        signature: None,
+        error: None,
+    };
+    let id = book.add(order);
+    save_book(&book)?;
+
+    let order = book.get_mut(id).expect("order just added");
+    say!("OK: order #{id} saved: {}", describe(order));
+    say!("Run `wmgr order watch` to execute it when it triggers.");
+    emit(order_json(order));
+    Ok(())
+}
+
+fn handle_list(args: OrderListArgs) -> Result<()> {
+    let book = load_book()?;
+    let orders: Vec<&Order> = book
+        .orders
+        .iter()
+        .filter(|order| args.all || order.status.is_active())
+        .collect();
+    if orders.is_empty() {
+        say!("No {}orders.", if args.all { "" } else { "open " });
+    }
+    for order in &orders {
+        let outcome = match order.status {
+            OrderStatus::Filled | OrderStatus::Submitting { .. } => order
+                .signature
+                .as_deref()
+                .map(|signature| format!(" ({signature})"))
+                .unwrap_or_default(),
+            OrderStatus::Failed => order
+                .error
+                .as_deref()
+                .map(|error| format!(" ({error})"))
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        say!(
+            "#{:<4} {:<10} {}{outcome}",
+            order.id,
+            order.status.label(),
+            describe(order)
+        );
+    }
+    emit(json!({
+        "orders": orders.iter().map(|order| order_json(order)).collect::<Vec<_>>(),
+    }));
+    Ok(())
+}
+
+fn handle_cancel(args: OrderCancelArgs) -> Result<()> {
+    let mut book = load_book()?;
+    let order = book
+        .get_mut(args.id)
+        .ok_or_else(|| wmgr_error!(InvalidArgument, "No order #{}", args.id))?;
+    if order.status != OrderStatus::Open {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Order #{} is already {}",
+            args.id,
+            order.status.label()
+        ));
+    }
+    order.status = OrderStatus::Cancelled;
+    order.closed_at = Some(now_unix());
+    let doc = order_json(order);
+    save_book(&book)?;
+    say!("OK: order #{} cancelled", args.id);
+    emit(doc);
+    Ok(())
+}
+
+/// Polls the pool price of every open order and executes the ones that trigger.
+async fn handle_watch(args: OrderWatchArgs, cfg: &WmgrConfig) -> Result<()> {
+    let session = Session::connect(args.key, args.rpc, cfg)?;
+    let mut pools = HashMap::new();
+    loop {
+        watch_pass(&session, &mut pools, cfg).await?;
+        if args.once {
+            return Ok(());
+        }
+        sleep(Duration::from_secs(args.interval.max(1))).await;
+    }
+}
+
+/// Settles orders sent on an earlier pass, then checks every open order once. The book is
+/// re-read on every pass, so orders added or cancelled from another shell are picked up. `pools`
+/// caches each order's pool and counter mint across passes: finding a pool may hit the Raydium
+/// API.
+pub(super) async fn watch_pass(
+    session: &Session,
+    pools: &mut HashMap<u32, (Pubkey, Pubkey)>,
+    cfg: &WmgrConfig,
+) -> Result<()> {
+    settle_submitted(session).await?;
+    let open: Vec<Order> = load_book()?.open_orders().cloned().collect();
+    for order in open {
+        let triggered = match check_trigger(&session.rpc, &order, pools).await {
+            Ok(triggered) => triggered,
+            Err(err) => {
+                say!("Order #{}: price check failed: {err}", order.id);
+                continue;
+            }
+        };
+        let Some(price) = triggered else {
+            continue;
+        };
+        let (pool_id, counter) = pools[&order.id];
+        say!(
+            "Order #{} triggered at {:.8} {} per {}",
+            order.id,
+            price.to_f64(),
+            token_symbol(&session.cluster, &counter),
+            order.token
+        );
+        execute_order(session, &order, pool_id, cfg).await?;
+    }
+    Ok(())
+}
+
+/// Looks up the transaction of every order still `submitting`: one whose send was cut off before
+/// it confirmed. A landed or failed transaction closes the order; one that expired unseen
+/// reopens it. The order is never sent again while its transaction can still land.
+async fn settle_submitted(session: &Session) -> Result<()> {
+    let submitting: Vec<Order> = load_book()?.submitting_orders().cloned().collect();
+    for order in submitting {
+        let (
+            OrderStatus::Submitting {
+                last_valid_block_height,
+            },
+            Some(signature),
+        ) = (order.status, order.signature.as_deref())
+        else {
+            continue;
+        };
+        let state = match session
+            .signature_state(signature, last_valid_block_height)
+            .await
+        {
+            Ok(state) => state,
+            Err(err) => {
+                say!(
+                    "Order #{}: status check of {signature} failed: {err}",
+                    order.id
+                );
+                continue;
+            }
+        };
+        let mut book = load_book()?;
+        let Some(stored) = book.get_mut(order.id) else {
+            continue;
+        };
+        match state {
+            SignatureState::Landed { .. } => {
+                stored.status = OrderStatus::Filled;
+                stored.closed_at = Some(now_unix());
+                say!("Order #{} filled ({signature}).", order.id);
+            }
+            SignatureState::Failed { slot, err } => {
+                stored.status = OrderStatus::Failed;
+                stored.error = Some(format!("Transaction failed on chain in slot {slot}: {err}"));
+                stored.closed_at = Some(now_unix());
+                say!("Order #{} failed on chain: {err}", order.id);
+            }
+            SignatureState::Pending => continue,
+            SignatureState::Expired => {
+                stored.status = OrderStatus::Open;
+                stored.signature = None;
+                say!(
+                    "Order #{}: {signature} expired without landing; the order is open again.",
+                    order.id
+                );
+            }
+        }
+        let doc = order_json(stored);
+        save_book(&book)?;
+        emit(doc);
+    }
+    Ok(())
+}
+
+/// The pool price when the order's condition holds, `None` while it does not.
+async fn check_trigger(
+    rpc: &RpcClient,
+    order: &Order,
+    pools: &mut HashMap<u32, (Pubkey, Pubkey)>,
+) -> Result<Option<Price>> {
+    let token = parse_token(&order.token)?;
+    let pool = match pools.get(&order.id) {
+        Some((pool_id, _)) => load_pool(rpc, pool_id).await?,
+        None => {
+            let with = order.with.as_deref().map(parse_token).transpose()?;
+            let (pool, counter) = resolve_pool(rpc, token, with, order.pool.as_deref()).await?;
+            pools.insert(order.id, (pool.id(), counter));
+            pool
+        }
+    };
+    let (_, counter) = pools[&order.id];
+    let price = pool.orient(&token.mint(), &counter)?.spot_price()?;
+    let trigger_price = Price::from_str(&order.trigger_price)?;
+    let triggered = match order.trigger {
+        OrderTrigger::PriceBelow => price <= trigger_price,
+        OrderTrigger::PriceAbove => price >= trigger_price,
+    };
+    Ok(triggered.then_some(price))
+}
+
+/// Runs the order through the buy/sell path without prompting and records the outcome. The
+/// order is saved as `submitting` with its signature before the broadcast. RPC failures before
+/// it, expired transactions and refusals that depend on the pool or wallet (see
+/// [`may_pass_later`]) leave the order open for the next pass; a send whose outcome is unknown
+/// stays `submitting` until the next pass looks it up; anything else, such as a token or pool
+/// that cannot be traded, closes it as failed.
+async fn execute_order(
+    session: &Session,
+    order: &Order,
+    pool_id: Pubkey,
+    cfg: &WmgrConfig,
+) -> Result<()> {
+    // Cancelled while its price was being checked.
+    if load_book()?.open_orders().all(|open| open.id != order.id) {
+        return Ok(());
+    }
+    let side = match order.side {
+        OrderSide::Buy => TradeSide::Buy,
+        OrderSide::Sell => TradeSide::Sell,
+    };
+    let trade = TradeArgs {
+        slippage: order.slippage_bps.map(Slippage::from_bps).transpose()?,
+        max_price_impact: order.max_price_impact,
+        ..session.trade_args(
+            order.amount.clone(),
+            parse_token(&order.token)?,
+            order.with.as_deref().map(parse_token).transpose()?,
+            Some(pool_id.to_string()),
+        )
+    };
+    let record = |pending: &PendingTrade| {
+        let mut book = load_book()?;
+        let stored = book
+            .get_mut(order.id)
+            .filter(|stored| stored.status == OrderStatus::Open)
+            .ok_or_else(|| wmgr_error!(InvalidArgument, "Order #{} was cancelled", order.id))?;
+        stored.status = OrderStatus::Submitting {
+            last_valid_block_height: pending.last_valid_block_height,
+        };
+        stored.signature = Some(pending.signature.clone());
+        save_book(&book)
+    };
+    let result = session.trade((side, side), trade, cfg, &record).await;
+
+    let mut book = load_book()?;
+    let Some(stored) = book.get_mut(order.id) else {
+        return Ok(());
+    };
+    // Cancelled before anything was sent.
+    if !stored.status.is_active() {
+        return Ok(());
+    }
+    match result {
+        Ok(outcome) => {
+            stored.status = OrderStatus::Filled;
+            stored.signature = Some(outcome.signature);
+            stored.closed_at = Some(now_unix());
+            say!("Order #{} filled.", order.id);
+        }
+        Err(err) if error_code(&err) == ErrorCode::TxUnconfirmed => {
+            say!(
+                "Order #{} sent but not confirmed; the next pass checks its signature: {err}",
+                order.id
+            );
+            return Ok(());
+        }
+        Err(err) if is_transient(&err) => {
+            stored.status = OrderStatus::Open;
+            stored.signature = None;
+            save_book(&book)?;
+            say!("Order #{} not sent, will retry: {err}", order.id);
+            return Ok(());
+        }
+        Err(err) if may_pass_later(&err) => {
+            stored.status = OrderStatus::Open;
+            stored.signature = None;
+            save_book(&book)?;
+            say!("Order #{} not sent, left open: {err}", order.id);
+            return Ok(());
+        }
+        Err(err) => {
+            stored.status = OrderStatus::Failed;
+            stored.error = Some(err.to_string());
+            stored.closed_at = Some(now_unix());
+            say!("Order #{} failed: {err}", order.id);
+        }
+    }
+    let doc = order_json(stored);
+    save_book(&book)?;
+    emit(doc);
+    Ok(())
+}
+
+/// Refusals before the send that a later pass may not hit: the price impact guard, a simulation
+/// that fails (e.g. on the slippage limit) and a balance too small for the trade.
+fn may_pass_later(err: &anyhow::Error) -> bool {
+    matches!(
+        error_code(err),
+        ErrorCode::SimulationFailed | ErrorCode::InsufficientFunds
+    ) || err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<WmgrError>(),
+            Some(WmgrError::PriceImpact(_))
+        )
+    })
+}
+
+fn load_book() -> Result<OrderBook> {
+    OrderBook::load_from_cwd()
+        .map_err(|err| wmgr_error!(Io, "Failed to read {ORDERS_FILE_NAME}: {err}"))
+}
+
+fn save_book(book: &OrderBook) -> Result<()> {
+    book.save_to_cwd()
+        .map_err(|err| wmgr_error!(Io, "Failed to write {ORDERS_FILE_NAME}: {err}"))
+}
+
+fn parse_token(value: &str) -> Result<SwapToken> {
+    SwapToken::from_str(value).map_err(|err| wmgr_error!(InvalidArgument, "{err}"))
+}
+
+/// `buy 10 sol with usdc when price <= 140`
+fn describe(order: &Order) -> String {
+    format!(
+        "{} {} {}{} when price {} {}",
+        match order.side {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        },
+        order.amount,
+        order.token,
+        order
+            .with
+            .as_deref()
+            .map(|with| format!(" with {with}"))
+            .unwrap_or_default(),
+        match order.trigger {
+            OrderTrigger::PriceBelow => "<=",
+            OrderTrigger::PriceAbove => ">=",
+        },
+        order.trigger_price
+    )
+}
+
+fn order_json(order: &Order) -> Value {
+    json!({
+        "id": order.id,
+        "status": order.status.label(),
+        "side": match order.side {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        },
+        "amount": order.amount,
+        "token": order.token,
+        "with": order.with,
+        "pool": order.pool,
+        "trigger": match order.trigger {
+            OrderTrigger::PriceBelow => "price_below",
+            OrderTrigger::PriceAbove => "price_above",
+        },
+        "trigger_price": order.trigger_price,
+        "slippage_bps": order.slippage_bps,
+        "max_price_impact": order.max_price_impact,
+        "created_at": order.created_at,
+        "closed_at": order.closed_at,
+        "signature": order.signature,
+        "error": order.error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn market_refusals_leave_the_order_open() {
+        assert!(may_pass_later(&wmgr_error!(
+            PriceImpact,
+            "Price impact 2.0000% exceeds --max-price-impact 1%"
+        )));
+        assert!(may_pass_later(&wmgr_error!(
+            SimulationFailed,
+            "Simulation failed: slippage exceeded"
+        )));
+        assert!(!may_pass_later(&wmgr_error!(
+            InvalidArgument,
+            "Pool does not trade this token"
+        )));
+        assert_eq!(
+            error_code(&wmgr_error!(PriceImpact, "too much impact")),
+            ErrorCode::InvalidArgument
+        );
+    }
+}
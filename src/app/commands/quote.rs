@@ -0,0 +1,286 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+
+use crate::app::cli::{QuoteArgs, QuoteSide};
+use crate::app::commands::commitment_from_arg;
+use crate::app::commands::swap::{resolve_routes, token_symbol};
+use crate::app::defaults::{resolve_slippage, resolve_solana_rpc_defaults};
+use crate::app::output::{emit, say};
+use crate::config::app_config::WmgrConfig;
+use crate::config::clusters::get_cluster_config;
+use crate::core::amount::{format_integer_amount, parse_amount_to_u64};
+use crate::core::price::Price;
+use crate::core::slippage::Slippage;
+use crate::infra::router::{OrientedRoute, Route};
+use crate::infra::venue::Pool;
+
+/// One rung of the ladder. `limit` is the minimum out for a sell, the maximum in for a buy;
+/// `route` indexes the candidate that quoted it.
+struct QuoteRow {
+    route: usize,
+    amount: String,
+    expected: u64,
+    limit: u64,
+    fee: u64,
+    price: Price,
+    price_impact: f64,
+}
+
+pub async fn handle_quote(args: QuoteArgs, cfg: &WmgrConfig) -> Result<()> {
+    let QuoteArgs {
+        side,
+        token,
+        amount,
+        sizes,
+        with,
+        pool,
+        venue,
+        route,
+        slippage,
+        rpc,
+    } = args;
+
+    let rpc = resolve_solana_rpc_defaults(rpc, cfg);
+    let commitment = commitment_from_arg(rpc.commitment);
+    let cluster = get_cluster_config(&rpc.cluster, rpc.rpc.as_deref())?;
+    say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+
+    let rpc = RpcClient::new_with_commitment(cluster.rpc_url.clone(), commitment);
+    let (routes, counter_mint) =
+        resolve_routes(&rpc, token, with, pool.as_deref(), venue, route).await?;
+    let slippage = resolve_slippage(slippage, cfg);
+
+    let (input_mint, output_mint) = match side {
+        QuoteSide::Buy => (counter_mint, token.mint()),
+        QuoteSide::Sell => (token.mint(), counter_mint),
+    };
+    let candidates = routes
+        .iter()
+        .map(|route| route.orient(&input_mint, &output_mint))
+        .collect::<Result<Vec<_>>>()?;
+    // Every candidate trades the same mints, so decimals agree.
+    let oriented = &candidates[0];
+    let input_symbol = token_symbol(&cluster.name, &input_mint);
+    let output_symbol = token_symbol(&cluster.name, &output_mint);
+
+    let amounts = if sizes.is_empty() {
+        amount.into_iter().collect()
+    } else {
+        sizes
+    };
+    let rows = amounts
+        .iter()
+        .map(|amount| best_row(side, amount.trim(), &candidates, slippage))
+        .collect::<Result<Vec<_>>>()?;
+
+    let other_decimals = match side {
+        QuoteSide::Sell => oriented.decimals_out,
+        QuoteSide::Buy => oriented.decimals_in,
+    };
+    let (amount_symbol, other_symbol) = match side {
+        QuoteSide::Sell => (&input_symbol, &output_symbol),
+        QuoteSide::Buy => (&output_symbol, &input_symbol),
+    };
+    let (expected_label, limit_label) = match side {
+        QuoteSide::Sell => ("Expected out", "Min out"),
+        QuoteSide::Buy => ("Expected in", "Max in"),
+    };
+
+    for route in &routes {
+        let label = if route.is_direct() { "Pool" } else { "Route" };
+        say!("{label}: {} ({})", route.id(), route.label());
+    }
+    let several = routes.len() > 1;
+    say!(
+        "{} {} for {}, slippage {}",
+        match side {
+            QuoteSide::Sell => "Sell",
+            QuoteSide::Buy => "Buy",
+        },
+        amount_symbol,
+        other_symbol,
+        slippage
+    );
+    say!(
+        "{:<16} {:<20} {:<20} {:<20} {:<16} Impact",
+        amount_symbol,
+        format!("{expected_label} ({other_symbol})"),
+        format!("{limit_label} ({other_symbol})"),
+        format!("Fee ({input_symbol})"),
+        "Price"
+    );
+
+    let mut quotes = Vec::with_capacity(rows.len());
+    for row in &rows {
+        match row {
+            Ok(row) => {
+                let expected = format_integer_amount(row.expected as u128, other_decimals);
+                let limit = format_integer_amount(row.limit as u128, other_decimals);
+                let fee = format_integer_amount(row.fee as u128, oriented.decimals_in);
+                let route = &routes[row.route];
+                say!(
+                    "{:<16} {:<20} {:<20} {:<20} {:<16.8} {:.4}%{}",
+                    row.amount,
+                    expected,
+                    limit,
+                    fee,
+                    row.price.to_f64(),
+                    row.price_impact,
+                    if several {
+                        format!("  {}", route.label())
+                    } else {
+                        String::new()
+                    }
+                );
+                quotes.push(quote_json(side, row, route, expected, limit, fee));
+            }
+            Err((amount, err)) => {
+                say!("{:<16} {err}", amount);
+                quotes.push(json!({ "amount": amount, "error": err.to_string() }));
+            }
+        }
+    }
+
+    let mut doc = json!({
+        "side": match side {
+            QuoteSide::Sell => "sell",
+            QuoteSide::Buy => "buy",
+        },
+        "token": amount_symbol,
+        "token_mint": token.mint().to_string(),
+        "with": other_symbol,
+        "with_mint": counter_mint.to_string(),
+        "price_unit": format!("{output_symbol} per {input_symbol}"),
+        "slippage_percent": slippage.percent(),
+        "slippage_bps": slippage.bps(),
+        "quotes": quotes,
+    });
+    match routes.as_slice() {
+        [route] => merge(&mut doc, route_json(route)),
+        routes => doc["pools"] = json!(routes.iter().map(route_json).collect::<Vec<_>>()),
+    }
+    emit(doc);
+    Ok(())
+}
+
+/// The best candidate's row for `amount`: most out for a sell, least in for a buy. When none can
+/// quote it, the first candidate's failure.
+fn best_row(
+    side: QuoteSide,
+    amount: &str,
+    candidates: &[OrientedRoute],
+    slippage: Slippage,
+) -> Result<Result<QuoteRow, (String, anyhow::Error)>> {
+    let mut best: Option<QuoteRow> = None;
+    let mut first_error = None;
+    for (index, route) in candidates.iter().enumerate() {
+        match quote_row(side, amount, index, route, slippage)? {
+            Ok(row) => {
+                let better = best.as_ref().is_none_or(|best| match side {
+                    QuoteSide::Sell => row.expected > best.expected,
+                    QuoteSide::Buy => row.expected < best.expected,
+                });
+                if better {
+                    best = Some(row);
+                }
+            }
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    Ok(best.ok_or_else(|| first_error.expect("at least one candidate")))
+}
+
+/// Parses the amount strictly, but keeps pool-side failures (e.g. a size above the reserve) as
+/// a row so the rest of the ladder is still shown.
+fn quote_row(
+    side: QuoteSide,
+    amount: &str,
+    index: usize,
+    route: &OrientedRoute,
+    slippage: Slippage,
+) -> Result<Result<QuoteRow, (String, anyhow::Error)>> {
+    let row = match side {
+        QuoteSide::Sell => {
+            let amount_in = parse_amount_to_u64(amount, route.decimals_in)?;
+            route
+                .quote_exact_in_within(amount_in, slippage)
+                .map(|quote| QuoteRow {
+                    route: index,
+                    amount: amount.to_string(),
+                    expected: quote.amount_out(),
+                    limit: slippage.min_out(quote.amount_out()),
+                    fee: quote.fee(),
+                    price: quote.price(),
+                    price_impact: quote.price_impact(),
+                })
+        }
+        QuoteSide::Buy => {
+            let amount_out = parse_amount_to_u64(amount, route.decimals_out)?;
+            route
+                .quote_exact_out_within(amount_out, slippage)
+                .map(|quote| QuoteRow {
+                    route: index,
+                    amount: amount.to_string(),
+                    expected: quote.amount_in(),
+                    limit: slippage.max_in(quote.amount_in()),
+                    fee: quote.fee(),
+                    price: quote.price(),
+                    price_impact: quote.price_impact(),
+                })
+        }
+    };
+    Ok(row.map_err(|err| (amount.to_string(), err)))
+}
+
+fn quote_json(
+    side: QuoteSide,
+    row: &QuoteRow,
+    route: &Route,
+    expected: String,
+    limit: String,
+    fee: String,
+) -> Value {
+    let (expected_key, limit_key) = match side {
+        QuoteSide::Sell => ("expected_out", "min_out"),
+        QuoteSide::Buy => ("expected_in", "max_in"),
+    };
+    let mut quote = json!({
+        "amount": row.amount,
+        expected_key: expected,
+        limit_key: limit,
+        "fee": fee,
+        "price": row.price.to_f64(),
+        "price_impact_percent": row.price_impact,
+    });
+    merge(&mut quote, route_json(route));
+    quote
+}
+
+/// `pool`, `venue` and `pool_type` of a direct route; `pool` (ids joined by `+`) and `route[]`
+/// of a two-hop one.
+fn route_json(route: &Route) -> Value {
+    match route.pools() {
+        [pool] => pool_json(pool),
+        pools => json!({
+            "pool": route.id(),
+            "route": pools.iter().map(|pool| pool_json(pool)).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+fn merge(doc: &mut Value, fields: Value) {
+    if let (Some(doc), Value::Object(fields)) = (doc.as_object_mut(), fields) {
+        doc.extend(fields);
+    }
+}
+
+fn pool_json(pool: &Pool) -> Value {
+    json!({
+        "pool": pool.id().to_string(),
+        "venue": pool.venue().as_str(),
+        "pool_type": pool.kind(),
+    })
+}
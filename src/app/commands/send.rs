@@ -1,24 +1,45 @@
 use anyhow::Result;
+use ethers::signers::Signer;
+use ethers::utils::format_units;
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_response::RpcSimulateTransactionResult;
+use solana_sdk::signature::Keypair;
 
 use crate::app::cli::{
-    SendCommand, SendErc20Args, SendEthArgs, SendKind, SendSolArgs, SendUsdcArgs,
+    ConfirmOptions, SendCommand, SendErc20Args, SendEthArgs, SendKind, SendSolArgs, SendSplArgs,
+    SendUsdcArgs,
 };
-use crate::app::commands::commitment_from_arg;
+use crate::app::commands::{commitment_from_arg, print_landed};
 use crate::app::defaults::{
     apply_evm_key_defaults, apply_solana_key_defaults, resolve_evm_tx_defaults,
     resolve_solana_rpc_defaults,
 };
+use crate::app::output::{emit, say};
 use crate::config::app_config::WmgrConfig;
 use crate::config::clusters::{get_cluster_config, get_usdc_mint_for_cluster};
-use crate::infra::evm::{create_evm_provider, get_erc20_meta, transfer_erc20, transfer_eth};
+use crate::config::tokens::get_token_symbol;
+use crate::core::amount::format_integer_amount;
+use crate::error::{wmgr_error, WmgrError};
+use crate::infra::evm::{
+    build_erc20_transfer, build_eth_transfer, create_evm_provider, get_erc20_meta,
+    resolve_fee_plan, send_evm_transfer, simulate_evm_transfer, EvmSimulation, EvmTransfer,
+    FeePlan,
+};
 use crate::infra::keys::evm::resolve_evm_wallet;
 use crate::infra::keys::solana::resolve_solana_keypair;
-use crate::infra::solana::{create_rpc_client, transfer_sol, transfer_spl_token};
+use crate::infra::solana::{
+    build_sol_transfer, build_spl_transfer, create_rpc_client, get_transaction_fee,
+    prepare_transaction, refresh_if_expired, simulate_prepared, simulation_error, submit_prepared,
+    LandedTransaction, SolanaTransfer,
+};
+use crate::utils::prompt::confirm;
 
 pub async fn handle_send(cmd: SendCommand, cfg: &WmgrConfig) -> Result<()> {
     match cmd.kind {
         SendKind::Sol(args) => send_sol(args, cfg).await,
         SendKind::Usdc(args) => send_usdc(args, cfg).await,
+        SendKind::Spl(args) => send_spl(args, cfg).await,
         SendKind::Eth(args) => send_eth(args, cfg).await,
         SendKind::Erc20(args) => send_erc20(args, cfg).await,
     }
@@ -30,17 +51,24 @@ async fn send_sol(args: SendSolArgs, cfg: &WmgrConfig) -> Result<()> {
         amount,
         key,
         rpc,
+        confirm,
     } = args;
     let key = apply_solana_key_defaults(key, cfg);
     let rpc = resolve_solana_rpc_defaults(rpc, cfg);
+    let budget = rpc.compute_budget()?;
     let keypair = resolve_solana_keypair(&key)?;
     let cluster = get_cluster_config(&rpc.cluster, rpc.rpc.as_deref())?;
-    println!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+    say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
     let commitment = commitment_from_arg(rpc.commitment);
     let client = create_rpc_client(&cluster.rpc_url, commitment);
 
-    let sig = transfer_sol(&client, &keypair, &to, &amount)?;
-    println!("SUCCESS: SOL sent. Signature: {sig}");
+    let transfer = build_sol_transfer(&client, &keypair, &to, &amount, &budget).await?;
+    let outcome = review_and_submit(&client, &keypair, &transfer, "SOL", &confirm).await?;
+    if let Some(landed) = &outcome.landed {
+        say!("SUCCESS: SOL sent. Signature: {}", landed.signature);
+        print_landed(landed);
+    }
+    emit(solana_send_json(&transfer, "SOL", &outcome));
     Ok(())
 }
 
@@ -50,47 +78,257 @@ async fn send_usdc(args: SendUsdcArgs, cfg: &WmgrConfig) -> Result<()> {
         amount,
         key,
         rpc,
+        confirm,
     } = args;
     let key = apply_solana_key_defaults(key, cfg);
     let rpc = resolve_solana_rpc_defaults(rpc, cfg);
+    let budget = rpc.compute_budget()?;
     let keypair = resolve_solana_keypair(&key)?;
     let cluster = get_cluster_config(&rpc.cluster, rpc.rpc.as_deref())?;
-    println!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+    say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
     let commitment = commitment_from_arg(rpc.commitment);
     let client = create_rpc_client(&cluster.rpc_url, commitment);
 
     let mint = get_usdc_mint_for_cluster(&cluster.name)?;
-    let sig = transfer_spl_token(&client, &keypair, &to, &amount, mint)?;
-    println!("SUCCESS: USDC sent. Signature: {sig}");
+    let transfer = build_spl_transfer(&client, &keypair, &to, &amount, mint, None, &budget).await?;
+    let outcome = review_and_submit(&client, &keypair, &transfer, "USDC", &confirm).await?;
+    if let Some(landed) = &outcome.landed {
+        say!("SUCCESS: USDC sent. Signature: {}", landed.signature);
+        print_landed(landed);
+    }
+    emit(solana_send_json(&transfer, "USDC", &outcome));
+    Ok(())
+}
+
+async fn send_spl(args: SendSplArgs, cfg: &WmgrConfig) -> Result<()> {
+    let SendSplArgs {
+        mint,
+        to,
+        amount,
+        memo,
+        key,
+        rpc,
+        confirm,
+    } = args;
+    let key = apply_solana_key_defaults(key, cfg);
+    let rpc = resolve_solana_rpc_defaults(rpc, cfg);
+    let budget = rpc.compute_budget()?;
+    let keypair = resolve_solana_keypair(&key)?;
+    let cluster = get_cluster_config(&rpc.cluster, rpc.rpc.as_deref())?;
+    say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+    let commitment = commitment_from_arg(rpc.commitment);
+    let client = create_rpc_client(&cluster.rpc_url, commitment);
+
+    let transfer = build_spl_transfer(
+        &client,
+        &keypair,
+        &to,
+        &amount,
+        &mint,
+        memo.as_deref(),
+        &budget,
+    )
+    .await?;
+    let symbol = get_token_symbol(&cluster.name, &mint).unwrap_or("tokens");
+    let outcome = review_and_submit(&client, &keypair, &transfer, symbol, &confirm).await?;
+    if let Some(landed) = &outcome.landed {
+        print_spl_transfer(&transfer, landed);
+    }
+    emit(solana_send_json(&transfer, symbol, &outcome));
     Ok(())
 }
 
+fn print_spl_transfer(transfer: &SolanaTransfer, landed: &LandedTransaction) {
+    say!(
+        "SUCCESS: Sent {} tokens ({}). Signature: {}",
+        format_integer_amount(transfer.amount as u128, transfer.decimals),
+        transfer.program_label,
+        landed.signature
+    );
+    print_landed(landed);
+    if transfer.transfer_fee > 0 {
+        let received = transfer.amount.saturating_sub(transfer.transfer_fee);
+        say!(
+            "Transfer fee: {} (recipient receives {})",
+            format_integer_amount(transfer.transfer_fee as u128, transfer.decimals),
+            format_integer_amount(received as u128, transfer.decimals)
+        );
+    }
+}
+
+struct SolanaSendOutcome {
+    network_fee: u64,
+    simulated_units: Option<u64>,
+    /// `None` for dry runs and when the user declined.
+    landed: Option<LandedTransaction>,
+}
+
+/// Signs the transfer once, shows the review screen with its simulation, and submits that same
+/// transaction once confirmed.
+async fn review_and_submit(
+    client: &RpcClient,
+    keypair: &Keypair,
+    transfer: &SolanaTransfer,
+    symbol: &str,
+    confirm: &ConfirmOptions,
+) -> Result<SolanaSendOutcome> {
+    let prepared = prepare_transaction(client, keypair, &transfer.instructions).await?;
+    let network_fee = get_transaction_fee(client, &prepared).await?;
+    let (simulation, _) = simulate_prepared(client, &prepared, &[]).await?;
+    print_solana_review(transfer, symbol, network_fee, &simulation);
+
+    if let Some(err) = &simulation.err {
+        if let Some(logs) = &simulation.logs {
+            for log in logs {
+                say!("{log}");
+            }
+        }
+        return Err(simulation_error(
+            &err.clone().into(),
+            &transfer.instructions,
+        ));
+    }
+    let mut outcome = SolanaSendOutcome {
+        network_fee,
+        simulated_units: simulation.units_consumed,
+        landed: None,
+    };
+    if !should_send(confirm)? {
+        return Ok(outcome);
+    }
+
+    let prepared = refresh_if_expired(client, keypair, &transfer.instructions, prepared).await?;
+    outcome.landed = Some(submit_prepared(client, &prepared).await?);
+    Ok(outcome)
+}
+
+fn solana_send_json(transfer: &SolanaTransfer, symbol: &str, outcome: &SolanaSendOutcome) -> Value {
+    let landed = outcome.landed.as_ref();
+    json!({
+        "chain": "solana",
+        "status": send_status(landed.is_some()),
+        "from": transfer.from.to_string(),
+        "to": transfer.recipient.to_string(),
+        "destination": transfer.destination.to_string(),
+        "mint": transfer.mint.map(|m| m.to_string()),
+        "program": transfer.program_label,
+        "symbol": symbol,
+        "raw": transfer.amount,
+        "amount": format_integer_amount(transfer.amount as u128, transfer.decimals),
+        "decimals": transfer.decimals,
+        "transfer_fee": transfer.transfer_fee,
+        "ata_rent": transfer.ata_rent,
+        "network_fee": outcome.network_fee,
+        "simulated_compute_units": outcome.simulated_units,
+        "signature": landed.map(|l| l.signature.to_string()),
+        "slot": landed.map(|l| l.slot),
+        "fee": landed.and_then(|l| l.fee),
+        "compute_units": landed.and_then(|l| l.compute_units),
+    })
+}
+
+fn print_solana_review(
+    transfer: &SolanaTransfer,
+    symbol: &str,
+    network_fee: u64,
+    simulation: &RpcSimulateTransactionResult,
+) {
+    say!("Review:");
+    say!("{:15}{}", "From:", transfer.from);
+    say!("{:15}{}", "To:", transfer.recipient);
+    if transfer.destination != transfer.recipient {
+        say!("{:15}{}", "Token account:", transfer.destination);
+    }
+    say!(
+        "{:15}{} {symbol} (raw {})",
+        "Amount:",
+        format_integer_amount(transfer.amount as u128, transfer.decimals),
+        transfer.amount
+    );
+    match transfer.mint {
+        Some(mint) => say!("{:15}{mint} ({})", "Mint:", transfer.program_label),
+        None => say!("{:15}{}", "Program:", transfer.program_label),
+    }
+    if transfer.transfer_fee > 0 {
+        let received = transfer.amount.saturating_sub(transfer.transfer_fee);
+        say!(
+            "{:15}{} (recipient receives {})",
+            "Transfer fee:",
+            format_integer_amount(transfer.transfer_fee as u128, transfer.decimals),
+            format_integer_amount(received as u128, transfer.decimals)
+        );
+    }
+    if let Some(rent) = transfer.ata_rent {
+        say!(
+            "{:15}{} SOL (creates recipient token account)",
+            "ATA rent:",
+            format_integer_amount(rent as u128, 9)
+        );
+    }
+    say!(
+        "{:15}{} SOL",
+        "Network fee:",
+        format_integer_amount(network_fee as u128, 9)
+    );
+    let units = simulation
+        .units_consumed
+        .map(|v| format!(", {v} CU"))
+        .unwrap_or_default();
+    match &simulation.err {
+        None => say!("{:15}ok{units}", "Simulation:"),
+        Some(err) => say!("{:15}failed: {err:?}{units}", "Simulation:"),
+    }
+}
+
 async fn send_eth(args: SendEthArgs, cfg: &WmgrConfig) -> Result<()> {
     let SendEthArgs {
         to,
         amount,
         key,
         tx,
+        confirm,
     } = args;
     let key = apply_evm_key_defaults(key, cfg);
     let tx = resolve_evm_tx_defaults(tx, cfg);
     let wallet = resolve_evm_wallet(&key)?;
     let (provider, evm_cfg) = create_evm_provider(tx.network.as_str(), tx.rpc.as_deref())?;
+    let fees = resolve_fee_plan(&provider, &tx.fee_options()).await?;
 
-    let tx_hash = transfer_eth(
-        provider,
-        wallet,
+    let mut transfer = build_eth_transfer(
+        wallet.address(),
         &to,
         &amount,
-        tx.gas_price.as_deref(),
+        &fees,
         tx.gas_limit,
         evm_cfg.chain_id,
-    )
-    .await?;
-    println!(
+    )?;
+    let simulation = simulate_evm_transfer(&provider, &mut transfer, &fees).await?;
+    print_evm_review(
+        &transfer,
+        evm_cfg.native_symbol,
+        evm_cfg.native_symbol,
+        &fees,
+        &simulation,
+    );
+    if let Some(err) = &simulation.error {
+        return Err(evm_simulation_error(err));
+    }
+    let mut doc = evm_send_json(&transfer, evm_cfg.native_symbol, &simulation);
+    if !should_send(&confirm)? {
+        doc["status"] = json!(send_status(false));
+        emit(doc);
+        return Ok(());
+    }
+
+    let tx_hash = send_evm_transfer(provider, wallet, transfer, evm_cfg.chain_id).await?;
+    say!(
         "SUCCESS: Sent native token on {} (chainId {}). Tx hash: {tx_hash}",
-        evm_cfg.name, evm_cfg.chain_id
+        evm_cfg.name,
+        evm_cfg.chain_id
     );
+    doc["status"] = json!(send_status(true));
+    doc["tx_hash"] = json!(format!("{tx_hash:?}"));
+    emit(doc);
     Ok(())
 }
 
@@ -102,6 +340,7 @@ async fn send_erc20(args: SendErc20Args, cfg: &WmgrConfig) -> Result<()> {
         decimals,
         key,
         tx,
+        confirm,
     } = args;
     let key = apply_evm_key_defaults(key, cfg);
     let tx = resolve_evm_tx_defaults(tx, cfg);
@@ -110,26 +349,126 @@ async fn send_erc20(args: SendErc20Args, cfg: &WmgrConfig) -> Result<()> {
 
     let meta = get_erc20_meta(provider.clone(), &token).await.ok();
     let decimals = decimals.or_else(|| meta.as_ref().map(|m| m.decimals));
+    let fees = resolve_fee_plan(&provider, &tx.fee_options()).await?;
 
-    let tx_hash = transfer_erc20(
-        provider,
-        wallet,
+    let mut transfer = build_erc20_transfer(
+        provider.clone(),
+        wallet.address(),
         &token,
         &to,
         &amount,
         decimals,
-        tx.gas_price.as_deref(),
+        &fees,
         tx.gas_limit,
         evm_cfg.chain_id,
     )
     .await?;
-
     let label = meta
         .and_then(|m| m.symbol)
         .unwrap_or_else(|| "token".to_string());
-    println!(
+    let simulation = simulate_evm_transfer(&provider, &mut transfer, &fees).await?;
+    print_evm_review(&transfer, &label, evm_cfg.native_symbol, &fees, &simulation);
+    if let Some(err) = &simulation.error {
+        return Err(evm_simulation_error(err));
+    }
+    let mut doc = evm_send_json(&transfer, &label, &simulation);
+    if !should_send(&confirm)? {
+        doc["status"] = json!(send_status(false));
+        emit(doc);
+        return Ok(());
+    }
+
+    let tx_hash = send_evm_transfer(provider, wallet, transfer, evm_cfg.chain_id).await?;
+    say!(
         "SUCCESS: Sent {} {} on {} (chainId {}). Tx hash: {tx_hash}",
-        amount, label, evm_cfg.name, evm_cfg.chain_id
+        amount,
+        label,
+        evm_cfg.name,
+        evm_cfg.chain_id
     );
+    doc["status"] = json!(send_status(true));
+    doc["tx_hash"] = json!(format!("{tx_hash:?}"));
+    emit(doc);
     Ok(())
 }
+
+fn print_evm_review(
+    transfer: &EvmTransfer,
+    symbol: &str,
+    native_symbol: &str,
+    fees: &FeePlan,
+    simulation: &EvmSimulation,
+) {
+    say!("Review:");
+    say!("{:15}{:?}", "From:", transfer.from);
+    say!("{:15}{:?}", "To:", transfer.recipient);
+    let formatted = format_units(transfer.amount, transfer.decimals as u32)
+        .unwrap_or_else(|_| transfer.amount.to_string());
+    say!(
+        "{:15}{formatted} {symbol} (raw {})",
+        "Amount:",
+        transfer.amount
+    );
+    if let Some(contract) = transfer.contract {
+        say!("{:15}{contract:?}", "Contract:");
+    }
+    say!("{:15}{}", "Fees:", fees.describe());
+    match &simulation.error {
+        None => {
+            let max_fee = format_units(simulation.max_fee_wei, 18)
+                .unwrap_or_else(|_| simulation.max_fee_wei.to_string());
+            say!("{:15}up to {max_fee} {native_symbol}", "Network fee:");
+            say!("{:15}ok, gas {}", "Simulation:", simulation.gas);
+        }
+        Some(err) => say!("{:15}failed: {err}", "Simulation:"),
+    }
+}
+
+fn evm_send_json(transfer: &EvmTransfer, symbol: &str, simulation: &EvmSimulation) -> Value {
+    json!({
+        "chain": "evm",
+        "chain_id": transfer.tx.chain_id().map(|id| id.as_u64()),
+        "from": format!("{:?}", transfer.from),
+        "to": format!("{:?}", transfer.recipient),
+        "contract": transfer.contract.map(|c| format!("{c:?}")),
+        "symbol": symbol,
+        "raw": transfer.amount.to_string(),
+        "amount": format_units(transfer.amount, transfer.decimals as u32).ok(),
+        "decimals": transfer.decimals,
+        "gas": simulation.gas.to_string(),
+        "max_network_fee_wei": simulation.max_fee_wei.to_string(),
+        "tx_hash": Value::Null,
+    })
+}
+
+fn send_status(sent: bool) -> &'static str {
+    if sent {
+        "sent"
+    } else {
+        "dry_run"
+    }
+}
+
+fn evm_simulation_error(err: &str) -> anyhow::Error {
+    if err.contains("insufficient funds") {
+        wmgr_error!(InsufficientFunds, "Insufficient funds: {err}")
+    } else {
+        wmgr_error!(SimulationFailed, "Simulation failed: {err}")
+    }
+}
+
+/// Applies `--dry-run`/`--yes`, otherwise asks before broadcasting. Declining is an error so
+/// scripts can tell it apart from a dry run.
+fn should_send(opts: &ConfirmOptions) -> Result<bool> {
+    if opts.dry_run {
+        say!("Dry run: transaction not sent.");
+        return Ok(false);
+    }
+    if opts.yes {
+        return Ok(true);
+    }
+    if !confirm("Send? (y/N):")? {
+        return Err(WmgrError::UserAborted.into());
+    }
+    Ok(true)
+}
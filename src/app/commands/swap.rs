@@ -1,50 +1,100 @@
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::{anyhow, Result};
-use raydium_amm_swap::amm::client::AmmSwapClient;
-use raydium_amm_swap::consts::SOL_MINT;
-use raydium_amm_swap::interface::{AmmPool, PoolKeys};
+use anyhow::Result;
+use serde_json::{json, Value};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
 use solana_sdk::signer::Signer;
-use solana_sdk::transaction::Transaction;
 
-use crate::app::cli::{SwapToken, TradeArgs};
-use crate::app::commands::commitment_from_arg;
+use crate::app::cli::{SwapToken, TradeArgs, VenueArg};
+use crate::app::commands::{commitment_from_arg, print_landed};
 use crate::app::defaults::{
-    apply_solana_key_defaults, resolve_slippage, resolve_solana_rpc_defaults,
+    apply_solana_key_defaults, resolve_max_price_impact, resolve_requote_threshold,
+    resolve_slippage, resolve_solana_rpc_defaults,
 };
+use crate::app::output::{emit, say};
 use crate::config::app_config::WmgrConfig;
 use crate::config::clusters::get_cluster_config;
-use crate::config::raydium::{SOL_USDC_POOL_ID, USDC_MINT};
+use crate::config::journal::{
+    AmountSource, EntrySide, QuoteLimit, QuotedTrade, TradeEntry, TradeJournal, JOURNAL_FILE_NAME,
+};
+use crate::config::tokens::get_token_symbol;
+use crate::core::amm_math::PathQuote;
 use crate::core::amount::{format_integer_amount, parse_amount_to_u64};
+use crate::core::price::Price;
+use crate::core::slippage::Slippage;
+use crate::error::{wmgr_error, WmgrError};
 use crate::infra::keys::solana::resolve_solana_keypair;
-use crate::infra::raydium::{
-    build_swap_instructions, compute_swap_quote, compute_swap_quote_out, SwapKind,
+use crate::infra::router::{find_pair_pool, find_routes, OrientedRoute, Route};
+use crate::infra::solana::{
+    get_transaction_fee, prepare_transaction, refresh_if_expired, simulate_prepared,
+    snapshot_accounts, submit_prepared, with_compute_budget, AccountSnapshot, PreparedTransaction,
 };
-use crate::utils::prompt::prompt;
+use crate::infra::venue::{build_path_instructions, load_pool, Pool, SwapKind, Venue};
+use crate::infra::wsol::load_wsol_context;
+use crate::utils::prompt::confirm;
+
+/// Simulated amounts further than this from the quote are flagged in the preview.
+const PREVIEW_DIVERGENCE_PERCENT: f64 = 0.1;
 
-#[derive(Clone, Copy)]
-enum TradeSide {
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum TradeSide {
     Buy,
     Sell,
 }
 
+/// A landed swap: its JSON document and what it moved, in raw units. `input`/`output` come from
+/// the confirmed transaction when it could be fetched, else the simulation, else the quote.
+pub(super) struct TradeOutcome {
+    pub doc: Value,
+    pub signature: String,
+    pub decimals_in: u8,
+    pub decimals_out: u8,
+    pub input: u64,
+    pub output: u64,
+}
+
+/// The signed trade about to be broadcast, for callers that record it first so a send whose
+/// outcome is lost can be looked up instead of repeated.
+pub(super) struct PendingTrade {
+    pub signature: String,
+    pub last_valid_block_height: u64,
+    /// `(input, output)` in raw units, from the simulation or else the quote.
+    pub input: u64,
+    pub output: u64,
+    pub decimals_in: u8,
+    pub decimals_out: u8,
+}
+
+/// Whether a trade waits for the user between the simulation and the send.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Review {
+    /// Ask to continue, then re-quote in case the pool moved while waiting.
+    Prompt,
+    /// Send right after a successful simulation; used by triggered orders.
+    Unattended,
+}
+
 struct TradeSummary {
     side: TradeSide,
-    input_symbol: &'static str,
-    output_symbol: &'static str,
+    input_symbol: String,
+    output_symbol: String,
     input_amount_ui: String,
     input_max_ui: String,
     output_expected_ui: String,
     output_min_ui: String,
     fee_ui: String,
-    slippage_percent: f64,
-    price: f64,
+    slippage: Slippage,
+    price: Price,
     price_impact: f64,
+    /// The same quote in raw units, for the trade journal.
+    quoted: QuotedTrade,
 }
 
 impl TradeSummary {
+    #[allow(clippy::too_many_arguments)]
     fn sell(
         input_amount: u64,
         output_expected: u64,
@@ -52,27 +102,36 @@ impl TradeSummary {
         fee: u64,
         input_decimals: u8,
         output_decimals: u8,
-        input_token: SwapToken,
-        output_token: SwapToken,
-        slippage_percent: f64,
-        price: f64,
+        input_symbol: String,
+        output_symbol: String,
+        slippage: Slippage,
+        price: Price,
         price_impact: f64,
     ) -> Self {
         Self {
             side: TradeSide::Sell,
-            input_symbol: input_token.symbol(),
-            output_symbol: output_token.symbol(),
+            input_symbol,
+            output_symbol,
             input_amount_ui: format_integer_amount(input_amount as u128, input_decimals),
             input_max_ui: format_integer_amount(input_amount as u128, input_decimals),
             output_expected_ui: format_integer_amount(output_expected as u128, output_decimals),
             output_min_ui: format_integer_amount(output_min as u128, output_decimals),
             fee_ui: format_integer_amount(fee as u128, input_decimals),
-            slippage_percent,
+            slippage,
             price,
             price_impact,
+            quoted: QuotedTrade {
+                input: input_amount,
+                output: output_expected,
+                limit: QuoteLimit::MinOutput(output_min),
+                pool_fee: fee,
+                price_impact,
+                slippage_bps: slippage.bps(),
+            },
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn buy(
         output_amount: u64,
         input_expected: u64,
@@ -80,27 +139,59 @@ impl TradeSummary {
         fee: u64,
         input_decimals: u8,
         output_decimals: u8,
-        input_token: SwapToken,
-        output_token: SwapToken,
-        slippage_percent: f64,
-        price: f64,
+        input_symbol: String,
+        output_symbol: String,
+        slippage: Slippage,
+        price: Price,
         price_impact: f64,
     ) -> Self {
         Self {
             side: TradeSide::Buy,
-            input_symbol: input_token.symbol(),
-            output_symbol: output_token.symbol(),
+            input_symbol,
+            output_symbol,
             input_amount_ui: format_integer_amount(input_expected as u128, input_decimals),
             input_max_ui: format_integer_amount(input_max as u128, input_decimals),
             output_expected_ui: format_integer_amount(output_amount as u128, output_decimals),
             output_min_ui: format_integer_amount(output_amount as u128, output_decimals),
             fee_ui: format_integer_amount(fee as u128, input_decimals),
-            slippage_percent,
+            slippage,
             price,
             price_impact,
+            quoted: QuotedTrade {
+                input: input_expected,
+                output: output_amount,
+                limit: QuoteLimit::MaxInput(input_max),
+                pool_fee: fee,
+                price_impact,
+                slippage_bps: slippage.bps(),
+            },
         }
     }
 
+    fn to_json(&self) -> Value {
+        json!({
+            "side": match self.side {
+                TradeSide::Buy => "buy",
+                TradeSide::Sell => "sell",
+            },
+            "input": {
+                "symbol": self.input_symbol,
+                "amount": self.input_amount_ui.trim(),
+                "max": self.input_max_ui.trim(),
+            },
+            "output": {
+                "symbol": self.output_symbol,
+                "expected": self.output_expected_ui.trim(),
+                "min": self.output_min_ui.trim(),
+            },
+            "fee": self.fee_ui.trim(),
+            "price": self.price.to_f64(),
+            "price_impact_percent": self.price_impact,
+            "slippage_percent": self.slippage.percent(),
+            "slippage_bps": self.slippage.bps(),
+        })
+    }
+
     fn print(&self) {
         const LABEL_WIDTH: usize = 16;
         const VALUE_WIDTH: usize = 20;
@@ -122,21 +213,21 @@ impl TradeSummary {
                     label: "Sell",
                     value: RowValue::Amount {
                         amount: self.input_amount_ui.trim().to_string(),
-                        symbol: self.input_symbol.to_string(),
+                        symbol: self.input_symbol.clone(),
                     },
                 });
                 rows.push(Row {
                     label: "Min receive",
                     value: RowValue::Amount {
                         amount: self.output_min_ui.trim().to_string(),
-                        symbol: self.output_symbol.to_string(),
+                        symbol: self.output_symbol.clone(),
                     },
                 });
                 rows.push(Row {
                     label: "Fee",
                     value: RowValue::Amount {
                         amount: self.fee_ui.trim().to_string(),
-                        symbol: self.input_symbol.to_string(),
+                        symbol: self.input_symbol.clone(),
                     },
                 });
             }
@@ -145,21 +236,21 @@ impl TradeSummary {
                     label: "Buy",
                     value: RowValue::Amount {
                         amount: self.output_expected_ui.trim().to_string(),
-                        symbol: self.output_symbol.to_string(),
+                        symbol: self.output_symbol.clone(),
                     },
                 });
                 rows.push(Row {
                     label: "Max spend",
                     value: RowValue::Amount {
                         amount: self.input_max_ui.trim().to_string(),
-                        symbol: self.input_symbol.to_string(),
+                        symbol: self.input_symbol.clone(),
                     },
                 });
                 rows.push(Row {
                     label: "Fee",
                     value: RowValue::Amount {
                         amount: self.fee_ui.trim().to_string(),
-                        symbol: self.input_symbol.to_string(),
+                        symbol: self.input_symbol.clone(),
                     },
                 });
             }
@@ -168,7 +259,7 @@ impl TradeSummary {
         rows.push(Row {
             label: "Price",
             value: RowValue::Amount {
-                amount: format!("{:.8}", self.price),
+                amount: format!("{:.8}", self.price.to_f64()),
                 symbol: format!("{} per {}", self.output_symbol, self.input_symbol),
             },
         });
@@ -178,13 +269,13 @@ impl TradeSummary {
         });
         rows.push(Row {
             label: "Slippage",
-            value: RowValue::Text(format!("{}%", self.slippage_percent)),
+            value: RowValue::Text(self.slippage.to_string()),
         });
 
         for row in rows {
             match row.value {
                 RowValue::Amount { amount, symbol } => {
-                    println!(
+                    say!(
                         "{:<label_width$} {:<value_width$} {}",
                         format!("{}:", row.label),
                         amount,
@@ -194,7 +285,7 @@ impl TradeSummary {
                     );
                 }
                 RowValue::Text(value) => {
-                    println!(
+                    say!(
                         "{:<label_width$} {}",
                         format!("{}:", row.label),
                         value,
@@ -215,282 +306,1054 @@ pub async fn handle_sell(args: TradeArgs, cfg: &WmgrConfig) -> Result<()> {
 }
 
 async fn handle_trade(side: TradeSide, args: TradeArgs, cfg: &WmgrConfig) -> Result<()> {
+    let key = apply_solana_key_defaults(args.key.clone(), cfg);
+    let keypair = resolve_solana_keypair(&key)?;
+    let outcome =
+        execute_trade(side, side, args, &keypair, cfg, Review::Prompt, &|_| Ok(())).await?;
+    emit(outcome.doc);
+    Ok(())
+}
+
+/// Quotes, simulates and sends one swap. `args.key` is not read; the caller resolves the signer
+/// so long-running callers do it once. `before_send` runs right before the broadcast; an error
+/// from it cancels the send.
+///
+/// `journal_side` is how the trade journal records it: usually `side`, but a sell of one token
+/// for another can be recorded as buying the other one instead.
+pub(super) async fn execute_trade(
+    side: TradeSide,
+    journal_side: TradeSide,
+    args: TradeArgs,
+    keypair: &Keypair,
+    cfg: &WmgrConfig,
+    review: Review,
+    before_send: &dyn Fn(&PendingTrade) -> Result<()>,
+) -> Result<TradeOutcome> {
     let TradeArgs {
         amount,
         token,
+        with,
+        pool,
+        venue,
+        route,
         slippage,
-        key,
+        max_price_impact,
+        requote_threshold,
+        use_wsol,
+        keep_wrapped,
+        key: _,
         rpc,
     } = args;
 
-    let key = apply_solana_key_defaults(key, cfg);
-    let keypair = resolve_solana_keypair(&key)?;
-    let keypair_copy = solana_keypair::Keypair::try_from(keypair.to_bytes().as_slice())
-        .map_err(|err| anyhow!("Failed to clone keypair: {err}"))?;
-
     let rpc = resolve_solana_rpc_defaults(rpc, cfg);
+    let budget = rpc.compute_budget()?;
     let commitment = commitment_from_arg(rpc.commitment);
     let cluster = get_cluster_config(&rpc.cluster, rpc.rpc.as_deref())?;
-    println!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+    say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
 
-    let rpc_for_client = RpcClient::new_with_commitment(cluster.rpc_url.clone(), commitment);
     let rpc = RpcClient::new_with_commitment(cluster.rpc_url.clone(), commitment);
+    let (routes, counter_mint) =
+        resolve_routes(&rpc, token, with, pool.as_deref(), venue, route).await?;
+    let slippage = resolve_slippage(slippage, cfg);
+    let max_price_impact = resolve_max_price_impact(max_price_impact, cfg);
+    let requote_threshold = resolve_requote_threshold(requote_threshold, cfg);
 
-    let client = AmmSwapClient::new(rpc_for_client, keypair_copy);
+    let (input_mint, output_mint) = match side {
+        TradeSide::Buy => (counter_mint, token.mint()),
+        TradeSide::Sell => (token.mint(), counter_mint),
+    };
+    if use_wsol && input_mint != spl_token::native_mint::id() {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "--use-wsol applies only when paying with SOL"
+        ));
+    }
+    if keep_wrapped && output_mint != spl_token::native_mint::id() {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "--keep-wrapped applies only when receiving SOL"
+        ));
+    }
+    let input_symbol = token_symbol(&cluster.name, &input_mint);
+    let output_symbol = token_symbol(&cluster.name, &output_mint);
+    let mut route = best_route(
+        side,
+        &amount,
+        routes,
+        (&input_mint, &output_mint),
+        slippage,
+        (&input_symbol, &output_symbol),
+    )?;
+    for pool in route.pools() {
+        say!("Pool: {} ({})", pool.id(), pool.label());
+    }
 
-    let pool_id = Pubkey::from_str(SOL_USDC_POOL_ID)
-        .map_err(|err| anyhow!("Invalid Raydium pool id: {err}"))?;
-    let pool_keys: PoolKeys<AmmPool> = client.fetch_pools_keys_by_id(&pool_id).await?;
-    let pool = pool_keys
-        .data
-        .get(0)
-        .ok_or_else(|| anyhow!("Raydium pool keys not found"))?;
+    let owner = keypair.pubkey();
 
-    if !pool_mints_match(pool) {
-        return Err(anyhow!("Raydium pool mints do not match SOL/USDC"));
-    }
+    let (instructions, prepared, mut doc, watched, sent) = loop {
+        let oriented = route.orient(&input_mint, &output_mint)?;
+        let TradeQuote {
+            swaps,
+            summary,
+            path,
+            quoted,
+        } = quote_trade(
+            side,
+            &amount,
+            &oriented,
+            slippage,
+            &input_symbol,
+            &output_symbol,
+        )?;
+        if let Some(max) = max_price_impact {
+            if summary.price_impact > max {
+                return Err(wmgr_error!(
+                    PriceImpact,
+                    "Price impact {:.4}% exceeds --max-price-impact {}%; the largest {} within it is {}",
+                    summary.price_impact,
+                    max,
+                    match side {
+                        TradeSide::Sell => "sell",
+                        TradeSide::Buy => "buy",
+                    },
+                    max_size_for_impact(side, &oriented, max, &input_symbol, &output_symbol)
+                ));
+            }
+        }
 
-    let rpc_pool = client.get_rpc_pool_info(&pool_id).await?;
-    let base_mint = pool.mint_a.address.as_str();
-    let quote_mint = pool.mint_b.address.as_str();
-    let base_decimals = u8::try_from(pool.mint_a.decimals)
-        .map_err(|_| anyhow!("Base mint decimals out of range"))?;
-    let quote_decimals = u8::try_from(pool.mint_b.decimals)
-        .map_err(|_| anyhow!("Quote mint decimals out of range"))?;
+        let expected = match (swaps.first(), swaps.last()) {
+            (Some(SwapKind::BaseIn { amount_in, .. }), _) => (*amount_in, quoted),
+            (_, Some(SwapKind::BaseOut { amount_out, .. })) => (quoted, *amount_out),
+            _ => return Err(wmgr_error!(Internal, "A swap needs at least one pool")),
+        };
 
-    let slippage_percent = resolve_slippage(slippage, cfg);
-    if !(0.0..=100.0).contains(&slippage_percent) {
-        return Err(anyhow!("Slippage percent must be between 0 and 100"));
-    }
+        // Re-read on every round: the wSOL balance decides how much gets wrapped.
+        let wsol = load_wsol_context(&rpc, &owner, use_wsol, keep_wrapped).await?;
+        let plan = build_path_instructions(
+            route.swap_legs(&input_mint, &output_mint, swaps.clone())?,
+            &owner,
+            &wsol,
+        )?;
+        let mut watched = vec![owner, plan.source, plan.destination];
+        watched.extend(plan.intermediates);
+        let instructions = with_compute_budget(&rpc, keypair, plan.instructions, &budget).await?;
+
+        let prepared = prepare_transaction(&rpc, keypair, &instructions).await?;
+        let network_fee = get_transaction_fee(&rpc, &prepared).await?;
+        let before = snapshot_accounts(&rpc, &watched).await?;
+        say!("Simulating swap...");
+        let (after, compute_units) = simulate_swap(&rpc, &prepared, &watched).await?;
+        say!("Simulation: ok");
+
+        let simulated = simulated_swap(
+            &before,
+            &after,
+            network_fee,
+            input_mint == spl_token::native_mint::id(),
+            output_mint == spl_token::native_mint::id(),
+        );
+        let sent = SentTrade {
+            quoted: summary.quoted.clone(),
+            expected,
+            simulated: simulated
+                .as_ref()
+                .map(|simulated| (simulated.input, simulated.output)),
+            decimals: (oriented.decimals_in, oriented.decimals_out),
+        };
+        let (compared_decimals, compared_symbol) = match side {
+            TradeSide::Sell => (oriented.decimals_out, &output_symbol),
+            TradeSide::Buy => (oriented.decimals_in, &input_symbol),
+        };
+
+        summary.print();
+        let mut doc = summary.to_json();
+        doc["pool"] = json!(route.id());
+        if route.is_direct() {
+            let pool = &route.pools()[0];
+            doc["venue"] = json!(pool.venue().as_str());
+            doc["pool_type"] = json!(pool.kind());
+        } else {
+            doc["route"] = route_legs(
+                &route,
+                &oriented,
+                &path,
+                (&input_mint, &output_mint),
+                &cluster.name,
+            )?;
+        }
+        let simulated_amount = simulated.as_ref().map(|simulated| match side {
+            TradeSide::Sell => simulated.output,
+            TradeSide::Buy => simulated.input,
+        });
+        let divergence = simulated_amount.map(|amount| divergence_percent(quoted, amount));
+        print_simulation(
+            side,
+            simulated.as_ref(),
+            compute_units,
+            compared_decimals,
+            compared_symbol,
+        );
+        if let Some(divergence) = divergence.filter(|d| d.abs() > PREVIEW_DIVERGENCE_PERCENT) {
+            say!(
+                "WARNING: simulation differs from the quote by {:+.4}%; the pool may have moved.",
+                divergence
+            );
+        }
+        doc["simulation"] = json!({
+            "input": simulated.as_ref().map(|s| format_integer_amount(s.input as u128, oriented.decimals_in)),
+            "output": simulated.as_ref().map(|s| format_integer_amount(s.output as u128, oriented.decimals_out)),
+            "lamports_spent": simulated.as_ref().map(|s| s.lamports_spent),
+            "compute_units": compute_units,
+            "divergence_percent": divergence,
+        });
+        if review == Review::Unattended {
+            break (instructions, prepared, doc, watched, sent);
+        }
+        if !confirm("Continue? (y/N):")? {
+            return Err(WmgrError::UserAborted.into());
+        }
 
-    let (input_token, output_token) = match side {
-        TradeSide::Buy => (token.other(), token),
-        TradeSide::Sell => (token, token.other()),
+        // The prompt may have taken minutes; min-out/max-in above came from the old reserves.
+        let fresh = route.reload(&rpc).await?;
+        let fresh_quoted = quote_trade(
+            side,
+            &amount,
+            &fresh.orient(&input_mint, &output_mint)?,
+            slippage,
+            &input_symbol,
+            &output_symbol,
+        )?
+        .quoted;
+        let drift = divergence_percent(quoted, fresh_quoted);
+        if drift.abs() <= requote_threshold {
+            break (instructions, prepared, doc, watched, sent);
+        }
+        say!(
+            "Quote moved {:+.4}% while waiting (threshold {}%); re-quoting.",
+            drift,
+            requote_threshold
+        );
+        route = fresh;
     };
 
-    let input_mint = mint_for_token(input_token);
-    let output_mint = mint_for_token(output_token);
+    // The simulated transaction is sent as-is unless its blockhash expired while prompting.
+    let prepared = refresh_if_expired(&rpc, keypair, &instructions, prepared).await?;
+    let (input, output) = sent.simulated.unwrap_or(sent.expected);
+    before_send(&PendingTrade {
+        signature: prepared.signature().to_string(),
+        last_valid_block_height: prepared.last_valid_block_height,
+        input,
+        output,
+        decimals_in: sent.decimals.0,
+        decimals_out: sent.decimals.1,
+    })?;
+    let landed = submit_prepared(&rpc, &prepared).await?;
+    say!("SUCCESS: Swap signature: {}", landed.signature);
+    print_landed(&landed);
+    doc["status"] = json!("sent");
+    doc["signature"] = json!(landed.signature.to_string());
+    doc["slot"] = json!(landed.slot);
+    doc["fee"] = json!(landed.fee);
+    doc["compute_units"] = json!(landed.compute_units);
+
+    let (decimals_in, decimals_out) = sent.decimals;
+    let confirmed = landed
+        .accounts
+        .as_ref()
+        .zip(landed.fee)
+        .and_then(|(accounts, fee)| {
+            let (before, after) = accounts.snapshots(&watched);
+            simulated_swap(
+                &before,
+                &after,
+                fee,
+                input_mint == spl_token::native_mint::id(),
+                output_mint == spl_token::native_mint::id(),
+            )
+        });
+    doc["confirmed"] = json!(confirmed.as_ref().map(|confirmed| json!({
+        "input": format_integer_amount(confirmed.input as u128, decimals_in),
+        "output": format_integer_amount(confirmed.output as u128, decimals_out),
+    })));
+    let ((input, output), amount_source) = match (&confirmed, sent.simulated) {
+        (Some(confirmed), _) => ((confirmed.input, confirmed.output), AmountSource::Confirmed),
+        (None, Some(simulated)) => (simulated, AmountSource::Simulated),
+        (None, None) => (sent.expected, AmountSource::Quoted),
+    };
 
-    let input_is_base = input_mint == base_mint;
-    let output_is_base = output_mint == base_mint;
-    if !input_is_base && input_mint != quote_mint {
-        return Err(anyhow!("Input mint not found in Raydium pool"));
+    let (token_mint, token_symbol, token_decimals, counter_symbol, counter_decimals) = match side {
+        TradeSide::Buy => (
+            output_mint,
+            &output_symbol,
+            decimals_out,
+            &input_symbol,
+            decimals_in,
+        ),
+        TradeSide::Sell => (
+            input_mint,
+            &input_symbol,
+            decimals_in,
+            &output_symbol,
+            decimals_out,
+        ),
+    };
+    let mut entry = TradeEntry {
+        signature: landed.signature.to_string(),
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        side: match side {
+            TradeSide::Buy => EntrySide::Buy,
+            TradeSide::Sell => EntrySide::Sell,
+        },
+        token_mint: token_mint.to_string(),
+        token_symbol: token_symbol.clone(),
+        token_decimals,
+        counter_mint: counter_mint.to_string(),
+        counter_symbol: counter_symbol.clone(),
+        counter_decimals,
+        pool: route.id(),
+        input,
+        output,
+        amount_source,
+        fee: landed.fee,
+        quote: sent.quoted,
+    };
+    if journal_side != side {
+        entry = entry.flipped();
     }
-    if !output_is_base && output_mint != quote_mint {
-        return Err(anyhow!("Output mint not found in Raydium pool"));
+    // The trade has landed either way; a journal that cannot be written must not hide that.
+    if let Err(err) = TradeJournal::append_in_cwd(entry) {
+        say!("WARNING: trade not recorded in {JOURNAL_FILE_NAME}: {err}");
     }
 
-    let (swap_kind, summary) = match side {
-        TradeSide::Sell => {
-            let input_decimals = if input_is_base {
-                base_decimals
-            } else {
-                quote_decimals
-            };
-            let output_decimals = if input_is_base {
-                quote_decimals
-            } else {
-                base_decimals
-            };
-            let reserve_in = if input_is_base {
-                rpc_pool.base_reserve
-            } else {
-                rpc_pool.quote_reserve
-            };
-            let reserve_out = if input_is_base {
-                rpc_pool.quote_reserve
-            } else {
-                rpc_pool.base_reserve
-            };
+    Ok(TradeOutcome {
+        doc,
+        signature: landed.signature.to_string(),
+        decimals_in,
+        decimals_out,
+        input,
+        output,
+    })
+}
 
-            let amount_in = parse_amount_to_u64(&amount, input_decimals)?;
-            let quote = compute_swap_quote(
-                amount_in,
-                reserve_in,
-                reserve_out,
-                input_decimals,
-                output_decimals,
-            )?;
-            let min_amount_out = apply_slippage_min(quote.amount_out, slippage_percent);
-            let swap_kind = SwapKind::BaseIn {
-                amount_in,
-                min_amount_out,
-            };
+/// What the last reviewed round quoted and simulated, carried past the send.
+struct SentTrade {
+    quoted: QuotedTrade,
+    /// `(input, output)` as quoted, with the exact side taken as given.
+    expected: (u64, u64),
+    simulated: Option<(u64, u64)>,
+    decimals: (u8, u8),
+}
+
+/// The largest trade size within `max_impact_percent`, in the token the amount is given in.
+fn max_size_for_impact(
+    side: TradeSide,
+    oriented: &OrientedRoute,
+    max_impact_percent: f64,
+    input_symbol: &str,
+    output_symbol: &str,
+) -> String {
+    let max_in = oriented.max_input_for_impact(max_impact_percent);
+    match side {
+        TradeSide::Sell => format!(
+            "{} {input_symbol}",
+            format_integer_amount(max_in as u128, oriented.decimals_in)
+        ),
+        TradeSide::Buy => {
+            let max_out = oriented
+                .quote_exact_in(max_in)
+                .map_or(0, |quote| quote.amount_out());
+            format!(
+                "{} {output_symbol}",
+                format_integer_amount(max_out as u128, oriented.decimals_out)
+            )
+        }
+    }
+}
+
+/// A trade quoted against current reserves, one swap per leg.
+struct TradeQuote {
+    swaps: Vec<SwapKind>,
+    summary: TradeSummary,
+    /// Every leg's quote, in trade order.
+    path: PathQuote,
+    /// The side the simulation and re-quote are checked against: output for a sell, input for a
+    /// buy.
+    quoted: u64,
+}
+
+/// Quotes `amount` against the route's current reserves.
+///
+/// Through two pools, a sell is quoted with the second leg spending only the first one's
+/// slippage minimum, and a buy with the first leg returning the second one's slippage maximum;
+/// see [`exact_in_swaps`] and [`exact_out_swaps`].
+fn quote_trade(
+    side: TradeSide,
+    amount: &str,
+    oriented: &OrientedRoute,
+    slippage: Slippage,
+    input_symbol: &str,
+    output_symbol: &str,
+) -> Result<TradeQuote> {
+    match side {
+        TradeSide::Sell => {
+            let amount_in = parse_amount_to_u64(amount, oriented.decimals_in)?;
+            let quote = oriented.quote_exact_in_within(amount_in, slippage)?;
+            let min_amount_out = slippage.min_out(quote.amount_out());
+            let swaps = exact_in_swaps(&quote, slippage);
 
-            let output_expected = quote.amount_out;
             let summary = TradeSummary::sell(
                 amount_in,
-                output_expected,
+                quote.amount_out(),
                 min_amount_out,
-                quote.fee,
-                input_decimals,
-                output_decimals,
-                input_token,
-                output_token,
-                slippage_percent,
-                quote.price,
-                quote.price_impact,
+                quote.fee(),
+                oriented.decimals_in,
+                oriented.decimals_out,
+                input_symbol.to_string(),
+                output_symbol.to_string(),
+                slippage,
+                quote.price(),
+                quote.price_impact(),
             );
-            (swap_kind, summary)
+            Ok(TradeQuote {
+                swaps,
+                summary,
+                quoted: quote.amount_out(),
+                path: quote,
+            })
         }
         TradeSide::Buy => {
-            let output_decimals = if output_is_base {
-                base_decimals
-            } else {
-                quote_decimals
-            };
-            let input_decimals = if output_is_base {
-                quote_decimals
-            } else {
-                base_decimals
-            };
-            let reserve_in = if output_is_base {
-                rpc_pool.quote_reserve
-            } else {
-                rpc_pool.base_reserve
-            };
-            let reserve_out = if output_is_base {
-                rpc_pool.base_reserve
-            } else {
-                rpc_pool.quote_reserve
-            };
-
-            let amount_out = parse_amount_to_u64(&amount, output_decimals)?;
-            let quote = compute_swap_quote_out(
-                amount_out,
-                reserve_in,
-                reserve_out,
-                input_decimals,
-                output_decimals,
-            )?;
-            let max_amount_in = apply_slippage_max(quote.amount_in, slippage_percent);
-
-            let swap_kind = SwapKind::BaseOut {
-                max_amount_in,
-                amount_out,
-            };
+            let amount_out = parse_amount_to_u64(amount, oriented.decimals_out)?;
+            let quote = oriented.quote_exact_out_within(amount_out, slippage)?;
+            let max_amount_in = slippage.max_in(quote.amount_in());
+            let swaps = exact_out_swaps(&quote, slippage);
 
             let summary = TradeSummary::buy(
                 amount_out,
-                quote.amount_in,
+                quote.amount_in(),
                 max_amount_in,
-                quote.fee,
-                input_decimals,
-                output_decimals,
-                input_token,
-                output_token,
-                slippage_percent,
-                quote.price,
-                quote.price_impact,
+                quote.fee(),
+                oriented.decimals_in,
+                oriented.decimals_out,
+                input_symbol.to_string(),
+                output_symbol.to_string(),
+                slippage,
+                quote.price(),
+                quote.price_impact(),
             );
-            (swap_kind, summary)
+            Ok(TradeQuote {
+                swaps,
+                summary,
+                quoted: quote.amount_in(),
+                path: quote,
+            })
         }
-    };
+    }
+}
 
-    let input_mint_key =
-        Pubkey::from_str(input_mint).map_err(|err| anyhow!("Invalid input mint: {err}"))?;
-    let output_mint_key =
-        Pubkey::from_str(output_mint).map_err(|err| anyhow!("Invalid output mint: {err}"))?;
-    ensure_spl_token_mint(&rpc, &input_mint_key, "input").await?;
-    ensure_spl_token_mint(&rpc, &output_mint_key, "output").await?;
+/// One exact-in swap per leg, each spending its quoted input with the slippage minimum on its
+/// quoted output. `quote` comes from [`OrientedRoute::quote_exact_in_within`], so every leg after
+/// the first spends exactly the previous leg's minimum: the first leg may come up short by the
+/// tolerance and the route still fills, while a shorter one fails the transaction instead of the
+/// next leg drawing on a balance already held in the intermediate token.
+fn exact_in_swaps(quote: &PathQuote, slippage: Slippage) -> Vec<SwapKind> {
+    quote
+        .legs
+        .iter()
+        .map(|leg| SwapKind::BaseIn {
+            amount_in: leg.amount_in,
+            min_amount_out: slippage.min_out(leg.amount_out),
+        })
+        .collect()
+}
 
-    let instructions = build_swap_instructions(
-        pool,
-        &keypair.pubkey(),
-        &input_mint_key,
-        &output_mint_key,
-        swap_kind,
-    )?;
+/// One exact-out swap per leg, each returning its quoted output for at most the slippage maximum
+/// of its quoted input. `quote` comes from [`OrientedRoute::quote_exact_out_within`], so every
+/// leg before the last returns exactly the next leg's maximum: the last leg may cost up to the
+/// tolerance more than quoted and the route still fills.
+fn exact_out_swaps(quote: &PathQuote, slippage: Slippage) -> Vec<SwapKind> {
+    quote
+        .legs
+        .iter()
+        .map(|leg| SwapKind::BaseOut {
+            max_amount_in: slippage.max_in(leg.amount_in),
+            amount_out: leg.amount_out,
+        })
+        .collect()
+}
 
-    println!("Simulating swap...");
-    simulate_transaction(&rpc, &keypair, &instructions).await?;
-    println!("Simulation: ok");
+/// Prints the legs of a route and returns them for the JSON document, each with its quoted input
+/// and output.
+fn route_legs(
+    route: &Route,
+    oriented: &OrientedRoute,
+    path: &PathQuote,
+    (input_mint, output_mint): (&Pubkey, &Pubkey),
+    cluster: &str,
+) -> Result<Value> {
+    const LABEL_WIDTH: usize = 16;
+    let legs = route.legs(input_mint, output_mint)?;
+    let mut docs = Vec::with_capacity(legs.len());
+    for (index, (((pool, leg_in, leg_out), curve), quote)) in
+        legs.iter().zip(oriented.legs()).zip(&path.legs).enumerate()
+    {
+        let input = format_integer_amount(quote.amount_in as u128, curve.decimals_in);
+        let output = format_integer_amount(quote.amount_out as u128, curve.decimals_out);
+        let (in_symbol, out_symbol) = (
+            token_symbol(cluster, leg_in),
+            token_symbol(cluster, leg_out),
+        );
+        say!(
+            "{:<LABEL_WIDTH$} {} {in_symbol} -> {} {out_symbol} ({})",
+            format!("Leg {}:", index + 1),
+            input.trim(),
+            output.trim(),
+            pool.label()
+        );
+        docs.push(json!({
+            "pool": pool.id().to_string(),
+            "venue": pool.venue().as_str(),
+            "pool_type": pool.kind(),
+            "input": { "symbol": in_symbol, "mint": leg_in.to_string(), "amount": input.trim() },
+            "output": { "symbol": out_symbol, "mint": leg_out.to_string(), "amount": output.trim() },
+        }));
+    }
+    Ok(json!(docs))
+}
+
+/// Picks the pool and the counter mint for `token` from `--pool`, `--with` or the default pair,
+/// on Raydium unless the pool given is elsewhere.
+pub(super) async fn resolve_pool(
+    rpc: &RpcClient,
+    token: SwapToken,
+    with: Option<SwapToken>,
+    pool: Option<&str>,
+) -> Result<(Pool, Pubkey)> {
+    let (mut pools, counter) = resolve_pools(rpc, token, with, pool, VenueArg::Raydium).await?;
+    Ok((pools.remove(0), counter))
+}
+
+/// The candidate routes for `token` and the counter mint. Without `route` these are the pools
+/// `resolve_pools` finds; with it, every direct and two-hop route on the venues `venue` selects.
+pub(super) async fn resolve_routes(
+    rpc: &RpcClient,
+    token: SwapToken,
+    with: Option<SwapToken>,
+    pool: Option<&str>,
+    venue: VenueArg,
+    route: bool,
+) -> Result<(Vec<Route>, Pubkey)> {
+    if !route {
+        let (pools, counter) = resolve_pools(rpc, token, with, pool, venue).await?;
+        return Ok((pools.into_iter().map(Route::direct).collect(), counter));
+    }
+    let mint = token.mint();
+    let counter = with.unwrap_or(token.default_counter()).mint();
+    let routes = find_routes(rpc, venues(venue), &mint, &counter).await?;
+    Ok((routes, counter))
+}
 
-    summary.print();
-    let answer = prompt("Continue? (y/N):")?;
-    let answer = answer.trim().to_ascii_lowercase();
-    if answer != "y" && answer != "yes" {
-        println!("Aborted.");
-        return Ok(());
+/// The candidate pools for `token` and the counter mint: the `--pool` given, or one pool per
+/// venue `venue` selects. Never empty; with `best`, a venue without a pool is left out.
+pub(super) async fn resolve_pools(
+    rpc: &RpcClient,
+    token: SwapToken,
+    with: Option<SwapToken>,
+    pool: Option<&str>,
+    venue: VenueArg,
+) -> Result<(Vec<Pool>, Pubkey)> {
+    let mint = token.mint();
+    if let Some(id) = pool {
+        let pool_id = Pubkey::from_str(id)
+            .map_err(|err| wmgr_error!(InvalidArgument, "Invalid pool id: {err}"))?;
+        let pool = load_pool(rpc, &pool_id).await?;
+        let counter = check_pool(&pool, &mint, with)?;
+        return Ok((vec![pool], counter));
     }
 
-    let sig = send_transaction(&rpc, &keypair, &instructions).await?;
-    println!("SUCCESS: Swap signature: {sig}");
-    Ok(())
+    let counter = with.unwrap_or(token.default_counter()).mint();
+    let venues = venues(venue);
+    let mut pools = Vec::with_capacity(venues.len());
+    let mut errors = Vec::new();
+    for venue in venues {
+        let found = async {
+            let pool = find_pair_pool(rpc, *venue, &mint, &counter).await?;
+            check_pool(&pool, &mint, with)?;
+            Ok::<_, anyhow::Error>(pool)
+        };
+        match found.await {
+            Ok(pool) => pools.push(pool),
+            Err(err) => errors.push((*venue, err)),
+        }
+    }
+    if pools.is_empty() {
+        if errors.len() == 1 {
+            return Err(errors.remove(0).1);
+        }
+        let reasons: Vec<String> = errors
+            .iter()
+            .map(|(venue, err)| format!("{}: {err}", venue.label()))
+            .collect();
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "No pool found for {mint}/{counter} ({})",
+            reasons.join("; ")
+        ));
+    }
+    for (venue, err) in &errors {
+        say!("{}: {err}", venue.label());
+    }
+    Ok((pools, counter))
 }
 
-fn mint_for_token(token: SwapToken) -> &'static str {
-    match token {
-        SwapToken::Sol => SOL_MINT,
-        SwapToken::Usdc => USDC_MINT,
+fn venues(venue: VenueArg) -> &'static [Venue] {
+    match venue {
+        VenueArg::Raydium => &[Venue::Raydium],
+        VenueArg::Orca => &[Venue::Orca],
+        VenueArg::Best => &[Venue::Raydium, Venue::Orca],
     }
 }
 
-fn apply_slippage_min(amount: u64, slippage_percent: f64) -> u64 {
-    let slippage = slippage_percent / 100.0;
-    ((amount as f64) * (1.0 - slippage)).floor() as u64
+/// Checks that `pool` trades `mint` and returns the counter mint: `with`, else the pool's other
+/// mint.
+fn check_pool(pool: &Pool, mint: &Pubkey, with: Option<SwapToken>) -> Result<Pubkey> {
+    let pool_id = pool.id();
+    if !pool.contains(mint) {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Mint {mint} not found in {} pool {pool_id}",
+            pool.label()
+        ));
+    }
+    let counter = match with {
+        Some(with) => with.mint(),
+        None => pool.other_mint(mint).unwrap_or_default(),
+    };
+    if counter == *mint || !pool.contains(&counter) {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Mint {counter} is not the other side of {} pool {pool_id}",
+            pool.label()
+        ));
+    }
+    Ok(counter)
 }
 
-fn apply_slippage_max(amount: u64, slippage_percent: f64) -> u64 {
-    let slippage = slippage_percent / 100.0;
-    ((amount as f64) * (1.0 + slippage)).ceil() as u64
+/// With several candidates, quotes each and keeps the one paying the most for a sell or costing
+/// the least for a buy. A candidate that cannot quote the size is skipped.
+fn best_route(
+    side: TradeSide,
+    amount: &str,
+    routes: Vec<Route>,
+    (input_mint, output_mint): (&Pubkey, &Pubkey),
+    slippage: Slippage,
+    (input_symbol, output_symbol): (&str, &str),
+) -> Result<Route> {
+    if routes.len() == 1 {
+        return Ok(routes.into_iter().next().expect("one route"));
+    }
+    let mut best: Option<(Route, u64)> = None;
+    let mut first_error = None;
+    for route in routes {
+        let quoted = route.orient(input_mint, output_mint).and_then(|oriented| {
+            let quoted = quote_trade(
+                side,
+                amount,
+                &oriented,
+                slippage,
+                input_symbol,
+                output_symbol,
+            )?
+            .quoted;
+            Ok((quoted, oriented.decimals_in, oriented.decimals_out))
+        });
+        let (quoted, decimals_in, decimals_out) = match quoted {
+            Ok(quoted) => quoted,
+            Err(err) => {
+                say!("{} {}: {err}", route.label(), route.id());
+                first_error.get_or_insert(err);
+                continue;
+            }
+        };
+        let (decimals, symbol, label) = match side {
+            TradeSide::Sell => (decimals_out, output_symbol, "out"),
+            TradeSide::Buy => (decimals_in, input_symbol, "in"),
+        };
+        say!(
+            "{} {}: {} {symbol} {label}",
+            route.label(),
+            route.id(),
+            format_integer_amount(quoted as u128, decimals)
+        );
+        let better = best.as_ref().is_none_or(|(_, best)| match side {
+            TradeSide::Sell => quoted > *best,
+            TradeSide::Buy => quoted < *best,
+        });
+        if better {
+            best = Some((route, quoted));
+        }
+    }
+    match (best, first_error) {
+        (Some((route, _)), _) => Ok(route),
+        (None, Some(err)) => Err(err),
+        (None, None) => Err(wmgr_error!(InvalidArgument, "No pool to quote")),
+    }
 }
 
-fn pool_mints_match(pool: &AmmPool) -> bool {
-    let a = pool.mint_a.address.as_str();
-    let b = pool.mint_b.address.as_str();
-    (a == SOL_MINT && b == USDC_MINT) || (a == USDC_MINT && b == SOL_MINT)
+/// `SOL` for the native mint, then known tokens, then the mint address.
+pub(super) fn token_symbol(cluster: &str, mint: &Pubkey) -> String {
+    if *mint == spl_token::native_mint::id() {
+        return "SOL".to_string();
+    }
+    let mint_str = mint.to_string();
+    get_token_symbol(cluster, &mint_str)
+        .map(str::to_string)
+        .unwrap_or(mint_str)
+}
+
+/// Wallet-side effect of the simulated swap, from account state before and after.
+#[derive(Debug, PartialEq, Eq)]
+struct SimulatedSwap {
+    input: u64,
+    output: u64,
+    /// Network fee plus rent for accounts the swap leaves open.
+    lamports_spent: u64,
 }
 
-async fn simulate_transaction(
+/// `before`/`after` hold the owner, source account and destination account, in that order, then
+/// the accounts a route passes through between its legs.
+///
+/// The SOL leg is measured as the owner's lamports plus wrapped SOL in the watched accounts, so
+/// it is exact whether SOL was wrapped into an ATA, a temporary account, or unwrapped on close.
+fn simulated_swap(
+    before: &[Option<AccountSnapshot>],
+    after: &[Option<AccountSnapshot>],
+    network_fee: u64,
+    input_is_sol: bool,
+    output_is_sol: bool,
+) -> Option<SimulatedSwap> {
+    let [owner_before, source_before, dest_before, ..] = before else {
+        return None;
+    };
+    let [owner_after, source_after, dest_after, ..] = after else {
+        return None;
+    };
+    if before.len() != after.len() {
+        return None;
+    }
+    let (accounts_before, accounts_after) = (&before[1..], &after[1..]);
+    let token_amount = |snapshot: &Option<AccountSnapshot>| {
+        snapshot
+            .and_then(|snapshot| snapshot.token_amount)
+            .unwrap_or_default()
+    };
+    let new_rent =
+        |before: &Option<AccountSnapshot>, after: &Option<AccountSnapshot>| match (before, after) {
+            (None, Some(after)) => after.rent_lamports(),
+            _ => 0,
+        };
+    let wrapped = |snapshot: &Option<AccountSnapshot>| {
+        snapshot
+            .filter(|snapshot| snapshot.is_native)
+            .and_then(|snapshot| snapshot.token_amount)
+            .unwrap_or_default() as i128
+    };
+    let lamports_spent = network_fee
+        + accounts_before
+            .iter()
+            .zip(accounts_after)
+            .map(|(before, after)| new_rent(before, after))
+            .sum::<u64>();
+
+    let sol_before =
+        owner_before.as_ref()?.lamports as i128 + accounts_before.iter().map(wrapped).sum::<i128>();
+    let sol_after =
+        owner_after.as_ref()?.lamports as i128 + accounts_after.iter().map(wrapped).sum::<i128>();
+    let sol_delta = sol_after - sol_before + lamports_spent as i128;
+
+    let input = if input_is_sol {
+        u64::try_from(-sol_delta).ok()?
+    } else {
+        token_amount(source_before).checked_sub(token_amount(source_after))?
+    };
+    let output = if output_is_sol {
+        u64::try_from(sol_delta).ok()?
+    } else {
+        token_amount(dest_after).checked_sub(token_amount(dest_before))?
+    };
+
+    Some(SimulatedSwap {
+        input,
+        output,
+        lamports_spent,
+    })
+}
+
+/// Signed difference of `simulated` from `quoted`, in percent.
+fn divergence_percent(quoted: u64, simulated: u64) -> f64 {
+    if quoted == 0 {
+        return 0.0;
+    }
+    (simulated as f64 - quoted as f64) / quoted as f64 * 100.0
+}
+
+fn print_simulation(
+    side: TradeSide,
+    simulated: Option<&SimulatedSwap>,
+    compute_units: Option<u64>,
+    decimals: u8,
+    symbol: &str,
+) {
+    const LABEL_WIDTH: usize = 16;
+    const VALUE_WIDTH: usize = 20;
+    let label = match side {
+        TradeSide::Sell => "Simulated out:",
+        TradeSide::Buy => "Simulated in:",
+    };
+    match simulated {
+        Some(simulated) => {
+            let amount = match side {
+                TradeSide::Sell => simulated.output,
+                TradeSide::Buy => simulated.input,
+            };
+            say!(
+                "{:<LABEL_WIDTH$} {:<VALUE_WIDTH$} {}",
+                label,
+                format_integer_amount(amount as u128, decimals),
+                symbol
+            );
+            say!(
+                "{:<LABEL_WIDTH$} {:<VALUE_WIDTH$} SOL",
+                "Fees + rent:",
+                format_integer_amount(simulated.lamports_spent as u128, 9)
+            );
+        }
+        None => say!("{:<LABEL_WIDTH$} unavailable", label),
+    }
+    if let Some(units) = compute_units {
+        say!("{:<LABEL_WIDTH$} {}", "Compute units:", units);
+    }
+}
+
+/// Simulates the signed swap and returns the post-state of `watched` and the compute units used.
+async fn simulate_swap(
     rpc: &RpcClient,
-    keypair: &solana_keypair::Keypair,
-    instructions: &[solana_sdk::instruction::Instruction],
-) -> Result<()> {
-    let recent_blockhash = rpc.get_latest_blockhash().await?;
-    let tx = Transaction::new_signed_with_payer(
-        instructions,
-        Some(&keypair.pubkey()),
-        &[keypair],
-        recent_blockhash,
-    );
-
-    let result = rpc.simulate_transaction(&tx).await?;
-    if let Some(err) = result.value.err {
-        if let Some(logs) = result.value.logs {
+    prepared: &PreparedTransaction,
+    watched: &[Pubkey],
+) -> Result<(Vec<Option<AccountSnapshot>>, Option<u64>)> {
+    let (result, after) = simulate_prepared(rpc, prepared, watched).await?;
+    if let Some(err) = result.err {
+        if let Some(logs) = result.logs {
             for log in logs {
-                println!("{log}");
+                say!("{log}");
             }
         }
-        return Err(anyhow!("Simulation failed: {err:?}"));
+        return Err(wmgr_error!(SimulationFailed, "Simulation failed: {err:?}"));
     }
-    Ok(())
+    Ok((after, result.units_consumed))
 }
 
-async fn send_transaction(
-    rpc: &RpcClient,
-    keypair: &solana_keypair::Keypair,
-    instructions: &[solana_sdk::instruction::Instruction],
-) -> Result<String> {
-    let recent_blockhash = rpc.get_latest_blockhash().await?;
-    let tx = Transaction::new_signed_with_payer(
-        instructions,
-        Some(&keypair.pubkey()),
-        &[keypair],
-        recent_blockhash,
-    );
-    let sig = rpc.send_and_confirm_transaction(&tx).await?;
-    Ok(sig.to_string())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::amm_math::{
+        quote_path_exact_in_within, quote_path_exact_out_within, ConstantProduct, FeeRate,
+    };
+    use crate::error::{error_code, ErrorCode};
+
+    const RENT: u64 = 2_039_280;
+
+    fn wallet(lamports: u64) -> Option<AccountSnapshot> {
+        Some(AccountSnapshot {
+            lamports,
+            token_amount: None,
+            is_native: false,
+        })
+    }
+
+    fn token(amount: u64) -> Option<AccountSnapshot> {
+        Some(AccountSnapshot {
+            lamports: RENT,
+            token_amount: Some(amount),
+            is_native: false,
+        })
+    }
+
+    fn wsol(amount: u64) -> Option<AccountSnapshot> {
+        Some(AccountSnapshot {
+            lamports: RENT + amount,
+            token_amount: Some(amount),
+            is_native: true,
+        })
+    }
+
+    #[test]
+    fn token_to_new_token_account() {
+        let before = [wallet(10_000_000), token(500), None];
+        let after = [wallet(10_000_000 - 5_000 - RENT), token(200), token(42)];
+        let simulated = simulated_swap(&before, &after, 5_000, false, false).unwrap();
+        assert_eq!(
+            simulated,
+            SimulatedSwap {
+                input: 300,
+                output: 42,
+                lamports_spent: 5_000 + RENT,
+            }
+        );
+    }
+
+    #[test]
+    fn sol_in_is_wrapped_first() {
+        let before = [wallet(10_000_000), None, token(7)];
+        let after = [
+            wallet(10_000_000 - 5_000 - RENT - 1_000_000),
+            wsol(0),
+            token(99),
+        ];
+        let simulated = simulated_swap(&before, &after, 5_000, true, false).unwrap();
+        assert_eq!(simulated.input, 1_000_000);
+        assert_eq!(simulated.output, 92);
+        assert_eq!(simulated.lamports_spent, 5_000 + RENT);
+    }
+
+    #[test]
+    fn sol_out_arrives_as_lamports() {
+        // The destination wSOL account is created and closed in the same transaction.
+        let before = [wallet(10_000_000), token(300), None];
+        let after = [wallet(10_000_000 - 5_000 + 750_000), token(0), None];
+        let simulated = simulated_swap(&before, &after, 5_000, false, true).unwrap();
+        assert_eq!(simulated.input, 300);
+        assert_eq!(simulated.output, 750_000);
+        assert_eq!(simulated.lamports_spent, 5_000);
+    }
 
-async fn ensure_spl_token_mint(rpc: &RpcClient, mint: &Pubkey, label: &str) -> Result<()> {
-    let account = rpc.get_account(mint).await?;
-    if account.owner != spl_token::id() {
-        return Err(anyhow!(
-            "{label} mint {} is not owned by the SPL Token program (owner: {}). Raydium AMM v4 only supports SPL Token mints.",
-            mint,
-            account.owner
+    #[test]
+    fn existing_wsol_is_spent_before_wrapping() {
+        // 400_000 wrapped already; only the 600_000 shortfall moves out of the wallet.
+        let before = [wallet(10_000_000), wsol(400_000), token(0)];
+        let after = [wallet(10_000_000 - 5_000 - 600_000), wsol(0), token(55)];
+        let simulated = simulated_swap(&before, &after, 5_000, true, false).unwrap();
+        assert_eq!(simulated.input, 1_000_000);
+        assert_eq!(simulated.output, 55);
+        assert_eq!(simulated.lamports_spent, 5_000);
+    }
+
+    #[test]
+    fn route_pays_rent_for_its_intermediate_account() {
+        // SOL in, through a new USDC account that keeps 3 units more than the second leg spent.
+        let before = [wallet(10_000_000), None, token(7), None];
+        let after = [
+            wallet(10_000_000 - 5_000 - 2 * RENT - 1_000_000),
+            wsol(0),
+            token(99),
+            token(3),
+        ];
+        let simulated = simulated_swap(&before, &after, 5_000, true, false).unwrap();
+        assert_eq!(simulated.input, 1_000_000);
+        assert_eq!(simulated.output, 92);
+        assert_eq!(simulated.lamports_spent, 5_000 + 2 * RENT);
+    }
+
+    /// Whether the swaps fill when each leg returns `outputs[i]`: every leg must meet its minimum
+    /// and the intermediate balance must cover what the next leg spends.
+    fn fills(swaps: &[SwapKind], outputs: &[u64]) -> bool {
+        let mut held = None;
+        swaps.iter().zip(outputs).all(|(swap, &output)| {
+            let SwapKind::BaseIn {
+                amount_in,
+                min_amount_out,
+            } = *swap
+            else {
+                return false;
+            };
+            let funded = held.is_none_or(|held| amount_in <= held);
+            held = Some(output);
+            funded && output >= min_amount_out
+        })
+    }
+
+    #[test]
+    fn first_leg_may_return_less_than_quoted() {
+        let pool = |reserve_in, reserve_out| ConstantProduct {
+            reserve_in,
+            reserve_out,
+            decimals_in: 0,
+            decimals_out: 0,
+            fee: FeeRate::RAYDIUM_V4,
+        };
+        let (first, second) = (pool(1_000_000, 2_000_000), pool(4_000_000, 1_000_000));
+        let slippage = Slippage::from_bps(50).unwrap();
+        let quote = quote_path_exact_in_within(&[&first, &second], 10_000, slippage).unwrap();
+        let swaps = exact_in_swaps(&quote, slippage);
+
+        let [leg1, leg2] = quote.legs.as_slice() else {
+            panic!("two legs");
+        };
+        assert_eq!(swaps[0].input_amount(), 10_000);
+        assert_eq!(swaps[1].input_amount(), slippage.min_out(leg1.amount_out));
+        assert!(fills(&swaps, &[leg1.amount_out, leg2.amount_out]));
+        assert!(fills(&swaps, &[leg1.amount_out - 1, leg2.amount_out]));
+        let floor = slippage.min_out(leg1.amount_out);
+        assert!(fills(&swaps, &[floor, leg2.amount_out]));
+        assert!(!fills(&swaps, &[floor - 1, leg2.amount_out]));
+        assert!(!fills(
+            &swaps,
+            &[leg1.amount_out, slippage.min_out(leg2.amount_out) - 1]
         ));
     }
-    Ok(())
+
+    #[test]
+    fn bought_route_funds_the_second_leg_maximum() {
+        let pool = |reserve_in, reserve_out| ConstantProduct {
+            reserve_in,
+            reserve_out,
+            decimals_in: 0,
+            decimals_out: 0,
+            fee: FeeRate::RAYDIUM_V4,
+        };
+        let (first, second) = (pool(1_000_000, 2_000_000), pool(4_000_000, 1_000_000));
+        let slippage = Slippage::from_bps(50).unwrap();
+        let quote = quote_path_exact_out_within(&[&first, &second], 5_000, slippage).unwrap();
+        let swaps = exact_out_swaps(&quote, slippage);
+        let [SwapKind::BaseOut {
+            max_amount_in,
+            amount_out: intermediate,
+        }, SwapKind::BaseOut {
+            max_amount_in: second_max_in,
+            amount_out,
+        }] = swaps[..]
+        else {
+            panic!("two exact-out legs");
+        };
+        assert_eq!(amount_out, 5_000);
+        assert_eq!(intermediate, second_max_in);
+        assert_eq!(max_amount_in, slippage.max_in(quote.amount_in()));
+    }
+
+    #[test]
+    fn no_candidate_is_an_invalid_argument() {
+        let mint = Pubkey::new_unique();
+        let err = best_route(
+            TradeSide::Sell,
+            "1",
+            Vec::new(),
+            (&mint, &mint),
+            Slippage::from_bps(50).unwrap(),
+            ("IN", "OUT"),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error_code(&err), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn kept_wrapped_output_counts_as_sol() {
+        let before = [wallet(10_000_000), token(300), wsol(1_000)];
+        let after = [wallet(10_000_000 - 5_000), token(0), wsol(751_000)];
+        let simulated = simulated_swap(&before, &after, 5_000, false, true).unwrap();
+        assert_eq!(simulated.input, 300);
+        assert_eq!(simulated.output, 750_000);
+        assert_eq!(simulated.lamports_spent, 5_000);
+    }
 }
@@ -0,0 +1,398 @@
+use std::fs;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::app::cli::{
+    CostMethodArg, SwapToken, TradesArgs, TradesCommand, TradesExportArgs, TradesListArgs,
+    TradesPnlArgs,
+};
+use crate::app::output::{emit, is_json, say};
+use crate::config::journal::{EntrySide, QuoteLimit, TradeEntry, TradeJournal, JOURNAL_FILE_NAME};
+use crate::core::amount::format_integer_amount;
+use crate::core::pnl::{position, CostMethod, Fill, Position};
+use crate::core::price::Price;
+use crate::error::wmgr_error;
+
+/// SOL has 9 decimals; network fees are lamports.
+const SOL_DECIMALS: u8 = 9;
+
+const CSV_HEADER: &str = "time,signature,side,token,token_mint,token_amount,counter,counter_mint,counter_amount,price,amount_source,network_fee_sol,quoted_input,quoted_output,quoted_limit,pool_fee,price_impact_percent,slippage_bps,pool";
+
+pub fn handle_trades(args: TradesArgs) -> Result<()> {
+    match args.command {
+        TradesCommand::List(args) => handle_list(args),
+        TradesCommand::Export(args) => handle_export(args),
+        TradesCommand::Pnl(args) => handle_pnl(args),
+    }
+}
+
+fn handle_list(args: TradesListArgs) -> Result<()> {
+    let journal = load_journal()?;
+    let mut entries: Vec<&TradeEntry> = journal
+        .entries
+        .iter()
+        .filter(|entry| matches_token(entry, args.token))
+        .collect();
+    if let Some(last) = args.last {
+        entries.drain(..entries.len().saturating_sub(last));
+    }
+    if entries.is_empty() {
+        say!("No trades in {JOURNAL_FILE_NAME}.");
+    }
+    for entry in &entries {
+        let (token_amount, counter_amount) = amounts_ui(entry);
+        say!(
+            "{:<12} {:<4} {} {} {} {} {} at {:.8} ({})",
+            entry.at,
+            entry.side.label(),
+            token_amount,
+            entry.token_symbol,
+            match entry.side {
+                EntrySide::Buy => "for",
+                EntrySide::Sell => "into",
+            },
+            counter_amount,
+            entry.counter_symbol,
+            price(entry).to_f64(),
+            entry.signature
+        );
+    }
+    emit(json!({
+        "trades": entries.iter().map(|entry| entry_json(entry)).collect::<Vec<_>>(),
+    }));
+    Ok(())
+}
+
+fn handle_export(args: TradesExportArgs) -> Result<()> {
+    let journal = load_journal()?;
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for entry in &journal.entries {
+        csv.push_str(&csv_row(entry));
+        csv.push('\n');
+    }
+    match &args.out {
+        Some(path) => {
+            fs::write(path, &csv)
+                .map_err(|err| wmgr_error!(Io, "Failed to write {}: {err}", path.display()))?;
+            say!(
+                "OK: {} trades written to {}",
+                journal.entries.len(),
+                path.display()
+            );
+            emit(json!({
+                "path": path.display().to_string(),
+                "trades": journal.entries.len(),
+            }));
+        }
+        // In JSON mode the CSV goes inside the result document, which is all stdout may hold.
+        None if is_json() => emit(json!({
+            "trades": journal.entries.len(),
+            "csv": csv,
+        })),
+        None => print!("{csv}"),
+    }
+    Ok(())
+}
+
+fn handle_pnl(args: TradesPnlArgs) -> Result<()> {
+    let journal = load_journal()?;
+    let method = match args.method {
+        CostMethodArg::Fifo => CostMethod::Fifo,
+        CostMethodArg::Average => CostMethod::Average,
+    };
+
+    let pairs = fills_by_pair(&journal.entries, args.token);
+    if pairs.is_empty() {
+        say!("No trades in {JOURNAL_FILE_NAME}.");
+    }
+
+    let mut docs = Vec::new();
+    for (first, fills) in pairs {
+        let trades = fills.len();
+        let position = position(fills, method);
+        let average_cost = average_cost(first, &position);
+        let token = |amount: u128| format_integer_amount(amount, first.token_decimals);
+        let counter = |amount: u128| format_integer_amount(amount, first.counter_decimals);
+        say!(
+            "{} in {}: {} trades, bought {}, sold {}, held {}",
+            first.token_symbol,
+            first.counter_symbol,
+            trades,
+            token(position.bought),
+            token(position.sold),
+            token(position.held)
+        );
+        say!(
+            "  cost basis {} {} (average {}), realised P&L {} {}",
+            counter(position.cost_basis),
+            first.counter_symbol,
+            average_cost
+                .map(|price| format!("{:.8}", price.to_f64()))
+                .unwrap_or_else(|| "n/a".to_string()),
+            signed(position.realised, first.counter_decimals),
+            first.counter_symbol
+        );
+        if position.unmatched > 0 {
+            say!(
+                "  {} {} sold without a journaled buy; left out of the P&L.",
+                token(position.unmatched),
+                first.token_symbol
+            );
+        }
+        docs.push(json!({
+            "token": first.token_symbol,
+            "token_mint": first.token_mint,
+            "counter": first.counter_symbol,
+            "counter_mint": first.counter_mint,
+            "trades": trades,
+            "bought": token(position.bought),
+            "sold": token(position.sold),
+            "held": token(position.held),
+            "cost_basis": counter(position.cost_basis),
+            "average_cost": average_cost.map(|price| price.to_f64()),
+            "realised": signed(position.realised, first.counter_decimals),
+            "unmatched": token(position.unmatched),
+        }));
+    }
+
+    let fees: u64 = journal
+        .entries
+        .iter()
+        .filter(|entry| matches_token(entry, args.token))
+        .filter_map(|entry| entry.fee)
+        .sum();
+    if fees > 0 {
+        say!(
+            "Network fees: {} SOL (not included above)",
+            format_integer_amount(fees as u128, SOL_DECIMALS)
+        );
+    }
+    emit(json!({
+        "method": match method {
+            CostMethod::Fifo => "fifo",
+            CostMethod::Average => "average",
+        },
+        "positions": docs,
+        "network_fees_sol": format_integer_amount(fees as u128, SOL_DECIMALS),
+    }));
+    Ok(())
+}
+
+/// One position per token and quote token, in order of first trade: P&L in USDC and in SOL
+/// do not add up.
+fn fills_by_pair(
+    entries: &[TradeEntry],
+    token: Option<SwapToken>,
+) -> Vec<(&TradeEntry, Vec<Fill>)> {
+    let mut pairs: Vec<(&TradeEntry, Vec<Fill>)> = Vec::new();
+    for entry in entries.iter().filter(|entry| matches_token(entry, token)) {
+        let (quantity, value) = entry.amounts();
+        let fill = Fill {
+            buy: entry.side == EntrySide::Buy,
+            quantity: quantity as u128,
+            value: value as u128,
+        };
+        match pairs.iter_mut().find(|(first, _)| {
+            first.token_mint == entry.token_mint && first.counter_mint == entry.counter_mint
+        }) {
+            Some((_, fills)) => fills.push(fill),
+            None => pairs.push((entry, vec![fill])),
+        }
+    }
+    pairs
+}
+
+fn load_journal() -> Result<TradeJournal> {
+    TradeJournal::load_from_cwd()
+        .map_err(|err| wmgr_error!(Io, "Failed to read {JOURNAL_FILE_NAME}: {err}"))
+}
+
+fn matches_token(entry: &TradeEntry, token: Option<SwapToken>) -> bool {
+    token.is_none_or(|token| entry.token_mint == token.mint().to_string())
+}
+
+/// `(token, counter)` amounts in UI units.
+fn amounts_ui(entry: &TradeEntry) -> (String, String) {
+    let (token, counter) = entry.amounts();
+    (
+        format_integer_amount(token as u128, entry.token_decimals),
+        format_integer_amount(counter as u128, entry.counter_decimals),
+    )
+}
+
+/// Counter token paid or received per token.
+fn price(entry: &TradeEntry) -> Price {
+    let (token, counter) = entry.amounts();
+    Price::from_amounts(
+        token as u128,
+        counter as u128,
+        entry.token_decimals,
+        entry.counter_decimals,
+    )
+}
+
+fn average_cost(first: &TradeEntry, position: &Position) -> Option<Price> {
+    (position.held > 0).then(|| {
+        Price::from_amounts(
+            position.held,
+            position.cost_basis,
+            first.token_decimals,
+            first.counter_decimals,
+        )
+    })
+}
+
+fn signed(amount: i128, decimals: u8) -> String {
+    let abs = format_integer_amount(amount.unsigned_abs(), decimals);
+    if amount < 0 {
+        format!("-{abs}")
+    } else {
+        abs
+    }
+}
+
+fn entry_json(entry: &TradeEntry) -> Value {
+    let (token_amount, counter_amount) = amounts_ui(entry);
+    let (decimals_in, decimals_out) = match entry.side {
+        EntrySide::Buy => (entry.counter_decimals, entry.token_decimals),
+        EntrySide::Sell => (entry.token_decimals, entry.counter_decimals),
+    };
+    json!({
+        "at": entry.at,
+        "signature": entry.signature,
+        "side": entry.side.label(),
+        "token": entry.token_symbol,
+        "token_mint": entry.token_mint,
+        "token_amount": token_amount,
+        "counter": entry.counter_symbol,
+        "counter_mint": entry.counter_mint,
+        "counter_amount": counter_amount,
+        "price": price(entry).to_f64(),
+        "amount_source": entry.amount_source.label(),
+        "fee": entry.fee,
+        "pool": entry.pool,
+        "quote": {
+            "input": format_integer_amount(entry.quote.input as u128, decimals_in),
+            "output": format_integer_amount(entry.quote.output as u128, decimals_out),
+            "limit": match entry.quote.limit {
+                QuoteLimit::MinOutput(min) => format_integer_amount(min as u128, decimals_out),
+                QuoteLimit::MaxInput(max) => format_integer_amount(max as u128, decimals_in),
+            },
+            "pool_fee": format_integer_amount(entry.quote.pool_fee as u128, decimals_in),
+            "price_impact_percent": entry.quote.price_impact,
+            "slippage_bps": entry.quote.slippage_bps,
+        },
+    })
+}
+
+fn csv_row(entry: &TradeEntry) -> String {
+    let doc = entry_json(entry);
+    let quote = &doc["quote"];
+    let text = |value: &Value| match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    [
+        text(&doc["at"]),
+        text(&doc["signature"]),
+        text(&doc["side"]),
+        text(&doc["token"]),
+        text(&doc["token_mint"]),
+        text(&doc["token_amount"]),
+        text(&doc["counter"]),
+        text(&doc["counter_mint"]),
+        text(&doc["counter_amount"]),
+        text(&doc["price"]),
+        text(&doc["amount_source"]),
+        entry
+            .fee
+            .map(|fee| format_integer_amount(fee as u128, SOL_DECIMALS))
+            .unwrap_or_default(),
+        text(&quote["input"]),
+        text(&quote["output"]),
+        text(&quote["limit"]),
+        text(&quote["pool_fee"]),
+        text(&quote["price_impact_percent"]),
+        text(&quote["slippage_bps"]),
+        text(&doc["pool"]),
+    ]
+    .iter()
+    .map(|field| csv_field(field))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Quotes a field that holds a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::journal::{AmountSource, QuotedTrade};
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    /// A sell of `input` SOL or USDC for the other one, as a swap journals it.
+    fn sell(token: &str, input: u64, output: u64) -> TradeEntry {
+        let (token, counter) = if token == SOL {
+            ((SOL, "SOL", 9), (USDC, "USDC", 6))
+        } else {
+            ((USDC, "USDC", 6), (SOL, "SOL", 9))
+        };
+        TradeEntry {
+            signature: "sig".to_string(),
+            at: 0,
+            side: EntrySide::Sell,
+            token_mint: token.0.to_string(),
+            token_symbol: token.1.to_string(),
+            token_decimals: token.2,
+            counter_mint: counter.0.to_string(),
+            counter_symbol: counter.1.to_string(),
+            counter_decimals: counter.2,
+            pool: "pool".to_string(),
+            input,
+            output,
+            amount_source: AmountSource::Confirmed,
+            fee: None,
+            quote: QuotedTrade {
+                input,
+                output,
+                limit: QuoteLimit::MinOutput(output),
+                pool_fee: 0,
+                price_impact: 0.0,
+                slippage_bps: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn dca_fills_count_as_buys_of_the_bought_token() {
+        // A DCA slot spends 150 USDC on 1 SOL; half of it is sold later for 80 USDC.
+        let entries = [
+            sell(USDC, 150_000_000, 1_000_000_000).flipped(),
+            sell(SOL, 500_000_000, 80_000_000),
+        ];
+        let pairs = fills_by_pair(&entries, Some(SwapToken::Sol));
+        let [(first, fills)] = pairs.as_slice() else {
+            panic!("one SOL/USDC position");
+        };
+        assert_eq!(first.side, EntrySide::Buy);
+        assert_eq!(first.counter_symbol, "USDC");
+        let position = position(fills.clone(), CostMethod::Fifo);
+        assert_eq!(position.bought, 1_000_000_000);
+        assert_eq!(position.sold, 500_000_000);
+        assert_eq!(position.cost_basis, 75_000_000);
+        assert_eq!(position.realised, 5_000_000);
+        assert_eq!(position.unmatched, 0);
+    }
+}
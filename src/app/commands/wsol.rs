@@ -0,0 +1,218 @@
+use anyhow::Result;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+
+use crate::app::cli::{SolanaKeyOptions, SolanaRpcOptions, UnwrapArgs, WrapArgs};
+use crate::app::commands::{commitment_from_arg, print_landed};
+use crate::app::defaults::{apply_solana_key_defaults, resolve_solana_rpc_defaults};
+use crate::app::output::{emit, say};
+use crate::config::app_config::WmgrConfig;
+use crate::config::clusters::get_cluster_config;
+use crate::core::amount::{format_integer_amount, parse_amount_to_u64};
+use crate::error::{wmgr_error, WmgrError};
+use crate::infra::keys::solana::resolve_solana_keypair;
+use crate::infra::solana::{
+    create_rpc_client, get_transaction_fee, prepare_transaction, refresh_if_expired,
+    simulate_prepared, simulation_error, snapshot_accounts, submit_prepared, with_compute_budget,
+    AccountSnapshot, ComputeBudget,
+};
+use crate::infra::wsol::{
+    build_unwrap_instructions, build_wrap_instructions, load_wsol_context, wsol_address,
+};
+use crate::utils::prompt::confirm;
+
+const SOL_DECIMALS: u8 = 9;
+
+#[derive(Clone, Copy)]
+enum WsolAction {
+    Wrap,
+    Unwrap,
+}
+
+impl WsolAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            WsolAction::Wrap => "wrap",
+            WsolAction::Unwrap => "unwrap",
+        }
+    }
+}
+
+pub async fn handle_wrap(args: WrapArgs, cfg: &WmgrConfig) -> Result<()> {
+    let WrapArgs { amount, key, rpc } = args;
+    let lamports = parse_amount_to_u64(&amount, SOL_DECIMALS)?;
+    if lamports == 0 {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Amount must be greater than 0"
+        ));
+    }
+
+    let (client, keypair, budget) = connect(key, rpc, cfg)?;
+    let instructions = build_wrap_instructions(&keypair.pubkey(), lamports)?;
+    let instructions = with_compute_budget(&client, &keypair, instructions, &budget).await?;
+    review_and_submit(&client, &keypair, &instructions, WsolAction::Wrap, lamports).await
+}
+
+pub async fn handle_unwrap(args: UnwrapArgs, cfg: &WmgrConfig) -> Result<()> {
+    let UnwrapArgs { amount, key, rpc } = args;
+    let lamports = if amount.trim().eq_ignore_ascii_case("all") {
+        None
+    } else {
+        let lamports = parse_amount_to_u64(&amount, SOL_DECIMALS)?;
+        if lamports == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Amount must be greater than 0"
+            ));
+        }
+        Some(lamports)
+    };
+
+    let (client, keypair, budget) = connect(key, rpc, cfg)?;
+    let owner = keypair.pubkey();
+    let wsol = load_wsol_context(&client, &owner, false, false).await?;
+    let Some(balance) = wsol.balance else {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "No wSOL account {} to unwrap",
+            wsol_address(&owner)
+        ));
+    };
+    if let Some(lamports) = lamports.filter(|lamports| *lamports > balance) {
+        return Err(wmgr_error!(
+            InsufficientFunds,
+            "Cannot unwrap {} SOL: wSOL balance is {} SOL",
+            format_integer_amount(lamports as u128, SOL_DECIMALS),
+            format_integer_amount(balance as u128, SOL_DECIMALS)
+        ));
+    }
+
+    let instructions = build_unwrap_instructions(&owner, lamports, wsol.rent)?;
+    let instructions = with_compute_budget(&client, &keypair, instructions, &budget).await?;
+    review_and_submit(
+        &client,
+        &keypair,
+        &instructions,
+        WsolAction::Unwrap,
+        lamports.unwrap_or(balance),
+    )
+    .await
+}
+
+fn connect(
+    key: SolanaKeyOptions,
+    rpc: SolanaRpcOptions,
+    cfg: &WmgrConfig,
+) -> Result<(RpcClient, Keypair, ComputeBudget)> {
+    let key = apply_solana_key_defaults(key, cfg);
+    let keypair = resolve_solana_keypair(&key)?;
+    let rpc = resolve_solana_rpc_defaults(rpc, cfg);
+    let budget = rpc.compute_budget()?;
+    let cluster = get_cluster_config(&rpc.cluster, rpc.rpc.as_deref())?;
+    say!("Using cluster: {}, RPC: {}", cluster.name, cluster.rpc_url);
+    let client = create_rpc_client(&cluster.rpc_url, commitment_from_arg(rpc.commitment));
+    Ok((client, keypair, budget))
+}
+
+/// Simulates the transaction against the wallet and its wSOL account, shows both balances before
+/// and after, and submits the same transaction once confirmed.
+async fn review_and_submit(
+    client: &RpcClient,
+    keypair: &Keypair,
+    instructions: &[Instruction],
+    action: WsolAction,
+    amount: u64,
+) -> Result<()> {
+    let owner = keypair.pubkey();
+    let account = wsol_address(&owner);
+    let watched = [owner, account];
+
+    let prepared = prepare_transaction(client, keypair, instructions).await?;
+    let network_fee = get_transaction_fee(client, &prepared).await?;
+    let before = snapshot_accounts(client, &watched).await?;
+    let (simulation, after) = simulate_prepared(client, &prepared, &watched).await?;
+    if let Some(err) = &simulation.err {
+        if let Some(logs) = &simulation.logs {
+            for log in logs {
+                say!("{log}");
+            }
+        }
+        return Err(simulation_error(&err.clone().into(), instructions));
+    }
+
+    let sol = |snapshots: &[Option<AccountSnapshot>]| {
+        snapshots[0]
+            .map(|snapshot| snapshot.lamports)
+            .unwrap_or_default()
+    };
+    let wrapped = |snapshots: &[Option<AccountSnapshot>]| {
+        snapshots[1].and_then(|snapshot| snapshot.token_amount)
+    };
+    let format_sol = |lamports: u64| format_integer_amount(lamports as u128, SOL_DECIMALS);
+    let format_wsol = |amount: Option<u64>| match amount {
+        Some(amount) => format!("{} wSOL", format_sol(amount)),
+        None => "(no account)".to_string(),
+    };
+
+    say!("Review:");
+    say!("{:15}{}", "Action:", action.as_str());
+    say!("{:15}{}", "Wallet:", owner);
+    say!("{:15}{}", "wSOL account:", account);
+    say!("{:15}{} SOL", "Amount:", format_sol(amount));
+    say!(
+        "{:15}{} SOL -> {} SOL",
+        "SOL:",
+        format_sol(sol(&before)),
+        format_sol(sol(&after))
+    );
+    say!(
+        "{:15}{} -> {}",
+        "wSOL:",
+        format_wsol(wrapped(&before)),
+        format_wsol(wrapped(&after))
+    );
+    say!("{:15}{} SOL", "Network fee:", format_sol(network_fee));
+    let units = simulation
+        .units_consumed
+        .map(|v| format!(", {v} CU"))
+        .unwrap_or_default();
+    say!("{:15}ok{units}", "Simulation:");
+
+    if !confirm("Continue? (y/N):")? {
+        return Err(WmgrError::UserAborted.into());
+    }
+
+    let prepared = refresh_if_expired(client, keypair, instructions, prepared).await?;
+    let landed = submit_prepared(client, &prepared).await?;
+    say!(
+        "SUCCESS: {} signature: {}",
+        action.as_str(),
+        landed.signature
+    );
+    print_landed(&landed);
+    emit(json!({
+        "chain": "solana",
+        "status": "sent",
+        "action": action.as_str(),
+        "owner": owner.to_string(),
+        "account": account.to_string(),
+        "amount": format_sol(amount),
+        "simulation": {
+            "sol_before": format_sol(sol(&before)),
+            "sol_after": format_sol(sol(&after)),
+            "wsol_before": wrapped(&before).map(format_sol),
+            "wsol_after": wrapped(&after).map(format_sol),
+            "compute_units": simulation.units_consumed,
+        },
+        "network_fee": network_fee,
+        "signature": landed.signature.to_string(),
+        "slot": landed.slot,
+        "fee": landed.fee,
+        "compute_units": landed.compute_units,
+    }));
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
+
+use anyhow::Result;
 
 use crate::{
     app::cli::{
@@ -6,11 +8,18 @@ use crate::{
         SolanaRpcOptions,
     },
     config::app_config::WmgrConfig,
+    core::slippage::Slippage,
+    infra::{
+        evm::FeeOptions,
+        solana::{ComputeBudget, PriorityFee},
+    },
 };
 
 const DEFAULT_SOLANA_CLUSTER: &str = "mainnet-beta";
 const DEFAULT_SOLANA_COMMITMENT: CommitmentArg = CommitmentArg::Confirmed;
-const DEFAULT_SLIPPAGE: f64 = 0.1;
+/// 0.1%.
+const DEFAULT_SLIPPAGE_BPS: u16 = 10;
+const DEFAULT_REQUOTE_THRESHOLD: f64 = 0.5;
 
 pub fn apply_solana_key_defaults(mut opts: SolanaKeyOptions, cfg: &WmgrConfig) -> SolanaKeyOptions {
     let uses_svpi = opts.svpi
@@ -105,6 +114,8 @@ pub struct ResolvedSolanaRpcOptions {
     pub cluster: String,
     pub rpc: Option<String>,
     pub commitment: CommitmentArg,
+    pub priority_fee: Option<String>,
+    pub compute_units: Option<u32>,
 }
 
 pub fn resolve_solana_rpc_defaults(
@@ -121,6 +132,24 @@ pub fn resolve_solana_rpc_defaults(
             .commitment
             .or_else(|| commitment_from_cfg(cfg))
             .unwrap_or(DEFAULT_SOLANA_COMMITMENT),
+        priority_fee: opts
+            .priority_fee
+            .or_else(|| cfg.solana_priority_fee.clone()),
+        compute_units: opts.compute_units.or(cfg.solana_compute_units),
+    }
+}
+
+impl ResolvedSolanaRpcOptions {
+    pub fn compute_budget(&self) -> Result<ComputeBudget> {
+        let priority_fee = self
+            .priority_fee
+            .as_deref()
+            .map(PriorityFee::from_str)
+            .transpose()?;
+        Ok(ComputeBudget {
+            priority_fee,
+            compute_units: self.compute_units,
+        })
     }
 }
 
@@ -138,6 +167,8 @@ pub fn resolve_balance_solana_defaults(
         commitment: commitment
             .or_else(|| commitment_from_cfg(cfg))
             .unwrap_or(DEFAULT_SOLANA_COMMITMENT),
+        priority_fee: None,
+        compute_units: None,
     }
 }
 
@@ -145,23 +176,72 @@ pub struct ResolvedEvmTxOptions {
     pub network: EvmNetworkArg,
     pub rpc: Option<String>,
     pub gas_price: Option<String>,
+    pub max_fee: Option<String>,
+    pub priority_fee: Option<String>,
     pub gas_limit: Option<u64>,
 }
 
 pub fn resolve_evm_tx_defaults(opts: EvmTxOptions, cfg: &WmgrConfig) -> ResolvedEvmTxOptions {
+    // Fee flags pick the fee mode: an explicit legacy or EIP-1559 flag hides config values of
+    // the other mode instead of combining with them.
+    let cli_legacy = opts.gas_price.is_some();
+    let cli_eip1559 = opts.max_fee.is_some() || opts.priority_fee.is_some();
+
+    let gas_price = if cli_eip1559 {
+        opts.gas_price
+    } else {
+        opts.gas_price.or_else(|| cfg.evm_gas_price.clone())
+    };
+    let (max_fee, priority_fee) = if cli_legacy {
+        (opts.max_fee, opts.priority_fee)
+    } else {
+        (
+            opts.max_fee.or_else(|| cfg.evm_max_fee.clone()),
+            opts.priority_fee.or_else(|| cfg.evm_priority_fee.clone()),
+        )
+    };
+
     ResolvedEvmTxOptions {
         network: opts
             .network
             .or_else(|| evm_network_from_cfg(cfg))
             .unwrap_or(EvmNetworkArg::Mainnet),
         rpc: opts.rpc.or_else(|| cfg.evm_rpc.clone()),
-        gas_price: opts.gas_price.or_else(|| cfg.evm_gas_price.clone()),
+        gas_price,
+        max_fee,
+        priority_fee,
         gas_limit: opts.gas_limit.or(cfg.evm_gas_limit),
     }
 }
 
-pub fn resolve_slippage(slippage: Option<f64>, cfg: &WmgrConfig) -> f64 {
-    slippage.or(cfg.slippage).unwrap_or(DEFAULT_SLIPPAGE)
+impl ResolvedEvmTxOptions {
+    pub fn fee_options(&self) -> FeeOptions<'_> {
+        FeeOptions {
+            gas_price: self.gas_price.as_deref(),
+            max_fee: self.max_fee.as_deref(),
+            priority_fee: self.priority_fee.as_deref(),
+        }
+    }
+}
+
+/// A config value above 100% (only possible in a hand-edited file) falls back to the default.
+pub fn resolve_slippage(slippage: Option<Slippage>, cfg: &WmgrConfig) -> Slippage {
+    slippage
+        .or_else(|| {
+            cfg.slippage_bps
+                .and_then(|bps| Slippage::from_bps(bps).ok())
+        })
+        .unwrap_or_else(|| Slippage::from_bps(DEFAULT_SLIPPAGE_BPS).expect("default slippage"))
+}
+
+pub fn resolve_max_price_impact(max_price_impact: Option<f64>, cfg: &WmgrConfig) -> Option<f64> {
+    max_price_impact.or(cfg.max_price_impact)
+}
+
+pub fn resolve_requote_threshold(requote_threshold: Option<f64>, cfg: &WmgrConfig) -> f64 {
+    requote_threshold
+        .or(cfg.requote_threshold)
+        .unwrap_or(DEFAULT_REQUOTE_THRESHOLD)
 }
 
 fn commitment_from_cfg(cfg: &WmgrConfig) -> Option<CommitmentArg> {
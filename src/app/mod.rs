@@ -2,3 +2,4 @@ pub mod cli;
 pub mod cli_mode;
 pub mod commands;
 pub mod defaults;
+pub mod output;
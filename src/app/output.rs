@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::{json, Value};
+
+use crate::app::cli::OutputFormat;
+use crate::error::{error_code, ErrorCode};
+
+/// Envelope schema of JSON output, in the style of SVPI's `svpi.response.v1`.
+pub const RESPONSE_SCHEMA: &str = "wmgr.response.v1";
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_format(format: OutputFormat) {
+    JSON_OUTPUT.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn format() -> OutputFormat {
+    if is_json() {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    }
+}
+
+pub fn is_json() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Prints a human-readable line: to stdout in text mode, to stderr in JSON mode so stdout only
+/// carries the JSON document.
+macro_rules! say {
+    () => {
+        $crate::app::output::say!("")
+    };
+    ($($arg:tt)*) => {
+        if $crate::app::output::is_json() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use say;
+
+/// Prints the command result document in JSON mode; a no-op in text mode.
+pub fn emit(result: Value) {
+    if is_json() {
+        println!(
+            "{}",
+            json!({ "schema": RESPONSE_SCHEMA, "ok": true, "result": result })
+        );
+    }
+}
+
+/// Reports a failed command: an error envelope in JSON mode, `error[code]: ...` on stderr
+/// otherwise. Returns the code so the caller can pick the exit status.
+pub fn report_error(err: &anyhow::Error) -> ErrorCode {
+    let code = error_code(err);
+    if is_json() {
+        emit_error(code, &err.to_string());
+    } else {
+        print_error(code, &err.to_string());
+    }
+    code
+}
+
+pub fn print_error(code: ErrorCode, message: &str) {
+    eprintln!("error[{}]: {message}", code.as_str());
+}
+
+/// Exits on a command line parse error, as an error envelope when `--output json` was requested.
+pub fn exit_parse_error(err: clap::Error) -> ! {
+    let args: Vec<String> = std::env::args().collect();
+    let json_requested = args.iter().any(|arg| arg == "--output=json")
+        || args
+            .windows(2)
+            .any(|w| w[0] == "--output" && w[1] == "json");
+    if json_requested && err.use_stderr() {
+        emit_error(ErrorCode::InvalidArgument, err.to_string().trim());
+        std::process::exit(err.exit_code());
+    }
+    err.exit()
+}
+
+fn emit_error(code: ErrorCode, message: &str) {
+    println!(
+        "{}",
+        json!({
+            "schema": RESPONSE_SCHEMA,
+            "ok": false,
+            "error": { "code": code.as_str(), "message": message },
+        })
+    );
+}
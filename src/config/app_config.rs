@@ -6,13 +6,19 @@ use std::{
 
 use borsh_derive::{BorshDeserialize, BorshSerialize};
 
+use crate::core::slippage::Slippage;
+
 pub const CONFIG_FILE_NAME: &str = ".wmgr";
 
-const CONFIG_MAGIC: [u8; 4] = *b"WCFG";
+/// Starts a versioned file: the magic, a version byte, then the Borsh-encoded [`WmgrConfig`].
+const CONFIG_MAGIC: [u8; 4] = *b"WCFV";
+/// Bump when the fields change, and decode the previous version in `from_bytes`.
+const CONFIG_VERSION: u8 = 1;
+/// Starts a file written before the version byte; see [`LegacyConfig`].
+const LEGACY_MAGIC: [u8; 4] = *b"WCFG";
 
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize)]
 pub struct WmgrConfig {
-    magic: [u8; 4],
     /// 0=manual, 1=svpi
     pub mode: u8,
     pub svpi_cmd: Option<String>,
@@ -22,31 +28,18 @@ pub struct WmgrConfig {
     pub solana_rpc: Option<String>,
     /// 0=processed, 1=confirmed, 2=finalized
     pub solana_commitment: Option<u8>,
-    pub slippage: Option<f64>,
+    /// Basis points (1 = 0.01%).
+    pub slippage_bps: Option<u16>,
     pub evm_network: Option<String>,
     pub evm_rpc: Option<String>,
     pub evm_gas_price: Option<String>,
     pub evm_gas_limit: Option<u64>,
-}
-
-impl Default for WmgrConfig {
-    fn default() -> Self {
-        Self {
-            magic: CONFIG_MAGIC,
-            mode: 0,
-            svpi_cmd: None,
-            svpi_file: None,
-            svpi_name: None,
-            solana_cluster: None,
-            solana_rpc: None,
-            solana_commitment: None,
-            slippage: None,
-            evm_network: None,
-            evm_rpc: None,
-            evm_gas_price: None,
-            evm_gas_limit: None,
-        }
-    }
+    pub evm_max_fee: Option<String>,
+    pub evm_priority_fee: Option<String>,
+    pub solana_priority_fee: Option<String>,
+    pub solana_compute_units: Option<u32>,
+    pub max_price_impact: Option<f64>,
+    pub requote_threshold: Option<f64>,
 }
 
 impl WmgrConfig {
@@ -71,19 +64,22 @@ impl WmgrConfig {
         };
 
         if bytes.len() == 1 {
-            let mut cfg = Self::default();
-            cfg.mode = bytes[0];
-            return Ok(Some(cfg));
+            return Ok(Some(Self {
+                mode: bytes[0],
+                ..Self::default()
+            }));
         }
 
-        let cfg = match <Self as borsh::BorshDeserialize>::try_from_slice(&bytes) {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
-        };
-        if cfg.magic != CONFIG_MAGIC {
-            return Ok(None);
-        }
-        Ok(Some(cfg))
+        // Saving over an unreadable file would silently drop the settings in it.
+        Self::from_bytes(&bytes).map(Some).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} is not a wmgr config or was written by a newer wmgr",
+                    path.display()
+                ),
+            )
+        })
     }
 
     pub fn save_to_cwd(&self) -> io::Result<()> {
@@ -92,29 +88,158 @@ impl WmgrConfig {
     }
 
     pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
-        let mut cfg = self.clone();
-        cfg.magic = CONFIG_MAGIC;
-
-        let bytes = borsh::to_vec(&cfg)
+        let bytes = self
+            .to_bytes()
             .map_err(|_| io::Error::new(ErrorKind::InvalidData, "Failed to serialize config"))?;
 
-        let Some(parent) = path.parent() else {
-            return Err(io::Error::new(
-                ErrorKind::InvalidInput,
-                "Invalid config path",
-            ));
-        };
-        let tmp_path = parent.join(format!("{CONFIG_FILE_NAME}.tmp"));
-
-        fs::write(&tmp_path, bytes)?;
-        if let Err(err) = fs::rename(&tmp_path, path) {
-            if err.kind() != ErrorKind::AlreadyExists {
-                return Err(err);
-            }
-            let _ = fs::remove_file(path);
-            fs::rename(&tmp_path, path)?;
+        replace_file(path, &bytes)
+    }
+
+    fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = CONFIG_MAGIC.to_vec();
+        bytes.push(CONFIG_VERSION);
+        borsh::to_writer(&mut bytes, self)?;
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (magic, rest) = bytes.split_first_chunk::<4>()?;
+        match *magic {
+            CONFIG_MAGIC => match rest.split_first()? {
+                (&CONFIG_VERSION, body) => {
+                    <Self as borsh::BorshDeserialize>::try_from_slice(body).ok()
+                }
+                _ => None,
+            },
+            LEGACY_MAGIC => <LegacyConfig as borsh::BorshDeserialize>::try_from_slice(rest)
+                .ok()
+                .map(Self::from),
+            _ => None,
         }
+    }
+}
 
-        Ok(())
+/// The layout written before the version byte, after [`LEGACY_MAGIC`].
+#[derive(BorshDeserialize)]
+struct LegacyConfig {
+    mode: u8,
+    svpi_cmd: Option<String>,
+    svpi_file: Option<String>,
+    svpi_name: Option<String>,
+    solana_cluster: Option<String>,
+    solana_rpc: Option<String>,
+    solana_commitment: Option<u8>,
+    /// Percent, e.g. `0.5`.
+    slippage: Option<f64>,
+    evm_network: Option<String>,
+    evm_rpc: Option<String>,
+    evm_gas_price: Option<String>,
+    evm_gas_limit: Option<u64>,
+}
+
+impl From<LegacyConfig> for WmgrConfig {
+    fn from(legacy: LegacyConfig) -> Self {
+        Self {
+            mode: legacy.mode,
+            svpi_cmd: legacy.svpi_cmd,
+            svpi_file: legacy.svpi_file,
+            svpi_name: legacy.svpi_name,
+            solana_cluster: legacy.solana_cluster,
+            solana_rpc: legacy.solana_rpc,
+            solana_commitment: legacy.solana_commitment,
+            slippage_bps: legacy.slippage.and_then(percent_to_bps),
+            evm_network: legacy.evm_network,
+            evm_rpc: legacy.evm_rpc,
+            evm_gas_price: legacy.evm_gas_price,
+            evm_gas_limit: legacy.evm_gas_limit,
+            ..Self::default()
+        }
+    }
+}
+
+/// Values outside 0-100% are dropped.
+fn percent_to_bps(percent: f64) -> Option<u16> {
+    let bps = (percent * 100.0).round();
+    (0.0..=f64::from(Slippage::MAX_BPS))
+        .contains(&bps)
+        .then_some(bps as u16)
+}
+
+/// Writes `bytes` to a sibling temp file and renames it over `path`, so a crash mid-write
+/// leaves the old file intact.
+pub(crate) fn replace_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "Invalid file path"));
+    };
+    let tmp_path = parent.join(format!("{}.tmp", name.to_string_lossy()));
+
+    fs::write(&tmp_path, bytes)?;
+    if let Err(err) = fs::rename(&tmp_path, path) {
+        if err.kind() != ErrorKind::AlreadyExists {
+            return Err(err);
+        }
+        let _ = fs::remove_file(path);
+        fs::rename(&tmp_path, path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::solana::PriorityFee;
+
+    fn legacy(slippage: Option<f64>) -> Vec<u8> {
+        let mut bytes = LEGACY_MAGIC.to_vec();
+        let rpc = Some("https://solana-rpc".to_string());
+        borsh::to_writer(&mut bytes, &(1u8, None::<String>, None::<String>)).unwrap();
+        borsh::to_writer(
+            &mut bytes,
+            &(None::<String>, None::<String>, rpc, Some(1u8)),
+        )
+        .unwrap();
+        borsh::to_writer(&mut bytes, &slippage).unwrap();
+        borsh::to_writer(&mut bytes, &(None::<String>, None::<String>)).unwrap();
+        borsh::to_writer(&mut bytes, &(None::<String>, Some(21_000u64))).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn reads_the_unversioned_layout() {
+        let cfg = WmgrConfig::from_bytes(&legacy(Some(0.5))).unwrap();
+        assert!(cfg.is_svpi_mode());
+        assert_eq!(cfg.solana_rpc.as_deref(), Some("https://solana-rpc"));
+        assert_eq!(cfg.slippage_bps, Some(50));
+        assert_eq!(cfg.evm_gas_limit, Some(21_000));
+        assert_eq!(cfg.evm_max_fee, None);
+
+        let cfg = WmgrConfig::from_bytes(&legacy(Some(150.0))).unwrap();
+        assert_eq!(cfg.slippage_bps, None);
+
+        let mut bytes = legacy(None);
+        bytes.push(0);
+        assert!(WmgrConfig::from_bytes(&bytes).is_none());
+    }
+
+    #[test]
+    fn round_trips_and_rejects_unknown_versions() {
+        let cfg = WmgrConfig {
+            slippage_bps: Some(75),
+            evm_priority_fee: Some("1.5".to_string()),
+            solana_priority_fee: Some("5000".to_string()),
+            ..WmgrConfig::default()
+        };
+        let mut bytes = cfg.to_bytes().unwrap();
+
+        let loaded = WmgrConfig::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.slippage_bps, Some(75));
+        assert_eq!(loaded.evm_priority_fee.as_deref(), Some("1.5"));
+        let fee = loaded.solana_priority_fee.as_deref().unwrap();
+        assert_eq!(
+            fee.parse::<PriorityFee>().unwrap(),
+            PriorityFee::MicroLamports(5000)
+        );
+        bytes[CONFIG_MAGIC.len()] = CONFIG_VERSION + 1;
+        assert!(WmgrConfig::from_bytes(&bytes).is_none());
     }
 }
@@ -1,4 +1,6 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+
+use crate::error::wmgr_error;
 
 pub struct ClusterConfig {
     pub name: String,
@@ -12,7 +14,7 @@ pub fn get_cluster_config(name: &str, override_url: Option<&str>) -> Result<Clus
         "devnet" => "https://api.devnet.solana.com",
         "testnet" => "https://api.testnet.solana.com",
         "localnet" => "http://127.0.0.1:8899",
-        _ => return Err(anyhow!("Unknown cluster: {name}")),
+        _ => return Err(wmgr_error!(InvalidArgument, "Unknown cluster: {name}")),
     };
 
     Ok(ClusterConfig {
@@ -27,6 +29,9 @@ pub fn get_usdc_mint_for_cluster(name: &str) -> Result<&'static str> {
         "devnet" => Ok("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"),
         "testnet" => Ok("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"),
         "localnet" => Ok("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
-        _ => Err(anyhow!("No USDC mint configured for cluster: {name}")),
+        _ => Err(wmgr_error!(
+            InvalidArgument,
+            "No USDC mint configured for cluster: {name}"
+        )),
     }
 }
@@ -0,0 +1,277 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+
+use crate::config::app_config::replace_file;
+use crate::core::price::Price;
+
+pub const DCA_FILE_NAME: &str = ".wmgr-dca";
+
+const DCA_MAGIC: [u8; 4] = *b"WDCA";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum DcaStatus {
+    Active,
+    Completed,
+    Cancelled,
+}
+
+impl DcaStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            DcaStatus::Active => "active",
+            DcaStatus::Completed => "completed",
+            DcaStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum FillOutcome {
+    Filled,
+    Skipped,
+    /// Sent as `DcaFill::signature` but not yet confirmed; the amounts are the simulated ones.
+    Pending {
+        last_valid_block_height: u64,
+    },
+}
+
+/// One slot of a schedule. Amounts are raw units of the spend and buy tokens; both are zero for
+/// a skipped slot.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct DcaFill {
+    /// Unix seconds.
+    pub at: u64,
+    pub outcome: FillOutcome,
+    pub spent: u64,
+    pub received: u64,
+    pub signature: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// Spend `spend_amount` of `spend_token` on `buy_token` every `interval_secs`, `count` times.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct DcaSchedule {
+    pub id: u32,
+    /// `sol`, `usdc` or a mint address.
+    pub buy_token: String,
+    pub spend_token: String,
+    pub spend_amount: String,
+    pub pool: Option<String>,
+    /// The interval as typed, e.g. `1d`.
+    pub every: String,
+    pub interval_secs: u64,
+    pub count: u32,
+    pub slippage_bps: Option<u16>,
+    pub max_price_impact: Option<f64>,
+    pub created_at: u64,
+    /// The next slot to run, in Unix seconds.
+    pub next_at: u64,
+    pub status: DcaStatus,
+    /// Learned on the first fill; needed to price the fill history.
+    pub spend_decimals: Option<u8>,
+    pub buy_decimals: Option<u8>,
+    pub fills: Vec<DcaFill>,
+}
+
+impl DcaSchedule {
+    pub fn remaining(&self) -> u32 {
+        self.count.saturating_sub(self.fills.len() as u32)
+    }
+
+    /// `(spent, received)` over filled slots, in raw units.
+    pub fn totals(&self) -> (u128, u128) {
+        self.fills
+            .iter()
+            .filter(|fill| fill.outcome == FillOutcome::Filled)
+            .fold((0, 0), |(spent, received), fill| {
+                (spent + fill.spent as u128, received + fill.received as u128)
+            })
+    }
+
+    /// The last slot, while its transaction is unconfirmed.
+    pub fn pending_fill(&self) -> Option<&DcaFill> {
+        self.fills
+            .last()
+            .filter(|fill| matches!(fill.outcome, FillOutcome::Pending { .. }))
+    }
+
+    /// Average price paid for the bought token, in the spend token.
+    pub fn average_price(&self) -> Option<Price> {
+        let (spent, received) = self.totals();
+        let (spend_decimals, buy_decimals) = (self.spend_decimals?, self.buy_decimals?);
+        let price = Price::from_amounts(received, spent, buy_decimals, spend_decimals);
+        (!price.is_zero()).then_some(price)
+    }
+}
+
+/// Schedules saved in `.wmgr-dca` next to `.wmgr`.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct DcaBook {
+    magic: [u8; 4],
+    next_id: u32,
+    pub schedules: Vec<DcaSchedule>,
+}
+
+impl Default for DcaBook {
+    fn default() -> Self {
+        Self {
+            magic: DCA_MAGIC,
+            next_id: 1,
+            schedules: Vec::new(),
+        }
+    }
+}
+
+impl DcaBook {
+    pub fn path_in_cwd() -> io::Result<PathBuf> {
+        Ok(std::env::current_dir()?.join(DCA_FILE_NAME))
+    }
+
+    pub fn load_from_cwd() -> io::Result<Self> {
+        let path = Self::path_in_cwd()?;
+        Self::load_from_path(&path)
+    }
+
+    /// A missing file is an empty book. An unreadable file is an error: saving over it would lose
+    /// the schedules and their fill history.
+    pub fn load_from_path(path: &Path) -> io::Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(v) => v,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        Self::from_bytes(&bytes).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is not a wmgr DCA file", path.display()),
+            )
+        })
+    }
+
+    pub fn save_to_cwd(&self) -> io::Result<()> {
+        let path = Self::path_in_cwd()?;
+        self.save_to_path(&path)
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
+        let bytes = borsh::to_vec(self)
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "Failed to serialize schedules"))?;
+        replace_file(path, &bytes)
+    }
+
+    /// Assigns the next id to `schedule` and appends it.
+    pub fn add(&mut self, mut schedule: DcaSchedule) -> u32 {
+        schedule.id = self.next_id;
+        self.next_id += 1;
+        self.schedules.push(schedule);
+        self.next_id - 1
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut DcaSchedule> {
+        self.schedules.iter_mut().find(|schedule| schedule.id == id)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let book = <Self as borsh::BorshDeserialize>::try_from_slice(bytes).ok()?;
+        (book.magic == DCA_MAGIC).then_some(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(outcome: FillOutcome, spent: u64, received: u64) -> DcaFill {
+        DcaFill {
+            at: 0,
+            outcome,
+            spent,
+            received,
+            signature: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn averages_filled_slots_only() {
+        let mut schedule = DcaSchedule {
+            id: 1,
+            buy_token: "sol".to_string(),
+            spend_token: "usdc".to_string(),
+            spend_amount: "50".to_string(),
+            pool: None,
+            every: "1d".to_string(),
+            interval_secs: 86_400,
+            count: 4,
+            slippage_bps: None,
+            max_price_impact: None,
+            created_at: 0,
+            next_at: 0,
+            status: DcaStatus::Active,
+            spend_decimals: None,
+            buy_decimals: None,
+            fills: Vec::new(),
+        };
+        // 50 USDC at 100 and at 150: 100 USDC for 0.8333 SOL, i.e. 120 per SOL.
+        schedule
+            .fills
+            .push(fill(FillOutcome::Filled, 50_000_000, 500_000_000));
+        schedule.fills.push(fill(FillOutcome::Skipped, 0, 0));
+        schedule
+            .fills
+            .push(fill(FillOutcome::Filled, 50_000_000, 333_333_333));
+        assert!(schedule.average_price().is_none());
+        (schedule.spend_decimals, schedule.buy_decimals) = (Some(6), Some(9));
+
+        let average = schedule.average_price().unwrap().to_f64();
+        assert!((average - 120.0).abs() < 1e-6, "average {average}");
+        assert_eq!(schedule.remaining(), 1);
+
+        let mut book = DcaBook::default();
+        assert_eq!(book.add(schedule), 1);
+        let bytes = borsh::to_vec(&book).unwrap();
+        assert_eq!(
+            DcaBook::from_bytes(&bytes).unwrap().schedules[0]
+                .fills
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn only_an_unconfirmed_last_slot_is_pending() {
+        let mut schedule = DcaSchedule {
+            id: 1,
+            buy_token: "sol".to_string(),
+            spend_token: "usdc".to_string(),
+            spend_amount: "50".to_string(),
+            pool: None,
+            every: "1d".to_string(),
+            interval_secs: 86_400,
+            count: 2,
+            slippage_bps: None,
+            max_price_impact: None,
+            created_at: 0,
+            next_at: 0,
+            status: DcaStatus::Active,
+            spend_decimals: None,
+            buy_decimals: None,
+            fills: vec![fill(FillOutcome::Filled, 50_000_000, 500_000_000)],
+        };
+        assert!(schedule.pending_fill().is_none());
+
+        let pending = FillOutcome::Pending {
+            last_valid_block_height: 42,
+        };
+        schedule.fills.push(fill(pending, 50_000_000, 400_000_000));
+        assert_eq!(schedule.pending_fill().unwrap().outcome, pending);
+        // Not confirmed, so not counted.
+        assert_eq!(schedule.totals(), (50_000_000, 500_000_000));
+        assert_eq!(schedule.remaining(), 0);
+    }
+}
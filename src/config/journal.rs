@@ -0,0 +1,231 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+
+use crate::config::app_config::replace_file;
+
+pub const JOURNAL_FILE_NAME: &str = ".wmgr-trades";
+
+const JOURNAL_MAGIC: [u8; 4] = *b"WTRD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum EntrySide {
+    Buy,
+    Sell,
+}
+
+impl EntrySide {
+    pub fn label(self) -> &'static str {
+        match self {
+            EntrySide::Buy => "buy",
+            EntrySide::Sell => "sell",
+        }
+    }
+}
+
+/// Where an entry's `input`/`output` were measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum AmountSource {
+    /// Token balance deltas of the confirmed transaction.
+    Confirmed,
+    /// The pre-send simulation, when the confirmed transaction could not be fetched.
+    Simulated,
+    /// The quote, when neither could be measured.
+    Quoted,
+}
+
+impl AmountSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            AmountSource::Confirmed => "confirmed",
+            AmountSource::Simulated => "simulated",
+            AmountSource::Quoted => "quoted",
+        }
+    }
+}
+
+/// The slippage bound a swap was sent with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum QuoteLimit {
+    /// Least output accepted for an exact input.
+    MinOutput(u64),
+    /// Most input accepted for an exact output.
+    MaxInput(u64),
+}
+
+/// The quote a trade was sent on, in raw units of the input and output tokens.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct QuotedTrade {
+    pub input: u64,
+    pub output: u64,
+    pub limit: QuoteLimit,
+    /// Pool fee, in the input token.
+    pub pool_fee: u64,
+    pub price_impact: f64,
+    pub slippage_bps: u16,
+}
+
+/// One landed `buy`/`sell`. `token` is the token bought or sold; `counter` is the token paid or
+/// received for it, so a buy spends `counter` as input and a sell receives it as output.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct TradeEntry {
+    pub signature: String,
+    /// Unix seconds.
+    pub at: u64,
+    pub side: EntrySide,
+    pub token_mint: String,
+    pub token_symbol: String,
+    pub token_decimals: u8,
+    pub counter_mint: String,
+    pub counter_symbol: String,
+    pub counter_decimals: u8,
+    pub pool: String,
+    pub input: u64,
+    pub output: u64,
+    pub amount_source: AmountSource,
+    /// Network fee in lamports.
+    pub fee: Option<u64>,
+    pub quote: QuotedTrade,
+}
+
+impl TradeEntry {
+    /// `(token amount, counter amount)` of the trade.
+    pub fn amounts(&self) -> (u64, u64) {
+        match self.side {
+            EntrySide::Buy => (self.output, self.input),
+            EntrySide::Sell => (self.input, self.output),
+        }
+    }
+
+    /// The same swap seen from its other token: selling `token` for `counter` is buying
+    /// `counter` with `token`, and the other way round. `input`/`output` stay as sent.
+    pub fn flipped(self) -> Self {
+        Self {
+            side: match self.side {
+                EntrySide::Buy => EntrySide::Sell,
+                EntrySide::Sell => EntrySide::Buy,
+            },
+            token_mint: self.counter_mint,
+            token_symbol: self.counter_symbol,
+            token_decimals: self.counter_decimals,
+            counter_mint: self.token_mint,
+            counter_symbol: self.token_symbol,
+            counter_decimals: self.token_decimals,
+            ..self
+        }
+    }
+}
+
+/// Trades saved in `.wmgr-trades` next to `.wmgr`, oldest first.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct TradeJournal {
+    magic: [u8; 4],
+    pub entries: Vec<TradeEntry>,
+}
+
+impl Default for TradeJournal {
+    fn default() -> Self {
+        Self {
+            magic: JOURNAL_MAGIC,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl TradeJournal {
+    pub fn path_in_cwd() -> io::Result<PathBuf> {
+        Ok(std::env::current_dir()?.join(JOURNAL_FILE_NAME))
+    }
+
+    pub fn load_from_cwd() -> io::Result<Self> {
+        let path = Self::path_in_cwd()?;
+        Self::load_from_path(&path)
+    }
+
+    /// A missing file is an empty journal; an unreadable one is an error, as for the order book.
+    pub fn load_from_path(path: &Path) -> io::Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(v) => v,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        Self::from_bytes(&bytes).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is not a wmgr trade journal", path.display()),
+            )
+        })
+    }
+
+    pub fn save_to_cwd(&self) -> io::Result<()> {
+        let path = Self::path_in_cwd()?;
+        self.save_to_path(&path)
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
+        let bytes = borsh::to_vec(self)
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "Failed to serialize journal"))?;
+        replace_file(path, &bytes)
+    }
+
+    /// Appends `entry` to the journal in the current directory.
+    pub fn append_in_cwd(entry: TradeEntry) -> io::Result<()> {
+        let mut journal = Self::load_from_cwd()?;
+        journal.entries.push(entry);
+        journal.save_to_cwd()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let journal = <Self as borsh::BorshDeserialize>::try_from_slice(bytes).ok()?;
+        (journal.magic == JOURNAL_MAGIC).then_some(journal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_entries() {
+        let entry = TradeEntry {
+            signature: "sig".to_string(),
+            at: 1,
+            side: EntrySide::Buy,
+            token_mint: "So11111111111111111111111111111111111111112".to_string(),
+            token_symbol: "SOL".to_string(),
+            token_decimals: 9,
+            counter_mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
+            counter_symbol: "USDC".to_string(),
+            counter_decimals: 6,
+            pool: "pool".to_string(),
+            input: 150_000_000,
+            output: 1_000_000_000,
+            amount_source: AmountSource::Confirmed,
+            fee: Some(5_000),
+            quote: QuotedTrade {
+                input: 149_900_000,
+                output: 1_000_000_000,
+                limit: QuoteLimit::MaxInput(150_049_900),
+                pool_fee: 374_750,
+                price_impact: 0.01,
+                slippage_bps: 10,
+            },
+        };
+        // A buy pays the counter token in and gets the token out.
+        assert_eq!(entry.amounts(), (1_000_000_000, 150_000_000));
+
+        let mut journal = TradeJournal::default();
+        journal.entries.push(entry);
+        let bytes = borsh::to_vec(&journal).unwrap();
+        let loaded = TradeJournal::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            loaded.entries[0].quote.limit,
+            QuoteLimit::MaxInput(150_049_900)
+        );
+        assert!(TradeJournal::from_bytes(&bytes[1..]).is_none());
+    }
+}
@@ -1,5 +1,10 @@
 pub mod app_config;
 pub mod clusters;
+pub mod dca;
 pub mod evm_networks;
+pub mod journal;
 pub mod mnemonics;
+pub mod orca;
+pub mod orders;
 pub mod raydium;
+pub mod tokens;
@@ -0,0 +1,5 @@
+pub const WHIRLPOOL_PROGRAM_ID: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+pub const WHIRLPOOLS_CONFIG: &str = "2LecshUwdy9xi7meFgHtFJQNSKk4KdTrxF5fAK5LnXzf";
+
+/// Tick spacings of Orca's public fee tiers, searched for a pool when `--pool` is not given.
+pub const TICK_SPACINGS: [u16; 9] = [1, 2, 4, 8, 16, 64, 96, 128, 256];
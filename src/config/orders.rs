@@ -0,0 +1,223 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+
+use crate::config::app_config::replace_file;
+
+pub const ORDERS_FILE_NAME: &str = ".wmgr-orders";
+
+const ORDERS_MAGIC: [u8; 4] = *b"WORD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+/// Which side of the trigger price fires the order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum OrderTrigger {
+    PriceBelow,
+    PriceAbove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum OrderStatus {
+    Open,
+    Filled,
+    Failed,
+    Cancelled,
+    /// Signed and about to be broadcast as `Order::signature`; settled by looking the signature
+    /// up, never by sending again.
+    Submitting {
+        last_valid_block_height: u64,
+    },
+}
+
+impl OrderStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            OrderStatus::Open => "open",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Failed => "failed",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Submitting { .. } => "submitting",
+        }
+    }
+
+    /// Open or waiting on its transaction.
+    pub fn is_active(self) -> bool {
+        matches!(self, OrderStatus::Open | OrderStatus::Submitting { .. })
+    }
+}
+
+/// A trade that `order watch` sends once the pool price crosses `trigger_price`.
+///
+/// Tokens, amounts and prices are kept as the user typed them and parsed again when the order
+/// runs, so an order means the same thing after a restart.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Order {
+    pub id: u32,
+    pub side: OrderSide,
+    pub amount: String,
+    /// `sol`, `usdc` or a mint address.
+    pub token: String,
+    pub with: Option<String>,
+    pub pool: Option<String>,
+    pub trigger: OrderTrigger,
+    /// Price of `token` in the counter token, in UI units.
+    pub trigger_price: String,
+    pub slippage_bps: Option<u16>,
+    pub max_price_impact: Option<f64>,
+    /// Unix seconds.
+    pub created_at: u64,
+    pub status: OrderStatus,
+    pub closed_at: Option<u64>,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Orders saved in `.wmgr-orders` next to `.wmgr`.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct OrderBook {
+    magic: [u8; 4],
+    next_id: u32,
+    pub orders: Vec<Order>,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self {
+            magic: ORDERS_MAGIC,
+            next_id: 1,
+            orders: Vec::new(),
+        }
+    }
+}
+
+impl OrderBook {
+    pub fn path_in_cwd() -> io::Result<PathBuf> {
+        Ok(std::env::current_dir()?.join(ORDERS_FILE_NAME))
+    }
+
+    pub fn load_from_cwd() -> io::Result<Self> {
+        let path = Self::path_in_cwd()?;
+        Self::load_from_path(&path)
+    }
+
+    /// A missing file is an empty book. An unreadable file is an error: saving over it would lose
+    /// the orders in it.
+    pub fn load_from_path(path: &Path) -> io::Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(v) => v,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        Self::from_bytes(&bytes).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is not a wmgr order book", path.display()),
+            )
+        })
+    }
+
+    pub fn save_to_cwd(&self) -> io::Result<()> {
+        let path = Self::path_in_cwd()?;
+        self.save_to_path(&path)
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
+        let bytes = borsh::to_vec(self).map_err(|_| {
+            io::Error::new(ErrorKind::InvalidData, "Failed to serialize order book")
+        })?;
+        replace_file(path, &bytes)
+    }
+
+    /// Assigns the next id to `order` and appends it.
+    pub fn add(&mut self, mut order: Order) -> u32 {
+        order.id = self.next_id;
+        self.next_id += 1;
+        self.orders.push(order);
+        self.next_id - 1
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Order> {
+        self.orders.iter_mut().find(|order| order.id == id)
+    }
+
+    pub fn open_orders(&self) -> impl Iterator<Item = &Order> {
+        self.orders
+            .iter()
+            .filter(|order| order.status == OrderStatus::Open)
+    }
+
+    pub fn submitting_orders(&self) -> impl Iterator<Item = &Order> {
+        self.orders
+            .iter()
+            .filter(|order| matches!(order.status, OrderStatus::Submitting { .. }))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let book = <Self as borsh::BorshDeserialize>::try_from_slice(bytes).ok()?;
+        (book.magic == ORDERS_MAGIC).then_some(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order() -> Order {
+        Order {
+            id: 0,
+            side: OrderSide::Buy,
+            amount: "10".to_string(),
+            token: "sol".to_string(),
+            with: None,
+            pool: None,
+            trigger: OrderTrigger::PriceBelow,
+            trigger_price: "140".to_string(),
+            slippage_bps: Some(50),
+            max_price_impact: None,
+            created_at: 1_700_000_000,
+            status: OrderStatus::Open,
+            closed_at: None,
+            signature: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_and_keeps_ids_unique() {
+        let mut book = OrderBook::default();
+        assert_eq!(book.add(order()), 1);
+        assert_eq!(book.add(order()), 2);
+        book.get_mut(1).unwrap().status = OrderStatus::Cancelled;
+
+        let bytes = borsh::to_vec(&book).unwrap();
+        let mut loaded = OrderBook::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.open_orders().map(|o| o.id).collect::<Vec<_>>(), [2]);
+        assert_eq!(loaded.add(order()), 3);
+        assert!(OrderBook::from_bytes(&bytes[1..]).is_none());
+    }
+
+    #[test]
+    fn submitting_orders_are_not_open() {
+        let mut book = OrderBook::default();
+        book.add(order());
+        book.get_mut(1).unwrap().status = OrderStatus::Submitting {
+            last_valid_block_height: 42,
+        };
+
+        let loaded = OrderBook::from_bytes(&borsh::to_vec(&book).unwrap()).unwrap();
+        assert_eq!(loaded.open_orders().count(), 0);
+        assert_eq!(
+            loaded.submitting_orders().map(|o| o.id).collect::<Vec<_>>(),
+            [1]
+        );
+    }
+}
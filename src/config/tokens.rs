@@ -0,0 +1,72 @@
+use crate::config::clusters::get_usdc_mint_for_cluster;
+
+pub struct KnownToken {
+    pub mint: &'static str,
+    pub symbol: &'static str,
+}
+
+/// The native mint has the same address on every cluster.
+const WSOL: KnownToken = KnownToken {
+    mint: "So11111111111111111111111111111111111111112",
+    symbol: "wSOL",
+};
+
+const MAINNET_TOKENS: &[KnownToken] = &[
+    KnownToken {
+        mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        symbol: "USDC",
+    },
+    KnownToken {
+        mint: "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB",
+        symbol: "USDT",
+    },
+    KnownToken {
+        mint: "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo",
+        symbol: "PYUSD",
+    },
+    KnownToken {
+        mint: "mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So",
+        symbol: "mSOL",
+    },
+    KnownToken {
+        mint: "J1toso1uCk3RLmjorhTtrVwY9HJ7X8V9yYac6Y7kGCPn",
+        symbol: "JitoSOL",
+    },
+    KnownToken {
+        mint: "4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R",
+        symbol: "RAY",
+    },
+    KnownToken {
+        mint: "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN",
+        symbol: "JUP",
+    },
+    KnownToken {
+        mint: "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263",
+        symbol: "BONK",
+    },
+    KnownToken {
+        mint: "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm",
+        symbol: "WIF",
+    },
+    KnownToken {
+        mint: "HZ1JovNiVvGrGNiiYvEozEVgZ58xaU3RKwX8eACQBCt3",
+        symbol: "PYTH",
+    },
+];
+
+pub fn get_token_symbol(cluster: &str, mint: &str) -> Option<&'static str> {
+    if get_usdc_mint_for_cluster(cluster).ok() == Some(mint) {
+        return Some("USDC");
+    }
+    if mint == WSOL.mint {
+        return Some(WSOL.symbol);
+    }
+    // Mainnet addresses mean nothing on other clusters.
+    if cluster.trim() != "mainnet-beta" {
+        return None;
+    }
+    MAINNET_TOKENS
+        .iter()
+        .find(|token| token.mint == mint)
+        .map(|token| token.symbol)
+}
@@ -1,86 +1,307 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use raydium_amm_swap::consts::{LIQUIDITY_FEES_DENOMINATOR, LIQUIDITY_FEES_NUMERATOR};
 
+use crate::core::price::Price;
+use crate::core::slippage::Slippage;
+use crate::error::wmgr_error;
+
 pub struct SwapQuote {
     pub amount_in: u64,
     pub amount_out: u64,
     pub fee: u64,
-    pub price: f64,
+    pub price: Price,
+    /// Percent by which the execution price, fees aside, falls short of `price`.
     pub price_impact: f64,
 }
 
-pub fn compute_swap_quote(
+/// Swap math of one pool seen from the input side. Every DEX integration quotes through this.
+///
+/// Amounts are raw token units; prices are output per input in UI units. Quotes round against
+/// the trader: exact-in floors the output, exact-out ceils the input.
+pub trait AmmCurve: Send + Sync {
+    fn spot_price(&self) -> Price;
+
+    fn quote_exact_in(&self, amount_in: u64) -> Result<SwapQuote>;
+
+    fn quote_exact_out(&self, amount_out: u64) -> Result<SwapQuote>;
+
+    /// Largest input whose price impact stays within `max_impact_percent`, or 0 when no size
+    /// does. Found by bisection over `quote_exact_in`, so sizes the curve cannot fill count as
+    /// exceeding the target.
+    fn max_input_for_impact(&self, max_impact_percent: f64) -> u64 {
+        largest_within(|amount| {
+            self.quote_exact_in(amount)
+                .is_ok_and(|quote| quote.price_impact <= max_impact_percent)
+        })
+    }
+}
+
+/// Quotes of consecutive swaps through different pools, each spending the previous one's output.
+pub struct PathQuote {
+    pub legs: Vec<SwapQuote>,
+}
+
+impl PathQuote {
+    pub fn amount_in(&self) -> u64 {
+        self.legs.first().map_or(0, |leg| leg.amount_in)
+    }
+
+    pub fn amount_out(&self) -> u64 {
+        self.legs.last().map_or(0, |leg| leg.amount_out)
+    }
+
+    /// Output per input at every leg's spot price.
+    pub fn price(&self) -> Price {
+        self.legs
+            .iter()
+            .skip(1)
+            .fold(self.legs[0].price, |price, leg| price.then(&leg.price))
+    }
+
+    /// Every leg's fee in raw input units: each scaled by the path input per unit of that leg's
+    /// input.
+    pub fn fee(&self) -> u64 {
+        let amount_in = self.amount_in() as u128;
+        self.legs
+            .iter()
+            .filter(|leg| leg.amount_in > 0)
+            .map(|leg| leg.fee as u128 * amount_in / leg.amount_in as u128)
+            .sum::<u128>()
+            .min(u64::MAX as u128) as u64
+    }
+
+    /// Impacts compound: each leg fills its input at `1 - impact` of its own spot price.
+    pub fn price_impact(&self) -> f64 {
+        if let [leg] = self.legs.as_slice() {
+            return leg.price_impact;
+        }
+        let kept = self
+            .legs
+            .iter()
+            .map(|leg| 1.0 - leg.price_impact / 100.0)
+            .product::<f64>();
+        (1.0 - kept) * 100.0
+    }
+}
+
+/// Quotes `amount_in` through `legs` in order. A leg that cannot fill fails the whole path.
+pub fn quote_path_exact_in(legs: &[&dyn AmmCurve], amount_in: u64) -> Result<PathQuote> {
+    quote_path_carrying(legs, amount_in, |amount| amount)
+}
+
+/// Quotes a path the way a route is sent: every leg after the first spends only the slippage
+/// minimum of the one before, so that leg may return up to the tolerance less than quoted and
+/// the next still fills. Whatever it returns above the minimum stays in the intermediate token.
+pub fn quote_path_exact_in_within(
+    legs: &[&dyn AmmCurve],
     amount_in: u64,
-    reserve_in: u64,
-    reserve_out: u64,
-    decimals_in: u8,
-    decimals_out: u8,
-) -> Result<SwapQuote> {
-    validate_exact_in(amount_in, reserve_in, reserve_out)?;
-
-    let fee = fee_for_amount(amount_in);
-    let amount_in_with_fee = amount_in.saturating_sub(fee);
-    let amount_out = constant_product_out(amount_in_with_fee, reserve_in, reserve_out)?;
-
-    let price = current_price(reserve_in, reserve_out, decimals_in, decimals_out);
-    let execution_price =
-        execution_price(amount_in_with_fee, amount_out, decimals_in, decimals_out);
-    let price_impact = price_impact(price, execution_price);
-
-    Ok(SwapQuote {
-        amount_in,
-        amount_out,
-        fee,
-        price,
-        price_impact,
-    })
+    slippage: Slippage,
+) -> Result<PathQuote> {
+    quote_path_carrying(legs, amount_in, |amount| slippage.min_out(amount))
 }
 
-pub fn compute_swap_quote_out(
+/// Quotes a path for an exact `amount_out`, walking the legs backwards: the last leg is quoted
+/// for `amount_out`, and every earlier one for the slippage maximum the leg after it may spend,
+/// so that leg may cost up to the tolerance more than quoted and still be funded. Whatever it
+/// leaves unspent stays in the intermediate token.
+pub fn quote_path_exact_out_within(
+    legs: &[&dyn AmmCurve],
     amount_out: u64,
-    reserve_in: u64,
-    reserve_out: u64,
-    decimals_in: u8,
-    decimals_out: u8,
-) -> Result<SwapQuote> {
-    validate_exact_out(amount_out, reserve_in, reserve_out)?;
-
-    let amount_in_with_fee = constant_product_in(amount_out, reserve_in, reserve_out)?;
-    let (amount_in, fee) = gross_input_from_net(amount_in_with_fee);
-    let net_input = amount_in.saturating_sub(fee);
-
-    let price = current_price(reserve_in, reserve_out, decimals_in, decimals_out);
-    let execution_price = execution_price(net_input, amount_out, decimals_in, decimals_out);
-    let price_impact = price_impact(price, execution_price);
-
-    Ok(SwapQuote {
-        amount_in,
-        amount_out,
-        fee,
-        price,
-        price_impact,
+    slippage: Slippage,
+) -> Result<PathQuote> {
+    let mut quotes = Vec::with_capacity(legs.len());
+    let mut amount = amount_out;
+    for leg in legs.iter().rev() {
+        let quote = leg.quote_exact_out(amount)?;
+        amount = slippage.max_in(quote.amount_in);
+        quotes.push(quote);
+    }
+    if quotes.is_empty() {
+        return Err(wmgr_error!(Internal, "A path needs at least one pool"));
+    }
+    quotes.reverse();
+    Ok(PathQuote { legs: quotes })
+}
+
+/// Quotes `legs` in order, each spending `carry` of the previous leg's output.
+fn quote_path_carrying(
+    legs: &[&dyn AmmCurve],
+    amount_in: u64,
+    carry: impl Fn(u64) -> u64,
+) -> Result<PathQuote> {
+    let mut quotes = Vec::with_capacity(legs.len());
+    let mut amount = amount_in;
+    for leg in legs {
+        let quote = leg.quote_exact_in(amount)?;
+        amount = carry(quote.amount_out);
+        quotes.push(quote);
+    }
+    if quotes.is_empty() {
+        return Err(wmgr_error!(Internal, "A path needs at least one pool"));
+    }
+    Ok(PathQuote { legs: quotes })
+}
+
+/// Largest path input whose compounded price impact stays within `max_impact_percent`.
+pub fn max_path_input_for_impact(legs: &[&dyn AmmCurve], max_impact_percent: f64) -> u64 {
+    largest_within(|amount| {
+        quote_path_exact_in(legs, amount)
+            .is_ok_and(|quote| quote.price_impact() <= max_impact_percent)
     })
 }
 
+/// Bisects for the largest amount `within` accepts, assuming it accepts every smaller one.
+fn largest_within(within: impl Fn(u64) -> bool) -> u64 {
+    let (mut low, mut high) = (0u64, u64::MAX);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if within(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
+
+/// A fee of `numerator / denominator` of the amount, rounded up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeRate {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl FeeRate {
+    /// Raydium AMM v4: 0.25% of the input.
+    pub const RAYDIUM_V4: Self = Self {
+        numerator: LIQUIDITY_FEES_NUMERATOR,
+        denominator: LIQUIDITY_FEES_DENOMINATOR,
+    };
+
+    pub fn fee(&self, amount: u64) -> u64 {
+        if amount == 0 || self.numerator == 0 {
+            return 0;
+        }
+        ceil_div(
+            amount as u128 * self.numerator as u128,
+            self.denominator as u128,
+        ) as u64
+    }
+
+    /// Smallest gross amount that leaves at least `net` once the fee is taken, with that fee.
+    pub fn gross_from_net(&self, net: u64) -> Result<(u64, u64)> {
+        if self.numerator >= self.denominator {
+            return Err(wmgr_error!(
+                Rpc,
+                "Invalid fee rate {}/{}",
+                self.numerator,
+                self.denominator
+            ));
+        }
+        let gross = ceil_div(
+            net as u128 * self.denominator as u128,
+            (self.denominator - self.numerator) as u128,
+        );
+        let mut gross = u64::try_from(gross)
+            .map_err(|_| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+        // The fee rounds up, so the ceiling above can still fall one unit short.
+        if gross - self.fee(gross) < net {
+            gross = gross
+                .checked_add(1)
+                .ok_or_else(|| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+        }
+        Ok((gross, self.fee(gross)))
+    }
+}
+
+/// `x * y = k` with the fee taken from the input.
+#[derive(Debug, Clone, Copy)]
+pub struct ConstantProduct {
+    pub reserve_in: u64,
+    pub reserve_out: u64,
+    pub decimals_in: u8,
+    pub decimals_out: u8,
+    pub fee: FeeRate,
+}
+
+impl AmmCurve for ConstantProduct {
+    fn spot_price(&self) -> Price {
+        Price::from_amounts(
+            self.reserve_in as u128,
+            self.reserve_out as u128,
+            self.decimals_in,
+            self.decimals_out,
+        )
+    }
+
+    fn quote_exact_in(&self, amount_in: u64) -> Result<SwapQuote> {
+        validate_exact_in(amount_in, self.reserve_in, self.reserve_out)?;
+
+        let fee = self.fee.fee(amount_in);
+        let amount_in_with_fee = amount_in.saturating_sub(fee);
+        let amount_out =
+            constant_product_out(amount_in_with_fee, self.reserve_in, self.reserve_out)?;
+
+        Ok(self.quote(amount_in, amount_out, fee, amount_in_with_fee))
+    }
+
+    fn quote_exact_out(&self, amount_out: u64) -> Result<SwapQuote> {
+        validate_exact_out(amount_out, self.reserve_in, self.reserve_out)?;
+
+        let amount_in_with_fee =
+            constant_product_in(amount_out, self.reserve_in, self.reserve_out)?;
+        let (amount_in, fee) = self.fee.gross_from_net(amount_in_with_fee)?;
+        Ok(self.quote(amount_in, amount_out, fee, amount_in - fee))
+    }
+}
+
+impl ConstantProduct {
+    fn quote(&self, amount_in: u64, amount_out: u64, fee: u64, net_in: u64) -> SwapQuote {
+        let price = self.spot_price();
+        SwapQuote {
+            amount_in,
+            amount_out,
+            fee,
+            price,
+            price_impact: price_impact(
+                &price,
+                net_in,
+                amount_out,
+                self.decimals_in,
+                self.decimals_out,
+            ),
+        }
+    }
+}
+
 fn validate_exact_in(amount_in: u64, reserve_in: u64, reserve_out: u64) -> Result<()> {
     if amount_in == 0 {
-        return Err(anyhow!("Amount must be greater than zero"));
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Amount must be greater than zero"
+        ));
     }
     if reserve_in == 0 || reserve_out == 0 {
-        return Err(anyhow!("Pool reserves are empty"));
+        return Err(wmgr_error!(Rpc, "Pool reserves are empty"));
     }
     Ok(())
 }
 
 fn validate_exact_out(amount_out: u64, reserve_in: u64, reserve_out: u64) -> Result<()> {
     if amount_out == 0 {
-        return Err(anyhow!("Amount must be greater than zero"));
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Amount must be greater than zero"
+        ));
     }
     if reserve_in == 0 || reserve_out == 0 {
-        return Err(anyhow!("Pool reserves are empty"));
+        return Err(wmgr_error!(Rpc, "Pool reserves are empty"));
     }
     if amount_out >= reserve_out {
-        return Err(anyhow!("Requested amount exceeds pool reserve"));
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Requested amount exceeds pool reserve"
+        ));
     }
     Ok(())
 }
@@ -91,107 +312,186 @@ fn constant_product_out(amount_in: u64, reserve_in: u64, reserve_out: u64) -> Re
     let reserve_out_u128 = reserve_out as u128;
     let denominator = reserve_in_u128 + amount_in_u128;
     if denominator == 0 {
-        return Err(anyhow!("Pool reserves are empty"));
+        return Err(wmgr_error!(Rpc, "Pool reserves are empty"));
     }
     let amount_out_u128 = (amount_in_u128 * reserve_out_u128) / denominator;
     if amount_out_u128 > u64::MAX as u128 {
-        return Err(anyhow!("Amount exceeds u64 limit"));
+        return Err(wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"));
     }
     Ok(amount_out_u128 as u64)
 }
 
-fn fee_for_amount(amount_in: u64) -> u64 {
-    if amount_in == 0 {
-        return 0;
-    }
-    let numerator = amount_in as u128 * LIQUIDITY_FEES_NUMERATOR as u128;
-    let denominator = LIQUIDITY_FEES_DENOMINATOR as u128;
-    ((numerator + denominator - 1) / denominator) as u64
-}
-
 fn constant_product_in(amount_out: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
     let reserve_in_u128 = reserve_in as u128;
     let reserve_out_u128 = reserve_out as u128;
     let amount_out_u128 = amount_out as u128;
     let denominator = reserve_out_u128.saturating_sub(amount_out_u128);
     if denominator == 0 {
-        return Err(anyhow!("Requested amount exceeds pool reserve"));
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Requested amount exceeds pool reserve"
+        ));
     }
     let numerator = reserve_in_u128 * amount_out_u128;
     let amount_in_u128 = ceil_div(numerator, denominator);
     if amount_in_u128 == 0 {
-        return Err(anyhow!("Amount too small for pool reserves"));
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Amount too small for pool reserves"
+        ));
     }
     if amount_in_u128 > u64::MAX as u128 {
-        return Err(anyhow!("Amount exceeds u64 limit"));
+        return Err(wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"));
     }
     Ok(amount_in_u128 as u64)
 }
 
-fn gross_input_from_net(amount_in_with_fee: u64) -> (u64, u64) {
-    let numerator = amount_in_with_fee as u128 * LIQUIDITY_FEES_DENOMINATOR as u128;
-    let denominator = (LIQUIDITY_FEES_DENOMINATOR - LIQUIDITY_FEES_NUMERATOR) as u128;
-    let mut amount_in = ceil_div(numerator, denominator) as u64;
-    let mut fee = fee_for_amount(amount_in);
-    if amount_in.saturating_sub(fee) < amount_in_with_fee {
-        amount_in = amount_in.saturating_add(1);
-        fee = fee_for_amount(amount_in);
-    }
-    (amount_in, fee)
-}
-
-fn ceil_div(numerator: u128, denominator: u128) -> u128 {
+pub(crate) fn ceil_div(numerator: u128, denominator: u128) -> u128 {
     if denominator == 0 {
         return 0;
     }
-    (numerator + denominator - 1) / denominator
+    numerator.div_ceil(denominator)
 }
 
-fn current_price(reserve_in: u64, reserve_out: u64, decimals_in: u8, decimals_out: u8) -> f64 {
-    let div_in = 10u128.pow(decimals_in as u32) as f64;
-    let div_out = 10u128.pow(decimals_out as u32) as f64;
-    let reserve_in_f = reserve_in as f64 / div_in;
-    let reserve_out_f = reserve_out as f64 / div_out;
-    if reserve_in_f == 0.0 {
-        0.0
-    } else {
-        reserve_out_f / reserve_in_f
-    }
-}
-
-fn execution_price(amount_in: u64, amount_out: u64, decimals_in: u8, decimals_out: u8) -> f64 {
-    let div_in = 10u128.pow(decimals_in as u32) as f64;
-    let div_out = 10u128.pow(decimals_out as u32) as f64;
-    let input_f = amount_in as f64 / div_in;
-    let output_f = amount_out as f64 / div_out;
-    if input_f == 0.0 {
-        0.0
-    } else {
-        output_f / input_f
-    }
-}
-
-fn price_impact(current_price: f64, execution_price: f64) -> f64 {
-    if current_price == 0.0 {
-        0.0
-    } else {
-        (current_price - execution_price) / current_price * 100.0
-    }
+/// Impact of filling `net_in` for `amount_out` against `spot`.
+pub(crate) fn price_impact(
+    spot: &Price,
+    net_in: u64,
+    amount_out: u64,
+    decimals_in: u8,
+    decimals_out: u8,
+) -> f64 {
+    let execution = Price::from_amounts(
+        net_in as u128,
+        amount_out as u128,
+        decimals_in,
+        decimals_out,
+    );
+    spot.impact_percent(&execution)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn pool(reserve_in: u64, reserve_out: u64) -> ConstantProduct {
+        ConstantProduct {
+            reserve_in,
+            reserve_out,
+            decimals_in: 0,
+            decimals_out: 0,
+            fee: FeeRate::RAYDIUM_V4,
+        }
+    }
+
     #[test]
     fn exact_in_constant_product() {
-        let quote = compute_swap_quote(100, 1_000, 1_000, 0, 0).unwrap();
+        let quote = pool(1_000, 1_000).quote_exact_in(100).unwrap();
         assert_eq!(quote.amount_out, 90);
     }
 
     #[test]
     fn exact_out_constant_product() {
-        let quote = compute_swap_quote_out(100, 1_000, 1_000, 0, 0).unwrap();
+        let quote = pool(1_000, 1_000).quote_exact_out(100).unwrap();
         assert_eq!(quote.amount_in, 113);
     }
+
+    #[test]
+    fn fee_rate_is_a_parameter() {
+        let mut curve = pool(1_000_000, 1_000_000);
+        curve.fee = FeeRate {
+            numerator: 30,
+            denominator: 10_000,
+        };
+        let quote = curve.quote_exact_in(10_000).unwrap();
+        assert_eq!(quote.fee, 30);
+        assert_eq!(quote.amount_out, 9_871);
+
+        let (gross, fee) = curve.fee.gross_from_net(9_970).unwrap();
+        assert_eq!((gross, fee), (10_000, 30));
+    }
+
+    #[test]
+    fn max_input_for_impact_is_the_boundary() {
+        let curve = pool(1_000_000_000, 1_000_000_000);
+        let max = curve.max_input_for_impact(1.0);
+        assert!(curve.quote_exact_in(max).unwrap().price_impact <= 1.0);
+        assert!(curve.quote_exact_in(max + 1).unwrap().price_impact > 1.0);
+        // Impact excluding the fee is amount / (reserve + amount): ~1% at ~10.1M net input.
+        assert!((10_100_000..10_140_000).contains(&max), "max {max}");
+    }
+
+    #[test]
+    fn path_spends_each_leg_output() {
+        let first = pool(1_000_000, 2_000_000);
+        let second = pool(4_000_000, 1_000_000);
+        let quote = quote_path_exact_in(&[&first, &second], 10_000).unwrap();
+        let direct = first.quote_exact_in(10_000).unwrap();
+        assert_eq!(quote.legs[1].amount_in, direct.amount_out);
+        assert_eq!(
+            quote.amount_out(),
+            second.quote_exact_in(direct.amount_out).unwrap().amount_out
+        );
+        assert_eq!(quote.amount_in(), 10_000);
+        let second_fee_in_input =
+            quote.legs[1].fee as u128 * 10_000 / quote.legs[1].amount_in as u128;
+        assert_eq!(quote.fee(), quote.legs[0].fee + second_fee_in_input as u64);
+        // 2 per input, then 0.25 per intermediate.
+        assert_eq!(quote.price().to_f64(), 0.5);
+        let kept =
+            (1.0 - quote.legs[0].price_impact / 100.0) * (1.0 - quote.legs[1].price_impact / 100.0);
+        assert!((quote.price_impact() - (1.0 - kept) * 100.0).abs() < 1e-12);
+        assert!(quote.price_impact() > quote.legs[0].price_impact);
+    }
+
+    #[test]
+    fn routed_legs_spend_the_previous_minimum() {
+        let first = pool(1_000_000, 2_000_000);
+        let second = pool(4_000_000, 1_000_000);
+        let slippage = Slippage::from_bps(100).unwrap();
+        let quote = quote_path_exact_in_within(&[&first, &second], 10_000, slippage).unwrap();
+        let direct = first.quote_exact_in(10_000).unwrap();
+        assert_eq!(quote.legs[0].amount_out, direct.amount_out);
+        assert_eq!(quote.legs[1].amount_in, slippage.min_out(direct.amount_out));
+        assert!(
+            quote.amount_out()
+                < quote_path_exact_in(&[&first, &second], 10_000)
+                    .unwrap()
+                    .amount_out()
+        );
+    }
+
+    #[test]
+    fn routed_exact_out_funds_the_next_maximum() {
+        let first = pool(1_000_000, 2_000_000);
+        let second = pool(4_000_000, 1_000_000);
+        let slippage = Slippage::from_bps(100).unwrap();
+        let quote = quote_path_exact_out_within(&[&first, &second], 5_000, slippage).unwrap();
+        let last = second.quote_exact_out(5_000).unwrap();
+        assert_eq!(quote.amount_out(), 5_000);
+        assert_eq!(quote.legs[1].amount_in, last.amount_in);
+        assert_eq!(quote.legs[0].amount_out, slippage.max_in(last.amount_in));
+        assert_eq!(
+            quote.amount_in(),
+            first
+                .quote_exact_out(slippage.max_in(last.amount_in))
+                .unwrap()
+                .amount_in
+        );
+        assert!(quote_path_exact_out_within(&[], 5_000, slippage).is_err());
+    }
+
+    #[test]
+    fn path_fails_when_a_leg_cannot_fill() {
+        let first = pool(1_000_000, 2_000_000);
+        let empty = pool(0, 0);
+        assert!(quote_path_exact_in(&[&first, &empty], 10_000).is_err());
+        assert!(quote_path_exact_in(&[], 10_000).is_err());
+
+        let max = max_path_input_for_impact(&[&first, &first], 1.0);
+        let within = quote_path_exact_in(&[&first, &first], max).unwrap();
+        assert!(within.price_impact() <= 1.0);
+        let beyond = quote_path_exact_in(&[&first, &first], max + 1).unwrap();
+        assert!(beyond.price_impact() > 1.0);
+    }
 }
@@ -1,24 +1,35 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+
+use crate::error::wmgr_error;
 
 pub fn parse_amount_to_u128(amount: &str, decimals: u8) -> Result<u128> {
     let trimmed = amount.trim();
     if trimmed.is_empty() {
-        return Err(anyhow!("Amount is empty"));
+        return Err(wmgr_error!(InvalidArgument, "Amount is empty"));
     }
     if !trimmed.chars().all(|c| c.is_ascii_digit() || c == '.') {
-        return Err(anyhow!("Invalid amount format: {amount}"));
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Invalid amount format: {amount}"
+        ));
     }
 
     let mut parts = trimmed.split('.');
     let left_raw = parts.next().unwrap_or("");
     let right_raw = parts.next().unwrap_or("");
     if parts.next().is_some() {
-        return Err(anyhow!("Invalid amount format: {amount}"));
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Invalid amount format: {amount}"
+        ));
     }
     if !left_raw.chars().all(|c| c.is_ascii_digit())
         || !right_raw.chars().all(|c| c.is_ascii_digit())
     {
-        return Err(anyhow!("Invalid amount format: {amount}"));
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Invalid amount format: {amount}"
+        ));
     }
 
     if decimals == 0 {
@@ -51,7 +62,7 @@ pub fn parse_amount_to_u128(amount: &str, decimals: u8) -> Result<u128> {
 pub fn parse_amount_to_u64(amount: &str, decimals: u8) -> Result<u64> {
     let value = parse_amount_to_u128(amount, decimals)?;
     if value > u64::MAX as u128 {
-        return Err(anyhow!("Amount exceeds u64 limit"));
+        return Err(wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"));
     }
     Ok(value as u64)
 }
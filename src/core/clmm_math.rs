@@ -0,0 +1,285 @@
+use anyhow::Result;
+use raydium_amm_swap::libraries::liquidity_math::add_delta;
+use raydium_amm_swap::libraries::swap_math::compute_swap_step;
+use raydium_amm_swap::libraries::tick_math::{
+    get_sqrt_price_at_tick, MAX_SQRT_PRICE_X64, MAX_TICK, MIN_SQRT_PRICE_X64, MIN_TICK,
+};
+
+use crate::core::amm_math::{price_impact, AmmCurve, SwapQuote};
+use crate::core::price::Price;
+use crate::core::transfer_fee::TransferFeeRate;
+use crate::error::wmgr_error;
+
+/// An initialized tick: liquidity added when the price crosses it upwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClmmTick {
+    pub index: i32,
+    pub liquidity_net: i128,
+}
+
+/// A Raydium CLMM pool seen from the input side of a swap.
+///
+/// `ticks` are the initialized ticks of the loaded tick arrays, ordered in the direction the
+/// price moves (descending for token 0 in, ascending for token 1 in). A swap that needs liquidity
+/// beyond the last of them fails rather than guessing.
+#[derive(Debug, Clone)]
+pub struct ClmmCurve {
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    /// Trade fee in millionths.
+    pub fee_rate: u32,
+    /// Token 0 in, token 1 out: the price moves down.
+    pub zero_for_one: bool,
+    pub decimals_in: u8,
+    pub decimals_out: u8,
+    pub ticks: Vec<ClmmTick>,
+    pub transfer_fee_in: TransferFeeRate,
+    pub transfer_fee_out: TransferFeeRate,
+}
+
+/// Pool-side totals of a swap: `amount_in` includes `fee`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ClmmSwap {
+    amount_in: u64,
+    amount_out: u64,
+    fee: u64,
+}
+
+impl AmmCurve for ClmmCurve {
+    /// Output per input from the current sqrt price.
+    fn spot_price(&self) -> Price {
+        Price::from_sqrt_price_x64(
+            self.sqrt_price_x64,
+            self.zero_for_one,
+            self.decimals_in,
+            self.decimals_out,
+        )
+    }
+
+    fn quote_exact_in(&self, amount_in: u64) -> Result<SwapQuote> {
+        self.validate(amount_in)?;
+        let pool_in = amount_in - self.transfer_fee_in.fee(amount_in);
+        let swap = self.swap(pool_in, true)?;
+        let amount_out = swap.amount_out - self.transfer_fee_out.fee(swap.amount_out);
+        if amount_out == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Amount too small for pool liquidity"
+            ));
+        }
+        Ok(self.quote(amount_in, amount_out, swap))
+    }
+
+    fn quote_exact_out(&self, amount_out: u64) -> Result<SwapQuote> {
+        self.validate(amount_out)?;
+        let pool_out = self
+            .transfer_fee_out
+            .pre_fee_amount(amount_out)
+            .ok_or_else(|| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+        let swap = self.swap(pool_out, false)?;
+        let amount_in = self
+            .transfer_fee_in
+            .pre_fee_amount(swap.amount_in)
+            .ok_or_else(|| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+        Ok(self.quote(amount_in, amount_out, swap))
+    }
+}
+
+impl ClmmCurve {
+    fn validate(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Amount must be greater than zero"
+            ));
+        }
+        if self.liquidity == 0 && self.ticks.is_empty() {
+            return Err(wmgr_error!(Rpc, "Pool has no liquidity"));
+        }
+        Ok(())
+    }
+
+    /// Walks initialized ticks the way the program does: swap within the current range up to
+    /// the next tick, cross it (applying its liquidity delta), repeat until the amount is used.
+    /// Ticks at or beyond the current one in the other direction are skipped up front.
+    fn swap(&self, amount: u64, is_base_input: bool) -> Result<ClmmSwap> {
+        let zero_for_one = self.zero_for_one;
+        let sqrt_price_limit = if zero_for_one {
+            MIN_SQRT_PRICE_X64 + 1
+        } else {
+            MAX_SQRT_PRICE_X64 - 1
+        };
+
+        let mut remaining = amount;
+        let mut calculated: u64 = 0;
+        let mut fee: u64 = 0;
+        let mut sqrt_price = self.sqrt_price_x64;
+        let mut liquidity = self.liquidity;
+        let tick_current = self.tick_current;
+        let mut ticks = self
+            .ticks
+            .iter()
+            .skip_while(|t| {
+                if zero_for_one {
+                    t.index > tick_current
+                } else {
+                    t.index <= tick_current
+                }
+            })
+            .peekable();
+
+        while remaining != 0 && sqrt_price != sqrt_price_limit {
+            let next = ticks.peek().copied().ok_or_else(|| {
+                wmgr_error!(
+                    InvalidArgument,
+                    "Not enough liquidity in the pool's nearby tick arrays for this amount"
+                )
+            })?;
+            let tick_next = next.index.clamp(MIN_TICK, MAX_TICK);
+            let sqrt_price_next =
+                get_sqrt_price_at_tick(tick_next).map_err(|err| wmgr_error!(Internal, "{err}"))?;
+            let target = if zero_for_one {
+                sqrt_price_next.max(sqrt_price_limit)
+            } else {
+                sqrt_price_next.min(sqrt_price_limit)
+            };
+
+            let step = compute_swap_step(
+                sqrt_price,
+                target,
+                liquidity,
+                remaining,
+                self.fee_rate,
+                is_base_input,
+                zero_for_one,
+                0,
+            )?;
+            sqrt_price = step.sqrt_price_next_x64;
+            let step_in = step
+                .amount_in
+                .checked_add(step.fee_amount)
+                .ok_or_else(|| wmgr_error!(Internal, "Swap step overflow"))?;
+            let (used, produced) = if is_base_input {
+                (step_in, step.amount_out)
+            } else {
+                (step.amount_out, step_in)
+            };
+            remaining = remaining
+                .checked_sub(used)
+                .ok_or_else(|| wmgr_error!(Internal, "Swap step exceeds the remaining amount"))?;
+            calculated = calculated
+                .checked_add(produced)
+                .ok_or_else(|| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+            fee += step.fee_amount;
+
+            if sqrt_price == sqrt_price_next {
+                let liquidity_net = if zero_for_one {
+                    -next.liquidity_net
+                } else {
+                    next.liquidity_net
+                };
+                liquidity =
+                    add_delta(liquidity, liquidity_net).map_err(|err| wmgr_error!(Rpc, "{err}"))?;
+                ticks.next();
+            }
+        }
+
+        if remaining != 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Not enough liquidity in the pool for this amount"
+            ));
+        }
+        let (amount_in, amount_out) = if is_base_input {
+            (amount, calculated)
+        } else {
+            (calculated, amount)
+        };
+        Ok(ClmmSwap {
+            amount_in,
+            amount_out,
+            fee,
+        })
+    }
+
+    fn quote(&self, amount_in: u64, amount_out: u64, swap: ClmmSwap) -> SwapQuote {
+        let price = self.spot_price();
+        SwapQuote {
+            amount_in,
+            amount_out,
+            fee: swap.fee,
+            price,
+            price_impact: price_impact(
+                &price,
+                swap.amount_in - swap.fee,
+                swap.amount_out,
+                self.decimals_in,
+                self.decimals_out,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Price 1.0 (tick 0) with `liquidity` between ticks -600 and 600.
+    fn curve(zero_for_one: bool, liquidity: u128) -> ClmmCurve {
+        let mut ticks = vec![
+            ClmmTick {
+                index: -600,
+                liquidity_net: liquidity as i128,
+            },
+            ClmmTick {
+                index: 600,
+                liquidity_net: -(liquidity as i128),
+            },
+        ];
+        if zero_for_one {
+            ticks.reverse();
+        }
+        ClmmCurve {
+            sqrt_price_x64: 1u128 << 64,
+            tick_current: 0,
+            liquidity,
+            fee_rate: 2_500,
+            zero_for_one,
+            decimals_in: 6,
+            decimals_out: 6,
+            ticks,
+            transfer_fee_in: TransferFeeRate::NONE,
+            transfer_fee_out: TransferFeeRate::NONE,
+        }
+    }
+
+    #[test]
+    fn small_swap_near_spot_price() {
+        let curve = curve(true, 1_000_000_000_000);
+        let quote = curve.quote_exact_in(1_000_000).unwrap();
+        assert_eq!(quote.fee, 2_500);
+        // Deep liquidity: output is the fee-adjusted input less a sliver of impact.
+        assert!(quote.amount_out < 997_500 && quote.amount_out > 997_400);
+        assert!((quote.price.to_f64() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn exact_out_round_trips_through_exact_in() {
+        for zero_for_one in [true, false] {
+            let curve = curve(zero_for_one, 50_000_000_000);
+            for amount_out in [1_000, 750_000, 100_000_000] {
+                let quote = curve.quote_exact_out(amount_out).unwrap();
+                let back = curve.quote_exact_in(quote.amount_in).unwrap();
+                assert!(back.amount_out >= amount_out, "amount_out {amount_out}");
+            }
+        }
+    }
+
+    #[test]
+    fn fails_past_the_last_loaded_tick() {
+        let curve = curve(false, 1_000_000);
+        assert!(curve.quote_exact_in(u64::MAX / 2).is_err());
+        assert!(curve.quote_exact_out(10_000_000).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::Result;
+
+use crate::core::amm_math::{ceil_div, price_impact, AmmCurve, SwapQuote};
+use crate::core::price::Price;
+use crate::core::transfer_fee::TransferFeeRate;
+use crate::error::wmgr_error;
+
+/// Raydium CPMM fee rates are in millionths.
+const FEE_RATE_DENOMINATOR: u128 = 1_000_000;
+
+/// A Raydium CPMM pool seen from the input side of a swap.
+///
+/// Reserves exclude accrued protocol, fund and creator fees. Amounts passed to and returned by
+/// the quote functions are what the user sends and receives, i.e. including Token-2022 transfer
+/// fees on either leg.
+#[derive(Debug, Clone, Copy)]
+pub struct CpmmCurve {
+    pub reserve_in: u64,
+    pub reserve_out: u64,
+    pub decimals_in: u8,
+    pub decimals_out: u8,
+    pub trade_fee_rate: u64,
+    /// Zero when the pool has no creator fee.
+    pub creator_fee_rate: u64,
+    /// Whether the creator fee is taken from the input (else from the output) of this swap.
+    pub creator_fee_on_input: bool,
+    pub transfer_fee_in: TransferFeeRate,
+    pub transfer_fee_out: TransferFeeRate,
+}
+
+impl AmmCurve for CpmmCurve {
+    fn spot_price(&self) -> Price {
+        Price::from_amounts(
+            self.reserve_in as u128,
+            self.reserve_out as u128,
+            self.decimals_in,
+            self.decimals_out,
+        )
+    }
+
+    fn quote_exact_in(&self, amount_in: u64) -> Result<SwapQuote> {
+        self.validate(amount_in)?;
+        let pool_in = amount_in - self.transfer_fee_in.fee(amount_in);
+        let trade_fee = fee_amount(pool_in, self.trade_fee_rate);
+        let creator_fee_in = if self.creator_fee_on_input {
+            fee_amount(pool_in, self.creator_fee_rate)
+        } else {
+            0
+        };
+        let net_in = pool_in
+            .checked_sub(trade_fee + creator_fee_in)
+            .filter(|net| *net > 0)
+            .ok_or_else(|| wmgr_error!(InvalidArgument, "Amount too small for pool fees"))?;
+
+        let swapped = (net_in as u128 * self.reserve_out as u128
+            / (self.reserve_in as u128 + net_in as u128)) as u64;
+        let pool_out = if self.creator_fee_on_input {
+            swapped
+        } else {
+            swapped - fee_amount(swapped, self.creator_fee_rate)
+        };
+        let amount_out = pool_out - self.transfer_fee_out.fee(pool_out);
+        if amount_out == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Amount too small for pool reserves"
+            ));
+        }
+
+        Ok(self.quote(
+            amount_in,
+            amount_out,
+            trade_fee + creator_fee_in,
+            net_in,
+            swapped,
+        ))
+    }
+
+    fn quote_exact_out(&self, amount_out: u64) -> Result<SwapQuote> {
+        self.validate(amount_out)?;
+        let pool_out = self
+            .transfer_fee_out
+            .pre_fee_amount(amount_out)
+            .ok_or_else(|| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+        let swapped = if self.creator_fee_on_input {
+            pool_out
+        } else {
+            pre_fee_amount(pool_out, self.creator_fee_rate)?
+        };
+        if swapped >= self.reserve_out {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Requested amount exceeds pool reserve"
+            ));
+        }
+
+        let net_in = ceil_div(
+            self.reserve_in as u128 * swapped as u128,
+            (self.reserve_out - swapped) as u128,
+        );
+        let net_in = u64::try_from(net_in)
+            .map_err(|_| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+        let input_fee_rate = if self.creator_fee_on_input {
+            self.trade_fee_rate + self.creator_fee_rate
+        } else {
+            self.trade_fee_rate
+        };
+        let pool_in = pre_fee_amount(net_in, input_fee_rate)?;
+        let amount_in = self
+            .transfer_fee_in
+            .pre_fee_amount(pool_in)
+            .ok_or_else(|| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+
+        Ok(self.quote(amount_in, amount_out, pool_in - net_in, net_in, swapped))
+    }
+}
+
+impl CpmmCurve {
+    fn validate(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Amount must be greater than zero"
+            ));
+        }
+        if self.reserve_in == 0 || self.reserve_out == 0 {
+            return Err(wmgr_error!(Rpc, "Pool reserves are empty"));
+        }
+        Ok(())
+    }
+
+    /// Price impact compares the curve's own in/out, leaving fees and transfer fees aside.
+    fn quote(
+        &self,
+        amount_in: u64,
+        amount_out: u64,
+        fee: u64,
+        curve_in: u64,
+        curve_out: u64,
+    ) -> SwapQuote {
+        let price = self.spot_price();
+        SwapQuote {
+            amount_in,
+            amount_out,
+            fee,
+            price,
+            price_impact: price_impact(
+                &price,
+                curve_in,
+                curve_out,
+                self.decimals_in,
+                self.decimals_out,
+            ),
+        }
+    }
+}
+
+fn fee_amount(amount: u64, rate: u64) -> u64 {
+    ceil_div(amount as u128 * rate as u128, FEE_RATE_DENOMINATOR) as u64
+}
+
+/// Smallest amount that still leaves `amount` after a `rate` fee is taken from it.
+fn pre_fee_amount(amount: u64, rate: u64) -> Result<u64> {
+    if rate as u128 >= FEE_RATE_DENOMINATOR {
+        return Err(wmgr_error!(Rpc, "Invalid pool fee rate {rate}"));
+    }
+    let gross = ceil_div(
+        amount as u128 * FEE_RATE_DENOMINATOR,
+        FEE_RATE_DENOMINATOR - rate as u128,
+    );
+    u64::try_from(gross).map_err(|_| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> CpmmCurve {
+        CpmmCurve {
+            reserve_in: 1_000_000_000,
+            reserve_out: 2_000_000_000,
+            decimals_in: 6,
+            decimals_out: 6,
+            trade_fee_rate: 2_500,
+            creator_fee_rate: 0,
+            creator_fee_on_input: true,
+            transfer_fee_in: TransferFeeRate::NONE,
+            transfer_fee_out: TransferFeeRate::NONE,
+        }
+    }
+
+    #[test]
+    fn exact_in_takes_trade_fee_from_input() {
+        let quote = curve().quote_exact_in(1_000_000).unwrap();
+        // 0.25% fee leaves 997_500; 997_500 * 2e9 / (1e9 + 997_500) = 1_993_011.9, floored.
+        assert_eq!(quote.fee, 2_500);
+        assert_eq!(quote.amount_out, 1_993_011);
+    }
+
+    #[test]
+    fn exact_out_round_trips_through_exact_in() {
+        let mut curve = curve();
+        curve.creator_fee_rate = 500;
+        curve.creator_fee_on_input = false;
+        curve.transfer_fee_in = TransferFeeRate {
+            basis_points: 100,
+            maximum_fee: u64::MAX,
+        };
+        curve.transfer_fee_out = TransferFeeRate {
+            basis_points: 50,
+            maximum_fee: u64::MAX,
+        };
+        for amount_out in [1_000, 500_000, 25_000_000] {
+            let quote = curve.quote_exact_out(amount_out).unwrap();
+            let back = curve.quote_exact_in(quote.amount_in).unwrap();
+            assert!(back.amount_out >= amount_out, "amount_out {amount_out}");
+        }
+    }
+
+    #[test]
+    fn transfer_fees_reduce_what_arrives() {
+        let plain = curve().quote_exact_in(1_000_000).unwrap();
+        let mut taxed = curve();
+        taxed.transfer_fee_in = TransferFeeRate {
+            basis_points: 100,
+            maximum_fee: u64::MAX,
+        };
+        let quote = taxed.quote_exact_in(1_000_000).unwrap();
+        assert!(quote.amount_out < plain.amount_out);
+        assert_eq!(quote.fee, fee_amount(990_000, 2_500));
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::Result;
+
+use crate::core::amm_math::ceil_div;
+use crate::core::slippage::Slippage;
+use crate::error::wmgr_error;
+
+/// Liquidity of a Raydium AMM v4 pool as its deposit and withdraw instructions price it, in raw
+/// units: tradable reserves (PnL owed to the protocol excluded) and the LP supply the pool
+/// records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LpReserves {
+    pub base: u64,
+    pub quote: u64,
+    pub lp_supply: u64,
+}
+
+/// Which amount of a deposit is exact; the program derives the other from the pool ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepositSide {
+    Base,
+    Quote,
+}
+
+/// A deposit at the current ratio: expected amounts, the bounds sent with it and the LP tokens
+/// it should mint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepositPlan {
+    pub side: DepositSide,
+    pub base: u64,
+    pub quote: u64,
+    pub max_base: u64,
+    pub max_quote: u64,
+    pub lp: u64,
+}
+
+impl LpReserves {
+    fn check(&self) -> Result<()> {
+        if self.base == 0 || self.quote == 0 || self.lp_supply == 0 {
+            return Err(wmgr_error!(Rpc, "Pool reserves are empty"));
+        }
+        Ok(())
+    }
+
+    /// Quote owed alongside `base`, rounded up as the program does.
+    pub fn quote_for_base(&self, base: u64) -> Result<u64> {
+        self.check()?;
+        to_u64(ceil_div(
+            base as u128 * self.quote as u128,
+            self.base as u128,
+        ))
+    }
+
+    /// Base owed alongside `quote`, rounded up as the program does.
+    pub fn base_for_quote(&self, quote: u64) -> Result<u64> {
+        self.check()?;
+        to_u64(ceil_div(
+            quote as u128 * self.base as u128,
+            self.quote as u128,
+        ))
+    }
+
+    /// LP tokens minted for a deposit whose exact side is `amount`, rounded down.
+    pub fn lp_for_deposit(&self, side: DepositSide, amount: u64) -> Result<u64> {
+        self.check()?;
+        let reserve = match side {
+            DepositSide::Base => self.base,
+            DepositSide::Quote => self.quote,
+        };
+        to_u64(amount as u128 * self.lp_supply as u128 / reserve as u128)
+    }
+
+    /// Base and quote paid out for burning `lp`, rounded down.
+    pub fn withdraw(&self, lp: u64) -> Result<(u64, u64)> {
+        self.check()?;
+        if lp > self.lp_supply {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "LP amount exceeds the pool's LP supply"
+            ));
+        }
+        let share = |reserve: u64| reserve as u128 * lp as u128 / self.lp_supply as u128;
+        Ok((to_u64(share(self.base))?, to_u64(share(self.quote))?))
+    }
+
+    /// Plans a deposit of `base` and/or `quote`. With one amount the other is derived from the
+    /// pool ratio and bounded by `slippage`; with both, the one that runs out first is exact and
+    /// the other is never exceeded.
+    pub fn plan_deposit(
+        &self,
+        base: Option<u64>,
+        quote: Option<u64>,
+        slippage: Slippage,
+    ) -> Result<DepositPlan> {
+        if base == Some(0) || quote == Some(0) {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Amount must be greater than 0"
+            ));
+        }
+        let side = match (base, quote) {
+            (Some(base), Some(quote)) if self.quote_for_base(base)? > quote => DepositSide::Quote,
+            (Some(_), _) => DepositSide::Base,
+            (None, Some(_)) => DepositSide::Quote,
+            (None, None) => {
+                return Err(wmgr_error!(
+                    InvalidArgument,
+                    "Give --amount-a, --amount-b or both"
+                ))
+            }
+        };
+        let bounded = |derived: u64, given: Option<u64>| {
+            let max = slippage.max_in(derived);
+            given.map_or(max, |given| max.min(given).max(derived))
+        };
+        let plan = match side {
+            DepositSide::Base => {
+                let base = base.expect("base side has a base amount");
+                let derived = self.quote_for_base(base)?;
+                DepositPlan {
+                    side,
+                    base,
+                    quote: derived,
+                    max_base: base,
+                    max_quote: bounded(derived, quote),
+                    lp: self.lp_for_deposit(side, base)?,
+                }
+            }
+            DepositSide::Quote => {
+                let quote = quote.expect("quote side has a quote amount");
+                let derived = self.base_for_quote(quote)?;
+                DepositPlan {
+                    side,
+                    base: derived,
+                    quote,
+                    max_base: bounded(derived, base),
+                    max_quote: quote,
+                    lp: self.lp_for_deposit(side, quote)?,
+                }
+            }
+        };
+        if plan.lp == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Deposit is too small to mint any LP tokens"
+            ));
+        }
+        Ok(plan)
+    }
+}
+
+fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1,000 SOL against 150,000 USDC, 10,000 LP.
+    const POOL: LpReserves = LpReserves {
+        base: 1_000_000_000_000,
+        quote: 150_000_000_000,
+        lp_supply: 10_000_000_000,
+    };
+
+    #[test]
+    fn deposits_at_the_pool_ratio() {
+        let slippage = Slippage::from_bps(100).unwrap();
+        let plan = POOL
+            .plan_deposit(Some(1_000_000_000), None, slippage)
+            .unwrap();
+        assert_eq!(plan.side, DepositSide::Base);
+        assert_eq!((plan.base, plan.quote), (1_000_000_000, 150_000_000));
+        assert_eq!(
+            (plan.max_base, plan.max_quote),
+            (1_000_000_000, 151_500_000)
+        );
+        assert_eq!(plan.lp, 10_000_000);
+
+        // 1 raw SOL needs a fraction of a raw USDC: rounded up against the depositor.
+        assert_eq!(POOL.quote_for_base(1).unwrap(), 1);
+    }
+
+    #[test]
+    fn both_amounts_keep_the_scarcer_side_exact() {
+        let slippage = Slippage::from_bps(100).unwrap();
+        // 1 SOL would need 150 USDC; only 100 is given, so USDC is exact.
+        let plan = POOL
+            .plan_deposit(Some(1_000_000_000), Some(100_000_000), slippage)
+            .unwrap();
+        assert_eq!(plan.side, DepositSide::Quote);
+        assert_eq!(plan.base, 666_666_667);
+        assert_eq!(plan.max_base, 673_333_333);
+
+        // The derived side never goes above what was given.
+        let plan = POOL
+            .plan_deposit(Some(1_000_000_000), Some(150_500_000), slippage)
+            .unwrap();
+        assert_eq!(plan.side, DepositSide::Base);
+        assert_eq!(plan.max_quote, 150_500_000);
+
+        assert!(POOL.plan_deposit(None, None, slippage).is_err());
+        assert!(POOL.plan_deposit(Some(1), None, slippage).is_err());
+    }
+
+    #[test]
+    fn withdraws_pro_rata() {
+        assert_eq!(
+            POOL.withdraw(1_000_000_000).unwrap(),
+            (100_000_000_000, 15_000_000_000)
+        );
+        assert!(POOL.withdraw(POOL.lp_supply + 1).is_err());
+    }
+}
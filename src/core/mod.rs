@@ -1,2 +1,13 @@
 pub mod amm_math;
 pub mod amount;
+pub mod clmm_math;
+pub mod cpmm_math;
+pub mod lp_math;
+pub mod pnl;
+pub mod price;
+pub mod schedule;
+pub mod slippage;
+// No integrated venue trades stable-swap pools yet; the curve is only built for its tests.
+#[cfg(test)]
+pub mod stable_math;
+pub mod transfer_fee;
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+
+/// How sold units are matched against earlier buys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostMethod {
+    /// Oldest lots first.
+    Fifo,
+    /// Every unit held costs the running average.
+    Average,
+}
+
+/// One trade of a token against a quote token, in raw units of each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fill {
+    pub buy: bool,
+    pub quantity: u128,
+    /// Quote token paid (buy) or received (sell).
+    pub value: u128,
+}
+
+/// Holdings and realised P&L of one token, in quote token raw units.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Position {
+    pub bought: u128,
+    pub sold: u128,
+    pub held: u128,
+    /// Cost of `held`.
+    pub cost_basis: u128,
+    pub realised: i128,
+    /// Units sold beyond what the fills bought, e.g. tokens acquired elsewhere. They have no
+    /// known cost and are left out of `realised`.
+    pub unmatched: u128,
+}
+
+/// Replays `fills` in order.
+pub fn position(fills: impl IntoIterator<Item = Fill>, method: CostMethod) -> Position {
+    let mut position = Position::default();
+    // (quantity, cost) per buy still held; a single running lot for average cost.
+    let mut lots: VecDeque<(u128, u128)> = VecDeque::new();
+    for fill in fills {
+        if fill.quantity == 0 {
+            continue;
+        }
+        if fill.buy {
+            position.bought += fill.quantity;
+            match (method, lots.back_mut()) {
+                (CostMethod::Average, Some(lot)) => {
+                    lot.0 += fill.quantity;
+                    lot.1 += fill.value;
+                }
+                _ => lots.push_back((fill.quantity, fill.value)),
+            }
+            continue;
+        }
+
+        position.sold += fill.quantity;
+        let mut left = fill.quantity;
+        let mut matched_cost = 0u128;
+        while left > 0 {
+            let Some(lot) = lots.front_mut() else {
+                break;
+            };
+            let take = left.min(lot.0);
+            let cost = if take == lot.0 {
+                lot.1
+            } else {
+                lot.1 * take / lot.0
+            };
+            lot.0 -= take;
+            lot.1 -= cost;
+            if lot.0 == 0 {
+                lots.pop_front();
+            }
+            matched_cost += cost;
+            left -= take;
+        }
+        let matched = fill.quantity - left;
+        position.unmatched += left;
+        let proceeds = fill.value * matched / fill.quantity;
+        position.realised += proceeds as i128 - matched_cost as i128;
+    }
+    position.held = lots.iter().map(|lot| lot.0).sum();
+    position.cost_basis = lots.iter().map(|lot| lot.1).sum();
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buy(quantity: u128, value: u128) -> Fill {
+        Fill {
+            buy: true,
+            quantity,
+            value,
+        }
+    }
+
+    fn sell(quantity: u128, value: u128) -> Fill {
+        Fill {
+            buy: false,
+            quantity,
+            value,
+        }
+    }
+
+    #[test]
+    fn fifo_sells_oldest_lots_first() {
+        // 10 at 100, 10 at 200, then 15 sold at 300.
+        let fills = [buy(10, 1_000), buy(10, 2_000), sell(15, 4_500)];
+        let position = position(fills, CostMethod::Fifo);
+        assert_eq!(position.realised, 4_500 - 1_000 - 1_000);
+        assert_eq!((position.held, position.cost_basis), (5, 1_000));
+    }
+
+    #[test]
+    fn average_cost_pools_buys() {
+        let fills = [buy(10, 1_000), buy(10, 2_000), sell(15, 4_500)];
+        let position = position(fills, CostMethod::Average);
+        assert_eq!(position.realised, 4_500 - 2_250);
+        assert_eq!((position.held, position.cost_basis), (5, 750));
+    }
+
+    #[test]
+    fn unknown_lots_are_left_out() {
+        let fills = [buy(10, 1_000), sell(20, 3_000)];
+        for method in [CostMethod::Fifo, CostMethod::Average] {
+            let position = position(fills, method);
+            assert_eq!(position.realised, 1_500 - 1_000);
+            assert_eq!((position.held, position.unmatched), (0, 10));
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use anyhow::Result;
+use raydium_amm_swap::libraries::big_num::U512;
+
+use crate::core::amount::parse_amount_to_u128;
+use crate::error::wmgr_error;
+
+/// Decimal places kept when a price is parsed from text.
+const PARSE_DECIMALS: u8 = 18;
+
+/// Output per input in UI units, kept as an exact ratio.
+///
+/// Ratios too large for `u128` are shifted down on both sides, which keeps ~38 significant
+/// digits. Floats only appear at the edge, for display and JSON.
+#[derive(Debug, Clone, Copy)]
+pub struct Price {
+    numerator: u128,
+    denominator: u128,
+}
+
+impl Price {
+    pub const ZERO: Self = Self {
+        numerator: 0,
+        denominator: 1,
+    };
+
+    /// `output / input` of raw amounts, adjusted to UI units.
+    pub fn from_amounts(input: u128, output: u128, decimals_in: u8, decimals_out: u8) -> Self {
+        Self::from_ratio(
+            U512::from(output) * pow10(decimals_in),
+            U512::from(input) * pow10(decimals_out),
+        )
+    }
+
+    /// Price of a concentrated-liquidity pool from its Q64.64 square root price (token 1 per
+    /// token 0), from the side of `zero_for_one`.
+    pub fn from_sqrt_price_x64(
+        sqrt_price_x64: u128,
+        zero_for_one: bool,
+        decimals_in: u8,
+        decimals_out: u8,
+    ) -> Self {
+        let squared = U512::from(sqrt_price_x64) * U512::from(sqrt_price_x64);
+        let one = U512::one() << 128;
+        let (output, input) = if zero_for_one {
+            (squared, one)
+        } else {
+            (one, squared)
+        };
+        Self::from_ratio(output * pow10(decimals_in), input * pow10(decimals_out))
+    }
+
+    /// Any ratio; a zero denominator gives `ZERO`.
+    pub fn from_ratio(numerator: U512, denominator: U512) -> Self {
+        if denominator.is_zero() || numerator.is_zero() {
+            return Self::ZERO;
+        }
+        let excess = numerator.bits().max(denominator.bits()).saturating_sub(128);
+        let (numerator, denominator) = (numerator >> excess, denominator >> excess);
+        if denominator.is_zero() {
+            // The numerator dwarfs the denominator beyond u128 range.
+            return Self {
+                numerator: u128::MAX,
+                denominator: 1,
+            };
+        }
+        Self {
+            numerator: numerator.as_u128(),
+            denominator: denominator.as_u128(),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    /// Input per output.
+    pub fn invert(&self) -> Self {
+        if self.is_zero() {
+            return Self::ZERO;
+        }
+        Self {
+            numerator: self.denominator,
+            denominator: self.numerator,
+        }
+    }
+
+    /// The price of trading through `self`, then through `next` with the output.
+    pub fn then(&self, next: &Price) -> Self {
+        Self::from_ratio(
+            U512::from(self.numerator) * U512::from(next.numerator),
+            U512::from(self.denominator) * U512::from(next.denominator),
+        )
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Percent by which `execution` falls short of `self`. The difference is taken exactly,
+    /// so tiny impacts on large trades are not lost to cancellation.
+    pub fn impact_percent(&self, execution: &Price) -> f64 {
+        if self.is_zero() {
+            return 0.0;
+        }
+        let spot = U512::from(self.numerator) * U512::from(execution.denominator);
+        let actual = U512::from(execution.numerator) * U512::from(self.denominator);
+        let (difference, sign) = if spot >= actual {
+            (spot - actual, 1.0)
+        } else {
+            (actual - spot, -1.0)
+        };
+        sign * Price::from_ratio(difference * U512::from(100u64), spot).to_f64()
+    }
+}
+
+impl FromStr for Price {
+    type Err = anyhow::Error;
+
+    /// A positive decimal such as `140` or `0.00025`; digits past 18 decimals are dropped.
+    fn from_str(s: &str) -> Result<Self> {
+        let value = parse_amount_to_u128(s, PARSE_DECIMALS)
+            .map_err(|_| wmgr_error!(InvalidArgument, "Invalid price '{}'", s.trim()))?;
+        if value == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Price must be greater than zero"
+            ));
+        }
+        Ok(Self::from_ratio(U512::from(value), pow10(PARSE_DECIMALS)))
+    }
+}
+
+impl PartialEq for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (U512::from(self.numerator) * U512::from(other.denominator))
+            .cmp(&(U512::from(other.numerator) * U512::from(self.denominator)))
+    }
+}
+
+fn pow10(exponent: u8) -> U512 {
+    U512::from(10u64).pow(U512::from(exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjusts_for_decimals() {
+        // 1 SOL (9 decimals) for 150 USDC (6 decimals).
+        let price = Price::from_amounts(1_000_000_000, 150_000_000, 9, 6);
+        assert_eq!(price.to_f64(), 150.0);
+        assert_eq!(price.invert().to_f64(), 1.0 / 150.0);
+        assert_eq!(price, Price::from_amounts(2, 300_000_000_000, 0, 9));
+    }
+
+    #[test]
+    fn sqrt_price_of_one() {
+        let price = Price::from_sqrt_price_x64(1 << 64, true, 6, 6);
+        assert_eq!(price.to_f64(), 1.0);
+        let price = Price::from_sqrt_price_x64(2 << 64, false, 9, 6);
+        assert_eq!(price.to_f64(), 250.0);
+    }
+
+    #[test]
+    fn chained_prices_multiply() {
+        // 150 USDC per SOL, then 0.5 of a 3-decimal token per USDC.
+        let sol_usdc = Price::from_amounts(1_000_000_000, 150_000_000, 9, 6);
+        let usdc_other = Price::from_amounts(2_000_000, 1_000, 6, 3);
+        assert_eq!(sol_usdc.then(&usdc_other).to_f64(), 75.0);
+        assert!(sol_usdc.then(&Price::ZERO).is_zero());
+    }
+
+    #[test]
+    fn parses_decimal_prices() {
+        let price: Price = "140.5".parse().unwrap();
+        assert_eq!(price, Price::from_amounts(2, 281, 0, 0));
+        assert!(price < "140.500000000000000001".parse().unwrap());
+        assert!("0".parse::<Price>().is_err());
+        assert!("-1".parse::<Price>().is_err());
+    }
+
+    #[test]
+    fn impact_survives_huge_amounts() {
+        let spot = Price::from_amounts(u64::MAX as u128, u64::MAX as u128 - 1, 9, 9);
+        let execution = Price::from_amounts(u64::MAX as u128, u64::MAX as u128 - 2, 9, 9);
+        let impact = spot.impact_percent(&execution);
+        assert!(impact > 0.0 && impact < 1e-15, "impact {impact}");
+        assert_eq!(spot.impact_percent(&spot), 0.0);
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+
+use crate::error::wmgr_error;
+
+/// Parses an interval such as `30m`, `12h`, `1d` or `2w` into seconds.
+pub fn parse_interval(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let invalid = || {
+        wmgr_error!(
+            InvalidArgument,
+            "Invalid interval '{value}': expected a number with s, m, h, d or w, e.g. 1d"
+        )
+    };
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+    match number.checked_mul(unit_secs) {
+        Some(0) => Err(wmgr_error!(
+            InvalidArgument,
+            "Interval must be greater than zero"
+        )),
+        Some(secs) => Ok(secs),
+        None => Err(invalid()),
+    }
+}
+
+/// Slots of a fixed-interval schedule that are due at `now`, given the first pending slot
+/// `next_at`. Returns the number of due slots (0 when `next_at` is still ahead) and the first
+/// slot after `now`. Only the latest due slot is worth running; the earlier ones were missed.
+pub fn due_slots(next_at: u64, interval: u64, now: u64) -> (u64, u64) {
+    if now < next_at || interval == 0 {
+        return (0, next_at);
+    }
+    let due = (now - next_at) / interval + 1;
+    (due, next_at.saturating_add(due.saturating_mul(interval)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(parse_interval("90s").unwrap(), 90);
+        assert_eq!(parse_interval("1d").unwrap(), 86_400);
+        assert_eq!(parse_interval(" 2w ").unwrap(), 1_209_600);
+        assert!(parse_interval("0h").is_err());
+        assert!(parse_interval("1").is_err());
+        assert!(parse_interval("d").is_err());
+        assert!(parse_interval("1.5h").is_err());
+    }
+
+    #[test]
+    fn counts_due_and_missed_slots() {
+        assert_eq!(due_slots(1_000, 100, 999), (0, 1_000));
+        assert_eq!(due_slots(1_000, 100, 1_000), (1, 1_100));
+        assert_eq!(due_slots(1_000, 100, 1_099), (1, 1_100));
+        assert_eq!(due_slots(1_000, 100, 1_350), (4, 1_400));
+    }
+}
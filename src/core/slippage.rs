@@ -0,0 +1,130 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use crate::core::amount::{format_integer_amount, parse_amount_to_u64};
+use crate::error::wmgr_error;
+
+const BPS_PER_UNIT: u128 = 10_000;
+
+/// Slippage tolerance in basis points (1 bps = 0.01%).
+///
+/// Bounds are computed in `u128` and always round in the trader's favour: the minimum output
+/// rounds up and the maximum input rounds down, so the tolerance actually enforced never
+/// exceeds the one asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Slippage {
+    bps: u16,
+}
+
+impl Slippage {
+    pub const MAX_BPS: u16 = 10_000;
+
+    pub fn from_bps(bps: u16) -> Result<Self> {
+        if bps > Self::MAX_BPS {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Slippage must be between 0 and 100 percent"
+            ));
+        }
+        Ok(Self { bps })
+    }
+
+    pub fn bps(&self) -> u16 {
+        self.bps
+    }
+
+    /// For display and JSON only.
+    pub fn percent(&self) -> f64 {
+        self.bps as f64 / 100.0
+    }
+
+    /// Smallest acceptable output for a quoted `amount`: `ceil(amount * (1 - s))`.
+    pub fn min_out(&self, amount: u64) -> u64 {
+        let scaled = amount as u128 * (BPS_PER_UNIT - self.bps as u128);
+        // Never above `amount`, so the cast cannot truncate.
+        scaled.div_ceil(BPS_PER_UNIT) as u64
+    }
+
+    /// Largest acceptable input for a quoted `amount`: `floor(amount * (1 + s))`, capped at
+    /// `u64::MAX`.
+    pub fn max_in(&self, amount: u64) -> u64 {
+        let scaled = amount as u128 * (BPS_PER_UNIT + self.bps as u128);
+        u64::try_from(scaled / BPS_PER_UNIT).unwrap_or(u64::MAX)
+    }
+}
+
+impl FromStr for Slippage {
+    type Err = anyhow::Error;
+
+    /// A percent with at most two decimals, e.g. `0.5` for 50 bps.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let decimals = s
+            .split_once('.')
+            .map_or(0, |(_, fraction)| fraction.trim_end_matches('0').len());
+        if decimals > 2 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Invalid slippage '{s}': the smallest step is 0.01 percent"
+            ));
+        }
+        let bps = parse_amount_to_u64(s, 2)
+            .map_err(|_| wmgr_error!(InvalidArgument, "Invalid slippage '{s}'"))?;
+        Self::from_bps(u16::try_from(bps).unwrap_or(u16::MAX))
+    }
+}
+
+impl fmt::Display for Slippage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", format_integer_amount(self.bps as u128, 2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn parses_percent_into_bps() {
+        assert_eq!("0.1".parse::<Slippage>().unwrap().bps(), 10);
+        assert_eq!("0.50".parse::<Slippage>().unwrap().bps(), 50);
+        assert_eq!("100".parse::<Slippage>().unwrap().bps(), 10_000);
+        assert!("0.005".parse::<Slippage>().is_err());
+        assert!("100.01".parse::<Slippage>().is_err());
+        assert!("-1".parse::<Slippage>().is_err());
+        assert_eq!(Slippage::from_bps(5).unwrap().to_string(), "0.05%");
+    }
+
+    #[test]
+    fn rounds_in_the_traders_favour() {
+        let slippage = Slippage::from_bps(10).unwrap();
+        // 999 * 0.999 = 998.001 and 999 * 1.001 = 999.999.
+        assert_eq!(slippage.min_out(999), 999);
+        assert_eq!(slippage.max_in(999), 999);
+        assert_eq!(slippage.max_in(u64::MAX), u64::MAX);
+    }
+
+    proptest! {
+        #[test]
+        fn min_out_never_exceeds_the_tolerance(amount: u64, bps in 0..=Slippage::MAX_BPS) {
+            let min_out = Slippage::from_bps(bps).unwrap().min_out(amount);
+            prop_assert!(min_out <= amount);
+            prop_assert!(
+                min_out as u128 * BPS_PER_UNIT >= amount as u128 * (BPS_PER_UNIT - bps as u128)
+            );
+        }
+
+        #[test]
+        fn max_in_never_exceeds_the_tolerance(amount: u64, bps in 0..=Slippage::MAX_BPS) {
+            let max_in = Slippage::from_bps(bps).unwrap().max_in(amount);
+            prop_assert!(max_in >= amount);
+            prop_assert!(
+                max_in as u128 * BPS_PER_UNIT <= amount as u128 * (BPS_PER_UNIT + bps as u128)
+            );
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use anyhow::Result;
+use raydium_amm_swap::libraries::big_num::{U256, U512};
+
+use crate::core::amm_math::{price_impact, AmmCurve, FeeRate, SwapQuote};
+use crate::core::price::Price;
+use crate::error::wmgr_error;
+
+const N_COINS: u64 = 2;
+/// Newton's method converges in a handful of rounds; this only bounds pathological inputs.
+const MAX_ITERATIONS: usize = 255;
+
+/// A two-coin StableSwap (Curve) pool seen from the input side of a swap.
+///
+/// `amp` is the amplification coefficient `A`. Reserves are normalized to the larger of the two
+/// decimals before the invariant is solved, and the fee is taken from the input like the other
+/// curves so `SwapQuote::fee` stays in input units.
+#[derive(Debug, Clone, Copy)]
+pub struct StableSwap {
+    pub reserve_in: u64,
+    pub reserve_out: u64,
+    pub decimals_in: u8,
+    pub decimals_out: u8,
+    pub amp: u64,
+    pub fee: FeeRate,
+}
+
+impl AmmCurve for StableSwap {
+    /// `-dy/dx` of the invariant at the current reserves:
+    /// `(4·Ann·x²y² + D³y) / (4·Ann·x²y² + D³x)`.
+    fn spot_price(&self) -> Price {
+        if self.reserve_in == 0 || self.reserve_out == 0 {
+            return Price::ZERO;
+        }
+        let Ok(d) = self.invariant() else {
+            return Price::ZERO;
+        };
+        let (x, y) = (
+            U512::from(self.normalized_in()),
+            U512::from(self.normalized_out()),
+        );
+        let d = U512::from(d.as_u128());
+        // Both sides are homogeneous of degree four in (x, y, D), so shifting all three keeps
+        // the ratio while bounding the products.
+        let shift = x.bits().max(y.bits()).max(d.bits()).saturating_sub(100);
+        let (x, y, d) = (x >> shift, y >> shift, d >> shift);
+        let common = U512::from(4u64) * U512::from(self.ann()) * x * x * y * y;
+        let d3 = d * d * d;
+        Price::from_ratio(common + d3 * y, common + d3 * x)
+    }
+
+    fn quote_exact_in(&self, amount_in: u64) -> Result<SwapQuote> {
+        self.validate(amount_in)?;
+        let fee = self.fee.fee(amount_in);
+        let net_in = amount_in - fee;
+        let d = self.invariant()?;
+        let new_in = self.normalized_in() + net_in as u128 * self.scale_in();
+        let new_out = self.solve(new_in, d)?;
+        // One normalized unit kept back against the solver's rounding, then floored.
+        let swapped = self
+            .normalized_out()
+            .saturating_sub(new_out)
+            .saturating_sub(1)
+            / self.scale_out();
+        let amount_out = u64::try_from(swapped)
+            .map_err(|_| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+        if amount_out == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Amount too small for pool reserves"
+            ));
+        }
+        Ok(self.quote(amount_in, amount_out, fee, net_in))
+    }
+
+    fn quote_exact_out(&self, amount_out: u64) -> Result<SwapQuote> {
+        self.validate(amount_out)?;
+        if amount_out >= self.reserve_out {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Requested amount exceeds pool reserve"
+            ));
+        }
+        let d = self.invariant()?;
+        let new_out = self.normalized_out() - amount_out as u128 * self.scale_out();
+        let new_in = self.solve(new_out, d)?;
+        let net_in = (new_in + 1)
+            .saturating_sub(self.normalized_in())
+            .div_ceil(self.scale_in());
+        let net_in = u64::try_from(net_in)
+            .map_err(|_| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"))?;
+        let (amount_in, fee) = self.fee.gross_from_net(net_in)?;
+        Ok(self.quote(amount_in, amount_out, fee, net_in))
+    }
+}
+
+impl StableSwap {
+    fn validate(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Amount must be greater than zero"
+            ));
+        }
+        if self.reserve_in == 0 || self.reserve_out == 0 {
+            return Err(wmgr_error!(Rpc, "Pool reserves are empty"));
+        }
+        if self.amp == 0 {
+            return Err(wmgr_error!(Rpc, "Invalid amplification coefficient 0"));
+        }
+        Ok(())
+    }
+
+    fn scale_in(&self) -> u128 {
+        10u128.pow(self.decimals_out.saturating_sub(self.decimals_in) as u32)
+    }
+
+    fn scale_out(&self) -> u128 {
+        10u128.pow(self.decimals_in.saturating_sub(self.decimals_out) as u32)
+    }
+
+    fn normalized_in(&self) -> u128 {
+        self.reserve_in as u128 * self.scale_in()
+    }
+
+    fn normalized_out(&self) -> u128 {
+        self.reserve_out as u128 * self.scale_out()
+    }
+
+    /// `A * n^n`, written as `A * n` per coin the way Curve's reference code does.
+    fn ann(&self) -> u128 {
+        self.amp as u128 * N_COINS as u128
+    }
+
+    /// The invariant `D` of the current normalized reserves.
+    fn invariant(&self) -> Result<U256> {
+        let (x, y) = (
+            U256::from(self.normalized_in()),
+            U256::from(self.normalized_out()),
+        );
+        let n = U256::from(N_COINS);
+        let ann = U256::from(self.ann());
+        let sum = x + y;
+        let mut d = sum;
+        for _ in 0..MAX_ITERATIONS {
+            let d_p = d * d / (x * n) * d / (y * n);
+            let previous = d;
+            d = (ann * sum + d_p * n) * d / ((ann - U256::one()) * d + (n + U256::one()) * d_p);
+            if abs_diff(d, previous) <= U256::one() {
+                return Ok(d);
+            }
+        }
+        Err(wmgr_error!(
+            Internal,
+            "StableSwap invariant did not converge"
+        ))
+    }
+
+    /// The other reserve that keeps invariant `d` once one reserve is `x`.
+    fn solve(&self, x: u128, d: U256) -> Result<u128> {
+        let n = U256::from(N_COINS);
+        let ann = U256::from(self.ann());
+        let x = U256::from(x);
+        let c = d * d / (x * n) * d / (ann * n);
+        let b = x + d / ann;
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let previous = y;
+            y = (y * y + c) / (U256::from(2u64) * y + b - d);
+            if abs_diff(y, previous) <= U256::one() {
+                return u128::try_from(y)
+                    .map_err(|_| wmgr_error!(InvalidArgument, "Amount exceeds u64 limit"));
+            }
+        }
+        Err(wmgr_error!(Internal, "StableSwap reserve did not converge"))
+    }
+
+    fn quote(&self, amount_in: u64, amount_out: u64, fee: u64, net_in: u64) -> SwapQuote {
+        let price = self.spot_price();
+        SwapQuote {
+            amount_in,
+            amount_out,
+            fee,
+            price,
+            price_impact: price_impact(
+                &price,
+                net_in,
+                amount_out,
+                self.decimals_in,
+                self.decimals_out,
+            ),
+        }
+    }
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(reserve_in: u64, reserve_out: u64) -> StableSwap {
+        StableSwap {
+            reserve_in,
+            reserve_out,
+            decimals_in: 6,
+            decimals_out: 6,
+            amp: 100,
+            fee: FeeRate {
+                numerator: 4,
+                denominator: 10_000,
+            },
+        }
+    }
+
+    #[test]
+    fn balanced_pool_trades_near_one_to_one() {
+        let curve = pool(1_000_000_000_000, 1_000_000_000_000);
+        assert!((curve.spot_price().to_f64() - 1.0).abs() < 1e-9);
+        let quote = curve.quote_exact_in(1_000_000_000).unwrap();
+        assert_eq!(quote.fee, 400_000);
+        // 0.1% of the pool moves a constant-product price by ~0.1%; A = 100 keeps it far lower.
+        assert!(quote.price_impact < 0.01, "impact {}", quote.price_impact);
+        assert!(quote.amount_out < 999_600_000 && quote.amount_out > 999_500_000);
+    }
+
+    #[test]
+    fn exact_out_round_trips_through_exact_in() {
+        let mut curve = pool(3_000_000_000_000, 1_000_000_000_000);
+        curve.decimals_in = 9;
+        for amount_out in [10, 1_000_000, 500_000_000_000] {
+            let quote = curve.quote_exact_out(amount_out).unwrap();
+            let back = curve.quote_exact_in(quote.amount_in).unwrap();
+            assert!(back.amount_out >= amount_out, "amount_out {amount_out}");
+        }
+    }
+
+    #[test]
+    fn imbalance_raises_the_price_of_the_scarce_coin() {
+        let curve = pool(3_000_000_000_000, 1_000_000_000_000);
+        let price = curve.spot_price().to_f64();
+        assert!(price < 1.0 && price > 0.9, "price {price}");
+        let max = curve.max_input_for_impact(0.5);
+        assert!(curve.quote_exact_in(max).unwrap().price_impact <= 0.5);
+        assert!(curve.quote_exact_in(max + 1).unwrap().price_impact > 0.5);
+    }
+}
@@ -0,0 +1,67 @@
+/// Token-2022 transfer fee for the current epoch; zero for mints without the extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferFeeRate {
+    pub basis_points: u16,
+    pub maximum_fee: u64,
+}
+
+const MAX_BASIS_POINTS: u128 = 10_000;
+
+impl TransferFeeRate {
+    pub const NONE: Self = Self {
+        basis_points: 0,
+        maximum_fee: 0,
+    };
+
+    /// Fee withheld when `amount` is transferred (rounded up, capped at `maximum_fee`).
+    pub fn fee(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.basis_points as u128).div_ceil(MAX_BASIS_POINTS);
+        fee.min(self.maximum_fee as u128) as u64
+    }
+
+    /// Amount to transfer so that `amount` arrives after the fee.
+    pub fn pre_fee_amount(&self, amount: u64) -> Option<u64> {
+        if self.basis_points == 0 || amount == 0 {
+            return Some(amount);
+        }
+        if self.basis_points as u128 >= MAX_BASIS_POINTS {
+            return amount.checked_add(self.maximum_fee);
+        }
+        let numerator = amount as u128 * MAX_BASIS_POINTS;
+        let denominator = MAX_BASIS_POINTS - self.basis_points as u128;
+        let raw = numerator.div_ceil(denominator);
+        let pre_fee = if raw - amount as u128 >= self.maximum_fee as u128 {
+            amount as u128 + self.maximum_fee as u128
+        } else {
+            raw
+        };
+        u64::try_from(pre_fee).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pre_fee_amount_covers_the_fee() {
+        let rate = TransferFeeRate {
+            basis_points: 150,
+            maximum_fee: 1_000_000,
+        };
+        for amount in [1, 99, 10_000, 123_456_789] {
+            let gross = rate.pre_fee_amount(amount).unwrap();
+            assert!(gross - rate.fee(gross) >= amount, "amount {amount}");
+        }
+
+        let capped = TransferFeeRate {
+            basis_points: 500,
+            maximum_fee: 10,
+        };
+        assert_eq!(capped.fee(1_000_000), 10);
+        assert_eq!(capped.pre_fee_amount(1_000_000), Some(1_000_010));
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+
+use ethers::contract::ContractError;
+use ethers::providers::{Http, Provider, ProviderError};
+use solana_client::client_error::ClientError;
+use solana_sdk::transaction::TransactionError;
+
+use crate::infra::solana::SubmitError;
+
+/// Stable error codes reported in JSON output and the REPL, each with its own exit status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    Internal,
+    InvalidArgument,
+    Config,
+    KeySource,
+    Svpi,
+    Rpc,
+    SimulationFailed,
+    InsufficientFunds,
+    TxFailed,
+    TxExpired,
+    TxUnconfirmed,
+    UserAborted,
+    Io,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Internal => "internal",
+            ErrorCode::InvalidArgument => "invalid_argument",
+            ErrorCode::Config => "config",
+            ErrorCode::KeySource => "key_source",
+            ErrorCode::Svpi => "svpi",
+            ErrorCode::Rpc => "rpc",
+            ErrorCode::SimulationFailed => "simulation_failed",
+            ErrorCode::InsufficientFunds => "insufficient_funds",
+            ErrorCode::TxFailed => "tx_failed",
+            ErrorCode::TxExpired => "tx_expired",
+            ErrorCode::TxUnconfirmed => "tx_unconfirmed",
+            ErrorCode::UserAborted => "user_aborted",
+            ErrorCode::Io => "io",
+        }
+    }
+
+    /// Process exit status. `2` matches clap's status for command line errors.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCode::Internal => 1,
+            ErrorCode::InvalidArgument => 2,
+            ErrorCode::Config => 3,
+            ErrorCode::KeySource => 4,
+            ErrorCode::Svpi => 5,
+            ErrorCode::Rpc => 6,
+            ErrorCode::SimulationFailed => 7,
+            ErrorCode::InsufficientFunds => 8,
+            ErrorCode::TxFailed => 9,
+            ErrorCode::TxExpired => 10,
+            ErrorCode::UserAborted => 11,
+            ErrorCode::Io => 12,
+            ErrorCode::TxUnconfirmed => 13,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WmgrError {
+    /// A bug or an invariant broken inside `wmgr`, not caused by the input or the network.
+    Internal(String),
+    InvalidArgument(String),
+    /// `--max-price-impact` refused the trade. Reported as an invalid argument, but unlike one it
+    /// may pass once the pool moves.
+    PriceImpact(String),
+    Config(String),
+    KeySource(String),
+    Svpi(String),
+    Rpc(String),
+    SimulationFailed(String),
+    InsufficientFunds(String),
+    UserAborted,
+    Io(String),
+}
+
+impl WmgrError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            WmgrError::Internal(_) => ErrorCode::Internal,
+            WmgrError::InvalidArgument(_) | WmgrError::PriceImpact(_) => ErrorCode::InvalidArgument,
+            WmgrError::Config(_) => ErrorCode::Config,
+            WmgrError::KeySource(_) => ErrorCode::KeySource,
+            WmgrError::Svpi(_) => ErrorCode::Svpi,
+            WmgrError::Rpc(_) => ErrorCode::Rpc,
+            WmgrError::SimulationFailed(_) => ErrorCode::SimulationFailed,
+            WmgrError::InsufficientFunds(_) => ErrorCode::InsufficientFunds,
+            WmgrError::UserAborted => ErrorCode::UserAborted,
+            WmgrError::Io(_) => ErrorCode::Io,
+        }
+    }
+}
+
+impl fmt::Display for WmgrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WmgrError::Internal(msg)
+            | WmgrError::InvalidArgument(msg)
+            | WmgrError::PriceImpact(msg)
+            | WmgrError::Config(msg)
+            | WmgrError::KeySource(msg)
+            | WmgrError::Svpi(msg)
+            | WmgrError::Rpc(msg)
+            | WmgrError::SimulationFailed(msg)
+            | WmgrError::InsufficientFunds(msg)
+            | WmgrError::Io(msg) => f.write_str(msg),
+            WmgrError::UserAborted => f.write_str("Aborted by user"),
+        }
+    }
+}
+
+impl std::error::Error for WmgrError {}
+
+/// `anyhow!` with a [`WmgrError`] variant: `wmgr_error!(Rpc, "Failed to fetch {mint}: {err}")`.
+macro_rules! wmgr_error {
+    ($kind:ident, $($arg:tt)*) => {
+        anyhow::Error::from($crate::error::WmgrError::$kind(format!($($arg)*)))
+    };
+}
+pub(crate) use wmgr_error;
+
+/// Classifies an error by the first cause in its chain that carries a known type.
+pub fn error_code(err: &anyhow::Error) -> ErrorCode {
+    for cause in err.chain() {
+        if let Some(err) = cause.downcast_ref::<WmgrError>() {
+            return err.code();
+        }
+        if let Some(err) = cause.downcast_ref::<SubmitError>() {
+            return match err {
+                SubmitError::Expired { .. } => ErrorCode::TxExpired,
+                SubmitError::Failed { .. } => ErrorCode::TxFailed,
+                SubmitError::Unconfirmed { .. } => ErrorCode::TxUnconfirmed,
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<ClientError>() {
+            // Preflight failures carry the simulated transaction error.
+            return match err.get_transaction_error() {
+                Some(tx_err) if is_fee_payer_underfunded(&tx_err) => ErrorCode::InsufficientFunds,
+                Some(_) => ErrorCode::SimulationFailed,
+                None => ErrorCode::Rpc,
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<ProviderError>() {
+            return evm_error_code(&err.to_string());
+        }
+        if let Some(err) = cause.downcast_ref::<ContractError<Provider<Http>>>() {
+            return evm_error_code(&err.to_string());
+        }
+        if cause.is::<std::io::Error>() {
+            return ErrorCode::Io;
+        }
+    }
+    ErrorCode::Internal
+}
+
+pub fn is_fee_payer_underfunded(err: &TransactionError) -> bool {
+    matches!(
+        err,
+        TransactionError::InsufficientFundsForFee
+            | TransactionError::InsufficientFundsForRent { .. }
+    )
+}
+
+fn evm_error_code(message: &str) -> ErrorCode {
+    if message.contains("insufficient funds") {
+        ErrorCode::InsufficientFunds
+    } else {
+        ErrorCode::Rpc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_by_first_typed_cause() {
+        let err = wmgr_error!(KeySource, "Keypair file must contain 64 bytes");
+        assert_eq!(error_code(&err), ErrorCode::KeySource);
+
+        let err = anyhow::Error::from(WmgrError::UserAborted).context("send sol");
+        assert_eq!(error_code(&err), ErrorCode::UserAborted);
+
+        let err = anyhow::anyhow!("something else");
+        assert_eq!(error_code(&err), ErrorCode::Internal);
+    }
+
+    #[test]
+    fn rpc_failure_after_the_send_is_not_plain_rpc() {
+        let err = anyhow::Error::from(SubmitError::Unconfirmed {
+            signature: solana_sdk::signature::Signature::default(),
+            last_valid_block_height: 100,
+            reason: "connection reset".to_string(),
+        });
+        assert_eq!(error_code(&err), ErrorCode::TxUnconfirmed);
+    }
+
+    #[test]
+    fn exit_codes_are_distinct() {
+        let codes = [
+            ErrorCode::Internal,
+            ErrorCode::InvalidArgument,
+            ErrorCode::Config,
+            ErrorCode::KeySource,
+            ErrorCode::Svpi,
+            ErrorCode::Rpc,
+            ErrorCode::SimulationFailed,
+            ErrorCode::InsufficientFunds,
+            ErrorCode::TxFailed,
+            ErrorCode::TxExpired,
+            ErrorCode::TxUnconfirmed,
+            ErrorCode::UserAborted,
+            ErrorCode::Io,
+        ];
+        let mut exits: Vec<i32> = codes.iter().map(|c| c.exit_code()).collect();
+        exits.sort_unstable();
+        exits.dedup();
+        assert_eq!(exits.len(), codes.len());
+    }
+}
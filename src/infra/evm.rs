@@ -1,17 +1,20 @@
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use ethers::prelude::*;
-use ethers::types::{Address, U256};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, Eip1559TransactionRequest, U256};
 use ethers::utils::{format_units, parse_ether, parse_units};
 
 use crate::config::evm_networks::{get_evm_network_config, EvmNetworkConfig};
+use crate::error::wmgr_error;
 
 abigen!(
     IERC20,
     r#"[
         function decimals() view returns (uint8)
         function symbol() view returns (string)
+        function balanceOf(address owner) view returns (uint256)
         function transfer(address to, uint256 amount) returns (bool)
     ]"#
 );
@@ -21,13 +24,19 @@ pub struct Erc20Meta {
     pub symbol: Option<String>,
 }
 
+pub struct Erc20Balance {
+    pub token: Address,
+    pub formatted: String,
+    pub symbol: Option<String>,
+}
+
 pub fn create_evm_provider(
     network: &str,
     rpc_override: Option<&str>,
 ) -> Result<(Provider<Http>, EvmNetworkConfig)> {
     let cfg = get_evm_network_config(network, rpc_override)?;
     let provider = Provider::<Http>::try_from(cfg.rpc_url.as_str())
-        .map_err(|err| anyhow!("Invalid RPC URL: {err}"))?;
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid RPC URL: {err}"))?;
     Ok((provider, cfg))
 }
 
@@ -40,76 +49,163 @@ pub async fn get_native_balance(
     Ok((balance, formatted))
 }
 
-pub async fn transfer_eth(
-    provider: Provider<Http>,
-    wallet: LocalWallet,
+/// A transfer built for review: the unsigned transaction plus what it moves.
+pub struct EvmTransfer {
+    pub from: Address,
+    pub recipient: Address,
+    /// ERC-20 contract, `None` for native transfers.
+    pub contract: Option<Address>,
+    pub amount: U256,
+    pub decimals: u8,
+    pub tx: TypedTransaction,
+}
+
+pub struct EvmSimulation {
+    pub gas: U256,
+    /// Upper bound of the network fee: gas times the max fee (or gas price) per gas.
+    pub max_fee_wei: U256,
+    /// Revert reason from `eth_call`/`eth_estimateGas`, if the transfer would fail.
+    pub error: Option<String>,
+}
+
+pub fn build_eth_transfer(
+    from: Address,
     to: &str,
     amount: &str,
-    gas_price: Option<&str>,
+    fees: &FeePlan,
     gas_limit: Option<u64>,
     chain_id: u64,
-) -> Result<TxHash> {
+) -> Result<EvmTransfer> {
     let to_addr: Address = to
         .parse()
-        .map_err(|err| anyhow!("Invalid recipient address: {err}"))?;
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid recipient address: {err}"))?;
     let value = parse_ether(amount)?;
 
-    let wallet = wallet.with_chain_id(chain_id);
-    let client = SignerMiddleware::new(provider, wallet);
-    let client = Arc::new(client);
-
-    let mut tx = TransactionRequest::new().to(to_addr).value(value);
-    if let Some(gp) = maybe_gas_price(gas_price)? {
-        tx = tx.gas_price(gp);
-    }
-    if let Some(gl) = gas_limit {
-        tx = tx.gas(gl);
-    }
-
-    let pending = client.send_transaction(tx, None).await?;
-    let tx_hash = pending.tx_hash();
-    let _receipt = pending.await?;
-    Ok(tx_hash)
+    let mut tx = build_typed_transaction(to_addr, value, None, fees, gas_limit);
+    tx.set_from(from);
+    tx.set_chain_id(chain_id);
+    Ok(EvmTransfer {
+        from,
+        recipient: to_addr,
+        contract: None,
+        amount: value,
+        decimals: 18,
+        tx,
+    })
 }
 
-pub async fn transfer_erc20(
+#[allow(clippy::too_many_arguments)]
+pub async fn build_erc20_transfer(
     provider: Provider<Http>,
-    wallet: LocalWallet,
+    from: Address,
     token: &str,
     to: &str,
     amount: &str,
     decimals: Option<u8>,
-    gas_price: Option<&str>,
+    fees: &FeePlan,
     gas_limit: Option<u64>,
     chain_id: u64,
-) -> Result<TxHash> {
+) -> Result<EvmTransfer> {
     let token_addr: Address = token
         .parse()
-        .map_err(|err| anyhow!("Invalid token address: {err}"))?;
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid token address: {err}"))?;
     let to_addr: Address = to
         .parse()
-        .map_err(|err| anyhow!("Invalid recipient address: {err}"))?;
-
-    let wallet = wallet.with_chain_id(chain_id);
-    let client = SignerMiddleware::new(provider, wallet);
-    let client = Arc::new(client);
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid recipient address: {err}"))?;
 
-    let contract = IERC20::new(token_addr, client.clone());
+    let contract = IERC20::new(token_addr, provider.into());
     let token_decimals = match decimals {
         Some(v) => v,
         None => contract.decimals().call().await?,
     };
-    let amount_units = parse_units(amount, token_decimals as u32)?.into();
+    let amount_units: U256 = parse_units(amount, token_decimals as u32)?.into();
 
-    let mut call = contract.transfer(to_addr, amount_units);
-    if let Some(gp) = maybe_gas_price(gas_price)? {
-        call = call.gas_price(gp);
-    }
-    if let Some(gl) = gas_limit {
-        call = call.gas(gl);
+    let call = contract.transfer(to_addr, amount_units);
+    let data = call
+        .calldata()
+        .ok_or_else(|| wmgr_error!(Internal, "Failed to encode ERC-20 transfer"))?;
+    let mut tx = build_typed_transaction(token_addr, U256::zero(), Some(data), fees, gas_limit);
+    tx.set_from(from);
+    tx.set_chain_id(chain_id);
+    Ok(EvmTransfer {
+        from,
+        recipient: to_addr,
+        contract: Some(token_addr),
+        amount: amount_units,
+        decimals: token_decimals,
+        tx,
+    })
+}
+
+/// Runs the transfer through `eth_call` and `eth_estimateGas` without broadcasting it.
+///
+/// On success the estimated gas is written into the transaction unless a gas limit was set.
+pub async fn simulate_evm_transfer(
+    provider: &Provider<Http>,
+    transfer: &mut EvmTransfer,
+    fees: &FeePlan,
+) -> Result<EvmSimulation> {
+    let call_error = match provider.call(&transfer.tx, None).await {
+        Err(err) => Some(err.to_string()),
+        // Some ERC-20 tokens signal failure by returning `false` instead of reverting.
+        Ok(output) if transfer.contract.is_some() && returns_false(&output) => {
+            Some("ERC-20 transfer returned false".to_string())
+        }
+        Ok(_) => None,
+    };
+    if let Some(error) = call_error {
+        return Ok(EvmSimulation {
+            gas: transfer.tx.gas().copied().unwrap_or_default(),
+            max_fee_wei: U256::zero(),
+            error: Some(error),
+        });
     }
+    let gas = match transfer.tx.gas().copied() {
+        Some(gas) => gas,
+        None => match provider.estimate_gas(&transfer.tx, None).await {
+            Ok(gas) => {
+                transfer.tx.set_gas(gas);
+                gas
+            }
+            Err(err) => {
+                return Ok(EvmSimulation {
+                    gas: U256::zero(),
+                    max_fee_wei: U256::zero(),
+                    error: Some(err.to_string()),
+                })
+            }
+        },
+    };
+    let price = match fees {
+        FeePlan::Eip1559 { max_fee, .. } => *max_fee,
+        FeePlan::Legacy {
+            gas_price: Some(gp),
+        } => *gp,
+        FeePlan::Legacy { gas_price: None } => provider.get_gas_price().await?,
+    };
+    Ok(EvmSimulation {
+        gas,
+        max_fee_wei: gas * price,
+        error: None,
+    })
+}
+
+fn returns_false(output: &Bytes) -> bool {
+    output.len() == 32 && output.iter().all(|b| *b == 0)
+}
+
+/// Signs and broadcasts the transfer, waiting for its receipt.
+pub async fn send_evm_transfer(
+    provider: Provider<Http>,
+    wallet: LocalWallet,
+    transfer: EvmTransfer,
+    chain_id: u64,
+) -> Result<TxHash> {
+    let wallet = wallet.with_chain_id(chain_id);
+    let client = SignerMiddleware::new(provider, wallet);
+    let client = Arc::new(client);
 
-    let pending = call.send().await?;
+    let pending = client.send_transaction(transfer.tx, None).await?;
     let tx_hash = pending.tx_hash();
     let _receipt = pending.await?;
     Ok(tx_hash)
@@ -118,15 +214,223 @@ pub async fn transfer_erc20(
 pub async fn get_erc20_meta(provider: Provider<Http>, token: &str) -> Result<Erc20Meta> {
     let token_addr: Address = token
         .parse()
-        .map_err(|err| anyhow!("Invalid token address: {err}"))?;
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid token address: {err}"))?;
     let contract = IERC20::new(token_addr, provider.into());
     let decimals = contract.decimals().call().await?;
     let symbol = contract.symbol().call().await.ok();
     Ok(Erc20Meta { decimals, symbol })
 }
 
-fn maybe_gas_price(gwei: Option<&str>) -> Result<Option<U256>> {
+pub async fn get_erc20_balance(
+    provider: Provider<Http>,
+    token: &str,
+    owner: Address,
+) -> Result<Erc20Balance> {
+    let token_addr: Address = token
+        .parse()
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid token address: {err}"))?;
+    let meta = get_erc20_meta(provider.clone(), token).await?;
+    let contract = IERC20::new(token_addr, provider.into());
+    let raw = contract.balance_of(owner).call().await?;
+    let formatted = format_units(raw, meta.decimals as u32)?;
+    Ok(Erc20Balance {
+        token: token_addr,
+        formatted,
+        symbol: meta.symbol,
+    })
+}
+
+pub enum FeePlan {
+    Legacy { gas_price: Option<U256> },
+    Eip1559 { max_fee: U256, priority_fee: U256 },
+}
+
+impl FeePlan {
+    pub fn describe(&self) -> String {
+        match self {
+            FeePlan::Legacy {
+                gas_price: Some(gp),
+            } => {
+                format!("legacy, gas price {} gwei", format_gwei(*gp))
+            }
+            FeePlan::Legacy { gas_price: None } => "legacy, gas price from RPC".to_string(),
+            FeePlan::Eip1559 {
+                max_fee,
+                priority_fee,
+            } => format!(
+                "EIP-1559, max fee {} gwei, priority fee {} gwei",
+                format_gwei(*max_fee),
+                format_gwei(*priority_fee)
+            ),
+        }
+    }
+}
+
+pub struct FeeOptions<'a> {
+    pub gas_price: Option<&'a str>,
+    pub max_fee: Option<&'a str>,
+    pub priority_fee: Option<&'a str>,
+}
+
+/// Number of recent blocks sampled by `eth_feeHistory` for the priority fee estimate.
+const FEE_HISTORY_BLOCKS: u64 = 10;
+/// Reward percentile used for the priority fee estimate.
+const FEE_HISTORY_PERCENTILE: f64 = 50.0;
+
+pub async fn resolve_fee_plan(provider: &Provider<Http>, opts: &FeeOptions<'_>) -> Result<FeePlan> {
+    let max_fee = maybe_gwei(opts.max_fee)?;
+    let priority_fee = maybe_gwei(opts.priority_fee)?;
+    let gas_price = maybe_gwei(opts.gas_price)?;
+
+    if gas_price.is_some() {
+        if max_fee.is_some() || priority_fee.is_some() {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Use either --gas-price (legacy) or --max-fee/--priority-fee (EIP-1559)"
+            ));
+        }
+        return Ok(FeePlan::Legacy { gas_price });
+    }
+
+    let latest = provider
+        .get_block(BlockNumber::Latest)
+        .await?
+        .ok_or_else(|| wmgr_error!(Rpc, "Latest block not found"))?;
+    if latest.base_fee_per_gas.is_none() {
+        // Chain without EIP-1559: a max fee is the most the user agreed to pay per gas.
+        return Ok(FeePlan::Legacy { gas_price: max_fee });
+    }
+
+    let (max_fee, priority_fee) = match (max_fee, priority_fee) {
+        (Some(max_fee), Some(priority_fee)) => (max_fee, priority_fee),
+        (max_fee, priority_fee) => {
+            let history = provider
+                .fee_history(
+                    FEE_HISTORY_BLOCKS,
+                    BlockNumber::Latest,
+                    &[FEE_HISTORY_PERCENTILE],
+                )
+                .await?;
+            let next_base_fee = history
+                .base_fee_per_gas
+                .last()
+                .copied()
+                .ok_or_else(|| wmgr_error!(Rpc, "eth_feeHistory returned no base fee"))?;
+            let rewards: Vec<U256> = history
+                .reward
+                .iter()
+                .filter_map(|r| r.first().copied())
+                .collect();
+            let priority_fee = match (priority_fee, max_fee) {
+                (Some(priority_fee), _) => priority_fee,
+                (None, Some(max_fee)) => suggest_priority_fee(&rewards).min(max_fee),
+                (None, None) => suggest_priority_fee(&rewards),
+            };
+            let max_fee = max_fee.unwrap_or_else(|| default_max_fee(next_base_fee, priority_fee));
+            (max_fee, priority_fee)
+        }
+    };
+
+    if priority_fee > max_fee {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Priority fee cannot exceed max fee"
+        ));
+    }
+    Ok(FeePlan::Eip1559 {
+        max_fee,
+        priority_fee,
+    })
+}
+
+/// Median of the non-zero priority rewards reported by `eth_feeHistory`.
+fn suggest_priority_fee(rewards: &[U256]) -> U256 {
+    let mut rewards: Vec<U256> = rewards.iter().copied().filter(|r| !r.is_zero()).collect();
+    rewards.sort();
+    if rewards.is_empty() {
+        U256::from(MIN_PRIORITY_FEE_WEI)
+    } else {
+        rewards[rewards.len() / 2]
+    }
+}
+
+/// Leaves room for the base fee to double before the transaction stops being includable.
+fn default_max_fee(next_base_fee: U256, priority_fee: U256) -> U256 {
+    next_base_fee * 2 + priority_fee
+}
+
+/// Fallback priority fee (1 gwei) when recent blocks carry no tips.
+const MIN_PRIORITY_FEE_WEI: u64 = 1_000_000_000;
+
+fn build_typed_transaction(
+    to: Address,
+    value: U256,
+    data: Option<Bytes>,
+    fees: &FeePlan,
+    gas_limit: Option<u64>,
+) -> TypedTransaction {
+    match fees {
+        FeePlan::Legacy { gas_price } => {
+            let mut tx = TransactionRequest::new().to(to).value(value);
+            if let Some(data) = data {
+                tx = tx.data(data);
+            }
+            if let Some(gp) = gas_price {
+                tx = tx.gas_price(*gp);
+            }
+            if let Some(gl) = gas_limit {
+                tx = tx.gas(gl);
+            }
+            tx.into()
+        }
+        FeePlan::Eip1559 {
+            max_fee,
+            priority_fee,
+        } => {
+            let mut tx = Eip1559TransactionRequest::new()
+                .to(to)
+                .value(value)
+                .max_fee_per_gas(*max_fee)
+                .max_priority_fee_per_gas(*priority_fee);
+            if let Some(data) = data {
+                tx = tx.data(data);
+            }
+            if let Some(gl) = gas_limit {
+                tx = tx.gas(gl);
+            }
+            tx.into()
+        }
+    }
+}
+
+fn format_gwei(value: U256) -> String {
+    format_units(value, "gwei").unwrap_or_else(|_| value.to_string())
+}
+
+fn maybe_gwei(gwei: Option<&str>) -> Result<Option<U256>> {
     let Some(value) = gwei else { return Ok(None) };
     let parsed = parse_units(value, "gwei")?.into();
     Ok(Some(parsed))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_median_priority_fee() {
+        let rewards = [0u64, 3, 1, 2].map(U256::from);
+        let priority_fee = suggest_priority_fee(&rewards);
+        assert_eq!(priority_fee, U256::from(2u64));
+        assert_eq!(
+            default_max_fee(U256::from(100u64), priority_fee),
+            U256::from(202u64)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_min_priority_fee() {
+        let priority_fee = suggest_priority_fee(&[U256::zero()]);
+        assert_eq!(priority_fee, U256::from(MIN_PRIORITY_FEE_WEI));
+    }
+}
@@ -1,11 +1,12 @@
 use std::fs;
 use std::str::FromStr;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::Result;
 use ethers::signers::{coins_bip39::English, LocalWallet, MnemonicBuilder};
 
 use super::svpi::get_data_from_svpi;
 use crate::app::cli::EvmKeyOptions;
+use crate::error::wmgr_error;
 use crate::utils::prompt::{prompt, prompt_hidden};
 
 pub const DEFAULT_EVM_PATH: &str = "m/44'/60'/0'/0/0";
@@ -30,12 +31,14 @@ pub fn resolve_evm_wallet(opts: &EvmKeyOptions) -> Result<LocalWallet> {
         .count();
 
     if sources == 0 {
-        return Err(anyhow!(
+        return Err(wmgr_error!(
+            KeySource,
             "Provide --privkey, --privkey-file, --seed, or --svpi for EVM"
         ));
     }
     if sources > 1 {
-        return Err(anyhow!(
+        return Err(wmgr_error!(
+            KeySource,
             "Use only one of --privkey, --privkey-file, --seed, or --svpi for EVM"
         ));
     }
@@ -43,15 +46,16 @@ pub fn resolve_evm_wallet(opts: &EvmKeyOptions) -> Result<LocalWallet> {
     if has_privkey {
         let normalized = normalize_privkey(opts.privkey.as_ref().unwrap())?;
         return LocalWallet::from_str(&normalized)
-            .map_err(|err| anyhow!("Invalid private key: {err}"));
+            .map_err(|err| wmgr_error!(KeySource, "Invalid private key: {err}"));
     }
 
     if has_privkey_file {
         let path = opts.privkey_file.as_ref().unwrap();
-        let raw = fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+        let raw = fs::read_to_string(path)
+            .map_err(|err| wmgr_error!(KeySource, "Failed to read {path:?}: {err}"))?;
         let normalized = normalize_privkey(&raw)?;
         return LocalWallet::from_str(&normalized)
-            .map_err(|err| anyhow!("Invalid private key: {err}"));
+            .map_err(|err| wmgr_error!(KeySource, "Invalid private key: {err}"));
     }
 
     let derivation_path = opts.path.as_deref().unwrap_or(DEFAULT_EVM_PATH);
@@ -76,7 +80,7 @@ pub fn resolve_evm_wallet(opts: &EvmKeyOptions) -> Result<LocalWallet> {
         if looks_like_hex_privkey(&data) {
             let normalized = normalize_privkey(&data)?;
             return LocalWallet::from_str(&normalized)
-                .map_err(|err| anyhow!("Invalid private key: {err}"));
+                .map_err(|err| wmgr_error!(KeySource, "Invalid private key: {err}"));
         }
         return wallet_from_mnemonic(&data, derivation_path, &seed_passphrase);
     }
@@ -94,20 +98,21 @@ pub fn wallet_from_mnemonic(
     builder = builder.phrase(mnemonic).password(passphrase);
     builder = builder
         .derivation_path(derivation_path)
-        .map_err(|err| anyhow!("Invalid derivation path: {err}"))?;
+        .map_err(|err| wmgr_error!(KeySource, "Invalid derivation path: {err}"))?;
     builder
         .build()
-        .map_err(|err| anyhow!("Failed to derive EVM wallet: {err}"))
+        .map_err(|err| wmgr_error!(KeySource, "Failed to derive EVM wallet: {err}"))
 }
 
 fn normalize_privkey(hex: &str) -> Result<String> {
     let trimmed = hex.trim();
     if trimmed.is_empty() {
-        return Err(anyhow!("Private key is required"));
+        return Err(wmgr_error!(KeySource, "Private key is required"));
     }
     let without_prefix = trimmed.strip_prefix("0x").unwrap_or(trimmed);
     if without_prefix.len() != 64 || !without_prefix.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Err(anyhow!(
+        return Err(wmgr_error!(
+            KeySource,
             "Private key must be 64 hex chars (with or without 0x prefix)"
         ));
     }
@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::Path;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::Result;
 use bip39::{Language, Mnemonic, Seed};
 use solana_derivation_path::DerivationPath;
 use solana_keypair::{seed_derivable::keypair_from_seed_and_derivation_path, Keypair};
@@ -9,6 +9,7 @@ use solana_keypair::{seed_derivable::keypair_from_seed_and_derivation_path, Keyp
 use super::svpi::get_data_from_svpi;
 use crate::app::cli::SolanaKeyOptions;
 use crate::config::mnemonics::get_mnemonic_profile;
+use crate::error::wmgr_error;
 use crate::utils::prompt::{prompt, prompt_hidden};
 
 pub fn resolve_solana_keypair(opts: &SolanaKeyOptions) -> Result<Keypair> {
@@ -30,10 +31,14 @@ pub fn resolve_solana_keypair(opts: &SolanaKeyOptions) -> Result<Keypair> {
         .count();
 
     if sources == 0 {
-        return Err(anyhow!("Provide --keyfile, --seed, or --svpi for Solana"));
+        return Err(wmgr_error!(
+            KeySource,
+            "Provide --keyfile, --seed, or --svpi for Solana"
+        ));
     }
     if sources > 1 {
-        return Err(anyhow!(
+        return Err(wmgr_error!(
+            KeySource,
             "Use only one of --keyfile, --seed, or --svpi for Solana"
         ));
     }
@@ -80,29 +85,35 @@ pub fn keypair_from_mnemonic(
     derivation_path: &str,
     passphrase: &str,
 ) -> Result<Keypair> {
-    let mnemonic =
-        Mnemonic::from_phrase(mnemonic, Language::English).context("Invalid BIP39 mnemonic")?;
+    let mnemonic = Mnemonic::from_phrase(mnemonic, Language::English)
+        .map_err(|err| wmgr_error!(KeySource, "Invalid BIP39 mnemonic: {err}"))?;
     let seed = Seed::new(&mnemonic, passphrase);
     let path = DerivationPath::from_absolute_path_str(derivation_path)
-        .context("Invalid derivation path")?;
+        .map_err(|err| wmgr_error!(KeySource, "Invalid derivation path: {err}"))?;
     keypair_from_seed_and_derivation_path(seed.as_bytes(), Some(path))
-        .map_err(|err| anyhow!("Failed to derive Solana keypair: {err}"))
+        .map_err(|err| wmgr_error!(KeySource, "Failed to derive Solana keypair: {err}"))
 }
 
 pub fn keypair_from_file(path: &Path) -> Result<Keypair> {
-    let raw = fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
-    let data: Vec<u8> =
-        serde_json::from_str(&raw).context("Keypair file must be a JSON array of numbers")?;
+    let raw = fs::read_to_string(path)
+        .map_err(|err| wmgr_error!(KeySource, "Failed to read {path:?}: {err}"))?;
+    let data: Vec<u8> = serde_json::from_str(&raw).map_err(|err| {
+        wmgr_error!(
+            KeySource,
+            "Keypair file must be a JSON array of numbers: {err}"
+        )
+    })?;
     if data.len() != 64 {
-        return Err(anyhow!("Keypair file must contain 64 bytes"));
+        return Err(wmgr_error!(KeySource, "Keypair file must contain 64 bytes"));
     }
-    Keypair::try_from(data.as_slice()).map_err(|err| anyhow!("Failed to parse keypair: {err}"))
+    Keypair::try_from(data.as_slice())
+        .map_err(|err| wmgr_error!(KeySource, "Failed to parse keypair: {err}"))
 }
 
 fn keypair_from_hex_privkey(value: &str) -> Result<Keypair> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
-        return Err(anyhow!("Private key is required"));
+        return Err(wmgr_error!(KeySource, "Private key is required"));
     }
     let without_prefix = trimmed.strip_prefix("0x").unwrap_or(trimmed);
     let bytes = decode_hex_bytes(without_prefix)?;
@@ -110,24 +121,29 @@ fn keypair_from_hex_privkey(value: &str) -> Result<Keypair> {
         32 => {
             let key: [u8; 32] = bytes
                 .try_into()
-                .map_err(|_| anyhow!("Solana private key must be 32 or 64 bytes"))?;
+                .map_err(|_| wmgr_error!(KeySource, "Solana private key must be 32 or 64 bytes"))?;
             Ok(Keypair::new_from_array(key))
         }
         64 => Keypair::try_from(bytes.as_slice())
-            .map_err(|err| anyhow!("Failed to parse Solana keypair: {err}")),
-        _ => Err(anyhow!("Solana private key must be 32 or 64 bytes")),
+            .map_err(|err| wmgr_error!(KeySource, "Failed to parse Solana keypair: {err}")),
+        _ => Err(wmgr_error!(
+            KeySource,
+            "Solana private key must be 32 or 64 bytes"
+        )),
     }
 }
 
 fn decode_hex_bytes(hex: &str) -> Result<Vec<u8>> {
     let bytes = hex.as_bytes();
-    if bytes.len() % 2 != 0 {
-        return Err(anyhow!("Solana private key must be hex"));
+    if !bytes.len().is_multiple_of(2) {
+        return Err(wmgr_error!(KeySource, "Solana private key must be hex"));
     }
     let mut out = Vec::with_capacity(bytes.len() / 2);
     for chunk in bytes.chunks(2) {
-        let hi = hex_value(chunk[0]).ok_or_else(|| anyhow!("Solana private key must be hex"))?;
-        let lo = hex_value(chunk[1]).ok_or_else(|| anyhow!("Solana private key must be hex"))?;
+        let hi = hex_value(chunk[0])
+            .ok_or_else(|| wmgr_error!(KeySource, "Solana private key must be hex"))?;
+        let lo = hex_value(chunk[1])
+            .ok_or_else(|| wmgr_error!(KeySource, "Solana private key must be hex"))?;
         out.push((hi << 4) | lo);
     }
     Ok(out)
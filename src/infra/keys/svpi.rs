@@ -3,9 +3,11 @@ use std::path::Path;
 use std::process::Command;
 use std::process::Stdio;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::Result;
 use serde_json::Value;
 
+use crate::error::wmgr_error;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SvpiData {
     pub data_type: String,
@@ -15,10 +17,11 @@ pub struct SvpiData {
 fn parse_first_json_value(raw: &str) -> Result<Value> {
     let raw = raw.trim();
     if raw.is_empty() {
-        return Err(anyhow!("SVPI returned empty output"));
+        return Err(wmgr_error!(Svpi, "SVPI returned empty output"));
     }
 
-    serde_json::from_str::<Value>(raw).context("Failed to parse JSON from SVPI output")
+    serde_json::from_str::<Value>(raw)
+        .map_err(|err| wmgr_error!(Svpi, "Failed to parse JSON from SVPI output: {err}"))
 }
 
 fn extract_typed_data(value: &Value) -> Result<SvpiData> {
@@ -27,14 +30,14 @@ fn extract_typed_data(value: &Value) -> Result<SvpiData> {
         .and_then(|v| v.as_str())
         .map(|v| v.trim().to_string())
         .filter(|v| !v.is_empty())
-        .ok_or_else(|| anyhow!("SVPI response did not include data_type"))?;
+        .ok_or_else(|| wmgr_error!(Svpi, "SVPI response did not include data_type"))?;
 
     let data = value
         .get("data")
         .and_then(|v| v.as_str())
         .map(|v| v.trim().to_string())
         .filter(|v| !v.is_empty())
-        .ok_or_else(|| anyhow!("SVPI response did not include data"))?;
+        .ok_or_else(|| wmgr_error!(Svpi, "SVPI response did not include data"))?;
 
     Ok(SvpiData { data_type, data })
 }
@@ -56,7 +59,7 @@ fn parse_svpi_error(value: &Value) -> anyhow::Error {
         .map(|v| format!(" Details: {v}"))
         .unwrap_or_default();
 
-    anyhow!("SVPI error ({}): {}.{details}", code, message)
+    wmgr_error!(Svpi, "SVPI error ({}): {}.{details}", code, message)
 }
 
 fn try_extract_svpi_data_from_json(raw: &str) -> Result<Option<SvpiData>> {
@@ -85,10 +88,10 @@ pub fn get_data_from_svpi(
     cmd_path: Option<&Path>,
 ) -> Result<SvpiData> {
     if name.trim().is_empty() {
-        return Err(anyhow!("SVPI name is required"));
+        return Err(wmgr_error!(Svpi, "SVPI name is required"));
     }
     if password.trim().is_empty() {
-        return Err(anyhow!("SVPI password is required"));
+        return Err(wmgr_error!(Svpi, "SVPI password is required"));
     }
 
     let cmd_label = cmd_path
@@ -112,14 +115,15 @@ pub fn get_data_from_svpi(
 
     let output = cmd
         .output()
-        .with_context(|| format!("Failed to execute SVPI command: {cmd_label}"))?;
+        .map_err(|err| wmgr_error!(Svpi, "Failed to execute SVPI command {cmd_label}: {err}"))?;
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
 
     let stdout_s = stdout.to_string();
     let stderr_s = stderr.to_string();
     if stdout_s.trim().is_empty() && stderr_s.trim().is_empty() {
-        return Err(anyhow!(
+        return Err(wmgr_error!(
+            Svpi,
             "SVPI returned no output (exit code {})",
             output.status
         ));
@@ -135,13 +139,15 @@ pub fn get_data_from_svpi(
     }
 
     if !output.status.success() {
-        return Err(anyhow!(
+        return Err(wmgr_error!(
+            Svpi,
             "SVPI exited with status {} (no parseable JSON output)",
             output.status
         ));
     }
 
-    Err(anyhow!(
+    Err(wmgr_error!(
+        Svpi,
         "SVPI returned unexpected output (expected JSON response)"
     ))
 }
@@ -1,4 +1,8 @@
 pub mod evm;
 pub mod keys;
+pub mod orca;
 pub mod raydium;
+pub mod router;
 pub mod solana;
+pub mod venue;
+pub mod wsol;
@@ -0,0 +1,570 @@
+use anyhow::Result;
+use raydium_amm_swap::libraries::tick_math::{MAX_TICK, MIN_TICK};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::config::orca::{TICK_SPACINGS, WHIRLPOOLS_CONFIG, WHIRLPOOL_PROGRAM_ID};
+use crate::core::clmm_math::{ClmmCurve, ClmmTick};
+use crate::error::wmgr_error;
+use crate::infra::raydium::{
+    anchor_discriminator, load_pool_mints, read_bytes, read_pubkey, read_u128, PoolMint,
+};
+use crate::infra::solana::MEMO_PROGRAM_ID;
+use crate::infra::venue::SwapKind;
+
+pub const WHIRLPOOL_PROGRAM: Pubkey = Pubkey::from_str_const(WHIRLPOOL_PROGRAM_ID);
+const WHIRLPOOLS_CONFIG_ID: Pubkey = Pubkey::from_str_const(WHIRLPOOLS_CONFIG);
+
+const WHIRLPOOL_SEED: &[u8] = b"whirlpool";
+const TICK_ARRAY_SEED: &[u8] = b"tick_array";
+const ORACLE_SEED: &[u8] = b"oracle";
+
+/// A swap takes exactly three tick arrays, in the direction the price moves.
+const SWAP_TICK_ARRAYS: usize = 3;
+const TICKS_PER_ARRAY: usize = 88;
+
+/// Whirlpool's sqrt price bounds, passed as the price limit so only min-out / max-in bind.
+const MIN_SQRT_PRICE_X64: u128 = 4_295_048_016;
+const MAX_SQRT_PRICE_X64: u128 = 79_226_673_515_401_279_992_447_579_055;
+
+/// `Whirlpool` layout: an 8-byte Anchor discriminator and the config, then the fields below.
+const POOL_LEN: usize = 653;
+const POOL_TICK_SPACING: usize = 41;
+const POOL_FEE_RATE: usize = 45;
+const POOL_LIQUIDITY: usize = 49;
+const POOL_SQRT_PRICE: usize = 65;
+const POOL_TICK_CURRENT: usize = 81;
+const POOL_MINT_A: usize = 101;
+const POOL_VAULT_A: usize = 133;
+const POOL_MINT_B: usize = 181;
+const POOL_VAULT_B: usize = 213;
+
+/// `TickArray` layout: discriminator, start index, 88 ticks of 113 bytes, then the whirlpool.
+const TICK_ARRAY_START: usize = 8;
+const FIXED_TICKS: usize = 12;
+const FIXED_TICK_LEN: usize = 113;
+const FIXED_TICK_LIQUIDITY_NET: usize = 1;
+const FIXED_WHIRLPOOL: usize = FIXED_TICKS + TICKS_PER_ARRAY * FIXED_TICK_LEN;
+
+/// `DynamicTickArray` layout: discriminator, start index, whirlpool and a 16-byte bitmap, then
+/// each tick as a one-byte tag followed by 112 bytes of data (liquidity net first) when
+/// initialized.
+const DYNAMIC_WHIRLPOOL: usize = 12;
+const DYNAMIC_TICKS: usize = 60;
+const DYNAMIC_TICK_DATA_LEN: usize = 112;
+
+/// An Orca Whirlpool with its accounts, mints, current price and the tick arrays a swap in
+/// either direction would walk.
+pub struct Whirlpool {
+    pub id: Pubkey,
+    pub mint_a: PoolMint,
+    pub mint_b: PoolMint,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+    pub oracle: Pubkey,
+    pub tick_spacing: u16,
+    /// Trade fee in millionths (hundredths of a basis point).
+    pub fee_rate: u16,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    /// Tick arrays below the current price, nearest first (token A in).
+    pub arrays_down: Vec<TickArray>,
+    /// Tick arrays above the current price, nearest first (token B in).
+    pub arrays_up: Vec<TickArray>,
+}
+
+/// A tick array a swap passes; `ticks` is empty when the account has not been created.
+pub struct TickArray {
+    pub address: Pubkey,
+    pub start: i32,
+    pub ticks: Vec<ClmmTick>,
+}
+
+/// Pool fields decoded from the `Whirlpool` account.
+#[derive(Debug, PartialEq, Eq)]
+struct WhirlpoolState {
+    tick_spacing: u16,
+    fee_rate: u16,
+    liquidity: u128,
+    sqrt_price_x64: u128,
+    tick_current: i32,
+    mint_a: Pubkey,
+    vault_a: Pubkey,
+    mint_b: Pubkey,
+    vault_b: Pubkey,
+}
+
+pub(crate) async fn load_whirlpool(
+    rpc: &RpcClient,
+    pool_id: &Pubkey,
+    account: Account,
+) -> Result<Whirlpool> {
+    let state = decode_whirlpool(pool_id, &account.data)?;
+    if state.tick_spacing == 0 {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Whirlpool {pool_id} has no tick spacing"
+        ));
+    }
+    let starts_down = swap_array_starts(state.tick_current, state.tick_spacing, true);
+    let starts_up = swap_array_starts(state.tick_current, state.tick_spacing, false);
+    let address = |start: &i32| tick_array_address(pool_id, *start);
+
+    let addresses: Vec<Pubkey> = starts_down.iter().chain(&starts_up).map(address).collect();
+    let accounts = rpc
+        .get_multiple_accounts(&addresses)
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch tick arrays for {pool_id}: {err}"))?;
+    let mut arrays = addresses
+        .iter()
+        .zip(starts_down.iter().chain(&starts_up))
+        .zip(accounts)
+        .map(|((address, start), account)| {
+            // Arrays nobody has created hold no initialized ticks; the program accepts them.
+            let ticks = match account {
+                Some(account) => {
+                    decode_tick_array(address, pool_id, state.tick_spacing, &account.data)?
+                }
+                None => Vec::new(),
+            };
+            Ok(TickArray {
+                address: *address,
+                start: *start,
+                ticks,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let arrays_up = arrays.split_off(starts_down.len());
+    let [mint_a, mint_b] = load_pool_mints(rpc, [state.mint_a, state.mint_b]).await?;
+
+    Ok(Whirlpool {
+        id: *pool_id,
+        mint_a,
+        mint_b,
+        vault_a: state.vault_a,
+        vault_b: state.vault_b,
+        oracle: Pubkey::find_program_address(&[ORACLE_SEED, pool_id.as_ref()], &WHIRLPOOL_PROGRAM)
+            .0,
+        tick_spacing: state.tick_spacing,
+        fee_rate: state.fee_rate,
+        sqrt_price_x64: state.sqrt_price_x64,
+        tick_current: state.tick_current,
+        liquidity: state.liquidity,
+        arrays_down: arrays,
+        arrays_up,
+    })
+}
+
+/// The Whirlpool for a mint pair in Orca's public config with the most liquidity at the current
+/// price, across the standard tick spacings.
+pub async fn find_whirlpool(rpc: &RpcClient, mint_a: &Pubkey, mint_b: &Pubkey) -> Result<Pubkey> {
+    // Whirlpools order their mints by address.
+    let (mint_a, mint_b) = if mint_a < mint_b {
+        (mint_a, mint_b)
+    } else {
+        (mint_b, mint_a)
+    };
+    let addresses: Vec<Pubkey> = TICK_SPACINGS
+        .iter()
+        .map(|spacing| whirlpool_address(mint_a, mint_b, *spacing))
+        .collect();
+    let accounts = rpc
+        .get_multiple_accounts(&addresses)
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch Orca Whirlpools: {err}"))?;
+    addresses
+        .iter()
+        .zip(accounts)
+        .filter_map(|(address, account)| {
+            let account = account.filter(|account| account.owner == WHIRLPOOL_PROGRAM)?;
+            let state = decode_whirlpool(address, &account.data).ok()?;
+            Some((*address, state.liquidity))
+        })
+        .filter(|(_, liquidity)| *liquidity > 0)
+        .max_by_key(|(_, liquidity)| *liquidity)
+        .map(|(address, _)| address)
+        .ok_or_else(|| {
+            wmgr_error!(
+                InvalidArgument,
+                "No Orca Whirlpool with liquidity found for {mint_a}/{mint_b}; pass --pool <ID>"
+            )
+        })
+}
+
+fn whirlpool_address(mint_a: &Pubkey, mint_b: &Pubkey, tick_spacing: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            WHIRLPOOL_SEED,
+            WHIRLPOOLS_CONFIG_ID.as_ref(),
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            &tick_spacing.to_le_bytes(),
+        ],
+        &WHIRLPOOL_PROGRAM,
+    )
+    .0
+}
+
+fn tick_array_address(pool_id: &Pubkey, start: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED,
+            pool_id.as_ref(),
+            start.to_string().as_bytes(),
+        ],
+        &WHIRLPOOL_PROGRAM,
+    )
+    .0
+}
+
+fn array_span(tick_spacing: u16) -> i32 {
+    tick_spacing as i32 * TICKS_PER_ARRAY as i32
+}
+
+/// Start indexes of the tick arrays a swap passes, nearest first. Moving up starts one tick
+/// spacing ahead, as Orca's SDK does, since the price may sit on the next array's first tick.
+fn swap_array_starts(tick_current: i32, tick_spacing: u16, a_to_b: bool) -> Vec<i32> {
+    let span = array_span(tick_spacing);
+    let shift = if a_to_b { 0 } else { tick_spacing as i32 };
+    let first = (tick_current + shift).div_euclid(span) * span;
+    let lowest = MIN_TICK.div_euclid(span) * span;
+    (0..SWAP_TICK_ARRAYS as i32)
+        .map(|step| if a_to_b { -step } else { step })
+        .map(|step| first + step * span)
+        .take_while(|start| (lowest..=MAX_TICK).contains(start))
+        .collect()
+}
+
+impl Whirlpool {
+    fn arrays(&self, a_to_b: bool) -> &[TickArray] {
+        if a_to_b {
+            &self.arrays_down
+        } else {
+            &self.arrays_up
+        }
+    }
+
+    pub fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        [self.mint_a, self.mint_b]
+            .iter()
+            .find(|pool_mint| pool_mint.mint == *mint)
+            .map_or(spl_token::id(), |pool_mint| pool_mint.program_id)
+    }
+
+    /// The pool seen from `input_mint`, which must be one of its mints.
+    pub fn curve(&self, input_mint: &Pubkey) -> ClmmCurve {
+        let a_to_b = *input_mint == self.mint_a.mint;
+        let (mint_in, mint_out) = if a_to_b {
+            (&self.mint_a, &self.mint_b)
+        } else {
+            (&self.mint_b, &self.mint_a)
+        };
+        let arrays = self.arrays(a_to_b);
+        let mut ticks: Vec<ClmmTick> = arrays
+            .iter()
+            .flat_map(|array| array.ticks.iter().copied())
+            .collect();
+        // A swap cannot leave the arrays it is given. A tick without liquidity at their far edge
+        // lets the curve price up to it and fail beyond, as the program would.
+        if let Some(last) = arrays.last() {
+            let edge = if a_to_b {
+                last.start
+            } else {
+                last.start + array_span(self.tick_spacing) - self.tick_spacing as i32
+            };
+            if !ticks.iter().any(|tick| tick.index == edge) {
+                ticks.push(ClmmTick {
+                    index: edge,
+                    liquidity_net: 0,
+                });
+            }
+        }
+        ticks.sort_by_key(|tick| tick.index);
+        if a_to_b {
+            ticks.reverse();
+        }
+        ClmmCurve {
+            sqrt_price_x64: self.sqrt_price_x64,
+            tick_current: self.tick_current,
+            liquidity: self.liquidity,
+            fee_rate: u32::from(self.fee_rate),
+            zero_for_one: a_to_b,
+            decimals_in: mint_in.decimals,
+            decimals_out: mint_out.decimals,
+            ticks,
+            transfer_fee_in: mint_in.transfer_fee,
+            transfer_fee_out: mint_out.transfer_fee,
+        }
+    }
+
+    /// A `swap_v2` instruction, which handles Token-2022 mints as well as SPL Token ones.
+    pub fn swap_instruction(
+        &self,
+        owner: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        input_mint: &Pubkey,
+        swap: &SwapKind,
+    ) -> Instruction {
+        let a_to_b = *input_mint == self.mint_a.mint;
+        let (owner_a, owner_b) = if a_to_b {
+            (source, destination)
+        } else {
+            (destination, source)
+        };
+        let (amount, threshold, amount_is_input) = match *swap {
+            SwapKind::BaseIn {
+                amount_in,
+                min_amount_out,
+            } => (amount_in, min_amount_out, true),
+            SwapKind::BaseOut {
+                max_amount_in,
+                amount_out,
+            } => (amount_out, max_amount_in, false),
+        };
+        let sqrt_price_limit = if a_to_b {
+            MIN_SQRT_PRICE_X64
+        } else {
+            MAX_SQRT_PRICE_X64
+        };
+        let mut data = anchor_discriminator("global:swap_v2").to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&threshold.to_le_bytes());
+        data.extend_from_slice(&sqrt_price_limit.to_le_bytes());
+        data.push(amount_is_input as u8);
+        data.push(a_to_b as u8);
+        // No remaining accounts (transfer hooks are not supported).
+        data.push(0);
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.mint_a.program_id, false),
+            AccountMeta::new_readonly(self.mint_b.program_id, false),
+            AccountMeta::new_readonly(MEMO_PROGRAM_ID, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(self.id, false),
+            AccountMeta::new_readonly(self.mint_a.mint, false),
+            AccountMeta::new_readonly(self.mint_b.mint, false),
+            AccountMeta::new(*owner_a, false),
+            AccountMeta::new(self.vault_a, false),
+            AccountMeta::new(*owner_b, false),
+            AccountMeta::new(self.vault_b, false),
+        ];
+        // Near the ends of the tick range fewer than three arrays exist; the last one repeats.
+        let arrays = self.arrays(a_to_b);
+        accounts.extend(
+            (0..SWAP_TICK_ARRAYS)
+                .filter_map(|index| arrays.get(index).or(arrays.last()))
+                .map(|array| AccountMeta::new(array.address, false)),
+        );
+        accounts.push(AccountMeta::new(self.oracle, false));
+
+        Instruction {
+            program_id: WHIRLPOOL_PROGRAM,
+            accounts,
+            data,
+        }
+    }
+}
+
+fn decode_whirlpool(pool_id: &Pubkey, data: &[u8]) -> Result<WhirlpoolState> {
+    if data.len() < POOL_LEN || data[..8] != anchor_discriminator("account:Whirlpool") {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Account {pool_id} is not an Orca Whirlpool"
+        ));
+    }
+    Ok(WhirlpoolState {
+        tick_spacing: u16::from_le_bytes(read_bytes(data, POOL_TICK_SPACING)),
+        fee_rate: u16::from_le_bytes(read_bytes(data, POOL_FEE_RATE)),
+        liquidity: read_u128(data, POOL_LIQUIDITY),
+        sqrt_price_x64: read_u128(data, POOL_SQRT_PRICE),
+        tick_current: i32::from_le_bytes(read_bytes(data, POOL_TICK_CURRENT)),
+        mint_a: read_pubkey(data, POOL_MINT_A),
+        vault_a: read_pubkey(data, POOL_VAULT_A),
+        mint_b: read_pubkey(data, POOL_MINT_B),
+        vault_b: read_pubkey(data, POOL_VAULT_B),
+    })
+}
+
+/// The initialized ticks of a fixed or dynamic tick array, in index order. Slot `n` holds tick
+/// `start + n * tick_spacing`.
+fn decode_tick_array(
+    address: &Pubkey,
+    pool_id: &Pubkey,
+    tick_spacing: u16,
+    data: &[u8],
+) -> Result<Vec<ClmmTick>> {
+    let invalid = || wmgr_error!(Rpc, "Account {address} is not a tick array of {pool_id}");
+    let discriminator = data.get(..8).ok_or_else(invalid)?;
+    let fixed = discriminator == anchor_discriminator("account:TickArray");
+    if !fixed && discriminator != anchor_discriminator("account:DynamicTickArray") {
+        return Err(invalid());
+    }
+    let whirlpool_offset = if fixed {
+        FIXED_WHIRLPOOL
+    } else {
+        DYNAMIC_WHIRLPOOL
+    };
+    if data.len() < whirlpool_offset + 32 || read_pubkey(data, whirlpool_offset) != *pool_id {
+        return Err(invalid());
+    }
+    let start = i32::from_le_bytes(read_bytes(data, TICK_ARRAY_START));
+
+    let mut ticks = Vec::new();
+    if fixed {
+        for slot in 0..TICKS_PER_ARRAY {
+            let offset = FIXED_TICKS + slot * FIXED_TICK_LEN;
+            if data[offset] != 0 {
+                ticks.push((
+                    slot,
+                    i128::from_le_bytes(read_bytes(data, offset + FIXED_TICK_LIQUIDITY_NET)),
+                ));
+            }
+        }
+    } else {
+        let mut offset = DYNAMIC_TICKS;
+        for slot in 0..TICKS_PER_ARRAY {
+            match data.get(offset) {
+                Some(0) => offset += 1,
+                Some(1) if data.len() >= offset + 1 + DYNAMIC_TICK_DATA_LEN => {
+                    ticks.push((slot, i128::from_le_bytes(read_bytes(data, offset + 1))));
+                    offset += 1 + DYNAMIC_TICK_DATA_LEN;
+                }
+                _ => return Err(invalid()),
+            }
+        }
+    }
+    Ok(ticks
+        .into_iter()
+        .map(|(slot, liquidity_net)| ClmmTick {
+            index: start + slot as i32 * tick_spacing as i32,
+            liquidity_net,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::amm_math::AmmCurve;
+    use crate::core::transfer_fee::TransferFeeRate;
+
+    fn tick_array(dynamic: bool, pool_id: &Pubkey, start: i32, ticks: &[(usize, i128)]) -> Vec<u8> {
+        let discriminator = if dynamic {
+            "account:DynamicTickArray"
+        } else {
+            "account:TickArray"
+        };
+        let mut data = anchor_discriminator(discriminator).to_vec();
+        data.extend_from_slice(&start.to_le_bytes());
+        if dynamic {
+            data.extend_from_slice(pool_id.as_ref());
+            data.extend_from_slice(&[0u8; 16]);
+            for slot in 0..TICKS_PER_ARRAY {
+                match ticks.iter().find(|(index, _)| *index == slot) {
+                    Some((_, net)) => {
+                        data.push(1);
+                        data.extend_from_slice(&net.to_le_bytes());
+                        data.extend_from_slice(&[0u8; DYNAMIC_TICK_DATA_LEN - 16]);
+                    }
+                    None => data.push(0),
+                }
+            }
+        } else {
+            for slot in 0..TICKS_PER_ARRAY {
+                let mut tick = [0u8; FIXED_TICK_LEN];
+                if let Some((_, net)) = ticks.iter().find(|(index, _)| *index == slot) {
+                    tick[0] = 1;
+                    tick[FIXED_TICK_LIQUIDITY_NET..FIXED_TICK_LIQUIDITY_NET + 16]
+                        .copy_from_slice(&net.to_le_bytes());
+                }
+                data.extend_from_slice(&tick);
+            }
+            data.extend_from_slice(pool_id.as_ref());
+        }
+        data
+    }
+
+    #[test]
+    fn decodes_fixed_and_dynamic_tick_arrays() {
+        let pool_id = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let expected = vec![
+            ClmmTick {
+                index: -5632,
+                liquidity_net: 7,
+            },
+            ClmmTick {
+                index: -5632 + 10 * 64,
+                liquidity_net: -7,
+            },
+        ];
+        for dynamic in [false, true] {
+            let data = tick_array(dynamic, &pool_id, -5632, &[(0, 7), (10, -7)]);
+            assert_eq!(
+                decode_tick_array(&address, &pool_id, 64, &data).unwrap(),
+                expected
+            );
+            // Another pool's array is rejected.
+            let other = Pubkey::new_unique();
+            assert!(decode_tick_array(&address, &other, 64, &data).is_err());
+        }
+    }
+
+    #[test]
+    fn swap_arrays_follow_the_price() {
+        // 88 ticks of spacing 64 per array.
+        assert_eq!(swap_array_starts(-5, 64, true), vec![-5632, -11264, -16896]);
+        // Ticks sit on multiples of the spacing: from -5 the next one up is 0, in the next array.
+        assert_eq!(swap_array_starts(-5, 64, false), vec![0, 5632, 11264]);
+        assert_eq!(swap_array_starts(-65, 64, false), vec![-5632, 0, 5632]);
+        // Near the top of the range fewer than three arrays exist.
+        assert_eq!(swap_array_starts(443_000, 64, false).len(), 1);
+    }
+
+    #[test]
+    fn curve_stops_at_the_last_array() {
+        let mint = |mint| PoolMint {
+            mint,
+            decimals: 6,
+            program_id: spl_token::id(),
+            transfer_fee: TransferFeeRate::NONE,
+        };
+        let array = |start| TickArray {
+            address: Pubkey::new_unique(),
+            start,
+            ticks: Vec::new(),
+        };
+        let pool = Whirlpool {
+            id: Pubkey::new_unique(),
+            mint_a: mint(Pubkey::new_unique()),
+            mint_b: mint(Pubkey::new_unique()),
+            vault_a: Pubkey::new_unique(),
+            vault_b: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+            tick_spacing: 1,
+            fee_rate: 3_000,
+            sqrt_price_x64: 1 << 64,
+            tick_current: 0,
+            liquidity: 1_000_000_000_000,
+            arrays_down: vec![array(0), array(-88), array(-176)],
+            arrays_up: vec![array(0), array(88), array(176)],
+        };
+        let curve = pool.curve(&pool.mint_a.mint);
+        assert_eq!(curve.ticks.last().map(|tick| tick.index), Some(-176));
+        let quote = curve.quote_exact_in(1_000_000).unwrap();
+        assert_eq!(quote.fee, 3_000);
+        // Past tick -176 the swap would need a fourth array.
+        assert!(curve.quote_exact_in(100_000_000_000).is_err());
+        assert_eq!(
+            pool.curve(&pool.mint_b.mint)
+                .ticks
+                .last()
+                .map(|tick| tick.index),
+            Some(263)
+        );
+    }
+}
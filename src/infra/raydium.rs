@@ -1,86 +1,574 @@
-use std::str::FromStr;
-
-use anyhow::{anyhow, Result};
-use raydium_amm_swap::amm::{AmmInstruction, SwapInstructionBaseIn, SwapInstructionBaseOut};
-use raydium_amm_swap::interface::AmmPool;
+use anyhow::Result;
+use borsh::BorshDeserialize;
+use raydium_amm_swap::amm::client::LiquidityStateLayoutV4;
+use raydium_amm_swap::amm::{
+    AmmInstruction, DepositInstruction, SwapInstructionBaseIn, SwapInstructionBaseOut,
+    WithdrawInstruction,
+};
+use raydium_amm_swap::consts::{AMM_V4, CLMM, CPMM};
+use sha2::{Digest, Sha256};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::account::Account;
 use solana_sdk::instruction::AccountMeta;
 use solana_sdk::instruction::Instruction;
+use solana_sdk::program_pack::Pack;
 use solana_sdk::pubkey::Pubkey;
-use solana_system_interface::instruction as system_instruction;
-use spl_associated_token_account_interface::address::get_associated_token_address;
+use spl_associated_token_account_interface::address::get_associated_token_address_with_program_id;
 use spl_associated_token_account_interface::instruction::create_associated_token_account_idempotent;
+use spl_token::state::Account as TokenAccount;
+
+use crate::core::amm_math::{AmmCurve, ConstantProduct, FeeRate};
+use crate::core::lp_math::{DepositPlan, DepositSide, LpReserves};
+use crate::core::transfer_fee::TransferFeeRate;
+use crate::error::wmgr_error;
+use crate::infra::solana::{get_mint_info, MintInfo};
+use crate::infra::venue::{OrientedPool, SwapKind};
+use crate::infra::wsol::{create_temp_wsol, temp_wsol_address};
+
+pub mod clmm;
+pub mod cpmm;
+
+use clmm::{load_clmm_pool, ClmmPool};
+use cpmm::{load_cpmm_pool, CpmmPool};
+
+const AMM_V4_PROGRAM_ID: Pubkey = Pubkey::from_str_const(AMM_V4);
+const CPMM_PROGRAM_ID: Pubkey = Pubkey::from_str_const(CPMM);
+const CLMM_PROGRAM_ID: Pubkey = Pubkey::from_str_const(CLMM);
 
-pub use crate::core::amm_math::{compute_swap_quote, compute_swap_quote_out};
+/// Size of the AMM v4 `AmmInfo` account.
+const AMM_INFO_LEN: usize = 752;
+/// `AmmInfo` offsets of the vaults and mints, as `LiquidityStateLayoutV4` lays them out.
+const AMM_BASE_VAULT: usize = 336;
+const AMM_QUOTE_VAULT: usize = 368;
+const AMM_BASE_MINT: usize = 400;
+const AMM_QUOTE_MINT: usize = 432;
+const AMM_AUTHORITY_SEED: &[u8] = b"amm authority";
+
+/// OpenBook/Serum `MarketState` layout: a 5-byte `serum` head, then the fields below.
+const MARKET_HEAD_LEN: usize = 5;
+const MARKET_LEN: usize = 388;
+const MARKET_OWN_ADDRESS: usize = MARKET_HEAD_LEN + 8;
+const MARKET_VAULT_SIGNER_NONCE: usize = MARKET_HEAD_LEN + 40;
+const MARKET_BASE_VAULT: usize = MARKET_HEAD_LEN + 112;
+const MARKET_QUOTE_VAULT: usize = MARKET_HEAD_LEN + 160;
+const MARKET_EVENT_QUEUE: usize = MARKET_HEAD_LEN + 248;
+const MARKET_BIDS: usize = MARKET_HEAD_LEN + 280;
+const MARKET_ASKS: usize = MARKET_HEAD_LEN + 312;
+
+/// Accounts a swap on an AMM v4 pool and its OpenBook market touches.
+pub struct AmmPoolKeys {
+    pub program_id: Pubkey,
+    pub amm_id: Pubkey,
+    pub authority: Pubkey,
+    pub open_orders: Pubkey,
+    pub target_orders: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub lp_mint: Pubkey,
+    pub market: MarketKeys,
+}
+
+/// OpenBook market accounts decoded from the market state.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MarketKeys {
+    pub program_id: Pubkey,
+    pub id: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub event_queue: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub vault_signer: Pubkey,
+}
 
-pub enum SwapKind {
-    BaseIn { amount_in: u64, min_amount_out: u64 },
-    BaseOut { max_amount_in: u64, amount_out: u64 },
+/// An AMM v4 pool with its keys, mints and current reserves.
+pub struct LoadedAmmPool {
+    pub id: Pubkey,
+    pub keys: AmmPoolKeys,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    /// LP supply as the pool accounts for it; deposits and withdrawals are priced against this.
+    pub lp_supply: u64,
 }
 
-impl SwapKind {
-    pub fn input_amount(&self) -> u64 {
+impl LoadedAmmPool {
+    pub fn lp_reserves(&self) -> LpReserves {
+        LpReserves {
+            base: self.base_reserve,
+            quote: self.quote_reserve,
+            lp_supply: self.lp_supply,
+        }
+    }
+}
+
+/// A mint traded by a CPMM, CLMM or Whirlpool pool, which may belong to either token program.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolMint {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub program_id: Pubkey,
+    /// Token-2022 transfer fee for the current epoch.
+    pub transfer_fee: TransferFeeRate,
+}
+
+/// A pool of any supported Raydium program, picked by the pool account's owner.
+pub enum RaydiumPool {
+    AmmV4(LoadedAmmPool),
+    Cpmm(CpmmPool),
+    Clmm(ClmmPool),
+}
+
+impl RaydiumPool {
+    pub fn id(&self) -> Pubkey {
+        match self {
+            RaydiumPool::AmmV4(pool) => pool.id,
+            RaydiumPool::Cpmm(pool) => pool.id,
+            RaydiumPool::Clmm(pool) => pool.id,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RaydiumPool::AmmV4(_) => "AMM v4",
+            RaydiumPool::Cpmm(_) => "CPMM",
+            RaydiumPool::Clmm(_) => "CLMM",
+        }
+    }
+
+    pub fn mints(&self) -> [Pubkey; 2] {
         match self {
-            SwapKind::BaseIn { amount_in, .. } => *amount_in,
-            SwapKind::BaseOut { max_amount_in, .. } => *max_amount_in,
+            RaydiumPool::AmmV4(pool) => [pool.base_mint, pool.quote_mint],
+            RaydiumPool::Cpmm(pool) => [pool.mint_0.mint, pool.mint_1.mint],
+            RaydiumPool::Clmm(pool) => [pool.mint_0.mint, pool.mint_1.mint],
         }
     }
+
+    pub fn contains(&self, mint: &Pubkey) -> bool {
+        self.mints().contains(mint)
+    }
+
+    /// Token program of `mint`'s accounts. AMM v4 only trades SPL Token mints.
+    pub fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        let pool_mints = match self {
+            RaydiumPool::AmmV4(_) => return spl_token::id(),
+            RaydiumPool::Cpmm(pool) => [pool.mint_0, pool.mint_1],
+            RaydiumPool::Clmm(pool) => [pool.mint_0, pool.mint_1],
+        };
+        pool_mints
+            .iter()
+            .find(|pool_mint| pool_mint.mint == *mint)
+            .map_or(spl_token::id(), |pool_mint| pool_mint.program_id)
+    }
+
+    pub fn orient(&self, input_mint: &Pubkey, output_mint: &Pubkey) -> Result<OrientedPool> {
+        if input_mint == output_mint || !self.contains(input_mint) || !self.contains(output_mint) {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Pool {} does not trade {input_mint} for {output_mint}",
+                self.id()
+            ));
+        }
+        let curve: Box<dyn AmmCurve> = match self {
+            RaydiumPool::AmmV4(pool) => {
+                let base_in = *input_mint == pool.base_mint;
+                let (reserve_in, reserve_out, decimals_in, decimals_out) = if base_in {
+                    (
+                        pool.base_reserve,
+                        pool.quote_reserve,
+                        pool.base_decimals,
+                        pool.quote_decimals,
+                    )
+                } else {
+                    (
+                        pool.quote_reserve,
+                        pool.base_reserve,
+                        pool.quote_decimals,
+                        pool.base_decimals,
+                    )
+                };
+                Box::new(ConstantProduct {
+                    reserve_in,
+                    reserve_out,
+                    decimals_in,
+                    decimals_out,
+                    fee: FeeRate::RAYDIUM_V4,
+                })
+            }
+            RaydiumPool::Cpmm(pool) => Box::new(pool.curve(input_mint)),
+            RaydiumPool::Clmm(pool) => Box::new(pool.curve(input_mint)),
+        };
+        let (decimals_in, decimals_out) = self.decimals(input_mint, output_mint);
+        Ok(OrientedPool::new(curve, decimals_in, decimals_out))
+    }
+
+    fn decimals(&self, input_mint: &Pubkey, output_mint: &Pubkey) -> (u8, u8) {
+        let decimals = |mint: &Pubkey| match self {
+            RaydiumPool::AmmV4(pool) if *mint == pool.base_mint => pool.base_decimals,
+            RaydiumPool::AmmV4(pool) => pool.quote_decimals,
+            RaydiumPool::Cpmm(pool) if *mint == pool.mint_0.mint => pool.mint_0.decimals,
+            RaydiumPool::Cpmm(pool) => pool.mint_1.decimals,
+            RaydiumPool::Clmm(pool) if *mint == pool.mint_0.mint => pool.mint_0.decimals,
+            RaydiumPool::Clmm(pool) => pool.mint_1.decimals,
+        };
+        (decimals(input_mint), decimals(output_mint))
+    }
 }
 
-pub fn build_swap_instructions(
-    pool: &AmmPool,
-    owner: &Pubkey,
-    input_mint: &Pubkey,
-    output_mint: &Pubkey,
-    swap: SwapKind,
-) -> Result<Vec<Instruction>> {
-    let source_ata = get_associated_token_address(owner, input_mint);
-    let destination_ata = get_associated_token_address(owner, output_mint);
+/// Loads an AMM v4, CPMM or CLMM pool from chain state, dispatching on the account's owner.
+pub async fn load_pool(rpc: &RpcClient, pool_id: &Pubkey) -> Result<RaydiumPool> {
+    let account = rpc
+        .get_account(pool_id)
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch Raydium pool {pool_id}: {err}"))?;
+    load_pool_account(rpc, pool_id, account).await
+}
 
-    let mut instructions = Vec::new();
-    instructions.push(create_associated_token_account_idempotent(
-        owner,
-        owner,
-        input_mint,
-        &spl_token::id(),
-    ));
-    if destination_ata != source_ata {
-        instructions.push(create_associated_token_account_idempotent(
-            owner,
-            owner,
-            output_mint,
-            &spl_token::id(),
+/// [`load_pool`] for a pool account already fetched.
+pub(crate) async fn load_pool_account(
+    rpc: &RpcClient,
+    pool_id: &Pubkey,
+    account: Account,
+) -> Result<RaydiumPool> {
+    Ok(match account.owner {
+        CPMM_PROGRAM_ID => RaydiumPool::Cpmm(load_cpmm_pool(rpc, pool_id, account).await?),
+        CLMM_PROGRAM_ID => RaydiumPool::Clmm(load_clmm_pool(rpc, pool_id, account).await?),
+        _ => RaydiumPool::AmmV4(load_amm_pool(rpc, pool_id, account).await?),
+    })
+}
+
+/// The AMM v4 program owning `account`. Any other owner is rejected, so a pool id given on the
+/// command line cannot pick the program the user signs for.
+fn amm_program(pool_id: &Pubkey, account: &Account) -> Result<Pubkey> {
+    if account.owner != AMM_V4_PROGRAM_ID {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Account {pool_id} is not a Raydium AMM v4, CPMM, CLMM or Orca Whirlpool pool (owner: {})",
+            account.owner
         ));
     }
+    Ok(account.owner)
+}
 
-    let input_amount = swap.input_amount();
-    if *input_mint == spl_token::native_mint::id() {
-        instructions.push(system_instruction::transfer(
-            owner,
-            &source_ata,
-            input_amount,
+/// Decodes an AMM v4 pool, its OpenBook market and vault balances from chain state only.
+async fn load_amm_pool(
+    rpc: &RpcClient,
+    pool_id: &Pubkey,
+    account: Account,
+) -> Result<LoadedAmmPool> {
+    // The owner becomes the program the swap and LP instructions are sent to.
+    let program_id = amm_program(pool_id, &account)?;
+    if account.data.len() != AMM_INFO_LEN {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Account {pool_id} is not a Raydium AMM v4, CPMM, CLMM or Orca Whirlpool pool (owner: {}, {} bytes)",
+            account.owner,
+            account.data.len()
         ));
-        instructions.push(spl_token::instruction::sync_native(
-            &spl_token::id(),
-            &source_ata,
-        )?);
-    }
-
-    let amm_program = parse_pubkey("amm program", &pool.program_id)?;
-    let amm_id = parse_pubkey("amm id", &pool.id)?;
-    let authority = parse_pubkey("amm authority", &pool.authority)?;
-    let open_orders = parse_pubkey("amm open orders", &pool.open_orders)?;
-    let coin_vault = parse_pubkey("amm coin vault", &pool.vault.a)?;
-    let pc_vault = parse_pubkey("amm pc vault", &pool.vault.b)?;
-    let market_program = parse_pubkey("market program", &pool.market_program_id)?;
-    let market_id = parse_pubkey("market id", &pool.market_id)?;
-    let market_bids = parse_pubkey("market bids", &pool.market_bids)?;
-    let market_asks = parse_pubkey("market asks", &pool.market_asks)?;
-    let market_event_queue = parse_pubkey("market event queue", &pool.market_event_queue)?;
-    let market_coin_vault = parse_pubkey("market base vault", &pool.market_base_vault)?;
-    let market_pc_vault = parse_pubkey("market quote vault", &pool.market_quote_vault)?;
-    let market_authority = parse_pubkey("market authority", &pool.market_authority)?;
-
-    let data = match swap {
+    }
+    let info = LiquidityStateLayoutV4::try_from_slice(&account.data)
+        .map_err(|err| wmgr_error!(Rpc, "Failed to decode Raydium pool {pool_id}: {err}"))?;
+    let authority =
+        Pubkey::create_program_address(&[AMM_AUTHORITY_SEED, &[info.nonce as u8]], &program_id)
+            .map_err(|err| wmgr_error!(Rpc, "Invalid AMM authority nonce for {pool_id}: {err}"))?;
+
+    let accounts = rpc
+        .get_multiple_accounts(&[info.market_id, info.base_vault, info.quote_vault])
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch pool accounts for {pool_id}: {err}"))?;
+    let [market, base_vault, quote_vault] = <[_; 3]>::try_from(accounts)
+        .map_err(|_| wmgr_error!(Rpc, "Unexpected account count for pool {pool_id}"))?;
+    let market =
+        market.ok_or_else(|| wmgr_error!(Rpc, "OpenBook market {} not found", info.market_id))?;
+    if market.owner != info.market_program_id {
+        return Err(wmgr_error!(
+            Rpc,
+            "Market {} is owned by {}, pool expects {}",
+            info.market_id,
+            market.owner,
+            info.market_program_id
+        ));
+    }
+    let market = decode_market(&info.market_program_id, &info.market_id, &market.data)?;
+    let base_balance = vault_balance(base_vault, &info.base_vault)?;
+    let quote_balance = vault_balance(quote_vault, &info.quote_vault)?;
+
+    let base_decimals = u8::try_from(info.base_decimal)
+        .map_err(|_| wmgr_error!(Rpc, "Base mint decimals out of range"))?;
+    let quote_decimals = u8::try_from(info.quote_decimal)
+        .map_err(|_| wmgr_error!(Rpc, "Quote mint decimals out of range"))?;
+
+    Ok(LoadedAmmPool {
+        id: *pool_id,
+        keys: AmmPoolKeys {
+            program_id,
+            amm_id: *pool_id,
+            authority,
+            open_orders: info.open_orders,
+            target_orders: info.target_orders,
+            base_vault: info.base_vault,
+            quote_vault: info.quote_vault,
+            lp_mint: info.lp_mint,
+            market,
+        },
+        base_mint: info.base_mint,
+        quote_mint: info.quote_mint,
+        base_decimals,
+        quote_decimals,
+        // PnL owed to the protocol sits in the vaults but is not tradable liquidity.
+        base_reserve: base_balance.saturating_sub(info.base_need_take_pnl),
+        quote_reserve: quote_balance.saturating_sub(info.quote_need_take_pnl),
+        lp_supply: info.lp_reserve,
+    })
+}
+
+/// Looks up the AMM v4 or CLMM pool for a mint pair with the most liquidity, from chain state
+/// only: every pool account holding both mints is listed with `getProgramAccounts`, and the one
+/// whose vault holds the most `mint_a` wins. CPMM pools are reached with `--pool`.
+pub async fn find_pool(rpc: &RpcClient, mint_a: &Pubkey, mint_b: &Pubkey) -> Result<Pubkey> {
+    // AMM v4 pools may hold the pair either way round; CLMM pools order their mints by address.
+    let (clmm_mints, clmm_vault) = if mint_a < mint_b {
+        ((clmm::POOL_MINT_0, clmm::POOL_MINT_1), clmm::POOL_VAULT_0)
+    } else {
+        ((clmm::POOL_MINT_1, clmm::POOL_MINT_0), clmm::POOL_VAULT_1)
+    };
+    let queries = [
+        PoolQuery {
+            program_id: AMM_V4_PROGRAM_ID,
+            len: AMM_INFO_LEN,
+            mints: (AMM_BASE_MINT, AMM_QUOTE_MINT),
+            vault: AMM_BASE_VAULT,
+        },
+        PoolQuery {
+            program_id: AMM_V4_PROGRAM_ID,
+            len: AMM_INFO_LEN,
+            mints: (AMM_QUOTE_MINT, AMM_BASE_MINT),
+            vault: AMM_QUOTE_VAULT,
+        },
+        PoolQuery {
+            program_id: CLMM_PROGRAM_ID,
+            len: clmm::POOL_LEN,
+            mints: clmm_mints,
+            vault: clmm_vault,
+        },
+    ];
+    let mut pools = Vec::new();
+    for query in &queries {
+        pools.extend(query.find(rpc, mint_a, mint_b).await?);
+    }
+    let vaults: Vec<Pubkey> = pools.iter().map(|(_, vault)| *vault).collect();
+    let mut balances = Vec::with_capacity(vaults.len());
+    // `getMultipleAccounts` takes at most 100 addresses.
+    for chunk in vaults.chunks(100) {
+        balances.extend(
+            rpc.get_multiple_accounts(chunk)
+                .await
+                .map_err(|err| wmgr_error!(Rpc, "Failed to fetch Raydium pool vaults: {err}"))?,
+        );
+    }
+    pools
+        .iter()
+        .zip(balances)
+        .filter_map(|((pool, vault), account)| Some((*pool, vault_balance(account, vault).ok()?)))
+        .filter(|(_, balance)| *balance > 0)
+        .max_by_key(|(_, balance)| *balance)
+        .map(|(pool, _)| pool)
+        .ok_or_else(|| {
+            wmgr_error!(
+                InvalidArgument,
+                "No Raydium pool with liquidity found for {mint_a}/{mint_b}; pass --pool <ID>"
+            )
+        })
+}
+
+/// Pool accounts of one program and layout that hold a mint pair at fixed offsets.
+struct PoolQuery {
+    program_id: Pubkey,
+    len: usize,
+    /// Offsets of the first and second mint searched for.
+    mints: (usize, usize),
+    /// Offset of the vault holding the first mint.
+    vault: usize,
+}
+
+impl PoolQuery {
+    /// `(pool, vault of mint_a)` for every matching account.
+    async fn find(
+        &self,
+        rpc: &RpcClient,
+        mint_a: &Pubkey,
+        mint_b: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Pubkey)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(self.len as u64),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(self.mints.0, mint_a.as_ref())),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(self.mints.1, mint_b.as_ref())),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = rpc
+            .get_program_ui_accounts_with_config(&self.program_id, config)
+            .await
+            .map_err(|err| wmgr_error!(Rpc, "Failed to list Raydium pools: {err}"))?;
+        accounts
+            .into_iter()
+            .map(|(pool, account)| {
+                let data = account
+                    .data
+                    .decode()
+                    .filter(|data| data.len() == self.len)
+                    .ok_or_else(|| wmgr_error!(Rpc, "Failed to decode Raydium pool {pool}"))?;
+                Ok((pool, read_pubkey(&data, self.vault)))
+            })
+            .collect()
+    }
+}
+
+/// Mint details of a CPMM, CLMM or Whirlpool pool, with transfer fees resolved for the current
+/// epoch.
+pub(crate) async fn load_pool_mints(rpc: &RpcClient, mints: [Pubkey; 2]) -> Result<[PoolMint; 2]> {
+    let mut infos = Vec::with_capacity(2);
+    for mint in &mints {
+        infos.push(get_mint_info(rpc, mint).await?);
+    }
+    let epoch = if infos.iter().any(|info| info.transfer_fee.is_some()) {
+        rpc.get_epoch_info()
+            .await
+            .map_err(|err| wmgr_error!(Rpc, "Failed to fetch epoch info: {err}"))?
+            .epoch
+    } else {
+        0
+    };
+    let pool_mint = |mint: Pubkey, info: &MintInfo| PoolMint {
+        mint,
+        decimals: info.decimals,
+        program_id: info.program_id,
+        transfer_fee: info
+            .transfer_fee
+            .map(|config| {
+                let fee = config.get_epoch_fee(epoch);
+                TransferFeeRate {
+                    basis_points: u16::from(fee.transfer_fee_basis_points),
+                    maximum_fee: u64::from(fee.maximum_fee),
+                }
+            })
+            .unwrap_or(TransferFeeRate::NONE),
+    };
+    Ok([
+        pool_mint(mints[0], &infos[0]),
+        pool_mint(mints[1], &infos[1]),
+    ])
+}
+
+/// First 8 bytes of `sha256(preimage)`: Anchor's account and instruction discriminators.
+pub(crate) fn anchor_discriminator(preimage: &str) -> [u8; 8] {
+    let hash = Sha256::digest(preimage.as_bytes());
+    hash[..8].try_into().expect("8-byte slice")
+}
+
+/// Decodes the accounts a swap needs from an OpenBook market account.
+pub fn decode_market(program_id: &Pubkey, market_id: &Pubkey, data: &[u8]) -> Result<MarketKeys> {
+    if data.len() < MARKET_LEN || &data[..MARKET_HEAD_LEN] != b"serum" {
+        return Err(wmgr_error!(
+            Rpc,
+            "Account {market_id} is not an OpenBook market"
+        ));
+    }
+    let own_address = read_pubkey(data, MARKET_OWN_ADDRESS);
+    if own_address != *market_id {
+        return Err(wmgr_error!(
+            Rpc,
+            "Market {market_id} records a different address ({own_address})"
+        ));
+    }
+    let nonce = read_u64(data, MARKET_VAULT_SIGNER_NONCE);
+    let vault_signer =
+        Pubkey::create_program_address(&[market_id.as_ref(), &nonce.to_le_bytes()], program_id)
+            .map_err(|err| wmgr_error!(Rpc, "Invalid vault signer nonce for {market_id}: {err}"))?;
+
+    Ok(MarketKeys {
+        program_id: *program_id,
+        id: *market_id,
+        bids: read_pubkey(data, MARKET_BIDS),
+        asks: read_pubkey(data, MARKET_ASKS),
+        event_queue: read_pubkey(data, MARKET_EVENT_QUEUE),
+        base_vault: read_pubkey(data, MARKET_BASE_VAULT),
+        quote_vault: read_pubkey(data, MARKET_QUOTE_VAULT),
+        vault_signer,
+    })
+}
+
+pub(crate) fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(read_bytes(data, offset))
+}
+
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(read_bytes(data, offset))
+}
+
+pub(crate) fn read_u128(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(read_bytes(data, offset))
+}
+
+pub(crate) fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    data[offset..offset + N]
+        .try_into()
+        .expect("fixed-size slice")
+}
+
+fn vault_balance(account: Option<Account>, vault: &Pubkey) -> Result<u64> {
+    let account = account.ok_or_else(|| wmgr_error!(Rpc, "Pool vault {vault} not found"))?;
+    let data = account
+        .data
+        .get(..TokenAccount::LEN)
+        .ok_or_else(|| wmgr_error!(Rpc, "Pool vault {vault} is not a token account"))?;
+    let token = TokenAccount::unpack(data)
+        .map_err(|err| wmgr_error!(Rpc, "Failed to decode pool vault {vault}: {err}"))?;
+    Ok(token.amount)
+}
+
+impl RaydiumPool {
+    pub fn swap_instruction(
+        &self,
+        owner: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        input_mint: &Pubkey,
+        swap: &SwapKind,
+    ) -> Result<Instruction> {
+        Ok(match self {
+            RaydiumPool::AmmV4(pool) => {
+                amm_v4_swap_instruction(&pool.keys, owner, source, destination, swap)?
+            }
+            RaydiumPool::Cpmm(pool) => {
+                pool.swap_instruction(owner, source, destination, input_mint, swap)
+            }
+            RaydiumPool::Clmm(pool) => {
+                pool.swap_instruction(owner, source, destination, input_mint, swap)
+            }
+        })
+    }
+}
+
+fn amm_v4_swap_instruction(
+    pool: &AmmPoolKeys,
+    owner: &Pubkey,
+    source: &Pubkey,
+    destination: &Pubkey,
+    swap: &SwapKind,
+) -> Result<Instruction> {
+    let data = match *swap {
         SwapKind::BaseIn {
             amount_in,
             min_amount_out,
@@ -97,50 +585,301 @@ pub fn build_swap_instructions(
         }),
     }
     .pack()
-    .map_err(|err| anyhow!("Failed to pack swap instruction: {err}"))?;
+    .map_err(|err| wmgr_error!(Internal, "Failed to pack swap instruction: {err}"))?;
 
     let accounts = vec![
         AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new(amm_id, false),
-        AccountMeta::new_readonly(authority, false),
-        AccountMeta::new(open_orders, false),
-        AccountMeta::new(coin_vault, false),
-        AccountMeta::new(pc_vault, false),
-        AccountMeta::new_readonly(market_program, false),
-        AccountMeta::new(market_id, false),
-        AccountMeta::new(market_bids, false),
-        AccountMeta::new(market_asks, false),
-        AccountMeta::new(market_event_queue, false),
-        AccountMeta::new(market_coin_vault, false),
-        AccountMeta::new(market_pc_vault, false),
-        AccountMeta::new_readonly(market_authority, false),
-        AccountMeta::new(source_ata, false),
-        AccountMeta::new(destination_ata, false),
+        AccountMeta::new(pool.amm_id, false),
+        AccountMeta::new_readonly(pool.authority, false),
+        AccountMeta::new(pool.open_orders, false),
+        AccountMeta::new(pool.base_vault, false),
+        AccountMeta::new(pool.quote_vault, false),
+        AccountMeta::new_readonly(pool.market.program_id, false),
+        AccountMeta::new(pool.market.id, false),
+        AccountMeta::new(pool.market.bids, false),
+        AccountMeta::new(pool.market.asks, false),
+        AccountMeta::new(pool.market.event_queue, false),
+        AccountMeta::new(pool.market.base_vault, false),
+        AccountMeta::new(pool.market.quote_vault, false),
+        AccountMeta::new_readonly(pool.market.vault_signer, false),
+        AccountMeta::new(*source, false),
+        AccountMeta::new(*destination, false),
         AccountMeta::new_readonly(*owner, true),
     ];
 
-    let swap_ix = Instruction {
-        program_id: amm_program,
+    Ok(Instruction {
+        program_id: pool.program_id,
         accounts,
         data,
+    })
+}
+
+/// Deposit or withdraw instructions plus the wallet accounts they move tokens through.
+pub struct LiquidityPlan {
+    pub instructions: Vec<Instruction>,
+    pub base_account: Pubkey,
+    pub quote_account: Pubkey,
+    pub lp_account: Pubkey,
+}
+
+/// The owner's LP token account for `pool`.
+pub fn lp_token_address(pool: &LoadedAmmPool, owner: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, &pool.keys.lp_mint, &spl_token::id())
+}
+
+/// Builds a deposit into an AMM v4 pool. A SOL side is paid from a temporary wSOL account
+/// funded with its maximum and closed in the same transaction, returning what was not used.
+pub fn build_deposit_instructions(
+    pool: &LoadedAmmPool,
+    owner: &Pubkey,
+    plan: &DepositPlan,
+    rent: u64,
+) -> Result<LiquidityPlan> {
+    let native_mint = spl_token::native_mint::id();
+    let temp_wsol = temp_wsol_address(owner)?;
+    let mut instructions = Vec::new();
+    let mut source = |mint: &Pubkey, max: u64| -> Result<Pubkey> {
+        if *mint != native_mint {
+            return Ok(get_associated_token_address_with_program_id(
+                owner,
+                mint,
+                &spl_token::id(),
+            ));
+        }
+        instructions.extend(create_temp_wsol(owner, &temp_wsol, rent + max)?);
+        Ok(temp_wsol)
     };
+    let base_account = source(&pool.base_mint, plan.max_base)?;
+    let quote_account = source(&pool.quote_mint, plan.max_quote)?;
+    let lp_account = lp_token_address(pool, owner);
+    instructions.push(create_associated_token_account_idempotent(
+        owner,
+        owner,
+        &pool.keys.lp_mint,
+        &spl_token::id(),
+    ));
+
+    let data = AmmInstruction::Deposit(DepositInstruction {
+        max_coin_amount: plan.max_base,
+        max_pc_amount: plan.max_quote,
+        base_side: match plan.side {
+            DepositSide::Base => 0,
+            DepositSide::Quote => 1,
+        },
+    })
+    .pack()
+    .map_err(|err| wmgr_error!(Internal, "Failed to pack deposit instruction: {err}"))?;
+    let keys = &pool.keys;
+    instructions.push(Instruction {
+        program_id: keys.program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(keys.amm_id, false),
+            AccountMeta::new_readonly(keys.authority, false),
+            AccountMeta::new_readonly(keys.open_orders, false),
+            AccountMeta::new(keys.target_orders, false),
+            AccountMeta::new(keys.lp_mint, false),
+            AccountMeta::new(keys.base_vault, false),
+            AccountMeta::new(keys.quote_vault, false),
+            AccountMeta::new_readonly(keys.market.id, false),
+            AccountMeta::new(base_account, false),
+            AccountMeta::new(quote_account, false),
+            AccountMeta::new(lp_account, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(keys.market.event_queue, false),
+        ],
+        data,
+    });
+    if base_account == temp_wsol || quote_account == temp_wsol {
+        instructions.push(close_temp_wsol(owner, &temp_wsol)?);
+    }
 
-    instructions.push(swap_ix);
+    Ok(LiquidityPlan {
+        instructions,
+        base_account,
+        quote_account,
+        lp_account,
+    })
+}
 
-    if *output_mint == spl_token::native_mint::id() {
-        // For swaps ending in wrapped SOL, close ATA in the same tx to unwrap to native SOL.
-        instructions.push(spl_token::instruction::close_account(
+/// Builds a withdrawal of `lp` tokens from an AMM v4 pool. `min_base`/`min_quote` are appended
+/// to the instruction, which the program enforces when present. SOL is paid out through a
+/// temporary wSOL account that is closed in the same transaction.
+pub fn build_withdraw_instructions(
+    pool: &LoadedAmmPool,
+    owner: &Pubkey,
+    lp: u64,
+    (min_base, min_quote): (u64, u64),
+    rent: u64,
+) -> Result<LiquidityPlan> {
+    let native_mint = spl_token::native_mint::id();
+    let temp_wsol = temp_wsol_address(owner)?;
+    let mut instructions = Vec::new();
+    let mut destination = |mint: &Pubkey| -> Result<Pubkey> {
+        if *mint == native_mint {
+            instructions.extend(create_temp_wsol(owner, &temp_wsol, rent)?);
+            return Ok(temp_wsol);
+        }
+        instructions.push(create_associated_token_account_idempotent(
+            owner,
+            owner,
+            mint,
             &spl_token::id(),
-            &destination_ata,
-            &owner,
+        ));
+        Ok(get_associated_token_address_with_program_id(
             owner,
-            &[],
-        )?);
+            mint,
+            &spl_token::id(),
+        ))
+    };
+    let base_account = destination(&pool.base_mint)?;
+    let quote_account = destination(&pool.quote_mint)?;
+    let lp_account = lp_token_address(pool, owner);
+
+    let mut data = AmmInstruction::Withdraw(WithdrawInstruction { amount: lp })
+        .pack()
+        .map_err(|err| wmgr_error!(Internal, "Failed to pack withdraw instruction: {err}"))?;
+    data.extend_from_slice(&min_base.to_le_bytes());
+    data.extend_from_slice(&min_quote.to_le_bytes());
+    let keys = &pool.keys;
+    instructions.push(Instruction {
+        program_id: keys.program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(keys.amm_id, false),
+            AccountMeta::new_readonly(keys.authority, false),
+            AccountMeta::new(keys.open_orders, false),
+            AccountMeta::new(keys.target_orders, false),
+            AccountMeta::new(keys.lp_mint, false),
+            AccountMeta::new(keys.base_vault, false),
+            AccountMeta::new(keys.quote_vault, false),
+            AccountMeta::new_readonly(keys.market.program_id, false),
+            AccountMeta::new(keys.market.id, false),
+            AccountMeta::new(keys.market.base_vault, false),
+            AccountMeta::new(keys.market.quote_vault, false),
+            AccountMeta::new_readonly(keys.market.vault_signer, false),
+            AccountMeta::new(lp_account, false),
+            AccountMeta::new(base_account, false),
+            AccountMeta::new(quote_account, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(keys.market.event_queue, false),
+            AccountMeta::new(keys.market.bids, false),
+            AccountMeta::new(keys.market.asks, false),
+        ],
+        data,
+    });
+    if base_account == temp_wsol || quote_account == temp_wsol {
+        instructions.push(close_temp_wsol(owner, &temp_wsol)?);
     }
 
-    Ok(instructions)
+    Ok(LiquidityPlan {
+        instructions,
+        base_account,
+        quote_account,
+        lp_account,
+    })
 }
 
-fn parse_pubkey(label: &str, value: &str) -> Result<Pubkey> {
-    Pubkey::from_str(value).map_err(|err| anyhow!("Invalid {label}: {err}"))
+fn close_temp_wsol(owner: &Pubkey, temp_wsol: &Pubkey) -> Result<Instruction> {
+    Ok(spl_token::instruction::close_account(
+        &spl_token::id(),
+        temp_wsol,
+        owner,
+        owner,
+        &[],
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market_bytes(program_id: &Pubkey, market_id: &Pubkey) -> (Vec<u8>, Pubkey) {
+        let (nonce, vault_signer) = (0u64..)
+            .find_map(|nonce| {
+                Pubkey::create_program_address(
+                    &[market_id.as_ref(), &nonce.to_le_bytes()],
+                    program_id,
+                )
+                .ok()
+                .map(|signer| (nonce, signer))
+            })
+            .unwrap();
+        let mut data = vec![0u8; MARKET_LEN];
+        data[..MARKET_HEAD_LEN].copy_from_slice(b"serum");
+        data[MARKET_OWN_ADDRESS..MARKET_OWN_ADDRESS + 32].copy_from_slice(market_id.as_ref());
+        data[MARKET_VAULT_SIGNER_NONCE..MARKET_VAULT_SIGNER_NONCE + 8]
+            .copy_from_slice(&nonce.to_le_bytes());
+        for (offset, byte) in [
+            (MARKET_BASE_VAULT, 1u8),
+            (MARKET_QUOTE_VAULT, 2),
+            (MARKET_EVENT_QUEUE, 3),
+            (MARKET_BIDS, 4),
+            (MARKET_ASKS, 5),
+        ] {
+            data[offset..offset + 32].fill(byte);
+        }
+        (data, vault_signer)
+    }
+
+    #[test]
+    fn amm_pool_must_be_owned_by_amm_v4() {
+        let pool_id = Pubkey::new_unique();
+        let account = |owner| Account {
+            lamports: 1,
+            data: vec![0; AMM_INFO_LEN],
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        assert_eq!(
+            amm_program(&pool_id, &account(AMM_V4_PROGRAM_ID)).unwrap(),
+            AMM_V4_PROGRAM_ID
+        );
+        assert!(amm_program(&pool_id, &account(Pubkey::new_unique())).is_err());
+    }
+
+    #[test]
+    fn pool_search_offsets_match_the_amm_layout() {
+        let mut data = vec![0; AMM_INFO_LEN];
+        for (offset, byte) in [
+            (AMM_BASE_VAULT, 1),
+            (AMM_QUOTE_VAULT, 2),
+            (AMM_BASE_MINT, 3),
+            (AMM_QUOTE_MINT, 4),
+        ] {
+            data[offset..offset + 32].fill(byte);
+        }
+        let info = LiquidityStateLayoutV4::try_from_slice(&data).unwrap();
+        assert_eq!(info.base_vault, Pubkey::new_from_array([1; 32]));
+        assert_eq!(info.quote_vault, Pubkey::new_from_array([2; 32]));
+        assert_eq!(info.base_mint, Pubkey::new_from_array([3; 32]));
+        assert_eq!(info.quote_mint, Pubkey::new_from_array([4; 32]));
+    }
+
+    #[test]
+    fn decodes_openbook_market() {
+        let program_id = Pubkey::new_unique();
+        let market_id = Pubkey::new_unique();
+        let (data, vault_signer) = market_bytes(&program_id, &market_id);
+
+        let market = decode_market(&program_id, &market_id, &data).unwrap();
+        assert_eq!(market.base_vault, Pubkey::new_from_array([1; 32]));
+        assert_eq!(market.quote_vault, Pubkey::new_from_array([2; 32]));
+        assert_eq!(market.event_queue, Pubkey::new_from_array([3; 32]));
+        assert_eq!(market.bids, Pubkey::new_from_array([4; 32]));
+        assert_eq!(market.asks, Pubkey::new_from_array([5; 32]));
+        assert_eq!(market.vault_signer, vault_signer);
+
+        assert!(decode_market(&program_id, &Pubkey::new_unique(), &data).is_err());
+        assert!(decode_market(&program_id, &market_id, &data[1..]).is_err());
+    }
+
+    #[test]
+    fn anchor_discriminator_matches_clmm_swap_v2() {
+        assert_eq!(
+            anchor_discriminator("global:swap_v2"),
+            raydium_amm_swap::consts::swap_v2_discriminator()
+        );
+    }
 }
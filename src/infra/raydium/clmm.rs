@@ -0,0 +1,423 @@
+use anyhow::Result;
+use raydium_amm_swap::consts::swap_v2_discriminator;
+use raydium_amm_swap::libraries::big_num::U1024;
+use raydium_amm_swap::libraries::tick_array_bit_map::{
+    check_current_tick_array_is_initialized, max_tick_in_tickarray_bitmap,
+    next_initialized_tick_array_start_index,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+use super::{
+    anchor_discriminator, load_pool_mints, read_bytes, read_pubkey, read_u128, PoolMint, SwapKind,
+};
+use crate::core::clmm_math::{ClmmCurve, ClmmTick};
+use crate::error::wmgr_error;
+use crate::infra::solana::MEMO_PROGRAM_ID;
+
+const TICK_ARRAY_SEED: &[u8] = b"tick_array";
+const BITMAP_EXTENSION_SEED: &[u8] = b"pool_tick_array_bitmap_extension";
+
+/// Tick arrays loaded in the swap direction. Each holds 60 ticks, so this covers far more than a
+/// wallet-sized swap on any pool with real liquidity, and keeps the transaction small.
+const MAX_TICK_ARRAYS: usize = 5;
+
+/// `PoolState` layout: an 8-byte Anchor discriminator and a bump, then the fields below.
+pub(super) const POOL_LEN: usize = 1544;
+const POOL_AMM_CONFIG: usize = 9;
+pub(super) const POOL_MINT_0: usize = 73;
+pub(super) const POOL_MINT_1: usize = 105;
+pub(super) const POOL_VAULT_0: usize = 137;
+pub(super) const POOL_VAULT_1: usize = 169;
+const POOL_OBSERVATION: usize = 201;
+const POOL_TICK_SPACING: usize = 235;
+const POOL_LIQUIDITY: usize = 237;
+const POOL_SQRT_PRICE: usize = 253;
+const POOL_TICK_CURRENT: usize = 269;
+const POOL_STATUS: usize = 389;
+const POOL_TICK_ARRAY_BITMAP: usize = 904;
+/// Bit 4 of `status` disables swaps.
+const STATUS_SWAP_DISABLED: u8 = 1 << 4;
+
+/// `AmmConfig` layout.
+const CONFIG_LEN: usize = 117;
+const CONFIG_TRADE_FEE_RATE: usize = 47;
+
+/// `TickArrayState` layout: discriminator, pool id, start index, then 60 ticks.
+const TICK_ARRAY_START: usize = 40;
+const TICK_ARRAY_TICKS: usize = 44;
+const TICKS_PER_ARRAY: usize = 60;
+const TICK_LEN: usize = 168;
+const TICK_LIQUIDITY_NET: usize = 4;
+const TICK_LIQUIDITY_GROSS: usize = 20;
+
+/// A Raydium CLMM pool with its accounts, mints, current price and the tick arrays around it.
+pub struct ClmmPool {
+    pub program_id: Pubkey,
+    pub id: Pubkey,
+    pub amm_config: Pubkey,
+    pub observation: Pubkey,
+    pub vault_0: Pubkey,
+    pub vault_1: Pubkey,
+    pub mint_0: PoolMint,
+    pub mint_1: PoolMint,
+    pub trade_fee_rate: u32,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    /// Present only when the pool has created one; passed ahead of the tick arrays.
+    pub bitmap_extension: Option<Pubkey>,
+    /// Tick arrays below the current price, nearest first (token 0 in).
+    pub arrays_down: Vec<TickArray>,
+    /// Tick arrays above the current price, nearest first (token 1 in).
+    pub arrays_up: Vec<TickArray>,
+}
+
+pub struct TickArray {
+    pub address: Pubkey,
+    pub ticks: Vec<ClmmTick>,
+}
+
+/// Pool fields decoded from the `PoolState` account, before the config and tick arrays are read.
+#[derive(Debug, PartialEq, Eq)]
+struct ClmmState {
+    amm_config: Pubkey,
+    mint_0: Pubkey,
+    mint_1: Pubkey,
+    vault_0: Pubkey,
+    vault_1: Pubkey,
+    observation: Pubkey,
+    tick_spacing: u16,
+    liquidity: u128,
+    sqrt_price_x64: u128,
+    tick_current: i32,
+    status: u8,
+    tick_array_bitmap: [u64; 16],
+}
+
+pub(super) async fn load_clmm_pool(
+    rpc: &RpcClient,
+    pool_id: &Pubkey,
+    account: Account,
+) -> Result<ClmmPool> {
+    let state = decode_pool(pool_id, &account.data)?;
+    if state.status & STATUS_SWAP_DISABLED != 0 {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Swaps are disabled on CLMM pool {pool_id}"
+        ));
+    }
+    let program_id = account.owner;
+    let boundary = max_tick_in_tickarray_bitmap(state.tick_spacing);
+    if state.tick_current < -boundary || state.tick_current >= boundary {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "CLMM pool {pool_id} trades outside the tick range wmgr can traverse"
+        ));
+    }
+
+    let bitmap = U1024(state.tick_array_bitmap);
+    let starts_down = tick_array_starts(bitmap, &state, true)?;
+    let starts_up = tick_array_starts(bitmap, &state, false)?;
+    let address = |start: &i32| tick_array_address(&program_id, pool_id, *start);
+    let extension =
+        Pubkey::find_program_address(&[BITMAP_EXTENSION_SEED, pool_id.as_ref()], &program_id).0;
+
+    let mut addresses = vec![state.amm_config, extension];
+    addresses.extend(starts_down.iter().map(address));
+    addresses.extend(starts_up.iter().map(address));
+    let mut accounts = rpc
+        .get_multiple_accounts(&addresses)
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch pool accounts for {pool_id}: {err}"))?
+        .into_iter();
+    let config = accounts
+        .next()
+        .flatten()
+        .ok_or_else(|| wmgr_error!(Rpc, "CLMM config {} not found", state.amm_config))?;
+    let trade_fee_rate = decode_config(&state.amm_config, &config.data)?;
+    let bitmap_extension = accounts.next().flatten().map(|_| extension);
+
+    let mut load_arrays = |count: usize, offset: usize| -> Result<Vec<TickArray>> {
+        addresses[offset..offset + count]
+            .iter()
+            .map(|address| {
+                let account = accounts
+                    .next()
+                    .flatten()
+                    .ok_or_else(|| wmgr_error!(Rpc, "Tick array {address} not found"))?;
+                Ok(TickArray {
+                    address: *address,
+                    ticks: decode_tick_array(address, &account.data)?,
+                })
+            })
+            .collect()
+    };
+    let arrays_down = load_arrays(starts_down.len(), 2)?;
+    let arrays_up = load_arrays(starts_up.len(), 2 + starts_down.len())?;
+    let [mint_0, mint_1] = load_pool_mints(rpc, [state.mint_0, state.mint_1]).await?;
+
+    Ok(ClmmPool {
+        program_id,
+        id: *pool_id,
+        amm_config: state.amm_config,
+        observation: state.observation,
+        vault_0: state.vault_0,
+        vault_1: state.vault_1,
+        mint_0,
+        mint_1,
+        trade_fee_rate,
+        sqrt_price_x64: state.sqrt_price_x64,
+        tick_current: state.tick_current,
+        liquidity: state.liquidity,
+        bitmap_extension,
+        arrays_down,
+        arrays_up,
+    })
+}
+
+/// Start indexes of the initialized tick arrays a swap would walk, nearest first.
+fn tick_array_starts(bitmap: U1024, state: &ClmmState, zero_for_one: bool) -> Result<Vec<i32>> {
+    let (initialized, mut start) =
+        check_current_tick_array_is_initialized(bitmap, state.tick_current, state.tick_spacing)
+            .map_err(|err| {
+                wmgr_error!(Rpc, "Failed to read the pool's tick array bitmap: {err}")
+            })?;
+    let mut starts = Vec::with_capacity(MAX_TICK_ARRAYS);
+    if initialized {
+        starts.push(start);
+    }
+    while starts.len() < MAX_TICK_ARRAYS {
+        let (found, next) = next_initialized_tick_array_start_index(
+            bitmap,
+            start,
+            state.tick_spacing,
+            zero_for_one,
+        )?;
+        if !found {
+            break;
+        }
+        starts.push(next);
+        start = next;
+    }
+    Ok(starts)
+}
+
+fn tick_array_address(program_id: &Pubkey, pool_id: &Pubkey, start: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[TICK_ARRAY_SEED, pool_id.as_ref(), &start.to_be_bytes()],
+        program_id,
+    )
+    .0
+}
+
+impl ClmmPool {
+    fn arrays(&self, zero_for_one: bool) -> &[TickArray] {
+        if zero_for_one {
+            &self.arrays_down
+        } else {
+            &self.arrays_up
+        }
+    }
+
+    /// The pool seen from `input_mint`, which must be one of its mints.
+    pub fn curve(&self, input_mint: &Pubkey) -> ClmmCurve {
+        let zero_for_one = *input_mint == self.mint_0.mint;
+        let (mint_in, mint_out) = if zero_for_one {
+            (&self.mint_0, &self.mint_1)
+        } else {
+            (&self.mint_1, &self.mint_0)
+        };
+        let mut ticks: Vec<ClmmTick> = self
+            .arrays(zero_for_one)
+            .iter()
+            .flat_map(|array| array.ticks.iter().copied())
+            .collect();
+        ticks.sort_by_key(|tick| tick.index);
+        if zero_for_one {
+            ticks.reverse();
+        }
+        ClmmCurve {
+            sqrt_price_x64: self.sqrt_price_x64,
+            tick_current: self.tick_current,
+            liquidity: self.liquidity,
+            fee_rate: self.trade_fee_rate,
+            zero_for_one,
+            decimals_in: mint_in.decimals,
+            decimals_out: mint_out.decimals,
+            ticks,
+            transfer_fee_in: mint_in.transfer_fee,
+            transfer_fee_out: mint_out.transfer_fee,
+        }
+    }
+
+    pub fn swap_instruction(
+        &self,
+        owner: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        input_mint: &Pubkey,
+        swap: &SwapKind,
+    ) -> Instruction {
+        let zero_for_one = *input_mint == self.mint_0.mint;
+        let (mint_in, mint_out, vault_in, vault_out) = if zero_for_one {
+            (&self.mint_0, &self.mint_1, self.vault_0, self.vault_1)
+        } else {
+            (&self.mint_1, &self.mint_0, self.vault_1, self.vault_0)
+        };
+        let (amount, threshold, is_base_input) = match *swap {
+            SwapKind::BaseIn {
+                amount_in,
+                min_amount_out,
+            } => (amount_in, min_amount_out, true),
+            SwapKind::BaseOut {
+                max_amount_in,
+                amount_out,
+            } => (amount_out, max_amount_in, false),
+        };
+        let mut data = swap_v2_discriminator().to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&threshold.to_le_bytes());
+        // No price limit: min-out / max-in already bound the trade.
+        data.extend_from_slice(&0u128.to_le_bytes());
+        data.push(is_base_input as u8);
+
+        let mut accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(self.amm_config, false),
+            AccountMeta::new(self.id, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new(vault_in, false),
+            AccountMeta::new(vault_out, false),
+            AccountMeta::new(self.observation, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_token_2022_interface::id(), false),
+            AccountMeta::new_readonly(MEMO_PROGRAM_ID, false),
+            AccountMeta::new_readonly(mint_in.mint, false),
+            AccountMeta::new_readonly(mint_out.mint, false),
+        ];
+        if let Some(extension) = self.bitmap_extension {
+            accounts.push(AccountMeta::new_readonly(extension, false));
+        }
+        accounts.extend(
+            self.arrays(zero_for_one)
+                .iter()
+                .map(|array| AccountMeta::new(array.address, false)),
+        );
+
+        Instruction {
+            program_id: self.program_id,
+            accounts,
+            data,
+        }
+    }
+}
+
+fn decode_pool(pool_id: &Pubkey, data: &[u8]) -> Result<ClmmState> {
+    if data.len() < POOL_LEN || data[..8] != anchor_discriminator("account:PoolState") {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Account {pool_id} is not a Raydium CLMM pool"
+        ));
+    }
+    let mut tick_array_bitmap = [0u64; 16];
+    for (index, word) in tick_array_bitmap.iter_mut().enumerate() {
+        *word = u64::from_le_bytes(read_bytes(data, POOL_TICK_ARRAY_BITMAP + index * 8));
+    }
+    Ok(ClmmState {
+        amm_config: read_pubkey(data, POOL_AMM_CONFIG),
+        mint_0: read_pubkey(data, POOL_MINT_0),
+        mint_1: read_pubkey(data, POOL_MINT_1),
+        vault_0: read_pubkey(data, POOL_VAULT_0),
+        vault_1: read_pubkey(data, POOL_VAULT_1),
+        observation: read_pubkey(data, POOL_OBSERVATION),
+        tick_spacing: u16::from_le_bytes(read_bytes(data, POOL_TICK_SPACING)),
+        liquidity: read_u128(data, POOL_LIQUIDITY),
+        sqrt_price_x64: read_u128(data, POOL_SQRT_PRICE),
+        tick_current: i32::from_le_bytes(read_bytes(data, POOL_TICK_CURRENT)),
+        status: data[POOL_STATUS],
+        tick_array_bitmap,
+    })
+}
+
+/// Returns the trade fee rate, in millionths.
+fn decode_config(config_id: &Pubkey, data: &[u8]) -> Result<u32> {
+    if data.len() < CONFIG_LEN || data[..8] != anchor_discriminator("account:AmmConfig") {
+        return Err(wmgr_error!(
+            Rpc,
+            "Account {config_id} is not a Raydium CLMM config"
+        ));
+    }
+    Ok(u32::from_le_bytes(read_bytes(data, CONFIG_TRADE_FEE_RATE)))
+}
+
+/// The initialized ticks of a tick array, in index order.
+fn decode_tick_array(address: &Pubkey, data: &[u8]) -> Result<Vec<ClmmTick>> {
+    if data.len() < TICK_ARRAY_TICKS + TICKS_PER_ARRAY * TICK_LEN
+        || data[..8] != anchor_discriminator("account:TickArrayState")
+    {
+        return Err(wmgr_error!(
+            Rpc,
+            "Account {address} is not a CLMM tick array"
+        ));
+    }
+    let start = i32::from_le_bytes(read_bytes(data, TICK_ARRAY_START));
+    let ticks = (0..TICKS_PER_ARRAY)
+        .map(|slot| TICK_ARRAY_TICKS + slot * TICK_LEN)
+        .filter(|offset| read_u128(data, offset + TICK_LIQUIDITY_GROSS) != 0)
+        .map(|offset| ClmmTick {
+            index: i32::from_le_bytes(read_bytes(data, offset)),
+            liquidity_net: i128::from_le_bytes(read_bytes(data, offset + TICK_LIQUIDITY_NET)),
+        })
+        .collect::<Vec<_>>();
+    if ticks.first().is_some_and(|tick| tick.index < start) {
+        return Err(wmgr_error!(
+            Rpc,
+            "Tick array {address} holds ticks below its start index {start}"
+        ));
+    }
+    Ok(ticks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_initialized_ticks() {
+        let address = Pubkey::new_unique();
+        let mut data = vec![0u8; TICK_ARRAY_TICKS + TICKS_PER_ARRAY * TICK_LEN];
+        data[..8].copy_from_slice(&anchor_discriminator("account:TickArrayState"));
+        data[TICK_ARRAY_START..TICK_ARRAY_START + 4].copy_from_slice(&(-600i32).to_le_bytes());
+        for (slot, index, net) in [(0usize, -600i32, 5i128), (7, -530, -5)] {
+            let offset = TICK_ARRAY_TICKS + slot * TICK_LEN;
+            data[offset..offset + 4].copy_from_slice(&index.to_le_bytes());
+            data[offset + TICK_LIQUIDITY_NET..offset + TICK_LIQUIDITY_NET + 16]
+                .copy_from_slice(&net.to_le_bytes());
+            data[offset + TICK_LIQUIDITY_GROSS..offset + TICK_LIQUIDITY_GROSS + 16]
+                .copy_from_slice(&5u128.to_le_bytes());
+        }
+        // Slot 3 carries an index but no liquidity, so it is not initialized.
+        let offset = TICK_ARRAY_TICKS + 3 * TICK_LEN;
+        data[offset..offset + 4].copy_from_slice(&(-570i32).to_le_bytes());
+
+        let ticks = decode_tick_array(&address, &data).unwrap();
+        assert_eq!(
+            ticks,
+            vec![
+                ClmmTick {
+                    index: -600,
+                    liquidity_net: 5
+                },
+                ClmmTick {
+                    index: -530,
+                    liquidity_net: -5
+                },
+            ]
+        );
+        assert!(decode_tick_array(&address, &data[..100]).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+use anyhow::Result;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+use super::{
+    anchor_discriminator, load_pool_mints, read_pubkey, read_u64, vault_balance, PoolMint, SwapKind,
+};
+use crate::core::cpmm_math::CpmmCurve;
+use crate::error::wmgr_error;
+
+const AUTH_SEED: &[u8] = b"vault_and_lp_mint_auth_seed";
+
+/// `PoolState` layout: an 8-byte Anchor discriminator, then the fields below.
+const POOL_LEN: usize = 637;
+const POOL_AMM_CONFIG: usize = 8;
+const POOL_TOKEN_0_VAULT: usize = 72;
+const POOL_TOKEN_1_VAULT: usize = 104;
+const POOL_TOKEN_0_MINT: usize = 168;
+const POOL_TOKEN_1_MINT: usize = 200;
+const POOL_OBSERVATION: usize = 296;
+const POOL_AUTH_BUMP: usize = 328;
+const POOL_STATUS: usize = 329;
+const POOL_PROTOCOL_FEES_0: usize = 341;
+const POOL_PROTOCOL_FEES_1: usize = 349;
+const POOL_FUND_FEES_0: usize = 357;
+const POOL_FUND_FEES_1: usize = 365;
+const POOL_CREATOR_FEE_ON: usize = 389;
+const POOL_ENABLE_CREATOR_FEE: usize = 390;
+const POOL_CREATOR_FEES_0: usize = 397;
+const POOL_CREATOR_FEES_1: usize = 405;
+/// Bit 2 of `status` disables swaps.
+const STATUS_SWAP_DISABLED: u8 = 1 << 2;
+
+/// `AmmConfig` layout.
+const CONFIG_LEN: usize = 236;
+const CONFIG_TRADE_FEE_RATE: usize = 12;
+const CONFIG_CREATOR_FEE_RATE: usize = 108;
+
+/// Which token a CPMM pool charges its creator fee in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CreatorFeeOn {
+    BothTokens,
+    Token0,
+    Token1,
+}
+
+/// A Raydium CPMM pool with its accounts, mints, fee rates and current reserves.
+pub struct CpmmPool {
+    pub program_id: Pubkey,
+    pub id: Pubkey,
+    pub authority: Pubkey,
+    pub amm_config: Pubkey,
+    pub observation: Pubkey,
+    pub vault_0: Pubkey,
+    pub vault_1: Pubkey,
+    pub mint_0: PoolMint,
+    pub mint_1: PoolMint,
+    pub reserve_0: u64,
+    pub reserve_1: u64,
+    pub trade_fee_rate: u64,
+    /// Zero when the pool does not charge a creator fee.
+    pub creator_fee_rate: u64,
+    creator_fee_on: CreatorFeeOn,
+}
+
+/// Pool fields decoded from the `PoolState` account, before the config and vaults are read.
+#[derive(Debug, PartialEq, Eq)]
+struct CpmmState {
+    amm_config: Pubkey,
+    vault_0: Pubkey,
+    vault_1: Pubkey,
+    mint_0: Pubkey,
+    mint_1: Pubkey,
+    observation: Pubkey,
+    auth_bump: u8,
+    status: u8,
+    /// Protocol, fund and creator fees held in each vault.
+    owed_0: u64,
+    owed_1: u64,
+    creator_fee_on: CreatorFeeOn,
+    creator_fee_enabled: bool,
+}
+
+pub(super) async fn load_cpmm_pool(
+    rpc: &RpcClient,
+    pool_id: &Pubkey,
+    account: Account,
+) -> Result<CpmmPool> {
+    let state = decode_pool(pool_id, &account.data)?;
+    if state.status & STATUS_SWAP_DISABLED != 0 {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Swaps are disabled on CPMM pool {pool_id}"
+        ));
+    }
+    let program_id = account.owner;
+    let authority =
+        Pubkey::create_program_address(&[AUTH_SEED, &[state.auth_bump]], &program_id)
+            .map_err(|err| wmgr_error!(Rpc, "Invalid CPMM authority bump for {pool_id}: {err}"))?;
+
+    let accounts = rpc
+        .get_multiple_accounts(&[state.amm_config, state.vault_0, state.vault_1])
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch pool accounts for {pool_id}: {err}"))?;
+    let [config, vault_0, vault_1] = <[_; 3]>::try_from(accounts)
+        .map_err(|_| wmgr_error!(Rpc, "Unexpected account count for pool {pool_id}"))?;
+    let config =
+        config.ok_or_else(|| wmgr_error!(Rpc, "CPMM config {} not found", state.amm_config))?;
+    let (trade_fee_rate, creator_fee_rate) = decode_config(&state.amm_config, &config.data)?;
+    let balance_0 = vault_balance(vault_0, &state.vault_0)?;
+    let balance_1 = vault_balance(vault_1, &state.vault_1)?;
+    let [mint_0, mint_1] = load_pool_mints(rpc, [state.mint_0, state.mint_1]).await?;
+
+    Ok(CpmmPool {
+        program_id,
+        id: *pool_id,
+        authority,
+        amm_config: state.amm_config,
+        observation: state.observation,
+        vault_0: state.vault_0,
+        vault_1: state.vault_1,
+        mint_0,
+        mint_1,
+        // Fees owed to the protocol, fund and creator sit in the vaults but are not liquidity.
+        reserve_0: balance_0.saturating_sub(state.owed_0),
+        reserve_1: balance_1.saturating_sub(state.owed_1),
+        trade_fee_rate,
+        creator_fee_rate: if state.creator_fee_enabled {
+            creator_fee_rate
+        } else {
+            0
+        },
+        creator_fee_on: state.creator_fee_on,
+    })
+}
+
+impl CpmmPool {
+    /// The pool seen from `input_mint`, which must be one of its mints.
+    pub fn curve(&self, input_mint: &Pubkey) -> CpmmCurve {
+        let zero_for_one = *input_mint == self.mint_0.mint;
+        let (mint_in, mint_out, reserve_in, reserve_out) = if zero_for_one {
+            (&self.mint_0, &self.mint_1, self.reserve_0, self.reserve_1)
+        } else {
+            (&self.mint_1, &self.mint_0, self.reserve_1, self.reserve_0)
+        };
+        CpmmCurve {
+            reserve_in,
+            reserve_out,
+            decimals_in: mint_in.decimals,
+            decimals_out: mint_out.decimals,
+            trade_fee_rate: self.trade_fee_rate,
+            creator_fee_rate: self.creator_fee_rate,
+            creator_fee_on_input: match self.creator_fee_on {
+                CreatorFeeOn::BothTokens => true,
+                CreatorFeeOn::Token0 => zero_for_one,
+                CreatorFeeOn::Token1 => !zero_for_one,
+            },
+            transfer_fee_in: mint_in.transfer_fee,
+            transfer_fee_out: mint_out.transfer_fee,
+        }
+    }
+
+    pub fn swap_instruction(
+        &self,
+        owner: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        input_mint: &Pubkey,
+        swap: &SwapKind,
+    ) -> Instruction {
+        let (mint_in, mint_out, vault_in, vault_out) = if *input_mint == self.mint_0.mint {
+            (&self.mint_0, &self.mint_1, self.vault_0, self.vault_1)
+        } else {
+            (&self.mint_1, &self.mint_0, self.vault_1, self.vault_0)
+        };
+        let (name, first, second) = match *swap {
+            SwapKind::BaseIn {
+                amount_in,
+                min_amount_out,
+            } => ("global:swap_base_input", amount_in, min_amount_out),
+            SwapKind::BaseOut {
+                max_amount_in,
+                amount_out,
+            } => ("global:swap_base_output", max_amount_in, amount_out),
+        };
+        let mut data = anchor_discriminator(name).to_vec();
+        data.extend_from_slice(&first.to_le_bytes());
+        data.extend_from_slice(&second.to_le_bytes());
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new_readonly(self.authority, false),
+                AccountMeta::new_readonly(self.amm_config, false),
+                AccountMeta::new(self.id, false),
+                AccountMeta::new(*source, false),
+                AccountMeta::new(*destination, false),
+                AccountMeta::new(vault_in, false),
+                AccountMeta::new(vault_out, false),
+                AccountMeta::new_readonly(mint_in.program_id, false),
+                AccountMeta::new_readonly(mint_out.program_id, false),
+                AccountMeta::new_readonly(mint_in.mint, false),
+                AccountMeta::new_readonly(mint_out.mint, false),
+                AccountMeta::new(self.observation, false),
+            ],
+            data,
+        }
+    }
+}
+
+fn decode_pool(pool_id: &Pubkey, data: &[u8]) -> Result<CpmmState> {
+    if data.len() < POOL_LEN || data[..8] != anchor_discriminator("account:PoolState") {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Account {pool_id} is not a Raydium CPMM pool"
+        ));
+    }
+    let creator_fee_on = match data[POOL_CREATOR_FEE_ON] {
+        0 => CreatorFeeOn::BothTokens,
+        1 => CreatorFeeOn::Token0,
+        2 => CreatorFeeOn::Token1,
+        other => {
+            return Err(wmgr_error!(
+                Rpc,
+                "Unknown creator fee mode {other} on CPMM pool {pool_id}"
+            ))
+        }
+    };
+    let owed = |protocol: usize, fund: usize, creator: usize| {
+        read_u64(data, protocol)
+            .saturating_add(read_u64(data, fund))
+            .saturating_add(read_u64(data, creator))
+    };
+    Ok(CpmmState {
+        amm_config: read_pubkey(data, POOL_AMM_CONFIG),
+        vault_0: read_pubkey(data, POOL_TOKEN_0_VAULT),
+        vault_1: read_pubkey(data, POOL_TOKEN_1_VAULT),
+        mint_0: read_pubkey(data, POOL_TOKEN_0_MINT),
+        mint_1: read_pubkey(data, POOL_TOKEN_1_MINT),
+        observation: read_pubkey(data, POOL_OBSERVATION),
+        auth_bump: data[POOL_AUTH_BUMP],
+        status: data[POOL_STATUS],
+        owed_0: owed(POOL_PROTOCOL_FEES_0, POOL_FUND_FEES_0, POOL_CREATOR_FEES_0),
+        owed_1: owed(POOL_PROTOCOL_FEES_1, POOL_FUND_FEES_1, POOL_CREATOR_FEES_1),
+        creator_fee_on,
+        creator_fee_enabled: data[POOL_ENABLE_CREATOR_FEE] != 0,
+    })
+}
+
+/// Returns the trade and creator fee rates, in millionths.
+fn decode_config(config_id: &Pubkey, data: &[u8]) -> Result<(u64, u64)> {
+    if data.len() < CONFIG_LEN || data[..8] != anchor_discriminator("account:AmmConfig") {
+        return Err(wmgr_error!(
+            Rpc,
+            "Account {config_id} is not a Raydium CPMM config"
+        ));
+    }
+    Ok((
+        read_u64(data, CONFIG_TRADE_FEE_RATE),
+        read_u64(data, CONFIG_CREATOR_FEE_RATE),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_pool_state() {
+        let pool_id = Pubkey::new_unique();
+        let mut data = vec![0u8; POOL_LEN];
+        data[..8].copy_from_slice(&anchor_discriminator("account:PoolState"));
+        for (offset, byte) in [
+            (POOL_AMM_CONFIG, 1u8),
+            (POOL_TOKEN_0_VAULT, 2),
+            (POOL_TOKEN_1_VAULT, 3),
+            (POOL_TOKEN_0_MINT, 4),
+            (POOL_TOKEN_1_MINT, 5),
+            (POOL_OBSERVATION, 6),
+        ] {
+            data[offset..offset + 32].fill(byte);
+        }
+        data[POOL_AUTH_BUMP] = 254;
+        data[POOL_PROTOCOL_FEES_0..POOL_PROTOCOL_FEES_0 + 8].copy_from_slice(&7u64.to_le_bytes());
+        data[POOL_FUND_FEES_1..POOL_FUND_FEES_1 + 8].copy_from_slice(&11u64.to_le_bytes());
+        data[POOL_CREATOR_FEES_1..POOL_CREATOR_FEES_1 + 8].copy_from_slice(&13u64.to_le_bytes());
+        data[POOL_CREATOR_FEE_ON] = 2;
+        data[POOL_ENABLE_CREATOR_FEE] = 1;
+
+        let state = decode_pool(&pool_id, &data).unwrap();
+        assert_eq!(state.amm_config, Pubkey::new_from_array([1; 32]));
+        assert_eq!(state.vault_1, Pubkey::new_from_array([3; 32]));
+        assert_eq!(state.mint_0, Pubkey::new_from_array([4; 32]));
+        assert_eq!(state.observation, Pubkey::new_from_array([6; 32]));
+        assert_eq!(state.auth_bump, 254);
+        assert_eq!((state.owed_0, state.owed_1), (7, 24));
+        assert_eq!(state.creator_fee_on, CreatorFeeOn::Token1);
+        assert!(state.creator_fee_enabled);
+
+        data[..8].fill(0);
+        assert!(decode_pool(&pool_id, &data).is_err());
+    }
+}
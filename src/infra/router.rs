@@ -0,0 +1,265 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::config::raydium::{SOL_USDC_POOL_ID, USDC_MINT};
+use crate::core::amm_math::{
+    max_path_input_for_impact, quote_path_exact_in, quote_path_exact_in_within,
+    quote_path_exact_out_within, AmmCurve, PathQuote,
+};
+use crate::core::slippage::Slippage;
+use crate::error::wmgr_error;
+use crate::infra::venue::{find_pool, load_pool, OrientedPool, Pool, SwapKind, SwapLeg, Venue};
+
+/// Tokens a two-hop route may pass through.
+const INTERMEDIATES: [Pubkey; 2] = [
+    spl_token::native_mint::ID,
+    Pubkey::from_str_const(USDC_MINT),
+];
+
+/// A path between two tokens: one pool, or two pools sharing an intermediate token. The pools
+/// are kept in the order they are found, from the traded token to its counter. Routes found
+/// together share the pools they have in common.
+pub struct Route {
+    pools: Vec<Arc<Pool>>,
+}
+
+/// A route seen from the input side of a swap, one oriented pool per leg.
+pub struct OrientedRoute {
+    pub decimals_in: u8,
+    pub decimals_out: u8,
+    legs: Vec<OrientedPool>,
+}
+
+impl Route {
+    pub fn direct(pool: Pool) -> Self {
+        Self {
+            pools: vec![Arc::new(pool)],
+        }
+    }
+
+    pub fn pools(&self) -> &[Arc<Pool>] {
+        &self.pools
+    }
+
+    pub fn is_direct(&self) -> bool {
+        self.pools.len() == 1
+    }
+
+    /// Pool ids joined by `+`, in the order found.
+    pub fn id(&self) -> String {
+        self.pools
+            .iter()
+            .map(|pool| pool.id().to_string())
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    /// Pool labels joined by `+`, e.g. `Raydium CLMM + Orca Whirlpool`.
+    pub fn label(&self) -> String {
+        self.pools
+            .iter()
+            .map(|pool| pool.label())
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+
+    /// The pools in trade order, each with the mints it swaps.
+    pub fn legs(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+    ) -> Result<Vec<(&Pool, Pubkey, Pubkey)>> {
+        let mut pools: Vec<&Pool> = self.pools.iter().map(Arc::as_ref).collect();
+        if !pools[0].contains(input_mint) {
+            pools.reverse();
+        }
+        let mut legs = Vec::with_capacity(pools.len());
+        let mut mint = *input_mint;
+        for pool in pools {
+            let next = pool.other_mint(&mint).ok_or_else(|| {
+                wmgr_error!(
+                    InvalidArgument,
+                    "Route {} does not trade {input_mint} for {output_mint}",
+                    self.id()
+                )
+            })?;
+            legs.push((pool, mint, next));
+            mint = next;
+        }
+        if mint != *output_mint {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Route {} does not trade {input_mint} for {output_mint}",
+                self.id()
+            ));
+        }
+        Ok(legs)
+    }
+
+    pub fn orient(&self, input_mint: &Pubkey, output_mint: &Pubkey) -> Result<OrientedRoute> {
+        let legs = self
+            .legs(input_mint, output_mint)?
+            .into_iter()
+            .map(|(pool, input, output)| pool.orient(&input, &output))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(OrientedRoute {
+            decimals_in: legs[0].decimals_in,
+            decimals_out: legs[legs.len() - 1].decimals_out,
+            legs,
+        })
+    }
+
+    /// The swaps to send, one per leg, zipped with `swaps` in trade order.
+    pub fn swap_legs(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+        swaps: Vec<SwapKind>,
+    ) -> Result<Vec<SwapLeg<'_>>> {
+        let legs = self.legs(input_mint, output_mint)?;
+        if legs.len() != swaps.len() {
+            return Err(wmgr_error!(
+                InvalidArgument,
+                "Route {} has {} legs, not {}",
+                self.id(),
+                legs.len(),
+                swaps.len()
+            ));
+        }
+        Ok(legs
+            .into_iter()
+            .zip(swaps)
+            .map(|((pool, input_mint, output_mint), swap)| SwapLeg {
+                pool,
+                input_mint,
+                output_mint,
+                swap,
+            })
+            .collect())
+    }
+
+    /// The same pools with fresh chain state.
+    pub async fn reload(&self, rpc: &RpcClient) -> Result<Route> {
+        let mut pools = Vec::with_capacity(self.pools.len());
+        for pool in &self.pools {
+            pools.push(Arc::new(load_pool(rpc, &pool.id()).await?));
+        }
+        Ok(Route { pools })
+    }
+}
+
+impl OrientedRoute {
+    pub fn legs(&self) -> &[OrientedPool] {
+        &self.legs
+    }
+
+    pub fn quote_exact_in(&self, amount_in: u64) -> Result<PathQuote> {
+        quote_path_exact_in(&self.curves(), amount_in)
+    }
+
+    /// Quotes `amount_in` as the route is sent under `slippage`; see
+    /// [`quote_path_exact_in_within`].
+    pub fn quote_exact_in_within(&self, amount_in: u64, slippage: Slippage) -> Result<PathQuote> {
+        quote_path_exact_in_within(&self.curves(), amount_in, slippage)
+    }
+
+    /// Quotes an exact `amount_out` as the route is sent under `slippage`; see
+    /// [`quote_path_exact_out_within`].
+    pub fn quote_exact_out_within(&self, amount_out: u64, slippage: Slippage) -> Result<PathQuote> {
+        quote_path_exact_out_within(&self.curves(), amount_out, slippage)
+    }
+
+    /// Largest input that keeps the compounded price impact within `max_impact_percent`.
+    pub fn max_input_for_impact(&self, max_impact_percent: f64) -> u64 {
+        match self.legs.as_slice() {
+            [pool] => pool.max_input_for_impact(max_impact_percent),
+            _ => max_path_input_for_impact(&self.curves(), max_impact_percent),
+        }
+    }
+
+    fn curves(&self) -> Vec<&dyn AmmCurve> {
+        self.legs.iter().map(OrientedPool::curve).collect()
+    }
+}
+
+/// The pool `venue` uses for a mint pair: Raydium's built-in SOL/USDC pool, else the one
+/// `find_pool` picks.
+pub async fn find_pair_pool(
+    rpc: &RpcClient,
+    venue: Venue,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+) -> Result<Pool> {
+    let sol_usdc = [
+        spl_token::native_mint::ID,
+        Pubkey::from_str_const(USDC_MINT),
+    ];
+    let pool_id = if venue == Venue::Raydium
+        && mint_a != mint_b
+        && sol_usdc.contains(mint_a)
+        && sol_usdc.contains(mint_b)
+    {
+        Pubkey::from_str_const(SOL_USDC_POOL_ID)
+    } else {
+        find_pool(rpc, venue, mint_a, mint_b).await?
+    };
+    load_pool(rpc, &pool_id).await
+}
+
+/// Every route from `mint` to `counter` on `venues`: a direct pool per venue, and two pools
+/// through SOL or USDC, mixing venues. Pairs without a pool are skipped; an error is returned
+/// only when no route is left.
+pub async fn find_routes(
+    rpc: &RpcClient,
+    venues: &[Venue],
+    mint: &Pubkey,
+    counter: &Pubkey,
+) -> Result<Vec<Route>> {
+    let mut routes: Vec<Route> = pair_pools(rpc, venues, mint, counter)
+        .await
+        .into_iter()
+        .map(|pool| Route { pools: vec![pool] })
+        .collect();
+    for via in INTERMEDIATES
+        .iter()
+        .filter(|via| *via != mint && *via != counter)
+    {
+        let first = pair_pools(rpc, venues, mint, via).await;
+        if first.is_empty() {
+            continue;
+        }
+        let second = pair_pools(rpc, venues, via, counter).await;
+        for first in &first {
+            for second in &second {
+                routes.push(Route {
+                    pools: vec![first.clone(), second.clone()],
+                });
+            }
+        }
+    }
+    if routes.is_empty() {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "No direct or two-hop route found for {mint}/{counter}"
+        ));
+    }
+    Ok(routes)
+}
+
+async fn pair_pools(
+    rpc: &RpcClient,
+    venues: &[Venue],
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+) -> Vec<Arc<Pool>> {
+    let mut pools = Vec::with_capacity(venues.len());
+    for venue in venues {
+        if let Ok(pool) = find_pair_pool(rpc, *venue, mint_a, mint_b).await {
+            pools.push(Arc::new(pool));
+        }
+    }
+    pools
+}
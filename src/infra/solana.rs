@@ -1,20 +1,55 @@
+use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::time::Duration;
 
-use anyhow::{anyhow, Result};
-use solana_client::rpc_client::RpcClient;
+use anyhow::Result;
+use solana_account_decoder_client_types::token::{UiAccountState, UiTokenAccount};
+use solana_account_decoder_client_types::{UiAccountData, UiAccountEncoding};
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{
+    RpcSendTransactionConfig, RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+    RpcTransactionConfig,
+};
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_client::rpc_response::RpcSimulateTransactionResult;
 use solana_commitment_config::CommitmentConfig;
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
 use solana_sdk::program_pack::Pack;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::Keypair;
+use solana_sdk::signature::{Keypair, Signature};
 use solana_sdk::signer::Signer;
-use solana_sdk::transaction::Transaction;
+use solana_sdk::transaction::{Transaction, TransactionError};
 use solana_system_interface::instruction as system_instruction;
-use spl_associated_token_account_interface::address::get_associated_token_address;
+use solana_transaction_status_client_types::option_serializer::OptionSerializer;
+use solana_transaction_status_client_types::{
+    UiTransactionEncoding, UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use spl_associated_token_account_interface::address::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+};
 use spl_associated_token_account_interface::instruction::create_associated_token_account_idempotent;
-use spl_token::instruction::transfer_checked;
 use spl_token::state::{Account as TokenAccount, Mint};
+use spl_token_2022_interface::extension::memo_transfer::MemoTransfer;
+use spl_token_2022_interface::extension::non_transferable::NonTransferable;
+use spl_token_2022_interface::extension::pausable::PausableConfig;
+use spl_token_2022_interface::extension::transfer_fee::instruction::transfer_checked_with_fee;
+use spl_token_2022_interface::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022_interface::extension::transfer_hook::TransferHook;
+use spl_token_2022_interface::extension::{
+    BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use spl_token_2022_interface::instruction::transfer_checked;
+use spl_token_2022_interface::state::{Account as Token2022Account, Mint as Token2022Mint};
+use tokio::time::sleep;
 
 use crate::core::amount::{format_integer_amount, parse_amount_to_u64};
+use crate::error::{is_fee_payer_underfunded, wmgr_error};
+
+pub const MEMO_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
 
 #[allow(unused)]
 pub struct BalanceResult {
@@ -30,86 +65,889 @@ pub fn create_rpc_client(rpc_url: &str, commitment: CommitmentConfig) -> RpcClie
     RpcClient::new_with_commitment(rpc_url.to_string(), commitment)
 }
 
-pub fn transfer_sol(client: &RpcClient, from: &Keypair, to: &str, amount: &str) -> Result<String> {
-    let to_pubkey =
-        Pubkey::from_str(to).map_err(|err| anyhow!("Invalid recipient address: {err}"))?;
-    let lamports = parse_amount_to_u64(amount, 9)?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityFee {
+    MicroLamports(u64),
+    Auto,
+}
 
-    let instruction = system_instruction::transfer(&from.pubkey(), &to_pubkey, lamports);
-    let recent_blockhash = client.get_latest_blockhash()?;
+impl FromStr for PriorityFee {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        s.parse::<u64>().map(Self::MicroLamports).map_err(|_| {
+            wmgr_error!(
+                InvalidArgument,
+                "Invalid priority fee '{s}': expected micro-lamports per CU or 'auto'"
+            )
+        })
+    }
+}
+
+impl std::fmt::Display for PriorityFee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MicroLamports(v) => write!(f, "{v}"),
+            Self::Auto => f.write_str("auto"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ComputeBudget {
+    pub priority_fee: Option<PriorityFee>,
+    pub compute_units: Option<u32>,
+}
+
+/// Upper bound for a transaction's compute unit limit.
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// Percentile of recent prioritization fees used by `--priority-fee auto`.
+const AUTO_PRIORITY_FEE_PERCENTILE: usize = 75;
+/// `getRecentPrioritizationFees` accepts at most this many account keys.
+const MAX_PRIORITIZATION_FEE_ACCOUNTS: usize = 128;
+
+/// Prepends ComputeBudget instructions to `instructions` according to `budget`.
+///
+/// Without a priority fee or an explicit compute unit limit the instructions are returned as-is.
+/// When only a priority fee is set, the compute unit limit is sized from a simulation so the fee
+/// is not paid on the default 200k-per-instruction limit.
+pub async fn with_compute_budget(
+    client: &RpcClient,
+    payer: &Keypair,
+    instructions: Vec<Instruction>,
+    budget: &ComputeBudget,
+) -> Result<Vec<Instruction>> {
+    if budget.priority_fee.is_none() && budget.compute_units.is_none() {
+        return Ok(instructions);
+    }
+
+    let micro_lamports = match budget.priority_fee {
+        Some(PriorityFee::MicroLamports(v)) => Some(v),
+        Some(PriorityFee::Auto) => Some(estimate_priority_fee(client, &instructions).await?),
+        None => None,
+    };
+
+    let compute_units = match budget.compute_units {
+        Some(v) => v,
+        None => {
+            let consumed =
+                simulate_compute_units(client, payer, &instructions, micro_lamports).await?;
+            compute_unit_limit_with_margin(consumed)
+        }
+    };
+
+    let mut out = Vec::with_capacity(instructions.len() + 2);
+    out.push(ComputeBudgetInstruction::set_compute_unit_limit(
+        compute_units,
+    ));
+    if let Some(micro_lamports) = micro_lamports {
+        out.push(ComputeBudgetInstruction::set_compute_unit_price(
+            micro_lamports,
+        ));
+    }
+    out.extend(instructions);
+    Ok(out)
+}
+
+async fn estimate_priority_fee(client: &RpcClient, instructions: &[Instruction]) -> Result<u64> {
+    let mut writable: Vec<Pubkey> = Vec::new();
+    for meta in instructions.iter().flat_map(|ix| ix.accounts.iter()) {
+        if meta.is_writable && !writable.contains(&meta.pubkey) {
+            writable.push(meta.pubkey);
+        }
+    }
+    writable.truncate(MAX_PRIORITIZATION_FEE_ACCOUNTS);
+
+    let fees = client
+        .get_recent_prioritization_fees(&writable)
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch recent prioritization fees: {err}"))?;
+    let mut values: Vec<u64> = fees.iter().map(|f| f.prioritization_fee).collect();
+    Ok(fee_percentile(&mut values, AUTO_PRIORITY_FEE_PERCENTILE))
+}
+
+async fn simulate_compute_units(
+    client: &RpcClient,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    micro_lamports: Option<u64>,
+) -> Result<u64> {
+    let mut sim_instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+        MAX_COMPUTE_UNIT_LIMIT,
+    )];
+    if let Some(micro_lamports) = micro_lamports {
+        sim_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            micro_lamports,
+        ));
+    }
+    sim_instructions.extend_from_slice(instructions);
+
+    let recent_blockhash = client.get_latest_blockhash().await?;
     let tx = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&from.pubkey()),
-        &[from],
+        &sim_instructions,
+        Some(&payer.pubkey()),
+        &[payer],
         recent_blockhash,
     );
+    let config = RpcSimulateTransactionConfig {
+        replace_recent_blockhash: true,
+        commitment: Some(client.commitment()),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = client
+        .simulate_transaction_with_config(&tx, config)
+        .await?
+        .value;
+    if let Some(err) = result.err {
+        return Err(wmgr_error!(
+            SimulationFailed,
+            "Simulation for compute unit sizing failed: {err:?}"
+        ));
+    }
+    result
+        .units_consumed
+        .ok_or_else(|| wmgr_error!(Rpc, "Simulation did not report consumed compute units"))
+}
+
+/// Adds 10% headroom (at least 1,000 CU) to a simulated compute unit count.
+fn compute_unit_limit_with_margin(consumed: u64) -> u32 {
+    let margin = (consumed / 10).max(1_000);
+    let limit = consumed.saturating_add(margin);
+    limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+}
+
+fn fee_percentile(values: &mut [u64], percentile: usize) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    let index = (values.len() - 1) * percentile.min(100) / 100;
+    values[index]
+}
+
+/// Interval between rebroadcasts of a pending transaction.
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+/// Attempts to fetch the landed transaction's metadata (fee, compute units, balances).
+const TRANSACTION_META_ATTEMPTS: usize = 5;
+
+/// A transaction signed once against a specific blockhash.
+pub struct PreparedTransaction {
+    pub transaction: Transaction,
+    pub last_valid_block_height: u64,
+}
+
+impl PreparedTransaction {
+    pub fn signature(&self) -> Signature {
+        self.transaction.signatures[0]
+    }
+}
+
+pub struct LandedTransaction {
+    pub signature: Signature,
+    pub slot: u64,
+    pub fee: Option<u64>,
+    pub compute_units: Option<u64>,
+    /// Account state before and after, when the metadata could be fetched.
+    pub accounts: Option<ConfirmedAccounts>,
+}
+
+/// Lamports and token balances of a landed transaction's accounts before and after it, as
+/// recorded in its metadata.
+pub struct ConfirmedAccounts {
+    keys: Vec<Pubkey>,
+    before: Vec<Option<AccountSnapshot>>,
+    after: Vec<Option<AccountSnapshot>>,
+}
+
+impl ConfirmedAccounts {
+    /// Snapshots of `addresses` before and after the transaction, in the shape
+    /// [`snapshot_accounts`] returns. Accounts the transaction did not touch are `None`.
+    pub fn snapshots(
+        &self,
+        addresses: &[Pubkey],
+    ) -> (Vec<Option<AccountSnapshot>>, Vec<Option<AccountSnapshot>>) {
+        let position = |address: &Pubkey| self.keys.iter().position(|key| key == address);
+        addresses
+            .iter()
+            .map(|address| match position(address) {
+                Some(index) => (self.before[index], self.after[index]),
+                None => (None, None),
+            })
+            .unzip()
+    }
+
+    fn from_meta(keys: Vec<Pubkey>, meta: &UiTransactionStatusMeta) -> Self {
+        let snapshots =
+            |lamports: &[u64], tokens: &OptionSerializer<Vec<UiTransactionTokenBalance>>| {
+                let tokens = match tokens {
+                    OptionSerializer::Some(tokens) => tokens.as_slice(),
+                    _ => &[],
+                };
+                (0..keys.len())
+                    .map(|index| {
+                        let lamports = lamports.get(index).copied().unwrap_or_default();
+                        let token = tokens
+                            .iter()
+                            .find(|token| token.account_index as usize == index);
+                        (lamports > 0).then(|| AccountSnapshot {
+                            lamports,
+                            token_amount: token
+                                .and_then(|token| token.ui_token_amount.amount.parse().ok()),
+                            is_native: token.is_some_and(|token| {
+                                token.mint == spl_token::native_mint::id().to_string()
+                            }),
+                        })
+                    })
+                    .collect()
+            };
+        Self {
+            before: snapshots(&meta.pre_balances, &meta.pre_token_balances),
+            after: snapshots(&meta.post_balances, &meta.post_token_balances),
+            keys,
+        }
+    }
+}
+
+/// Why a submitted transaction did not succeed.
+#[derive(Debug)]
+pub enum SubmitError {
+    /// The blockhash expired before the transaction was seen by the cluster. It is safe to retry.
+    Expired {
+        signature: Signature,
+        last_valid_block_height: u64,
+    },
+    /// The transaction was included in a block but its execution failed. Fees were charged.
+    Failed {
+        signature: Signature,
+        slot: u64,
+        err: TransactionError,
+    },
+    /// The transaction was broadcast, or may have been, but the RPC stopped answering before its
+    /// status was known. It can still land until `last_valid_block_height`; retrying before
+    /// checking `signature` may execute it twice.
+    Unconfirmed {
+        signature: Signature,
+        last_valid_block_height: u64,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Expired {
+                signature,
+                last_valid_block_height,
+            } => write!(
+                f,
+                "Transaction expired, not landed: blockhash passed block height {last_valid_block_height} (signature {signature}). It is safe to retry."
+            ),
+            Self::Failed {
+                signature,
+                slot,
+                err,
+            } => write!(
+                f,
+                "Transaction failed on chain in slot {slot}: {err} (signature {signature})"
+            ),
+            Self::Unconfirmed {
+                signature,
+                last_valid_block_height,
+                reason,
+            } => write!(
+                f,
+                "Transaction sent but not confirmed: {reason} (signature {signature}). It may still land until block height {last_valid_block_height}; check the signature before retrying."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+/// Signs `instructions` once with a fresh blockhash.
+pub async fn prepare_transaction(
+    client: &RpcClient,
+    payer: &Keypair,
+    instructions: &[Instruction],
+) -> Result<PreparedTransaction> {
+    let (blockhash, last_valid_block_height) = client
+        .get_latest_blockhash_with_commitment(client.commitment())
+        .await?;
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        blockhash,
+    );
+    Ok(PreparedTransaction {
+        transaction,
+        last_valid_block_height,
+    })
+}
+
+/// Re-signs with a fresh blockhash if `prepared` can no longer land (e.g. after a long prompt).
+pub async fn refresh_if_expired(
+    client: &RpcClient,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    prepared: PreparedTransaction,
+) -> Result<PreparedTransaction> {
+    if client.get_block_height().await? > prepared.last_valid_block_height {
+        return prepare_transaction(client, payer, instructions).await;
+    }
+    Ok(prepared)
+}
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    Ok(sig.to_string())
+/// Maps a failed simulation to a typed error, telling underfunded transfers apart.
+pub fn simulation_error(err: &TransactionError, instructions: &[Instruction]) -> anyhow::Error {
+    // Custom error 1 is `ResultWithNegativeLamports` for the System program and
+    // `InsufficientFunds` for both token programs.
+    let underfunded = is_fee_payer_underfunded(err)
+        || match err {
+            TransactionError::InstructionError(_, InstructionError::InsufficientFunds) => true,
+            TransactionError::InstructionError(index, InstructionError::Custom(1)) => {
+                instructions.get(*index as usize).is_some_and(|ix| {
+                    ix.program_id == solana_system_interface::program::ID
+                        || ix.program_id == spl_token::id()
+                        || ix.program_id == spl_token_2022_interface::id()
+                })
+            }
+            _ => false,
+        };
+    if underfunded {
+        wmgr_error!(InsufficientFunds, "Insufficient funds: {err}")
+    } else {
+        wmgr_error!(SimulationFailed, "Simulation failed: {err}")
+    }
 }
 
-pub fn transfer_spl_token(
+/// Lamports and token balance of an account, captured before a simulation or read back from it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountSnapshot {
+    pub lamports: u64,
+    /// Balance when the account is an SPL Token or Token-2022 account.
+    pub token_amount: Option<u64>,
+    /// Wrapped SOL accounts hold their token balance as lamports on top of rent.
+    pub is_native: bool,
+}
+
+impl AccountSnapshot {
+    /// `None` for missing or closed (zero-lamport) accounts.
+    pub fn from_account(account: &Account) -> Option<Self> {
+        if account.lamports == 0 {
+            return None;
+        }
+        // Token-2022 accounts share the SPL Token layout, with extensions after it.
+        let is_token_account =
+            account.owner == spl_token::id() || account.owner == spl_token_2022_interface::id();
+        let token = is_token_account
+            .then(|| account.data.get(..TokenAccount::LEN))
+            .flatten()
+            .and_then(|data| TokenAccount::unpack(data).ok());
+        Some(Self {
+            lamports: account.lamports,
+            token_amount: token.map(|token| token.amount),
+            is_native: token.is_some_and(|token| token.is_native()),
+        })
+    }
+
+    /// Lamports held for rent exemption rather than as a balance.
+    pub fn rent_lamports(&self) -> u64 {
+        if self.is_native {
+            self.lamports
+                .saturating_sub(self.token_amount.unwrap_or_default())
+        } else {
+            self.lamports
+        }
+    }
+}
+
+pub async fn snapshot_accounts(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<Vec<Option<AccountSnapshot>>> {
+    let accounts = client.get_multiple_accounts(addresses).await?;
+    Ok(accounts
+        .iter()
+        .map(|account| account.as_ref().and_then(AccountSnapshot::from_account))
+        .collect())
+}
+
+/// Simulates the exact signed transaction that will be submitted, returning the post-state of
+/// `addresses` (empty to skip).
+pub async fn simulate_prepared(
+    client: &RpcClient,
+    prepared: &PreparedTransaction,
+    addresses: &[Pubkey],
+) -> Result<(RpcSimulateTransactionResult, Vec<Option<AccountSnapshot>>)> {
+    let accounts = (!addresses.is_empty()).then(|| RpcSimulateTransactionAccountsConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        addresses: addresses.iter().map(Pubkey::to_string).collect(),
+    });
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: true,
+        commitment: Some(client.commitment()),
+        accounts,
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = client
+        .simulate_transaction_with_config(&prepared.transaction, config)
+        .await?
+        .value;
+    let snapshots = result
+        .accounts
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|account| {
+            account
+                .as_ref()
+                .and_then(|account| account.decode::<Account>())
+                .and_then(|account| AccountSnapshot::from_account(&account))
+        })
+        .collect();
+    Ok((result, snapshots))
+}
+
+/// Broadcasts a signed transaction and rebroadcasts it until it reaches the client's commitment
+/// or its blockhash expires.
+///
+/// The transaction is never re-signed, so a rebroadcast cannot land twice. Failures other than a
+/// preflight rejection are reported as [`SubmitError`].
+pub async fn submit_prepared(
+    client: &RpcClient,
+    prepared: &PreparedTransaction,
+) -> Result<LandedTransaction> {
+    let signature = prepared.signature();
+    let commitment = client.commitment();
+
+    // The first broadcast runs preflight so obvious failures surface before anything lands.
+    let first = RpcSendTransactionConfig {
+        preflight_commitment: Some(commitment.commitment),
+        max_retries: Some(0),
+        ..RpcSendTransactionConfig::default()
+    };
+    // Past this point the transaction may be on its way to a leader, so RPC failures are
+    // reported as `Unconfirmed` rather than as plain RPC errors.
+    let unconfirmed = |err: ClientError| SubmitError::Unconfirmed {
+        signature,
+        last_valid_block_height: prepared.last_valid_block_height,
+        reason: err.to_string(),
+    };
+    if let Err(err) = client
+        .send_transaction_with_config(&prepared.transaction, first)
+        .await
+    {
+        // A preflight rejection carries the simulated error: nothing was forwarded.
+        if err.get_transaction_error().is_some() {
+            return Err(err.into());
+        }
+        return Err(unconfirmed(err).into());
+    }
+
+    let rebroadcast = RpcSendTransactionConfig {
+        skip_preflight: true,
+        max_retries: Some(0),
+        ..RpcSendTransactionConfig::default()
+    };
+    let slot = loop {
+        sleep(REBROADCAST_INTERVAL).await;
+
+        let statuses = client
+            .get_signature_statuses(&[signature])
+            .await
+            .map_err(unconfirmed)?
+            .value;
+        if let Some(Some(status)) = statuses.into_iter().next() {
+            if let Some(err) = status.err {
+                return Err(SubmitError::Failed {
+                    signature,
+                    slot: status.slot,
+                    err,
+                }
+                .into());
+            }
+            if status.satisfies_commitment(commitment) {
+                break status.slot;
+            }
+            // Seen but not yet at the requested commitment: keep waiting without rebroadcasting.
+            continue;
+        }
+
+        let block_height = client.get_block_height().await.map_err(unconfirmed)?;
+        if block_height > prepared.last_valid_block_height {
+            // One last status check: the transaction may have landed right at the boundary.
+            let statuses = client
+                .get_signature_statuses(&[signature])
+                .await
+                .map_err(unconfirmed)?
+                .value;
+            if let Some(Some(status)) = statuses.into_iter().next() {
+                if let Some(err) = status.err {
+                    return Err(SubmitError::Failed {
+                        signature,
+                        slot: status.slot,
+                        err,
+                    }
+                    .into());
+                }
+                continue;
+            }
+            return Err(SubmitError::Expired {
+                signature,
+                last_valid_block_height: prepared.last_valid_block_height,
+            }
+            .into());
+        }
+
+        // Rebroadcast errors are transient (e.g. the node already saw the transaction).
+        let _ = client
+            .send_transaction_with_config(&prepared.transaction, rebroadcast)
+            .await;
+    };
+
+    let meta = fetch_transaction_meta(client, &signature).await;
+    Ok(LandedTransaction {
+        signature,
+        slot,
+        fee: meta.as_ref().map(|meta| meta.fee),
+        compute_units: meta
+            .as_ref()
+            .and_then(|meta| match meta.compute_units_consumed {
+                OptionSerializer::Some(v) => Some(v),
+                _ => None,
+            }),
+        accounts: meta.as_ref().map(|meta| {
+            ConfirmedAccounts::from_meta(prepared.transaction.message.account_keys.clone(), meta)
+        }),
+    })
+}
+
+/// Where a transaction sent earlier stands, for callers that recorded its signature before
+/// broadcasting and lost track of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureState {
+    /// Reached the client's commitment.
+    Landed { slot: u64 },
+    /// Included in a block, but its execution failed.
+    Failed { slot: u64, err: TransactionError },
+    /// Not seen, and its blockhash has expired: it can no longer land.
+    Expired,
+    /// Not seen yet, or seen below the client's commitment.
+    Pending,
+}
+
+/// Looks up a transaction by `signature`, treating it as expired once the chain is past
+/// `last_valid_block_height` without having seen it.
+pub async fn signature_state(
+    client: &RpcClient,
+    signature: &Signature,
+    last_valid_block_height: u64,
+) -> Result<SignatureState> {
+    // Read the height first: a transaction missing from a status fetched afterwards did not land
+    // before that height.
+    let block_height = client.get_block_height().await?;
+    let status = client
+        .get_signature_statuses_with_history(&[*signature])
+        .await?
+        .value
+        .into_iter()
+        .next()
+        .flatten();
+    Ok(match status {
+        Some(status) => match status.err {
+            Some(err) => SignatureState::Failed {
+                slot: status.slot,
+                err,
+            },
+            None if status.satisfies_commitment(client.commitment()) => {
+                SignatureState::Landed { slot: status.slot }
+            }
+            None => SignatureState::Pending,
+        },
+        None if block_height > last_valid_block_height => SignatureState::Expired,
+        None => SignatureState::Pending,
+    })
+}
+
+/// Best-effort lookup of a landed transaction's metadata.
+async fn fetch_transaction_meta(
+    client: &RpcClient,
+    signature: &Signature,
+) -> Option<UiTransactionStatusMeta> {
+    // getTransaction does not accept `processed`.
+    let commitment = if client.commitment().is_finalized() {
+        CommitmentConfig::finalized()
+    } else {
+        CommitmentConfig::confirmed()
+    };
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+    };
+    for attempt in 0..TRANSACTION_META_ATTEMPTS {
+        if attempt > 0 {
+            sleep(REBROADCAST_INTERVAL).await;
+        }
+        let Ok(tx) = client.get_transaction_with_config(signature, config).await else {
+            continue;
+        };
+        return tx.transaction.meta;
+    }
+    None
+}
+
+/// A transfer built and validated against current chain state, ready for review and submission.
+pub struct SolanaTransfer {
+    pub from: Pubkey,
+    pub recipient: Pubkey,
+    /// Account credited by the transfer: the recipient itself for SOL, its ATA for tokens.
+    pub destination: Pubkey,
+    pub mint: Option<Pubkey>,
+    pub program_label: &'static str,
+    pub amount: u64,
+    pub decimals: u8,
+    /// Token-2022 transfer fee withheld from `amount`.
+    pub transfer_fee: u64,
+    /// Rent the sender pays when the recipient ATA has to be created.
+    pub ata_rent: Option<u64>,
+    pub instructions: Vec<Instruction>,
+}
+
+pub async fn build_sol_transfer(
+    client: &RpcClient,
+    from: &Keypair,
+    to: &str,
+    amount: &str,
+    budget: &ComputeBudget,
+) -> Result<SolanaTransfer> {
+    let to_pubkey = Pubkey::from_str(to)
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid recipient address: {err}"))?;
+    let lamports = parse_amount_to_u64(amount, 9)?;
+
+    let instruction = system_instruction::transfer(&from.pubkey(), &to_pubkey, lamports);
+    let instructions = with_compute_budget(client, from, vec![instruction], budget).await?;
+    Ok(SolanaTransfer {
+        from: from.pubkey(),
+        recipient: to_pubkey,
+        destination: to_pubkey,
+        mint: None,
+        program_label: "System",
+        amount: lamports,
+        decimals: 9,
+        transfer_fee: 0,
+        ata_rent: None,
+        instructions,
+    })
+}
+
+/// Network fee for a prepared transaction (base fee plus priority fee).
+pub async fn get_transaction_fee(
+    client: &RpcClient,
+    prepared: &PreparedTransaction,
+) -> Result<u64> {
+    Ok(client
+        .get_fee_for_message(&prepared.transaction.message)
+        .await?)
+}
+
+pub struct MintInfo {
+    pub program_id: Pubkey,
+    pub decimals: u8,
+    pub transfer_fee: Option<TransferFeeConfig>,
+    pub transfer_hook: bool,
+    pub non_transferable: bool,
+    pub paused: bool,
+    /// Size of a new token account for this mint, including required extensions.
+    pub account_len: usize,
+}
+
+/// `Token-2022` or `SPL Token`, for the program that owns a mint or token account.
+pub fn token_program_label(program_id: &Pubkey) -> &'static str {
+    if *program_id == spl_token_2022_interface::id() {
+        "Token-2022"
+    } else {
+        "SPL Token"
+    }
+}
+
+pub async fn build_spl_transfer(
     client: &RpcClient,
     from: &Keypair,
     to: &str,
     amount: &str,
     mint: &str,
-) -> Result<String> {
-    let to_owner =
-        Pubkey::from_str(to).map_err(|err| anyhow!("Invalid recipient address: {err}"))?;
-    let mint_pubkey =
-        Pubkey::from_str(mint).map_err(|err| anyhow!("Invalid mint address: {err}"))?;
+    memo: Option<&str>,
+    budget: &ComputeBudget,
+) -> Result<SolanaTransfer> {
+    let to_owner = Pubkey::from_str(to)
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid recipient address: {err}"))?;
+    let mint_pubkey = Pubkey::from_str(mint)
+        .map_err(|err| wmgr_error!(InvalidArgument, "Invalid mint address: {err}"))?;
 
-    let decimals = get_mint_decimals(client, &mint_pubkey).unwrap_or(6);
+    let mint_info = get_mint_info(client, &mint_pubkey).await?;
+    check_transferable(&mint_pubkey, &mint_info)?;
+
+    let decimals = mint_info.decimals;
     let amount_u64 = parse_amount_to_u64(amount, decimals)?;
+    let to_ata = get_associated_token_address_with_program_id(
+        &to_owner,
+        &mint_pubkey,
+        &mint_info.program_id,
+    );
 
-    let from_ata = get_associated_token_address(&from.pubkey(), &mint_pubkey);
-    let to_ata = get_associated_token_address(&to_owner, &mint_pubkey);
+    let recipient_account = client
+        .get_account_with_commitment(&to_ata, client.commitment())
+        .await?
+        .value;
+    let ata_rent = match recipient_account.as_ref() {
+        Some(_) => None,
+        None => Some(
+            client
+                .get_minimum_balance_for_rent_exemption(mint_info.account_len)
+                .await?,
+        ),
+    };
+    let memo_required = match recipient_account.as_ref() {
+        Some(account) => requires_incoming_memo(&to_ata, account)?,
+        None => false,
+    };
+    if memo_required && memo.is_none() {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Recipient token account {to_ata} requires a memo on incoming transfers (use --memo)"
+        ));
+    }
 
-    let mut instructions = Vec::new();
-    instructions.push(create_associated_token_account_idempotent(
-        &from.pubkey(),
-        &from.pubkey(),
-        &mint_pubkey,
-        &spl_token::id(),
-    ));
-    instructions.push(create_associated_token_account_idempotent(
+    let fee = match mint_info.transfer_fee.as_ref() {
+        Some(config) => {
+            let epoch = client.get_epoch_info().await?.epoch;
+            epoch_transfer_fee(config, epoch, amount_u64)?
+        }
+        None => 0,
+    };
+
+    let instructions = spl_transfer_instructions(
         &from.pubkey(),
         &to_owner,
         &mint_pubkey,
-        &spl_token::id(),
-    ));
-    instructions.push(transfer_checked(
-        &spl_token::id(),
-        &from_ata,
-        &mint_pubkey,
-        &to_ata,
-        &from.pubkey(),
-        &[],
+        &mint_info,
         amount_u64,
+        fee,
+        memo,
+    )?;
+    let instructions = with_compute_budget(client, from, instructions, budget).await?;
+
+    Ok(SolanaTransfer {
+        from: from.pubkey(),
+        recipient: to_owner,
+        destination: to_ata,
+        mint: Some(mint_pubkey),
+        program_label: token_program_label(&mint_info.program_id),
+        amount: amount_u64,
         decimals,
-    )?);
+        transfer_fee: fee,
+        ata_rent,
+        instructions,
+    })
+}
 
-    let recent_blockhash = client.get_latest_blockhash()?;
-    let tx = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&from.pubkey()),
-        &[from],
-        recent_blockhash,
-    );
+/// Rejects mints whose transfers cannot be built here.
+fn check_transferable(mint: &Pubkey, info: &MintInfo) -> Result<()> {
+    if info.non_transferable {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Mint {mint} is non-transferable"
+        ));
+    }
+    if info.paused {
+        return Err(wmgr_error!(InvalidArgument, "Mint {mint} is paused"));
+    }
+    if info.transfer_hook {
+        return Err(wmgr_error!(
+            InvalidArgument,
+            "Mint {mint} uses a transfer hook, which is not supported"
+        ));
+    }
+    Ok(())
+}
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    Ok(sig.to_string())
+/// The fee withheld from a transfer of `amount` in `epoch`.
+fn epoch_transfer_fee(config: &TransferFeeConfig, epoch: u64, amount: u64) -> Result<u64> {
+    config
+        .calculate_epoch_fee(epoch, amount)
+        .ok_or_else(|| wmgr_error!(InvalidArgument, "Failed to calculate transfer fee"))
 }
 
-pub fn get_balances(client: &RpcClient, owner: &Pubkey, mint: &Pubkey) -> Result<BalanceResult> {
-    let lamports = client.get_balance(owner)?;
+/// Creates the recipient's ATA, then adds the memo, which must come before a transfer into an
+/// account that requires one, then the checked transfer, stating `fee` when the mint charges
+/// one.
+fn spl_transfer_instructions(
+    from: &Pubkey,
+    to_owner: &Pubkey,
+    mint: &Pubkey,
+    info: &MintInfo,
+    amount: u64,
+    fee: u64,
+    memo: Option<&str>,
+) -> Result<Vec<Instruction>> {
+    let program_id = info.program_id;
+    let from_ata = get_associated_token_address_with_program_id(from, mint, &program_id);
+    let to_ata = get_associated_token_address_with_program_id(to_owner, mint, &program_id);
+
+    let mut instructions = vec![create_associated_token_account_idempotent(
+        from,
+        to_owner,
+        mint,
+        &program_id,
+    )];
+    if let Some(memo) = memo {
+        instructions.push(build_memo_instruction(memo, from));
+    }
+    if info.transfer_fee.is_some() {
+        instructions.push(transfer_checked_with_fee(
+            &program_id,
+            &from_ata,
+            mint,
+            &to_ata,
+            from,
+            &[],
+            amount,
+            info.decimals,
+            fee,
+        )?);
+    } else {
+        instructions.push(transfer_checked(
+            &program_id,
+            &from_ata,
+            mint,
+            &to_ata,
+            from,
+            &[],
+            amount,
+            info.decimals,
+        )?);
+    }
+    Ok(instructions)
+}
+
+pub async fn get_balances(
+    client: &RpcClient,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Result<BalanceResult> {
+    let lamports = client.get_balance(owner).await?;
     let sol = format_integer_amount(lamports as u128, 9);
 
-    let decimals = get_mint_decimals(client, mint).unwrap_or(6);
+    let decimals = get_mint_info(client, mint).await?.decimals;
 
     let ata = get_associated_token_address(owner, mint);
-    let usdc_raw = match client.get_account(&ata) {
+    let usdc_raw = match client.get_account(&ata).await {
         Ok(account) => TokenAccount::unpack(&account.data)?.amount,
         Err(_) => 0,
     };
@@ -125,8 +963,363 @@ pub fn get_balances(client: &RpcClient, owner: &Pubkey, mint: &Pubkey) -> Result
     })
 }
 
-fn get_mint_decimals(client: &RpcClient, mint: &Pubkey) -> Result<u8> {
-    let account = client.get_account(mint)?;
-    let mint_state = Mint::unpack(&account.data)?;
-    Ok(mint_state.decimals)
+pub struct TokenAccountEntry {
+    pub address: Pubkey,
+    pub amount: u64,
+    pub is_ata: bool,
+    pub frozen: bool,
+}
+
+pub struct TokenHolding {
+    pub mint: Pubkey,
+    pub program_id: Pubkey,
+    pub decimals: u8,
+    pub total: u128,
+    pub accounts: Vec<TokenAccountEntry>,
+}
+
+pub async fn get_token_holdings(client: &RpcClient, owner: &Pubkey) -> Result<Vec<TokenHolding>> {
+    let mut holdings: BTreeMap<Pubkey, TokenHolding> = BTreeMap::new();
+
+    for program_id in [spl_token::id(), spl_token_2022_interface::id()] {
+        let accounts = client
+            .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(program_id))
+            .await
+            .map_err(|err| wmgr_error!(Rpc, "Failed to fetch token accounts: {err}"))?;
+
+        for keyed in accounts {
+            let address = Pubkey::from_str(&keyed.pubkey)
+                .map_err(|err| wmgr_error!(Rpc, "Invalid token account address: {err}"))?;
+            let token_account = parse_token_account(&keyed.account.data).map_err(|err| {
+                wmgr_error!(Rpc, "Failed to decode token account {address}: {err}")
+            })?;
+            let mint = Pubkey::from_str(&token_account.mint).map_err(|err| {
+                wmgr_error!(Rpc, "Invalid mint in token account {address}: {err}")
+            })?;
+            let amount = u64::from_str(&token_account.token_amount.amount).map_err(|err| {
+                wmgr_error!(Rpc, "Invalid amount in token account {address}: {err}")
+            })?;
+
+            let ata = get_associated_token_address_with_program_id(owner, &mint, &program_id);
+            let entry = TokenAccountEntry {
+                address,
+                amount,
+                is_ata: address == ata,
+                frozen: token_account.state == UiAccountState::Frozen,
+            };
+
+            let holding = holdings.entry(mint).or_insert_with(|| TokenHolding {
+                mint,
+                program_id,
+                decimals: token_account.token_amount.decimals,
+                total: 0,
+                accounts: Vec::new(),
+            });
+            holding.total += amount as u128;
+            holding.accounts.push(entry);
+        }
+    }
+
+    Ok(holdings.into_values().collect())
+}
+
+fn parse_token_account(data: &UiAccountData) -> Result<UiTokenAccount> {
+    let UiAccountData::Json(parsed) = data else {
+        return Err(wmgr_error!(Rpc, "expected jsonParsed account data"));
+    };
+    let info = parsed
+        .parsed
+        .get("info")
+        .cloned()
+        .ok_or_else(|| wmgr_error!(Rpc, "missing parsed account info"))?;
+    Ok(serde_json::from_value(info)?)
+}
+
+pub async fn get_mint_info(client: &RpcClient, mint: &Pubkey) -> Result<MintInfo> {
+    let account = client
+        .get_account(mint)
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch mint {mint}: {err}"))?;
+    mint_info_from_account(mint, &account)
+}
+
+fn mint_info_from_account(mint: &Pubkey, account: &Account) -> Result<MintInfo> {
+    let program_id = account.owner;
+
+    if program_id == spl_token::id() {
+        let mint_state = Mint::unpack(&account.data)
+            .map_err(|err| wmgr_error!(InvalidArgument, "Failed to decode mint {mint}: {err}"))?;
+        return Ok(MintInfo {
+            program_id,
+            decimals: mint_state.decimals,
+            transfer_fee: None,
+            transfer_hook: false,
+            non_transferable: false,
+            paused: false,
+            account_len: TokenAccount::LEN,
+        });
+    }
+
+    if program_id == spl_token_2022_interface::id() {
+        let state = StateWithExtensions::<Token2022Mint>::unpack(&account.data).map_err(|err| {
+            wmgr_error!(
+                InvalidArgument,
+                "Failed to decode Token-2022 mint {mint}: {err}"
+            )
+        })?;
+        let transfer_fee = state.get_extension::<TransferFeeConfig>().ok().copied();
+        let transfer_hook = state
+            .get_extension::<TransferHook>()
+            .map(|hook| Option::<Pubkey>::from(hook.program_id).is_some())
+            .unwrap_or(false);
+        let non_transferable = state.get_extension::<NonTransferable>().is_ok();
+        let paused = state
+            .get_extension::<PausableConfig>()
+            .map(|cfg| bool::from(cfg.paused))
+            .unwrap_or(false);
+        // The ATA program always initializes new Token-2022 accounts as immutable-owner.
+        let mint_extensions = state
+            .get_extension_types()
+            .map_err(|err| wmgr_error!(Rpc, "Failed to read extensions of mint {mint}: {err}"))?;
+        let mut account_extensions =
+            ExtensionType::get_required_init_account_extensions(&mint_extensions);
+        if !account_extensions.contains(&ExtensionType::ImmutableOwner) {
+            account_extensions.push(ExtensionType::ImmutableOwner);
+        }
+        let account_len =
+            ExtensionType::try_calculate_account_len::<Token2022Account>(&account_extensions)
+                .map_err(|err| {
+                    wmgr_error!(Rpc, "Failed to size token account for mint {mint}: {err}")
+                })?;
+        return Ok(MintInfo {
+            program_id,
+            decimals: state.base.decimals,
+            transfer_fee,
+            transfer_hook,
+            non_transferable,
+            paused,
+            account_len,
+        });
+    }
+
+    Err(wmgr_error!(
+        InvalidArgument,
+        "Mint {mint} is not owned by a token program (owner: {program_id})"
+    ))
+}
+
+fn requires_incoming_memo(token_account: &Pubkey, account: &Account) -> Result<bool> {
+    if account.owner != spl_token_2022_interface::id() {
+        return Ok(false);
+    }
+    let state = StateWithExtensions::<Token2022Account>::unpack(&account.data)
+        .map_err(|err| wmgr_error!(Rpc, "Failed to decode token account {token_account}: {err}"))?;
+    Ok(state
+        .get_extension::<MemoTransfer>()
+        .map(|ext| bool::from(ext.require_incoming_transfer_memos))
+        .unwrap_or(false))
+}
+
+fn build_memo_instruction(memo: &str, signer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: MEMO_PROGRAM_ID,
+        accounts: vec![AccountMeta::new_readonly(*signer, true)],
+        data: memo.as_bytes().to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{error_code, ErrorCode};
+
+    #[test]
+    fn compute_unit_margin() {
+        assert_eq!(compute_unit_limit_with_margin(5_000), 6_000);
+        assert_eq!(compute_unit_limit_with_margin(100_000), 110_000);
+        assert_eq!(
+            compute_unit_limit_with_margin(1_390_000),
+            MAX_COMPUTE_UNIT_LIMIT
+        );
+    }
+
+    #[test]
+    fn parses_priority_fee() {
+        assert_eq!(
+            PriorityFee::from_str("5000").unwrap(),
+            PriorityFee::MicroLamports(5000)
+        );
+        assert_eq!(PriorityFee::from_str("AUTO").unwrap(), PriorityFee::Auto);
+        assert!(PriorityFee::from_str("1.5").is_err());
+    }
+
+    /// A Token-2022 mint with a 1% fee capped at 500 units, rising to 2% from epoch 10, and
+    /// optionally a transfer hook.
+    fn fee_mint(hook: bool) -> Account {
+        use spl_token_2022_interface::extension::transfer_fee::TransferFee;
+        use spl_token_2022_interface::extension::{
+            BaseStateWithExtensionsMut, StateWithExtensionsMut,
+        };
+
+        let mut extensions = vec![ExtensionType::TransferFeeConfig];
+        if hook {
+            extensions.push(ExtensionType::TransferHook);
+        }
+        let len = ExtensionType::try_calculate_account_len::<Token2022Mint>(&extensions).unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<Token2022Mint>::unpack_uninitialized(&mut data).unwrap();
+        let fees = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        fees.older_transfer_fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: 500.into(),
+            transfer_fee_basis_points: 100.into(),
+        };
+        fees.newer_transfer_fee = TransferFee {
+            epoch: 10.into(),
+            maximum_fee: 500.into(),
+            transfer_fee_basis_points: 200.into(),
+        };
+        if hook {
+            let program_id = Some(Pubkey::new_unique()).try_into().unwrap();
+            state
+                .init_extension::<TransferHook>(true)
+                .unwrap()
+                .program_id = program_id;
+        }
+        state.base = Token2022Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Token2022Mint::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        Account {
+            lamports: 1,
+            data,
+            owner: spl_token_2022_interface::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn transfer_fee_follows_the_epoch_and_cap() {
+        let mint = Pubkey::new_unique();
+        let info = mint_info_from_account(&mint, &fee_mint(false)).unwrap();
+        check_transferable(&mint, &info).unwrap();
+        let config = info.transfer_fee.as_ref().unwrap();
+
+        assert_eq!(epoch_transfer_fee(config, 5, 10_000).unwrap(), 100);
+        assert_eq!(epoch_transfer_fee(config, 10, 10_000).unwrap(), 200);
+        assert_eq!(epoch_transfer_fee(config, 10, 1_000_000).unwrap(), 500);
+        // Rounds up: 1% of 150 is 1.5.
+        assert_eq!(epoch_transfer_fee(config, 0, 150).unwrap(), 2);
+    }
+
+    #[test]
+    fn rejects_transfer_hook_mints() {
+        let mint = Pubkey::new_unique();
+        let info = mint_info_from_account(&mint, &fee_mint(true)).unwrap();
+        assert!(info.transfer_hook);
+        let err = check_transferable(&mint, &info).unwrap_err();
+        assert_eq!(
+            crate::error::error_code(&err),
+            crate::error::ErrorCode::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn memo_precedes_the_fee_transfer() {
+        let (from, to, mint) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let info = mint_info_from_account(&mint, &fee_mint(false)).unwrap();
+        let program_id = spl_token_2022_interface::id();
+
+        let instructions =
+            spl_transfer_instructions(&from, &to, &mint, &info, 10_000, 100, Some("invoice 7"))
+                .unwrap();
+        let expected = transfer_checked_with_fee(
+            &program_id,
+            &get_associated_token_address_with_program_id(&from, &mint, &program_id),
+            &mint,
+            &get_associated_token_address_with_program_id(&to, &mint, &program_id),
+            &from,
+            &[],
+            10_000,
+            6,
+            100,
+        )
+        .unwrap();
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[1].program_id, MEMO_PROGRAM_ID);
+        assert_eq!(instructions[1].data, b"invoice 7");
+        assert!(instructions[1].accounts[0].is_signer);
+        assert_eq!(instructions[2], expected);
+
+        let plain = MintInfo {
+            transfer_fee: None,
+            ..info
+        };
+        let instructions =
+            spl_transfer_instructions(&from, &to, &mint, &plain, 10_000, 0, None).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_ne!(instructions[1].data, expected.data);
+    }
+
+    #[test]
+    fn detects_required_incoming_memos() {
+        use spl_token_2022_interface::extension::{
+            BaseStateWithExtensionsMut, StateWithExtensionsMut,
+        };
+        use spl_token_2022_interface::state::AccountState;
+
+        let len = ExtensionType::try_calculate_account_len::<Token2022Account>(&[
+            ExtensionType::MemoTransfer,
+        ])
+        .unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<Token2022Account>::unpack_uninitialized(&mut data).unwrap();
+        state
+            .init_extension::<MemoTransfer>(true)
+            .unwrap()
+            .require_incoming_transfer_memos = true.into();
+        state.base = Token2022Account {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            state: AccountState::Initialized,
+            ..Token2022Account::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        let address = Pubkey::new_unique();
+        let mut account = Account {
+            lamports: 1,
+            data,
+            owner: spl_token_2022_interface::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        assert!(requires_incoming_memo(&address, &account).unwrap());
+        account.owner = spl_token::id();
+        assert!(!requires_incoming_memo(&address, &account).unwrap());
+
+        // Chain data that does not decode is an RPC-side problem, not a bug.
+        account.owner = spl_token_2022_interface::id();
+        account.data.truncate(10);
+        let err = requires_incoming_memo(&address, &account).unwrap_err();
+        assert_eq!(error_code(&err), ErrorCode::Rpc);
+    }
+
+    #[test]
+    fn fee_percentile_picks_upper_quartile() {
+        let mut fees = vec![0, 0, 100, 200, 50, 0, 1_000, 10];
+        assert_eq!(fee_percentile(&mut fees, 75), 100);
+        assert_eq!(fee_percentile(&mut [], 75), 0);
+    }
 }
@@ -0,0 +1,360 @@
+use anyhow::Result;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_system_interface::instruction as system_instruction;
+use spl_associated_token_account_interface::address::get_associated_token_address_with_program_id;
+use spl_associated_token_account_interface::instruction::create_associated_token_account_idempotent;
+
+use crate::core::amm_math::AmmCurve;
+use crate::core::price::Price;
+use crate::error::wmgr_error;
+use crate::infra::orca::{self, Whirlpool, WHIRLPOOL_PROGRAM};
+use crate::infra::raydium::{self, RaydiumPool};
+use crate::infra::wsol::{create_temp_wsol, temp_wsol_address, WsolContext};
+
+/// A DEX wmgr can route a swap through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Venue {
+    Raydium,
+    Orca,
+}
+
+impl Venue {
+    pub fn label(self) -> &'static str {
+        match self {
+            Venue::Raydium => "Raydium",
+            Venue::Orca => "Orca",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Venue::Raydium => "raydium",
+            Venue::Orca => "orca",
+        }
+    }
+}
+
+/// A pool on any supported venue.
+#[allow(clippy::large_enum_variant)]
+pub enum Pool {
+    Raydium(RaydiumPool),
+    Orca(Whirlpool),
+}
+
+/// A pool seen from the input side of a swap.
+pub struct OrientedPool {
+    pub decimals_in: u8,
+    pub decimals_out: u8,
+    curve: Box<dyn AmmCurve>,
+}
+
+impl Pool {
+    pub fn id(&self) -> Pubkey {
+        match self {
+            Pool::Raydium(pool) => pool.id(),
+            Pool::Orca(pool) => pool.id,
+        }
+    }
+
+    pub fn venue(&self) -> Venue {
+        match self {
+            Pool::Raydium(_) => Venue::Raydium,
+            Pool::Orca(_) => Venue::Orca,
+        }
+    }
+
+    /// Pool type within its venue.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Pool::Raydium(pool) => pool.kind(),
+            Pool::Orca(_) => "Whirlpool",
+        }
+    }
+
+    /// Venue and pool type, e.g. `Raydium CLMM`.
+    pub fn label(&self) -> String {
+        format!("{} {}", self.venue().label(), self.kind())
+    }
+
+    fn mints(&self) -> [Pubkey; 2] {
+        match self {
+            Pool::Raydium(pool) => pool.mints(),
+            Pool::Orca(pool) => [pool.mint_a.mint, pool.mint_b.mint],
+        }
+    }
+
+    pub fn contains(&self, mint: &Pubkey) -> bool {
+        self.mints().contains(mint)
+    }
+
+    pub fn other_mint(&self, mint: &Pubkey) -> Option<Pubkey> {
+        match self.mints() {
+            [a, b] if a == *mint => Some(b),
+            [a, b] if b == *mint => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Token program of `mint`'s accounts.
+    pub fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        match self {
+            Pool::Raydium(pool) => pool.token_program(mint),
+            Pool::Orca(pool) => pool.token_program(mint),
+        }
+    }
+
+    pub fn orient(&self, input_mint: &Pubkey, output_mint: &Pubkey) -> Result<OrientedPool> {
+        match self {
+            Pool::Raydium(pool) => pool.orient(input_mint, output_mint),
+            Pool::Orca(pool) => {
+                if input_mint == output_mint
+                    || !self.contains(input_mint)
+                    || !self.contains(output_mint)
+                {
+                    return Err(wmgr_error!(
+                        InvalidArgument,
+                        "Pool {} does not trade {input_mint} for {output_mint}",
+                        pool.id
+                    ));
+                }
+                let curve = pool.curve(input_mint);
+                let (decimals_in, decimals_out) = (curve.decimals_in, curve.decimals_out);
+                Ok(OrientedPool::new(
+                    Box::new(curve),
+                    decimals_in,
+                    decimals_out,
+                ))
+            }
+        }
+    }
+
+    fn swap_instruction(
+        &self,
+        owner: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        input_mint: &Pubkey,
+        swap: &SwapKind,
+    ) -> Result<Instruction> {
+        match self {
+            Pool::Raydium(pool) => {
+                pool.swap_instruction(owner, source, destination, input_mint, swap)
+            }
+            Pool::Orca(pool) => {
+                Ok(pool.swap_instruction(owner, source, destination, input_mint, swap))
+            }
+        }
+    }
+}
+
+impl OrientedPool {
+    pub(crate) fn new(curve: Box<dyn AmmCurve>, decimals_in: u8, decimals_out: u8) -> Self {
+        Self {
+            decimals_in,
+            decimals_out,
+            curve,
+        }
+    }
+
+    pub(crate) fn curve(&self) -> &dyn AmmCurve {
+        self.curve.as_ref()
+    }
+
+    /// Output per input at the pool's current price, in UI units.
+    pub fn spot_price(&self) -> Result<Price> {
+        let price = self.curve.spot_price();
+        if price.is_zero() {
+            return Err(wmgr_error!(Rpc, "Pool reserves are empty"));
+        }
+        Ok(price)
+    }
+
+    /// Largest input that keeps price impact within `max_impact_percent`.
+    pub fn max_input_for_impact(&self, max_impact_percent: f64) -> u64 {
+        self.curve.max_input_for_impact(max_impact_percent)
+    }
+}
+
+/// Loads a Raydium or Orca pool from chain state, dispatching on the account's owner.
+pub async fn load_pool(rpc: &RpcClient, pool_id: &Pubkey) -> Result<Pool> {
+    let account = rpc
+        .get_account(pool_id)
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch pool {pool_id}: {err}"))?;
+    Ok(if account.owner == WHIRLPOOL_PROGRAM {
+        Pool::Orca(orca::load_whirlpool(rpc, pool_id, account).await?)
+    } else {
+        Pool::Raydium(raydium::load_pool_account(rpc, pool_id, account).await?)
+    })
+}
+
+/// Looks up a pool for a mint pair on `venue`: the Raydium AMM v4 or CLMM pool holding the most
+/// of `mint_a`, or the Whirlpool with the most liquidity at the current price.
+pub async fn find_pool(
+    rpc: &RpcClient,
+    venue: Venue,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+) -> Result<Pubkey> {
+    match venue {
+        Venue::Raydium => raydium::find_pool(rpc, mint_a, mint_b).await,
+        Venue::Orca => orca::find_whirlpool(rpc, mint_a, mint_b).await,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapKind {
+    BaseIn { amount_in: u64, min_amount_out: u64 },
+    BaseOut { max_amount_in: u64, amount_out: u64 },
+}
+
+impl SwapKind {
+    pub fn input_amount(&self) -> u64 {
+        match self {
+            SwapKind::BaseIn { amount_in, .. } => *amount_in,
+            SwapKind::BaseOut { max_amount_in, .. } => *max_amount_in,
+        }
+    }
+}
+
+/// One swap of a path: `pool` trades `input_mint` for `output_mint`.
+pub struct SwapLeg<'a> {
+    pub pool: &'a Pool,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub swap: SwapKind,
+}
+
+/// Swap instructions plus the token accounts they debit and credit. `intermediates` hold what
+/// one leg of a path passes to the next.
+pub struct SwapPlan {
+    pub instructions: Vec<Instruction>,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub intermediates: Vec<Pubkey>,
+}
+
+/// Builds the swaps of a path in one transaction, each leg spending from the account the
+/// previous one paid into. SOL goes through a temporary wSOL account created and closed in the
+/// same transaction, so an existing wSOL ATA is only touched with `use_wsol` or `keep_wrapped`.
+/// Other tokens between legs pass through the owner's ATA.
+pub fn build_path_instructions(
+    legs: Vec<SwapLeg>,
+    owner: &Pubkey,
+    wsol: &WsolContext,
+) -> Result<SwapPlan> {
+    let (Some(first), Some(last)) = (legs.first(), legs.last()) else {
+        return Err(wmgr_error!(Internal, "A swap needs at least one pool"));
+    };
+    let (input_mint, output_mint) = (&first.input_mint, &last.output_mint);
+    let native_mint = spl_token::native_mint::id();
+    let temp_wsol = temp_wsol_address(owner)?;
+    let input_amount = first.swap.input_amount();
+    let pool = first.pool;
+
+    let mut instructions = Vec::new();
+    let mut close_temp = false;
+
+    let source = if *input_mint != native_mint {
+        get_associated_token_address_with_program_id(
+            owner,
+            input_mint,
+            &pool.token_program(input_mint),
+        )
+    } else if wsol.use_wsol {
+        let ata =
+            get_associated_token_address_with_program_id(owner, &native_mint, &spl_token::id());
+        let shortfall = input_amount.saturating_sub(wsol.balance.unwrap_or_default());
+        instructions.push(create_associated_token_account_idempotent(
+            owner,
+            owner,
+            &native_mint,
+            &spl_token::id(),
+        ));
+        if shortfall > 0 {
+            instructions.push(system_instruction::transfer(owner, &ata, shortfall));
+            instructions.push(spl_token::instruction::sync_native(&spl_token::id(), &ata)?);
+        }
+        ata
+    } else {
+        instructions.extend(create_temp_wsol(
+            owner,
+            &temp_wsol,
+            wsol.rent + input_amount,
+        )?);
+        close_temp = true;
+        temp_wsol
+    };
+
+    let mut intermediates = Vec::with_capacity(legs.len() - 1);
+    for leg in &legs[..legs.len() - 1] {
+        let mint = &leg.output_mint;
+        if *mint == native_mint {
+            instructions.extend(create_temp_wsol(owner, &temp_wsol, wsol.rent)?);
+            close_temp = true;
+            intermediates.push(temp_wsol);
+        } else {
+            let token_program = leg.pool.token_program(mint);
+            instructions.push(create_associated_token_account_idempotent(
+                owner,
+                owner,
+                mint,
+                &token_program,
+            ));
+            intermediates.push(get_associated_token_address_with_program_id(
+                owner,
+                mint,
+                &token_program,
+            ));
+        }
+    }
+
+    let destination = if *output_mint != native_mint || wsol.keep_wrapped {
+        let token_program = last.pool.token_program(output_mint);
+        instructions.push(create_associated_token_account_idempotent(
+            owner,
+            owner,
+            output_mint,
+            &token_program,
+        ));
+        get_associated_token_address_with_program_id(owner, output_mint, &token_program)
+    } else {
+        instructions.extend(create_temp_wsol(owner, &temp_wsol, wsol.rent)?);
+        close_temp = true;
+        temp_wsol
+    };
+
+    let accounts: Vec<Pubkey> = std::iter::once(source)
+        .chain(intermediates.iter().copied())
+        .chain(std::iter::once(destination))
+        .collect();
+    for (leg, pair) in legs.iter().zip(accounts.windows(2)) {
+        instructions.push(leg.pool.swap_instruction(
+            owner,
+            &pair[0],
+            &pair[1],
+            &leg.input_mint,
+            &leg.swap,
+        )?);
+    }
+
+    if close_temp {
+        // Unwraps whatever the temporary account holds: the output, or unspent input.
+        instructions.push(spl_token::instruction::close_account(
+            &spl_token::id(),
+            &temp_wsol,
+            owner,
+            owner,
+            &[],
+        )?);
+    }
+
+    Ok(SwapPlan {
+        instructions,
+        source,
+        destination,
+        intermediates,
+    })
+}
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_system_interface::instruction as system_instruction;
+use spl_associated_token_account_interface::address::get_associated_token_address;
+use spl_associated_token_account_interface::instruction::create_associated_token_account_idempotent;
+use spl_token::state::Account as TokenAccount;
+
+use crate::error::wmgr_error;
+
+/// Seed of the owner-derived account that holds SOL wrapped for a single transaction.
+const TEMP_WSOL_SEED: &str = "wmgr-wsol";
+const WSOL_DECIMALS: u8 = 9;
+
+/// State of the owner's wrapped SOL account and how a swap may use it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WsolContext {
+    /// Spend wSOL already in the owner's ATA, wrapping only the shortfall.
+    pub use_wsol: bool,
+    /// Leave SOL output wrapped in the owner's ATA.
+    pub keep_wrapped: bool,
+    /// Balance of the owner's wSOL ATA, `None` when it does not exist.
+    pub balance: Option<u64>,
+    /// Rent-exempt minimum of a token account.
+    pub rent: u64,
+}
+
+pub async fn load_wsol_context(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    use_wsol: bool,
+    keep_wrapped: bool,
+) -> Result<WsolContext> {
+    let ata = wsol_address(owner);
+    let account = rpc
+        .get_account_with_commitment(&ata, rpc.commitment())
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch wSOL account {ata}: {err}"))?
+        .value;
+    let balance = match account {
+        Some(account) => Some(
+            TokenAccount::unpack(&account.data)
+                .map_err(|err| wmgr_error!(Rpc, "Failed to decode wSOL account {ata}: {err}"))?
+                .amount,
+        ),
+        None => None,
+    };
+    let rent = rpc
+        .get_minimum_balance_for_rent_exemption(TokenAccount::LEN)
+        .await
+        .map_err(|err| wmgr_error!(Rpc, "Failed to fetch token account rent: {err}"))?;
+    Ok(WsolContext {
+        use_wsol,
+        keep_wrapped,
+        balance,
+        rent,
+    })
+}
+
+/// The owner's wSOL associated token account.
+pub fn wsol_address(owner: &Pubkey) -> Pubkey {
+    get_associated_token_address(owner, &spl_token::native_mint::id())
+}
+
+pub fn temp_wsol_address(owner: &Pubkey) -> Result<Pubkey> {
+    Pubkey::create_with_seed(owner, TEMP_WSOL_SEED, &spl_token::id())
+        .map_err(|err| wmgr_error!(Internal, "Failed to derive temporary wSOL account: {err}"))
+}
+
+/// Creates and initializes the temporary wSOL account at `account` holding `lamports`.
+pub fn create_temp_wsol(
+    owner: &Pubkey,
+    account: &Pubkey,
+    lamports: u64,
+) -> Result<[Instruction; 2]> {
+    Ok([
+        system_instruction::create_account_with_seed(
+            owner,
+            account,
+            owner,
+            TEMP_WSOL_SEED,
+            lamports,
+            TokenAccount::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account3(
+            &spl_token::id(),
+            account,
+            &spl_token::native_mint::id(),
+            owner,
+        )?,
+    ])
+}
+
+/// Moves `amount` lamports into the owner's wSOL ATA, creating it when missing.
+pub fn build_wrap_instructions(owner: &Pubkey, amount: u64) -> Result<Vec<Instruction>> {
+    let ata = wsol_address(owner);
+    Ok(vec![
+        create_associated_token_account_idempotent(
+            owner,
+            owner,
+            &spl_token::native_mint::id(),
+            &spl_token::id(),
+        ),
+        system_instruction::transfer(owner, &ata, amount),
+        spl_token::instruction::sync_native(&spl_token::id(), &ata)?,
+    ])
+}
+
+/// Unwraps `amount` from the owner's wSOL ATA, or closes the ATA when `amount` is `None`.
+///
+/// A token account cannot release part of its native balance, so a partial unwrap moves the
+/// amount into a temporary account and closes that one instead.
+pub fn build_unwrap_instructions(
+    owner: &Pubkey,
+    amount: Option<u64>,
+    rent: u64,
+) -> Result<Vec<Instruction>> {
+    let ata = wsol_address(owner);
+    let Some(amount) = amount else {
+        return Ok(vec![spl_token::instruction::close_account(
+            &spl_token::id(),
+            &ata,
+            owner,
+            owner,
+            &[],
+        )?]);
+    };
+
+    let temp = temp_wsol_address(owner)?;
+    let mut instructions = create_temp_wsol(owner, &temp, rent)?.to_vec();
+    instructions.push(spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        &ata,
+        &spl_token::native_mint::id(),
+        &temp,
+        owner,
+        &[],
+        amount,
+        WSOL_DECIMALS,
+    )?);
+    instructions.push(spl_token::instruction::close_account(
+        &spl_token::id(),
+        &temp,
+        owner,
+        owner,
+        &[],
+    )?);
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unwrap_all_closes_the_ata() {
+        let owner = Pubkey::new_unique();
+        let instructions = build_unwrap_instructions(&owner, None, 2_039_280).unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].accounts[0].pubkey, wsol_address(&owner));
+    }
+
+    #[test]
+    fn partial_unwrap_goes_through_a_temporary_account() {
+        let owner = Pubkey::new_unique();
+        let temp = temp_wsol_address(&owner).unwrap();
+        let instructions = build_unwrap_instructions(&owner, Some(500), 2_039_280).unwrap();
+        // create, initialize, transfer from the ATA, close the temporary account.
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[2].accounts[0].pubkey, wsol_address(&owner));
+        assert_eq!(instructions[2].accounts[2].pubkey, temp);
+        assert_eq!(instructions[3].accounts[0].pubkey, temp);
+    }
+}
@@ -2,6 +2,7 @@ mod alloc;
 mod app;
 mod config;
 mod core;
+mod error;
 mod infra;
 mod utils;
 
@@ -12,9 +13,12 @@ static GLOBAL_ALLOCATOR: alloc::ZeroingAllocator = alloc::ZeroingAllocator;
 
 #[tokio::main]
 async fn main() {
-    let cli = app::cli::Cli::parse();
+    let cli = match app::cli::Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => app::output::exit_parse_error(err),
+    };
     if let Err(err) = app::commands::run(cli).await {
-        eprintln!("Error: {err}");
-        std::process::exit(1);
+        let code = app::output::report_error(&err);
+        std::process::exit(code.exit_code());
     }
 }
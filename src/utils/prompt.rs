@@ -1,6 +1,8 @@
 use std::io::{self, Write};
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+
+use crate::error::wmgr_error;
 
 pub fn prompt(message: &str) -> Result<String> {
     eprint!("{message} ");
@@ -10,9 +12,15 @@ pub fn prompt(message: &str) -> Result<String> {
     Ok(input.trim().to_string())
 }
 
+/// Asks a yes/no question; anything other than `y`/`yes` is a no.
+pub fn confirm(message: &str) -> Result<bool> {
+    let answer = prompt(message)?.to_ascii_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
 pub fn prompt_hidden(message: &str) -> Result<String> {
     let prompt = format!("{message} ");
     let value = rpassword::prompt_password(prompt)
-        .map_err(|err| anyhow!("Failed to read password: {err}"))?;
+        .map_err(|err| wmgr_error!(Io, "Failed to read password: {err}"))?;
     Ok(value.trim().to_string())
 }
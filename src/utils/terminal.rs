@@ -1,6 +1,9 @@
 use rustyline::{error::ReadlineError, DefaultEditor};
 use std::io::{IsTerminal, Write};
 
+use crate::error::ErrorCode;
+
+#[allow(clippy::large_enum_variant)]
 pub enum ReplReader {
     Rustyline(DefaultEditor),
     Plain,
@@ -12,7 +15,10 @@ impl ReplReader {
             match DefaultEditor::new() {
                 Ok(v) => return Self::Rustyline(v),
                 Err(err) => {
-                    eprintln!("device_error: Failed to initialize interactive console: {err}");
+                    eprintln!(
+                        "error[{}]: Failed to initialize interactive console: {err}",
+                        ErrorCode::Io.as_str()
+                    );
                 }
             }
         }